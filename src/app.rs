@@ -1,100 +1,974 @@
-use std::io::{Stdout, Write, stdout};
-use tui::{ Terminal, buffer::{Buffer, Cell}, backend::{CrosstermBackend, Backend}};
+
+use std::{collections::VecDeque, fs, io, path::{Path, PathBuf}};
+use futures::{Stream, StreamExt, future};
+use tokio::time;
+use tui::{ Terminal, backend::Backend};
 
 use super::{
-    ui,
     prefix::Prefix,
     mode::Mode,
+    key::{KeyCode, KeyEvent, KeyModifiers, maps::KeyMaps, notation},
+    op::Operation,
+    overview::{ViewContext, ViewRegistry},
+    window::{OpenIn, SplitDir, Tab},
+    buffer::{Buffer, grapheme::col_at_display, indent},
+    change::ChangeLog,
+    files::{self, FileOp},
+    finder::{Finder, FinderItem},
+    prefix::{FindPre, FindTarget},
+    key::GlobalPrefixKey,
+    register::{self, Registers},
+    mark::{JumpList, Marks},
+    fold::FileFolds,
+    syntax::{Grammars, Highlighter},
+    theme::{ColorDepth, Theme},
+    options::{FoldMethod, Options, Wrap, modeline},
+    editorconfig::{self, Properties},
+    ex,
+    macros::Recording,
+    prompt::{Prompt, Subject},
+    swap,
+    watch::{Stamp, Watcher},
+    vcs::{self, Differ},
+    op::{InsertOp, MotionOp, Op, ScrollOp},
+    types::{Cursor, Direction, Level, Message},
     LxResult,
 };
-use crossterm::{Result as CTResult, cursor::{CursorShape, self, position}, event::{self, Event, EventStream, KeyCode, KeyEvent, KeyModifiers, poll}, execute, queue, style::{self, SetBackgroundColor, SetColors}, terminal::{self, ClearType,  disable_raw_mode, enable_raw_mode}};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// Time spent highlighting per frame, so large files never hold up input
+const HIGHLIGHT_BUDGET: Duration = Duration::from_millis(8);
+/// Messages kept for `:messages`
+const MESSAGE_HISTORY: usize = 200;
+/// Pause in input after which unsaved changes are journaled to swap files
+const SWAP_INTERVAL: Duration = Duration::from_secs(4);
+/// How often overview views refresh
+const TICK_RATE: Duration = Duration::from_millis(250);
 
-// TODO make wrapper type for key event / event type
 // TODO use tui buffer type for buffers
-pub struct Lx<W: Write + Backend> {
+pub struct Lx<W: Backend> {
+    /// Keys typed this session, apart from those macros play
     pub prev_keys: Vec<KeyEvent>,
+    /// The macro being recorded
+    pub recording: Option<Recording>,
+    /// Keys of the macros playing which are still to be handled
+    pub replay: VecDeque<KeyEvent>,
+    /// Whether keys of `replay` are being handled
+    pub replaying: bool,
+    /// Register of the last macro played, for `@@`
+    pub last_macro: Option<char>,
+    pub changes: ChangeLog,
     pub prefix: Option<Prefix>,
     pub term: Terminal<W>,
-    pub buf: Vec<String>,
-    pub buf_idx: usize,
+    pub buf: Vec<Buffer>,
+    pub tabs: Vec<Tab>,
+    pub tab_idx: usize,
     pub cmd_buf: String,
+    pub history: Vec<String>,
+    pub registers: Registers,
+    /// File marks kept while their buffers are closed
+    pub marks: Marks,
+    pub jumps: JumpList,
+    /// Folds of files which aren't open
+    pub file_folds: FileFolds,
+    pub keymaps: KeyMaps,
+    pub views: ViewRegistry,
+    /// File operations made this session, most recent last, for undoing
+    pub file_history: Vec<FileOp>,
+    /// Bumped on every file operation done or undone, so views of the files know when they
+    /// are stale
+    pub files_version: u64,
+    /// Message shown on the message line until the next key press
+    pub message: Option<Message>,
+    /// Every message shown this session, oldest first, for `:messages`
+    pub messages: Vec<Message>,
+    pub finder: Option<Finder>,
+    /// Questions waiting for an answer, the first one shown on the message line
+    pub prompts: Vec<Prompt>,
+    /// When unsaved changes were last journaled to swap files
+    pub journaled: Instant,
+    /// When changed buffers were last written by `autosave`
+    pub autosaved: Instant,
+    /// When the last input arrived
+    pub input_at: Instant,
+    pub grammars: Grammars,
+    pub theme: Theme,
+    pub color_depth: ColorDepth,
+    pub options: Options,
+    pub vcs: Differ,
+    pub watcher: Watcher,
+    /// Directory the unsaved changes of buffers are journaled to, if they are journaled
+    pub swap_dir: Option<PathBuf>,
+    /// What to write to standard output once the editor exits, from writing a buffer
+    /// which goes there
+    pub output: Option<Vec<u8>>,
     pub mode: Mode,
-    pub pos: (u16, u16),
     pub quit: bool,
 }
 
-impl Default for Lx<CrosstermBackend<Stdout>> {
-    fn default() -> Self {
-        let backend = CrosstermBackend::new(stdout());
-        let term = Terminal::new(backend)
-            .expect("Could not initialize TUI");
-        let mut prev_keys = Vec::with_capacity(5);
-        prev_keys.push(KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') });
-        Self {
+impl<W: Backend> Lx<W> {
+    /// An editor drawing to `backend`, with one empty buffer in Insert mode. Drawing to a
+    /// `TestBackend` runs it without a terminal, as the tests do.
+    pub fn new_with_backend(backend: W) -> LxResult<Self> {
+        let term = Terminal::new(backend)?;
+        let (grammars, mut errors) = Grammars::load();
+        let color_depth = ColorDepth::detect();
+        let theme = Theme::load("default", color_depth).unwrap_or_else(|e| {
+            errors.push(e);
+            Theme::default()
+        });
+        let mut lx = Self {
             quit: false,
             prefix: None,
             term,
-            buf_idx: 0,
+            tabs: vec![Tab::default()],
+            tab_idx: 0,
             cmd_buf: String::new(),
-            buf: vec![String::new()],
+            history: Vec::new(),
+            registers: Registers::default(),
+            marks: Marks::default(),
+            jumps: JumpList::default(),
+            file_folds: FileFolds::default(),
+            keymaps: KeyMaps::default(),
+            views: ViewRegistry::builtin(),
+            file_history: Vec::new(),
+            files_version: 0,
+            message: None,
+            messages: Vec::new(),
+            finder: None,
+            prompts: Vec::new(),
+            journaled: Instant::now(),
+            autosaved: Instant::now(),
+            input_at: Instant::now(),
+            grammars,
+            theme,
+            color_depth,
+            buf: vec![Buffer::new()],
             mode: Mode::insert(),
-            pos: (0, 0),
+            options: Options::default(),
+            vcs: Differ::default(),
+            watcher: Watcher::default(),
+            swap_dir: Some(swap::default_dir()),
+            output: None,
             prev_keys: Vec::with_capacity(4),
+            recording: None,
+            replay: VecDeque::new(),
+            replaying: false,
+            last_macro: None,
+            changes: ChangeLog::default(),
+        };
+        for e in errors {
+            lx.error(e);
         }
+        Ok(lx)
     }
-}
-impl Lx<CrosstermBackend<Stdout>> {
 
-    pub fn run(&mut self) -> LxResult<()> {
-        terminal::enable_raw_mode()?;
-        self.term.clear()?;
-        /* let mut reader = EventStream::new();
-            let mut ev = reader.next().fuse(); */
-        // self.init();
-        execute!(self.term.backend_mut(),
-            event::EnableMouseCapture,
-            terminal::EnterAlternateScreen,
-            terminal::EnableLineWrap,
-            cursor::EnableBlinking,
-            terminal::SetTitle("lx editor"),
-        )?;
+    /// Shows an informational message
+    pub fn info(&mut self, text: impl ToString) {
+        self.show_message(Level::Info, text.to_string());
+    }
+    /// Shows an error message
+    pub fn error(&mut self, text: impl ToString) {
+        self.show_message(Level::Error, text.to_string());
+    }
+    fn show_message(&mut self, level: Level, text: String) {
+        // An error stops the macros playing, as a failed motion does
+        if level == Level::Error {
+            self.abort_macro();
+        }
+        let message = Message { level, text };
+        if self.messages.len() == MESSAGE_HISTORY {
+            self.messages.remove(0);
+        }
+        self.messages.push(message.clone());
+        self.message = Some(message);
+    }
+    /// Index of the buffer shown in the focused window
+    pub fn buf_idx(&self) -> usize {
+        self.tabs[self.tab_idx].window().buf_idx
+    }
+    /// An empty buffer with the global file options
+    fn new_buf(&self) -> Buffer {
+        Buffer { file: self.options.file.clone(), ..Buffer::new() }
+    }
+    pub fn create_buf(&mut self) {
+        self.buf.push(self.new_buf());
+        self.switch_buf(self.buf.len() - 1);
+    }
+    /// Closes a buffer, pointing any window showing it (or a later buffer) at the buffer
+    /// before it. Quits once the last buffer is closed.
+    pub fn close_buf(&mut self, idx: usize) {
+        if idx >= self.buf.len() {
+            return;
+        }
+        self.keep_marks(idx);
+        self.keep_folds(idx);
+        let mut closed = self.buf.remove(idx);
+        if let Err(e) = closed.remove_swap() {
+            self.error(e);
+        }
+        // Another buffer of the same file still wants to hear of changes to it
+        if let Some(path) = closed.path.filter(|p| !self.buf.iter().any(|b| b.path.as_ref() == Some(p))) {
+            self.watcher.unwatch(&path);
+        }
+        if self.buf.is_empty() {
+            self.quit = true;
+            self.buf.push(self.new_buf());
+        }
+        for win in self.tabs.iter_mut().flat_map(|t| t.windows.iter_mut()) {
+            if win.buf_idx == idx {
+                win.reset(idx.saturating_sub(1));
+            } else if win.buf_idx > idx {
+                win.buf_idx -= 1;
+            }
+        }
+    }
+    /// Highlights the buffers shown in the current tab within the frame's time budget,
+    /// all of those folded by syntax. Returns whether everything visible is highlighted.
+    pub fn highlight(&mut self) -> bool {
+        let deadline = Instant::now() + HIGHLIGHT_BUDGET;
+        let height = self.term.size().map(|r| r.height as usize).unwrap_or_default();
+        let mut done = true;
+        for w in self.tabs[self.tab_idx].windows.iter() {
+            let buf = &mut self.buf[w.buf_idx];
+            let upto = match buf.fold_options(&self.options).foldmethod {
+                FoldMethod::Syntax => usize::MAX,
+                // The window's own height isn't known before its first draw
+                _ => w.last_line(buf, &self.options, height) + 1,
+            };
+            done &= buf.highlight(upto, deadline);
+        }
+        done
+    }
+    /// Shows buffer `idx` in the focused window. Switching buffers is a jump.
+    pub fn switch_buf(&mut self, idx: usize) {
+        if idx < self.buf.len() && idx != self.buf_idx() {
+            self.push_jump();
+            self.tabs[self.tab_idx].window_mut().show(idx);
+        }
+    }
+    /// Cursor of the focused window
+    pub fn cursor(&self) -> Cursor {
+        self.tabs[self.tab_idx].window().cursor
+    }
+    /// Moves the focused window's cursor onto the text and scrolls to keep it in view. In
+    /// Insert mode the cursor may sit just past the end of a line.
+    pub fn set_cursor(&mut self, to: Cursor) {
+        let to = self.clamp_cursor(to);
+        let idx = self.buf_idx();
+        let tabstop = self.buf[idx].indent(&self.options).tabstop;
+        self.tabs[self.tab_idx].window_mut().set_cursor(to, &self.buf[idx], tabstop);
+        self.scroll_to_cursor();
+    }
+    /// Moves the cursor to `line`, at the display column wanted by earlier vertical motions
+    pub fn move_vertical(&mut self, line: usize) {
+        let buf = &self.buf[self.buf_idx()];
+        let want = self.tabs[self.tab_idx].window().want_col;
+        let line = line.min(buf.line_count() - 1);
+        let to = self.clamp_cursor(Cursor::new(line, col_at_display(buf.line(line), want, buf.indent(&self.options).tabstop)));
+        self.tabs[self.tab_idx].window_mut().cursor = to;
+        self.scroll_to_cursor();
+    }
+    fn clamp_cursor(&self, to: Cursor) -> Cursor {
+        self.buf[self.buf_idx()].clamp(to, matches!(self.mode, Mode::Insert(_)))
+    }
+    pub fn scroll_to_cursor(&mut self) {
+        let idx = self.buf_idx();
+        self.tabs[self.tab_idx].window_mut().scroll_to_cursor(&self.buf[idx], &self.options);
+    }
+    /// Applies `:set` arguments, or with none shows the current options. With `local`, as
+    /// for `:setlocal`, only window-local wrap options and buffer-local options are taken
+    /// and they are set for the focused window or buffer alone. `:set` on a window-local
+    /// option sets the global value and drops the focused window's own, while on a
+    /// buffer-local option it sets the focused buffer's value too.
+    pub fn set_options(&mut self, args: &str, local: bool) {
+        if args.is_empty() {
+            self.info(self.options.to_string());
+            return;
+        }
+        for arg in args.split_whitespace() {
+            let idx = self.buf_idx();
+            let win = self.tabs[self.tab_idx].window_mut();
+            let buf = &mut self.buf[idx];
+            let global = &self.options.wrap;
+            let result = match arg.strip_suffix('?') {
+                Some(name) if Buffer::is_option(name) => {
+                    buf.get_option(name, &self.options).ok_or_else(|| format!("Unknown option: {}", name))
+                },
+                Some(name) => {
+                    let value = if local { win.wrap(&self.options).get(name) } else { self.options.get(name) };
+                    value.ok_or_else(|| format!("Unknown option: {}", name))
+                },
+                None if local && Buffer::is_option(arg) => buf.set_option(arg, &self.options)
+                    .map(|_| String::new())
+                    .map_err(|e| e.to_string()),
+                None if local && !Wrap::is_option(arg) => Err(format!("Not a local option: {}", arg)),
+                None if local => win.wrap.get_or_insert_with(|| global.clone()).set(arg)
+                    .map(|_| String::new())
+                    .map_err(|e| e.to_string()),
+                None => {
+                    if Wrap::is_option(arg) {
+                        win.wrap = None;
+                    }
+                    if Buffer::is_option(arg) {
+                        let _ = buf.set_option(arg, &self.options);
+                    }
+                    self.options.set(arg).map(|_| String::new()).map_err(|e| e.to_string())
+                },
+            };
+            match result {
+                Ok(value) if value.is_empty() => {},
+                Ok(value) => { self.info(value); },
+                Err(e) => {
+                    self.error(e);
+                    return;
+                },
+            }
+        }
+        self.scroll_to_cursor();
+    }
+    /// Shows the file at `path`, reusing its buffer if it is already open
+    pub fn open(&mut self, path: &Path, open_in: OpenIn) -> io::Result<()> {
+        let idx = self.load(path)?;
+        match open_in {
+            OpenIn::Current => self.switch_buf(idx),
+            OpenIn::Split(dir) => self.tabs[self.tab_idx].split(dir, idx),
+            OpenIn::Tab => {
+                self.tabs.push(Tab::new(idx));
+                self.tab_idx = self.tabs.len() - 1;
+            },
+        }
+        Ok(())
+    }
+    /// The index of the buffer holding the file at `path`, reading it into a new one if no
+    /// buffer does
+    pub fn load(&mut self, path: &Path) -> io::Result<usize> {
+        Ok(match self.buf.iter().position(|b| b.is_path(path)) {
+            Some(idx) => idx,
+            None => {
+                let editorconfig = editorconfig::properties(path).unwrap_or_else(|e| {
+                    self.error(e);
+                    Properties::default()
+                }).options();
+                let mut buf = Buffer::open(path, self.options.file.clone())?;
+                buf.swap_dir = self.swap_dir.clone();
+                buf.syntax = self.grammars.detect(path, &buf.text).map(Highlighter::new);
+                buf.indent = indent::detect(&buf.text, &self.options.indent);
+                // Settings given explicitly win over detected ones, and modelines over EditorConfig
+                for arg in editorconfig.iter().chain(modeline::find(&buf.text).iter()) {
+                    if let Err(e) = buf.set_option(arg, &self.options) {
+                        self.error(format!("{}: {}", path.display(), e));
+                    }
+                }
+                self.watcher.watch(path);
+                self.buf.push(buf);
+                let idx = self.buf.len() - 1;
+                self.request_diff(idx);
+                self.check_swap(idx);
+                self.adopt_marks(idx);
+                self.adopt_folds(idx);
+                idx
+            },
+        })
+    }
+    /// Shows a new scratch buffer holding `bytes`, as read from standard input
+    pub fn open_bytes(&mut self, bytes: &[u8]) {
+        let mut buf = Buffer::from_bytes(bytes, self.options.file.clone());
+        buf.indent = indent::detect(&buf.text, &self.options.indent);
+        self.buf.push(buf);
+        self.switch_buf(self.buf.len() - 1);
+    }
+    /// Asks what to do about the swap file left for the file of buffer `idx`, if there is
+    /// one. A swap file a crashed session left with nothing more than the file is removed.
+    fn check_swap(&mut self, idx: usize) {
+        let (dir, path) = match (self.swap_dir.clone(), self.buf[idx].path.clone()) {
+            (Some(dir), Some(path)) => (dir, path),
+            _ => return,
+        };
+        let swap = match swap::read(&dir, &path) {
+            Ok(Some(swap)) => swap,
+            Ok(None) => return,
+            Err(e) => return self.error(e),
+        };
+        let question = if swap.is_running() {
+            format!("{} is being edited by process {}:", path.display(), swap.pid)
+        } else if swap.text == self.buf[idx].text {
+            if let Err(e) = swap::remove(&dir, &path) {
+                self.error(e);
+            }
+            return;
+        } else {
+            format!("{} has unsaved changes from a session that crashed:", path.display())
+        };
+        let subject = Subject::Swap { buf: idx, swap };
+        self.prompts.push(Prompt::new(subject, question, &["recover", "open read-only", "delete"]));
+    }
+    /// Brings buffers of the file at `path` up to date after something else changed it.
+    /// Buffers without unsaved changes are reloaded; for the others it asks what to do.
+    pub fn check_file(&mut self, path: &Path) {
+        for idx in 0..self.buf.len() {
+            let b = &mut self.buf[idx];
+            let stamp = Stamp::of(path);
+            if !b.is_path(path) || b.stamp == stamp {
+                continue;
+            }
+            // Asked once per change, whatever the answer
+            b.stamp = stamp;
+            let name = b.name();
+            if stamp.is_none() {
+                self.error(format!("{} was deleted outside the editor", name));
+            } else if !b.modified {
+                match self.reload_buf(idx) {
+                    Ok(()) => self.info(format!("Reloaded {}", name)),
+                    Err(e) => self.error(e),
+                }
+                self.request_diff(idx);
+            } else if !self.prompts.iter().any(|p| p.subject == Subject::Changed { buf: idx }) {
+                let question = format!("{} changed on disk and has unsaved changes:", name);
+                self.prompts.push(Prompt::new(Subject::Changed { buf: idx }, question, &["reload", "keep", "diff"]));
+            }
+        }
+    }
+    /// Reads buffer `idx` from its file again, keeping the cursors and scroll positions of
+    /// the windows showing it on the text when the file got shorter
+    fn reload_buf(&mut self, idx: usize) -> io::Result<()> {
+        let result = self.buf[idx].reload();
+        let buf = &self.buf[idx];
+        let tabstop = buf.indent(&self.options).tabstop;
+        let past_end = matches!(self.mode, Mode::Insert(_));
+        for win in self.tabs.iter_mut().flat_map(|t| t.windows.iter_mut()).filter(|w| w.buf_idx == idx) {
+            win.set_cursor(buf.clamp(win.cursor, past_end), buf, tabstop);
+            win.scroll_to_cursor(buf, &self.options);
+        }
+        result
+    }
+    /// Shows how buffer `idx` differs from its file in a split next to it
+    fn diff_with_file(&mut self, idx: usize) -> io::Result<()> {
+        let b = &self.buf[idx];
+        let path = b.path.clone().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No file name"))?;
+        let end = if b.no_eol { "" } else { "\n" };
+        let mut diff = self.new_buf();
+        diff.text = vcs::diff_text(&path, &format!("{}{}", b.text, end))?;
+        diff.text.pop();
+        self.buf.push(diff);
+        self.tabs[self.tab_idx].split(SplitDir::Vertical, self.buf.len() - 1);
+        Ok(())
+    }
+    /// Acts on the answer to a prompt, `None` when it was dismissed
+    fn answer_prompt(&mut self, prompt: Prompt, choice: Option<&str>) {
+        match prompt.subject {
+            Subject::Swap { buf, swap } => {
+                let b = &mut self.buf[buf];
+                match choice {
+                    Some("recover") => {
+                        b.text = swap.text;
+                        b.touch();
+                        // The swap file holds just this text, so the buffer takes it over
+                        b.swapped = Some(b.version);
+                        let name = b.name();
+                        self.info(format!("Recovered {}, :w to keep the changes", name));
+                    },
+                    Some("delete") => {
+                        if let Err(e) = fs::remove_file(&swap.path) {
+                            self.error(e);
+                        }
+                    },
+                    // Read-only keeps the swap file as it is, so it is the safe way out
+                    _ => b.readonly = true,
+                }
+            },
+            Subject::Changed { buf } => match choice {
+                Some("reload") => {
+                    if let Err(e) = self.reload_buf(buf) {
+                        self.error(e);
+                    }
+                },
+                Some("diff") => {
+                    if let Err(e) = self.diff_with_file(buf) {
+                        self.error(e);
+                    }
+                    // Asked again with the diff in view
+                    self.prompts.insert(0, Prompt::new(prompt.subject, prompt.question, &["reload", "keep"]));
+                },
+                // Keeping the buffer's text, it overwrites the file when saved
+                _ => {},
+            },
+            Subject::Delete { path } => if choice == Some("yes") {
+                self.file_op(FileOp::trash(path));
+            },
+            // The rest ask for a file name, and nothing is done without one
+            subject => match (subject, choice.filter(|name| !name.is_empty())) {
+                (Subject::Create { dir }, Some(name)) => {
+                    self.file_op(FileOp::create(dir.join(name.trim_end_matches('/')), name.ends_with('/')));
+                },
+                (Subject::Rename { path }, Some(name)) => {
+                    let to = path.with_file_name(name);
+                    self.file_op(FileOp::rename(path, to));
+                },
+                // Joined to the root, an absolute path replaces it
+                (Subject::Move { path, root }, Some(name)) => self.file_op(FileOp::rename(path, root.join(name))),
+                (Subject::Copy { path, root }, Some(name)) => self.file_op(FileOp::copy(path, root.join(name))),
+                _ => {},
+            },
+        }
+    }
+    /// When a prefix key waiting for the rest of its key sequence gives up, if one is
+    pub fn prefix_timeout(&self) -> Option<Instant> {
+        let timeoutlen = Duration::from_millis(self.options.timeoutlen);
+        self.prefix.as_ref().filter(|_| !timeoutlen.is_zero()).map(|_| self.input_at + timeoutlen)
+    }
+    /// When unsaved changes are next journaled: once input pauses for `SWAP_INTERVAL`, and
+    /// at least every few of those while it doesn't. Nothing is due until there is input.
+    pub fn journal_deadline(&self) -> Option<Instant> {
+        let due = (self.input_at + SWAP_INTERVAL).min(self.journaled + SWAP_INTERVAL * 4);
+        Some(due).filter(|_| self.input_at >= self.journaled)
+    }
+    /// Journals the unsaved changes of every buffer to its swap file, and diffs the text
+    /// of those changed since their last diff, so their signs follow the edits
+    pub fn journal(&mut self) {
+        self.journaled = Instant::now();
+        let errors: Vec<io::Error> = self.buf.iter_mut().filter_map(|b| b.journal().err()).collect();
+        for e in errors {
+            self.error(e);
+        }
+        for idx in 0..self.buf.len() {
+            if self.buf[idx].diffed != Some(self.buf[idx].version) {
+                self.request_diff(idx);
+            }
+        }
+    }
+    /// When changed buffers are next written to their files, if the `autosave` option is
+    /// set: once input pauses for that long after the last time they were
+    pub fn autosave_deadline(&self) -> Option<Instant> {
+        let autosave = Duration::from_millis(self.options.autosave);
+        Some(self.input_at + autosave).filter(|_| !autosave.is_zero() && self.input_at >= self.autosaved)
+    }
+    /// Writes the buffers with changes to their files. Only files still on disk are written,
+    /// so deleted ones aren't put back and new ones aren't made unasked.
+    pub fn autosave(&mut self) {
+        self.autosaved = Instant::now();
+        for idx in 0..self.buf.len() {
+            let b = &self.buf[idx];
+            if b.modified && !b.readonly && !b.stdout && b.stamp.is_some() {
+                self.write_buf(idx, false);
+            }
+        }
+    }
+    /// Starts diffing the text of buffer `idx` against version control, if it has a file
+    pub fn request_diff(&mut self, idx: usize) {
+        let b = &mut self.buf[idx];
+        if let Some(path) = b.path.clone() {
+            let end = if b.no_eol || b.text.is_empty() { "" } else { "\n" };
+            self.vcs.request(path, format!("{}{}", b.text, end));
+            b.diffed = Some(b.version);
+        }
+    }
+    /// Waits on input, timers, file changes and background jobs all at once, so none of them
+    /// holds up the others, until the editor quits or `input` ends. Input comes first: each
+    /// item is a key, or `None` for other input which only needs a redraw. `draw` runs once
+    /// no input is waiting, so a burst of keys is drawn once, and gives whether it should
+    /// run again before further input, as when highlighting runs out of budget.
+    pub async fn event_loop<I>(&mut self, mut input: I, mut draw: impl FnMut(&mut Self) -> LxResult<bool>) -> LxResult<()>
+    where I: Stream<Item = io::Result<Option<KeyEvent>>> + Unpin {
+        let mut tick = time::interval(TICK_RATE);
+        let mut redraw = true;
         loop {
-            queue!(
-                &mut self.term.backend_mut(),
-                style::ResetColor,
-                cursor::Show,
-            )?;
-            let event = event::read()?;
-            self.match_event(event)?;
-            ui::draw_ui(self).expect("Error drawing tui");
-            // self.term.flush()?;
+            let timeout = self.prefix_timeout();
+            let journal = self.journal_deadline();
+            let autosave = self.autosave_deadline();
+            let overview = matches!(self.mode, Mode::Overview(_));
+            let indexing = self.finder.as_ref().is_some_and(Finder::indexing);
+            let finder = &mut self.finder;
+            redraw = tokio::select! {
+                biased;
+                key = input.next() => match key {
+                    Some(key) => {
+                        self.input_at = Instant::now();
+                        if let Some(key) = key? {
+                            self.match_key_event(key)?;
+                        }
+                        true
+                    },
+                    None => return Ok(()),
+                },
+                _ = time::sleep_until(timeout.unwrap_or_else(Instant::now).into()), if timeout.is_some() => {
+                    self.prefix = None;
+                    true
+                },
+                (path, info) = self.vcs.next() => {
+                    for b in self.buf.iter_mut().filter(|b| b.is_path(&path)) {
+                        b.signs.vcs = info.signs.clone();
+                        b.branch = info.branch.clone();
+                    }
+                    true
+                },
+                paths = self.watcher.changed() => {
+                    for path in paths {
+                        self.check_file(&path);
+                    }
+                    true
+                },
+                _ = async { finder.as_mut().expect("finder is indexing").indexed().await }, if indexing => true,
+                _ = time::sleep_until(journal.unwrap_or_else(Instant::now).into()), if journal.is_some() => {
+                    self.journal();
+                    redraw
+                },
+                _ = time::sleep_until(autosave.unwrap_or_else(Instant::now).into()), if autosave.is_some() => {
+                    self.autosave();
+                    true
+                },
+                _ = tick.tick(), if overview => {
+                    let mut views = std::mem::take(&mut self.views);
+                    if let Some(view) = views.focused_mut() {
+                        view.tick(&self.view_ctx());
+                    }
+                    self.views = views;
+                    true
+                },
+                _ = future::ready(()), if redraw => draw(self)?,
+            };
+            if self.quit {
+                self.remove_swaps();
+                return Ok(());
+            }
         }
     }
-
+    /// Removes the swap files of all buffers, as the editor quits
+    pub fn remove_swaps(&mut self) {
+        for b in self.buf.iter_mut() {
+            let _ = b.remove_swap();
+        }
+    }
+    /// Points buffers at the new location of files moved from `from` to `to`
+    fn relocate_buffers(&mut self, from: &Path, to: &Path) {
+        for b in self.buf.iter_mut() {
+            if let Some(new) = b.path.as_deref().and_then(|p| files::relocate(p, from, to)) {
+                // The swap file is named after the path, so it is journaled again under the new one
+                let _ = b.remove_swap();
+                self.watcher.watch(&new);
+                if let Some(old) = b.path.replace(new) {
+                    self.watcher.unwatch(&old);
+                }
+            }
+        }
+    }
+    /// Marks buffers of files under `path` as no longer saved anywhere once it is deleted.
+    /// They keep their paths, so `:w` puts the files back where they were rather than
+    /// writing into the trash.
+    fn detach_buffers(&mut self, path: &Path) {
+        for b in self.bufs_under(path) {
+            b.stamp = None;
+            b.modified = true;
+        }
+    }
+    /// Buffers of files at or below `path`
+    fn bufs_under<'a>(&'a mut self, path: &'a Path) -> impl Iterator<Item = &'a mut Buffer> {
+        self.buf.iter_mut().filter(move |b| b.path.as_ref().is_some_and(|p| p.starts_with(path)))
+    }
+    pub fn file_op(&mut self, op: FileOp) {
+        match op.apply() {
+            Ok(()) => {
+                if let Some((from, to)) = op.moved() {
+                    self.relocate_buffers(from, to);
+                }
+                if let Some(path) = op.trashed() {
+                    self.detach_buffers(path);
+                }
+                self.info(op.to_string());
+                self.file_history.push(op);
+                self.files_version += 1;
+            },
+            Err(e) => { self.error(format!("Could not {}: {}", op, e)); },
+        }
+    }
+    pub fn undo_file_op(&mut self) {
+        let op = match self.file_history.pop() {
+            Some(op) => op,
+            None => {
+                self.error("No file operations to undo");
+                return;
+            },
+        };
+        match op.undo() {
+            Ok(()) => {
+                if let Some((from, to)) = op.moved() {
+                    self.relocate_buffers(to, from);
+                }
+                // Back in place, the files aren't reported as changed on disk
+                if let Some(path) = op.trashed() {
+                    for b in self.bufs_under(path) {
+                        b.stamp = b.path.as_deref().and_then(Stamp::of);
+                    }
+                }
+                self.info(format!("Undid {}", op));
+                self.files_version += 1;
+            },
+            Err(e) => {
+                self.error(format!("Could not undo {}: {}", op, e));
+                self.file_history.push(op);
+            },
+        }
+    }
+    pub fn open_finder(&mut self, target: FindTarget) {
+        self.finder = Some(Finder::new(target, &self.buf, &self.history));
+    }
+    /// Closes the finder and opens everything chosen in it, at the line the query ends with
+    /// if it names one. Several files each get their own split or tab; with `OpenIn::Current`
+    /// they are all loaded and the last is shown.
+    pub fn finder_accept(&mut self, open_in: OpenIn) {
+        let finder = match self.finder.take() {
+            Some(finder) => finder,
+            None => return,
+        };
+        let line = finder.line();
+        for item in finder.chosen() {
+            match item {
+                FinderItem::File(path) => {
+                    if let Err(e) = self.open(&path, open_in) {
+                        self.error(format!("{}: {}", path.display(), e));
+                    }
+                },
+                FinderItem::Buffer(idx) => match open_in {
+                    OpenIn::Current => self.switch_buf(idx),
+                    OpenIn::Split(dir) => self.tabs[self.tab_idx].split(dir, idx),
+                    OpenIn::Tab => {
+                        self.tabs.push(Tab::new(idx));
+                        self.tab_idx = self.tabs.len() - 1;
+                    },
+                },
+                FinderItem::History(cmd) => {
+                    self.cmd_buf = cmd;
+                    self.mode = Mode::command();
+                    return;
+                },
+            }
+            if let Some(line) = line {
+                self.move_vertical(line);
+            }
+        }
+        if !matches!(self.mode, Mode::Insert(_)) {
+            self.mode = Mode::edit();
+        }
+    }
+    pub fn new_tab(&mut self) {
+        let buf_idx = self.buf_idx();
+        self.tabs.push(Tab::new(buf_idx));
+        self.tab_idx = self.tabs.len() - 1;
+    }
+    pub fn close_tab(&mut self) {
+        if self.tabs.len() > 1 {
+            self.tabs.remove(self.tab_idx);
+            self.tab_idx = self.tab_idx.min(self.tabs.len() - 1);
+        }
+    }
+    pub fn switch_tab(&mut self, idx: usize) {
+        if idx < self.tabs.len() {
+            self.tab_idx = idx;
+        }
+    }
+    pub fn view_ctx(&self) -> ViewContext<'_> {
+        ViewContext {
+            buffers: &self.buf,
+            buf_idx: self.buf_idx(),
+            tabs: &self.tabs,
+            tab_idx: self.tab_idx,
+            history: &self.history,
+            registers: &self.registers,
+            marks: &self.marks,
+            jumps: &self.jumps,
+            keymaps: &self.keymaps,
+            theme: &self.theme,
+            messages: &self.messages,
+            files_version: self.files_version,
+        }
+    }
+    /// Switches to the theme called `name`, or with no name reports the current theme
+    pub fn colorscheme(&mut self, name: &str) {
+        if name.is_empty() {
+            self.info(format!("{} (available: {})", self.theme.name, Theme::available().join(", ")));
+            return;
+        }
+        match Theme::load(name, self.color_depth) {
+            Ok(theme) => self.theme = theme,
+            Err(e) => self.error(e),
+        }
+    }
+    /// Handles `keys` as if they were typed, written as `key::notation::parse` reads them.
+    /// Keys after one that quits are dropped.
+    pub fn feed(&mut self, keys: &str) -> LxResult<()> {
+        for ke in notation::parse(keys) {
+            if self.quit {
+                break;
+            }
+            self.match_key_event(ke)?;
+        }
+        Ok(())
+    }
+    /// Runs the command typed on the command line, remembering it in the history
     pub fn exec_cmd(&mut self) -> LxResult<()> {
-        match self.cmd_buf.as_str() {
-            "quit" => { self.quit = true; },
+        let cmd = std::mem::take(&mut self.cmd_buf);
+        self.run_cmd(&cmd)?;
+        if !cmd.is_empty() {
+            self.registers.set(register::COMMAND, cmd.clone());
+            self.history.push(cmd);
+        }
+        Ok(())
+    }
+    /// Runs an editor command, as given on the command line without the `:`. Commands on
+    /// lines may start with a range, as `ex::range` reads them.
+    pub fn run_cmd(&mut self, cmd: &str) -> LxResult<()> {
+        let last = self.buf[self.buf_idx()].line_count() - 1;
+        let (range, rest) = match ex::range(cmd.trim_start(), self.cursor().line, last) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.error(e);
+                return Ok(());
+            },
+        };
+        let (name, arg) = ex::name(rest);
+        match name {
+            "q" | "quit" | "qa" | "qall" | "quitall" => { self.quit_if_written(); },
+            "q!" | "quit!" | "qa!" | "qall!" | "quitall!" => { self.quit = true; },
+            "wq" | "wq!" => {
+                if self.write_buf(self.buf_idx(), name.ends_with('!')) {
+                    self.quit_if_written();
+                }
+            },
+            "x" | "xit" | "exit" => {
+                let idx = self.buf_idx();
+                // Standard output is written the first time even without changes
+                let unwritten = self.buf[idx].modified || (self.buf[idx].stdout && self.output.is_none());
+                if !unwritten || self.write_buf(idx, false) {
+                    self.quit_if_written();
+                }
+            },
+            "wa" | "wall" => { self.write_all(); },
+            "wqa" | "wqall" | "xa" | "xall" => {
+                if self.write_all() {
+                    self.quit_if_written();
+                }
+            },
+            "d" | "delete" => { self.delete_lines(range.unwrap_or_else(|| self.current_line()), arg); },
+            "y" | "yank" => { self.yank_lines(range.unwrap_or_else(|| self.current_line()), arg); },
+            "pu" | "put" => { self.put_lines(range.map_or(self.cursor().line, |r| r.last), arg); },
+            "fo" | "fold" => {
+                let range = range.unwrap_or_else(|| self.current_line());
+                self.fold_lines(range.first, range.last);
+            },
+            "foldo" | "foldopen" => { self.set_folds(range.unwrap_or_else(|| self.current_line()), false); },
+            "foldc" | "foldclose" => { self.set_folds(range.unwrap_or_else(|| self.current_line()), true); },
+            "s" | "substitute" => { self.substitute(range.unwrap_or_else(|| self.current_line()), arg); },
+            "g" | "global" => { self.global(range.unwrap_or_else(|| self.all_lines()), arg, true)?; },
+            "g!" | "global!" | "v" | "vglobal" => { self.global(range.unwrap_or_else(|| self.all_lines()), arg, false)?; },
             "command" => { self.mode = Mode::command(); },
             "insert" => { self.mode = Mode::insert(); },
             "edit" => { self.mode = Mode::edit(); },
             "overview" => { self.mode = Mode::overview(); },
-            _ => {  }
+            "tabnew" => { self.new_tab(); },
+            "tabclose" => { self.close_tab(); },
+            "w" | "write" | "w!" | "write!" => { self.write_buf(self.buf_idx(), name.ends_with('!')); },
+            "colo" | "colorscheme" => { self.colorscheme(arg.trim()); },
+            "se" | "set" => { self.set_options(arg.trim(), false); },
+            "setl" | "setlocal" => { self.set_options(arg.trim(), true); },
+            "mes" | "messages" => match arg.trim() {
+                "clear" => self.messages.clear(),
+                _ => {
+                    self.views.focus_title("Messages");
+                    self.mode = Mode::overview();
+                },
+            },
+            "so" | "source" => {
+                if let Err(e) = self.source(Path::new(arg.trim())) {
+                    self.error(format!("{}: {}", arg.trim(), e));
+                }
+            },
+            // A range alone moves to its last line
+            "" if arg.is_empty() => {
+                if let Some(range) = range {
+                    self.push_jump();
+                    self.go_to_line(range.last);
+                }
+            },
+            _ => { self.error(format!("Not an editor command: {}", cmd)); },
+        }
+        Ok(())
+    }
+    /// Writes buffer `idx` to its file, even when it is read-only if `force`. Returns
+    /// whether it was written.
+    fn write_buf(&mut self, idx: usize, force: bool) -> bool {
+        if force {
+            self.buf[idx].readonly = false;
+        }
+        if self.buf[idx].stdout {
+            match self.buf[idx].encode() {
+                Ok(bytes) => {
+                    self.output = Some(bytes);
+                    self.buf[idx].modified = false;
+                    self.info("Written to standard output on exit");
+                    return true;
+                },
+                Err(e) => {
+                    self.error(e);
+                    return false;
+                },
+            }
+        }
+        match self.buf[idx].save() {
+            Ok(()) => {
+                self.request_diff(idx);
+                self.info(format!("Wrote {}", self.buf[idx].name()));
+                true
+            },
+            Err(e) => {
+                self.error(format!("{}: {}", self.buf[idx].name(), e));
+                false
+            },
+        }
+    }
+    /// Writes every buffer with changes, returning whether all of them were written
+    fn write_all(&mut self) -> bool {
+        let modified: Vec<usize> = (0..self.buf.len()).filter(|i| self.buf[*i].modified).collect();
+        // Every buffer gets its try, whether or not the ones before it were written
+        modified.into_iter().filter(|idx| !self.write_buf(*idx, false)).count() == 0
+    }
+    /// Quits, unless a buffer has changes which aren't written
+    fn quit_if_written(&mut self) {
+        match self.buf.iter().find(|b| b.modified) {
+            Some(b) => {
+                let name = b.name();
+                self.error(format!("No write since last change for {} (add ! to override)", name));
+            },
+            None => self.quit = true,
+        }
+    }
+    /// Runs the commands in the file at `path`, one per line. Blank lines and lines
+    /// starting with `"` are skipped.
+    pub fn source(&mut self, path: &Path) -> LxResult<()> {
+        let text = fs::read_to_string(path)?;
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('"')) {
+            self.run_cmd(line.trim_start_matches(':'))?;
         }
         Ok(())
     }
     /// STEP 1
         /// Step 1.1: Check if prev keypress triggered prefix
-    fn match_key_event(&mut self, kv: KeyEvent) -> LxResult<()> {
-        let prev_key = *self.prev_keys.last()
-            .unwrap_or(&KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') });
-
-        if let Some(prefix) = Prefix::match_global_key(prev_key) {
+    pub fn match_key_event(&mut self, kv: KeyEvent) -> LxResult<()> {
+        self.message = None;
+        let inserting = matches!(self.mode, Mode::Insert(_));
+        if !self.prompts.is_empty() {
+            self.match_prompt_key_event(kv);
+        } else if self.finder.is_some() {
+            self.match_finder_key_event(kv);
+        } else if let Some(prefix) = self.prefix.take() {
             self.match_prefix_key_event(prefix, kv)?;
 
+        } else if self.edit_keys_first(kv) {
+            self.match_edit_key_event(kv)?;
         } else if let Some(prefix) = Prefix::match_global_key(kv) {
             self.prefix = Some(prefix);
 
+        } else if matches!(self.mode, Mode::Overview(_)) && self.match_overview_key_event(kv)? {
+            // Overview views get the first look at keys, so they can act on <CR>
         } else if let Some(mode) = self.mode.match_key(kv) {
             self.mode_switch(mode)?;
         } else {
@@ -102,165 +976,140 @@ impl Lx<CrosstermBackend<Stdout>> {
                 Mode::Insert(_ctx) => {self.match_insert_key_event(kv)?;}
                 Mode::Edit(_ctx) => {self.match_edit_key_event(kv)?;}
                 Mode::Command(_ctx) => {self.match_command_key_event(kv)?;}
-                Mode::Overview(_ctx) => { self.match_overview_key_event(kv)?; }
+                Mode::Overview(_ctx) => {}
             }
         }
-        self.prev_keys.push(kv);
-        if self.quit {
-            self.exit()?;
+        if inserting && !matches!(self.mode, Mode::Insert(_)) {
+            if let Some(change) = self.changes.end_insert().cloned() {
+                self.repeat_insert(&change)?;
+            }
+        }
+        if !self.replaying {
+            self.prev_keys.push(kv);
         }
         Ok(())
     }
-    fn mode_switch(&mut self, mode: Mode) -> LxResult<()> {
+    /// Keys which Edit mode sees before the global prefixes: the rest of a pending key
+    /// sequence, and `<C-f>` which pages forward there rather than opening the finder
+    fn edit_keys_first(&self, kv: KeyEvent) -> bool {
+        match &self.mode {
+            Mode::Edit(em) => em.is_pending()
+                || kv == KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('f') },
+            _ => false,
+        }
+    }
+    pub fn mode_switch(&mut self, mode: Mode) -> LxResult<()> {
         if self.mode != mode {
-            match self.mode {
-                Mode::Insert(_) | Mode::Edit(_) => {
-                    execute!(self.term.backend_mut(),
-                        cursor::SavePosition)?;
-                },
-                _ => {  }
-            }
-            match mode {
-                Mode::Insert(_) => {
-                    execute!(self.term.backend_mut(),
-                        cursor::RestorePosition,
-                        cursor::SetCursorShape(CursorShape::Line),
-                    )?;
-                },
-                Mode::Command(_) => {
-                    execute!(self.term.backend_mut(),
-                        cursor::MoveToRow(terminal::size()?.1-1),
-                        cursor::SetCursorShape(CursorShape::Line),)?;
-                },
-                Mode::Edit(_) => {
-                    execute!(self.term.backend_mut(),
-                        cursor::SetCursorShape(CursorShape::Block),
-                        cursor::RestorePosition)?;
-                },
-                Mode::Overview(_) => {
-                    execute!(self.term.backend_mut(),
-                        cursor::SetCursorShape(CursorShape::Block))?;
-                }
-            }
+            let leaving_insert = matches!(self.mode, Mode::Insert(_));
             self.mode = mode;
+            // The cursor can't stay past the end of the line outside Insert mode
+            if leaving_insert {
+                let at = self.cursor();
+                self.set_cursor(Cursor::new(at.line, at.col.saturating_sub(1)));
+            } else {
+                self.set_cursor(self.cursor());
+            }
         }
         Ok(())
     }
-    fn write_char(&mut self, ch: char) {
-        self.buf[self.buf_idx].push(ch);
-    }
     fn write_cmd_char(&mut self, ch: char) {
         self.cmd_buf.push(ch);
     }
 
-    fn match_event(&mut self, ev: Event) -> LxResult<()> {
-        match ev {
-            Event::Key(ke) => {self.match_key_event(ke)?;  },
-            Event::Resize(x,y) => {
-            } ,
-            _ => {},
-        }
-        Ok(())
-    }
-    fn match_leader_event(&mut self, ke: KeyEvent) -> CTResult<()> {
-        if poll(Duration::from_millis(1_000))? {
-            match ke {
-                KeyEvent { code: KeyCode::Enter,.. } => {
-                },
-                KeyEvent { code: KeyCode::Char(c), .. }  => match c {
-                    '1'..='9' => { self.switch_buf(c as usize)?; },
-                    'b' => { self.prefix = Some(Prefix::buffer()) },
-                    'f' => { self.prefix = Some(Prefix::find()) },
-                    'w' => { self.prefix = Some(Prefix::window()) },
-                    '/' => { self.prefix = Some(Prefix::search()) },
-                    't' => { self.prefix = Some(Prefix::tab()) },
-                    _ =>  {}
-
-                }
-                KeyEvent { code, modifiers } => {
+    /// Keys following the leader either act directly or chain into another prefix
+    fn match_leader_event(&mut self, ke: KeyEvent) {
+        if let KeyEvent { code: KeyCode::Char(c), .. } = ke {
+            match c {
+                '1'..='9' => { self.switch_buf(c as usize - '1' as usize); },
+                'b' => { self.prefix = Some(Prefix::buffer()) },
+                'f' => { self.prefix = Some(Prefix::find()) },
+                'w' => { self.prefix = Some(Prefix::window()) },
+                '/' => { self.prefix = Some(Prefix::search()) },
+                't' => { self.prefix = Some(Prefix::tab()) },
+                _ =>  {}
 
-                },
             }
-        } else {
         }
-        self.prefix = None;
-        Ok(())
     }
 
-    fn match_insert_ctrl(&mut self, code: KeyCode) -> CTResult<()> {
-        // println!("CTRL PRESSED");
-        match code {
-            KeyCode::Char('h') => {
-                execute!(self.term.backend_mut(), cursor::MoveLeft(1))?;
-                self.pos.0 -= 1;
-            }
-            KeyCode::Char('j') => {
-                execute!(self.term.backend_mut(), cursor::MoveDown(1))?;
-                self.pos.1 -= 1;
-            }
-            KeyCode::Char('k') => {
-                execute!(self.term.backend_mut(), cursor::MoveUp(1))?;
-                self.pos.1 += 1;
-            }
-            KeyCode::Char('l') => {
-                execute!(self.term.backend_mut(), cursor::MoveRight(1))?;
-                self.pos.0 += 1;
-            }
+    fn match_insert_ctrl(&mut self, code: KeyCode) -> LxResult<()> {
+        self.changes.end_insert();
+        let op = match code {
+            KeyCode::Char('h') => MotionOp::Left(1),
+            KeyCode::Char('j') => MotionOp::Down(1),
+            KeyCode::Char('k') => MotionOp::Up(1),
+            KeyCode::Char('l') => MotionOp::Right(1),
             KeyCode::Char('q') => {
                 self.quit = true;
+                return Ok(());
             }
-            _ => {}
-        }
-        execute!(self.term.backend_mut(), cursor::SavePosition)?;
-        Ok(())
-    }
-    fn match_shift(&mut self, code: KeyCode) -> CTResult<()> {
-        match code {
-            KeyCode::Char('h') => {execute!(self.term.backend_mut(), cursor::MoveLeft(1))?;}
-            KeyCode::Char('j') => {execute!(self.term.backend_mut(), cursor::MoveDown(1))?;}
-            KeyCode::Char('k') => {execute!(self.term.backend_mut(), cursor::MoveUp(1))?;}
-            KeyCode::Char('l') => {execute!(self.term.backend_mut(), cursor::MoveRight(1))?;}
-            _ => {}
-        }
-        Ok(())
+            _ => return Ok(()),
+        };
+        op.exec_app(self)
     }
 
-    fn match_insert_key_event(&mut self, kv: KeyEvent, ) -> CTResult<()> {
-        match kv {
-            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::PageUp } => {
-                execute!(self.term.backend_mut(), terminal::ScrollUp(3))?;
-            },
-            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::PageDown } => {
-                execute!(self.term.backend_mut(), terminal::ScrollDown(3))?;
-            },
+    fn match_insert_key_event(&mut self, kv: KeyEvent, ) -> LxResult<()> {
+        let op = match kv {
             KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') } => {
                 self.quit = true;
+                return Ok(());
             },
             KeyEvent { modifiers: KeyModifiers::CONTROL, code } => {
-                self.match_insert_ctrl(code)?;
-            },
-            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Backspace } => {
-                self.buf[self.buf_idx].pop();
-                execute!(self.term.backend_mut(), cursor::MoveLeft(1))?;
+                return self.match_insert_ctrl(code);
             },
-            KeyEvent { code: KeyCode::Char(c), .. } => self.write_char(c),
+            KeyEvent { code: KeyCode::PageUp, .. } => Op::Scroll(ScrollOp::Page(Direction::Prev, 1)),
+            KeyEvent { code: KeyCode::PageDown, .. } => Op::Scroll(ScrollOp::Page(Direction::Next, 1)),
+            KeyEvent { code: KeyCode::Left, .. } => Op::Motion(MotionOp::Left(1)),
+            KeyEvent { code: KeyCode::Right, .. } => Op::Motion(MotionOp::Right(1)),
+            KeyEvent { code: KeyCode::Up, .. } => Op::Motion(MotionOp::Up(1)),
+            KeyEvent { code: KeyCode::Down, .. } => Op::Motion(MotionOp::Down(1)),
+            KeyEvent { code: KeyCode::Home, .. } => Op::Motion(MotionOp::LineEdge(Direction::Prev)),
+            KeyEvent { code: KeyCode::End, .. } => Op::Motion(MotionOp::LineEdge(Direction::Next)),
+            KeyEvent { code: KeyCode::Backspace, .. } => Op::Insert(InsertOp::Backspace(1)),
+            KeyEvent { code: KeyCode::Delete, .. } => Op::Insert(InsertOp::Delete(1)),
+            KeyEvent { code: KeyCode::Enter, .. } => Op::Insert(InsertOp::Return(1)),
+            KeyEvent { code: KeyCode::Tab, .. } => Op::Insert(InsertOp::InsertChar('\t')),
+            KeyEvent { code: KeyCode::Char(c), .. } => Op::Insert(InsertOp::InsertChar(c)),
             /* KeyEvent { modifiers: KeyModifiers::SHIFT, code } => {
                 self.match_shift(code)?;
             }, */
-            KeyEvent { code, .. } => { self.match_key_code(code)?; },
+            KeyEvent { code, .. } => {
+                self.match_key_code(code)?;
+                return Ok(());
+            },
+        };
+        match &op {
+            Op::Insert(insert) => self.changes.insert(insert.clone()),
+            // Moving starts a new change, so `.` repeats what was typed after the move
+            _ => { self.changes.end_insert(); },
         }
-        Ok(())
+        op.exec_app(self)
     }
-    fn match_overview_key_event(&mut self, kv: KeyEvent, ) -> CTResult<()> {
-        match kv {
-            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') } => {
-                self.quit = true;
+    /// Cycles views with the navigation keys, and otherwise hands the key to the focused
+    /// view. Returns whether the key was consumed.
+    fn match_overview_key_event(&mut self, kv: KeyEvent, ) -> LxResult<bool> {
+        if let KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') } = kv {
+            self.quit = true;
+            return Ok(true);
+        }
+        if self.keymaps.navigation.next.matches(&kv) {
+            self.views.next();
+            return Ok(true);
+        } else if self.keymaps.navigation.previous.matches(&kv) {
+            self.views.prev();
+            return Ok(true);
+        }
+        let mut views = std::mem::take(&mut self.views);
+        let op = views.focused_mut()
+            .and_then(|view| view.handle_key(&self.view_ctx(), kv));
+        self.views = views;
+        match op {
+            Some(op) => {
+                op.exec_app(self)?;
+                Ok(true)
             },
-            KeyEvent { modifiers: KeyModifiers::CONTROL, code } => {  },
-            KeyEvent { modifiers: KeyModifiers::SHIFT, code } => {  },
-            _ => {}
+            None => Ok(false),
         }
-        Ok(())
     }
     fn match_command_key_event(&mut self, kv: KeyEvent, ) -> LxResult<()> {
         match kv {
@@ -284,77 +1133,119 @@ impl Lx<CrosstermBackend<Stdout>> {
         }
         Ok(())
     }
-    fn match_edit_key_event(&mut self, kv: KeyEvent, ) -> CTResult<()> {
-        let leader = KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char(' ') };
-        if self.prev_keys.last() == Some(&leader) {
-            self.match_leader_event(kv)?;
-        } else {
-            match kv {
-                KeyEvent { modifiers: KeyModifiers::CONTROL, code } => match code {
-                    KeyCode::Char('q') => { self.quit = true; },
-                    _ => {  }
-                },
-                KeyEvent { modifiers: KeyModifiers::NONE, code } => match code {
-                    KeyCode::Char('q') => { self.close_buf()?; },
-                    KeyCode::Char('c') => { self.create_buf()?; },
-                    KeyCode::Char('n') => { execute!(self.term.backend_mut(), cursor::MoveToNextLine(1))?; },
-                    KeyCode::Char('p') => { execute!(self.term.backend_mut(), cursor::MoveToPreviousLine(1))?; },
-                    KeyCode::Char('j') | KeyCode::Down  => { execute!(self.term.backend_mut(), cursor::MoveDown(1))?; },
-                    KeyCode::Char('h') | KeyCode::Left => { execute!(self.term.backend_mut(), cursor::MoveLeft(1))?; },
-                    KeyCode::Char('k') | KeyCode::Up => { execute!(self.term.backend_mut(), cursor::MoveUp(1))?; },
-                    KeyCode::Char('l') | KeyCode::Right => { execute!(self.term.backend_mut(), cursor::MoveRight(1))?; },
-                    KeyCode::PageUp => { execute!(self.term.backend_mut(), terminal::ScrollUp(3))?; },
-                    KeyCode::PageDown => { execute!(self.term.backend_mut(), terminal::ScrollDown(3))?; },
-                    _ => {  }
-                }
-                KeyEvent { modifiers, code } => {}
-
-            }
+    fn match_edit_key_event(&mut self, kv: KeyEvent, ) -> LxResult<()> {
+        if let KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') } = kv {
+            self.quit = true;
+            return Ok(());
+        }
+        let op = match &mut self.mode {
+            // `q` on its own ends a recording
+            Mode::Edit(em) if self.recording.is_some() && !em.is_pending() && kv == KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE) => {
+                self.stop_recording();
+                return Ok(());
+            },
+            Mode::Edit(em) => em.feed(kv),
+            _ => None,
+        };
+        match op {
+            Some(Op::Edit(edit)) if edit.changes() => self.make_change(edit),
+            Some(op) => op.exec_app(self),
+            None => Ok(()),
         }
-        Ok(())
     }
-    fn match_prefix_key_event(&mut self, prefix: Prefix, kv: KeyEvent) -> CTResult<()> {
-        match prefix {
-            Prefix::Leader(_) => {  },
-            _ => { }
+    /// Keys typed while a prompt is shown: the key of a choice answers it, `<Esc>` dismisses it.
+    /// A prompt for text takes what is typed until `<CR>` answers it with the text.
+    fn match_prompt_key_event(&mut self, kv: KeyEvent) {
+        if let Some(input) = self.prompts[0].input.as_mut() {
+            let answer = match kv.code {
+                KeyCode::Esc => None,
+                KeyCode::Enter => Some(input.clone()),
+                KeyCode::Backspace => { input.pop(); return; },
+                KeyCode::Char(c) if !kv.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                    input.push(c);
+                    return;
+                },
+                _ => return,
+            };
+            let prompt = self.prompts.remove(0);
+            return self.answer_prompt(prompt, answer.as_deref());
         }
-        Ok(())
+        let choice = match kv.code {
+            KeyCode::Esc => None,
+            KeyCode::Char(c) => match self.prompts[0].choice(c) {
+                Some(choice) => Some(choice),
+                None => return,
+            },
+            _ => return,
+        };
+        let prompt = self.prompts.remove(0);
+        self.answer_prompt(prompt, choice);
     }
-
-    fn match_key_code(&mut self, kc: KeyCode) -> CTResult<()> {
-        match kc {
-            KeyCode::Char('q') => { self.quit = true; }
-            KeyCode::Esc => {},
+    /// Keys typed while the finder is open: text edits the query, `<C-n>`/`<C-p>` or arrows
+    /// move, `<Tab>` marks, `<CR>` opens, `<C-s>`/`<C-v>`/`<C-t>` open in splits or tabs.
+    fn match_finder_key_event(&mut self, kv: KeyEvent) {
+        let finder = match self.finder.as_mut() {
+            Some(finder) => finder,
+            None => return,
+        };
+        match kv {
+            KeyEvent { code: KeyCode::Esc, .. } |
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('c') } => { self.finder = None; },
+            KeyEvent { code: KeyCode::Enter, .. } => { self.finder_accept(OpenIn::Current); },
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('s') } => {
+                self.finder_accept(OpenIn::Split(SplitDir::Horizontal));
+            },
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('v') } => {
+                self.finder_accept(OpenIn::Split(SplitDir::Vertical));
+            },
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('t') } => { self.finder_accept(OpenIn::Tab); },
+            KeyEvent { code: KeyCode::Down, .. } |
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('n') } |
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('j') } => { finder.next(); },
+            KeyEvent { code: KeyCode::Up, .. } |
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('p') } |
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('k') } => { finder.prev(); },
+            KeyEvent { code: KeyCode::Tab, .. } => { finder.toggle_mark(); },
+            KeyEvent { code: KeyCode::Backspace, .. } => { finder.pop(); },
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Char(c) } |
+            KeyEvent { modifiers: KeyModifiers::SHIFT, code: KeyCode::Char(c) } => { finder.push(c); },
             _ => {}
         }
-        // println!("Cursor at : {:?}", position());
-        Ok(())
-    }
-    fn create_buf(&mut self) -> CTResult<()> {
-        self.buf.push(String::new());
-        self.buf_idx = self.buf.len();
-        Ok(())
     }
-    fn close_buf(&mut self) -> CTResult<()> {
-        self.buf.remove(self.buf_idx);
-        if self.buf.len() == 0 {
-             self.quit = true;
-        } else {
-            self.buf_idx -= 1;
+    fn match_prefix_key_event(&mut self, prefix: Prefix, kv: KeyEvent) -> LxResult<()> {
+        if let Prefix::Leader(_) = prefix {
+            self.match_leader_event(kv);
+            return Ok(());
+        }
+        if let Prefix::Find(_) = prefix {
+            if let Some(op) = <FindPre as GlobalPrefixKey>::match_key_op(kv) {
+                op.exec_app(self)?;
+            }
+            return Ok(());
+        }
+        if let (Prefix::Window(_), KeyCode::Char(c)) = (prefix, kv.code) {
+            let tab = &mut self.tabs[self.tab_idx];
+            match c {
+                'w' | 'l' | 'j' => { tab.focus_next(); },
+                'W' | 'h' | 'k' => { tab.focus_prev(); },
+                'q' | 'c' => { tab.close_window(); },
+                's' => {
+                    let idx = tab.window().buf_idx;
+                    tab.split(SplitDir::Horizontal, idx);
+                },
+                'v' => {
+                    let idx = tab.window().buf_idx;
+                    tab.split(SplitDir::Vertical, idx);
+                },
+                _ => {}
+            }
         }
         Ok(())
     }
-    fn switch_buf(&mut self, idx: usize) -> CTResult<()> {
-        self.buf_idx = idx;
-        Ok(())
-    }
-    pub fn exit(&mut self, ) -> CTResult<()> {
-        execute!(self.term.backend_mut(),
-            event::DisableMouseCapture,
-            terminal::LeaveAlternateScreen,
-            terminal::Clear(ClearType::All),
-            )?;
-        terminal::disable_raw_mode()?;
+    fn match_key_code(&mut self, kc: KeyCode) -> LxResult<()> {
+        if let KeyCode::Char('q') = kc {
+            self.quit = true;
+        }
         Ok(())
     }
 }
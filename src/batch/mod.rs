@@ -0,0 +1,150 @@
+//! Batch mode, for scripts: commands run over files without a terminal, as in
+//! `lx --batch -c 'g/TODO/d' -c wq *.rs`. Nothing is drawn, and errors go to stderr.
+
+use std::{io::{self, Write}, path::Path};
+use tui::{backend::Backend, buffer::Cell, layout::Rect};
+
+use crate::{Lx, cli::{Args, Layout}, types::Level, vcs::Differ, watch::Watcher};
+
+/// A backend which draws nowhere, for an editor without a screen
+#[derive(Debug, Default)]
+pub struct Headless;
+
+impl Backend for Headless {
+    fn draw<'a, I>(&mut self, _content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>
+    {
+        Ok(())
+    }
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn show_cursor(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        Ok((0, 0))
+    }
+    fn set_cursor(&mut self, _x: u16, _y: u16) -> io::Result<()> {
+        Ok(())
+    }
+    fn clear(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn size(&self) -> io::Result<Rect> {
+        Ok(Rect::new(0, 0, 80, 24))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the commands of `args` on each of its files in turn, in an editor of its own, or
+/// once on an empty buffer when there are none. The config file only runs when given
+/// with `--config`. Returns whether every file went without an error.
+pub fn run(args: &Args) -> bool {
+    let files: Vec<Option<&Path>> = if args.files.is_empty() {
+        vec![None]
+    } else {
+        args.files.iter().map(|f| Some(f.as_path())).collect()
+    };
+    // A file failing doesn't stop the others
+    files.into_iter().filter(|file| !edit(args, *file)).count() == 0
+}
+
+/// Writes the errors shown since the last call to stderr, returning whether there were none
+fn report(lx: &mut Lx<Headless>, name: &str) -> bool {
+    let mut ok = true;
+    for message in lx.messages.drain(..).filter(|m| m.level == Level::Error) {
+        eprintln!("{}: {}", name, message.text);
+        ok = false;
+    }
+    ok
+}
+
+/// Runs the commands on one file. Changes the commands don't write are thrown away, and
+/// the first command to fail skips the rest.
+fn edit(args: &Args, file: Option<&Path>) -> bool {
+    let name = file.as_ref().map_or_else(|| "lx".to_string(), |f| f.display().to_string());
+    let mut lx = match Lx::new_with_backend(Headless) {
+        Ok(lx) => lx,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            return false;
+        },
+    };
+    // Grammars or themes which fail to load don't matter without a screen
+    lx.messages.clear();
+    // Nor do signs or files changing while the commands run
+    lx.vcs = Differ::off();
+    lx.watcher = Watcher::off();
+    let setup = Args {
+        files: file.into_iter().map(Path::to_path_buf).collect(),
+        layout: Layout::Buffers,
+        clean: args.clean || args.config.is_none(),
+        commands: Vec::new(),
+        ..args.clone()
+    };
+    let started = lx.start(setup);
+    if let Err(e) = started {
+        eprintln!("{}: {}", name, e);
+        return false;
+    }
+    if !report(&mut lx, &name) {
+        return false;
+    }
+    // Nobody is there to answer
+    if let Some(prompt) = lx.prompts.first() {
+        eprintln!("{}: {}", name, prompt.question);
+        return false;
+    }
+    for cmd in &args.commands {
+        if lx.quit {
+            break;
+        }
+        if let Err(e) = lx.run_cmd(cmd) {
+            eprintln!("{}: {}", name, e);
+            return false;
+        }
+        if !report(&mut lx, &name) {
+            return false;
+        }
+    }
+    match lx.output {
+        Some(bytes) => match io::stdout().write_all(&bytes) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                false
+            },
+        },
+        None if args.filter => {
+            eprintln!("{}: Nothing written to standard output", name);
+            false
+        },
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use super::*;
+    use crate::tests::TempDir;
+
+    #[test]
+    fn files_without_matches_go_on_to_later_commands() {
+        let dir = TempDir::new("batch");
+        let (f, g) = (dir.file("f.rs", "a\n// TODO b\nc\n"), dir.file("g.rs", "x\ny\n"));
+        let args = Args {
+            files: vec![f.clone(), g.clone()],
+            commands: vec!["g/TODO/d".to_string(), "%s/y/z/".to_string(), "wq".to_string()],
+            batch: true,
+            ..Args::default()
+        };
+        assert!(run(&args));
+        assert_eq!(fs::read_to_string(&f).unwrap(), "a\nc\n");
+        assert_eq!(fs::read_to_string(&g).unwrap(), "x\nz\n");
+    }
+}
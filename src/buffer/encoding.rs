@@ -0,0 +1,146 @@
+use std::{convert::TryFrom, io};
+
+use super::grapheme;
+use crate::options::{Encoding, FileFormat};
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF16LE_BOM: &[u8] = b"\xff\xfe";
+const UTF16BE_BOM: &[u8] = b"\xfe\xff";
+
+/// Whether `bytes` look like UTF-16 without a byte order mark: ASCII text has a zero in
+/// every other byte. Gives the encoding when most of the high or low bytes are zero.
+fn utf16_without_bom(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 2 || bytes.len() % 2 != 0 {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let zeros = |offset: usize| bytes.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+    let (even, odd) = (zeros(0), zeros(1));
+    if odd * 10 >= pairs * 7 && even == 0 {
+        Some(Encoding::Utf16Le)
+    } else if even * 10 >= pairs * 7 && odd == 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Whether `bytes`, which aren't valid UTF-8, read as Latin-1 text: no NULs and none of
+/// the C1 control bytes, which text in other encodings is full of
+fn is_latin1(bytes: &[u8]) -> bool {
+    !bytes.iter().any(|b| *b == 0 || (0x80..=0x9f).contains(b))
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|c| from_bytes([c[0], c[1]]));
+    char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+}
+
+/// Decodes file contents, working out their encoding from a byte order mark or the bytes
+/// themselves, and gives whether there was a byte order mark. Text that is neither UTF-16
+/// nor Latin-1 is read as UTF-8, keeping invalid bytes as raw byte chars.
+pub fn decode(bytes: &[u8]) -> (String, Encoding, bool) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (grapheme::decode(rest), Encoding::Utf8Bom, true);
+    }
+    let utf16 = match (bytes.strip_prefix(UTF16LE_BOM), bytes.strip_prefix(UTF16BE_BOM)) {
+        (Some(rest), _) => Some((rest, Encoding::Utf16Le, true)),
+        (_, Some(rest)) => Some((rest, Encoding::Utf16Be, true)),
+        _ => utf16_without_bom(bytes).map(|enc| (bytes, enc, false)),
+    };
+    match utf16 {
+        Some((rest, Encoding::Utf16Be, bom)) => (decode_utf16(rest, u16::from_be_bytes), Encoding::Utf16Be, bom),
+        Some((rest, _, bom)) => (decode_utf16(rest, u16::from_le_bytes), Encoding::Utf16Le, bom),
+        None if std::str::from_utf8(bytes).is_err() && is_latin1(bytes) => {
+            (bytes.iter().map(|b| *b as char).collect(), Encoding::Latin1, false)
+        },
+        None => (grapheme::decode(bytes), Encoding::Utf8, false),
+    }
+}
+
+/// Encodes text for writing to a file. UTF-16 files get a byte order mark if `bom` says
+/// so, as when the file had one; `utf-8-bom` always has one. Fails on chars Latin-1 has no
+/// byte for.
+pub fn encode(text: &str, encoding: Encoding, bom: bool) -> io::Result<Vec<u8>> {
+    let mark = |mark: &'static [u8]| if bom { mark } else { &[] };
+    Ok(match encoding {
+        Encoding::Utf8 => grapheme::encode(text),
+        Encoding::Utf8Bom => [UTF8_BOM, &grapheme::encode(text)].concat(),
+        Encoding::Utf16Le => mark(UTF16LE_BOM).iter().copied()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect(),
+        Encoding::Utf16Be => mark(UTF16BE_BOM).iter().copied()
+            .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+            .collect(),
+        Encoding::Latin1 => text.chars()
+            .map(|c| grapheme::raw_byte(c).or_else(|| u8::try_from(c as u32).ok()).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData, format!("Can't write U+{:04X} in latin1", c as u32))))
+            .collect::<io::Result<Vec<u8>>>()?,
+    })
+}
+
+/// The line ending most lines of `text` end with, if any end at all
+pub fn line_endings(text: &str) -> Option<FileFormat> {
+    let bytes = text.as_bytes();
+    let crlf = text.matches("\r\n").count();
+    let lf = bytes.iter().filter(|b| **b == b'\n').count() - crlf;
+    let cr = bytes.iter().filter(|b| **b == b'\r').count() - crlf;
+    match (crlf, lf, cr) {
+        (0, 0, 0) => None,
+        _ if crlf >= lf && crlf >= cr => Some(FileFormat::Dos),
+        _ if cr > lf => Some(FileFormat::Mac),
+        _ => Some(FileFormat::Unix),
+    }
+}
+
+/// Turns the line endings of `format` in `text` into `\n`. Other line endings are left as
+/// they are, so they show as stray control chars.
+pub fn normalize(text: String, format: FileFormat) -> String {
+    match format {
+        FileFormat::Unix => text,
+        FileFormat::Dos => text.replace("\r\n", "\n"),
+        FileFormat::Mac => text.replace('\r', "\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings_round_trip() {
+        let text = "héllo wörld\nline two";
+        let encodings = [(Encoding::Utf8, false), (Encoding::Utf8Bom, true), (Encoding::Utf16Le, true),
+            (Encoding::Utf16Be, true), (Encoding::Latin1, false)];
+        for (enc, bom) in encodings.iter() {
+            let bytes = encode(text, *enc, *bom).unwrap();
+            assert_eq!(decode(&bytes), (text.to_string(), *enc, *bom), "{:?}", enc);
+        }
+        assert!(encode("€", Encoding::Latin1, false).is_err());
+    }
+
+    #[test]
+    fn utf16_without_bom_and_invalid_utf8() {
+        let le: Vec<u8> = "plain ascii".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        assert_eq!(decode(&le), ("plain ascii".to_string(), Encoding::Utf16Le, false));
+        // Written back without the byte order mark it didn't have
+        for enc in [Encoding::Utf16Le, Encoding::Utf16Be].iter() {
+            let bytes = encode("plain ascii\n", *enc, false).unwrap();
+            assert_eq!(bytes.len(), 24);
+            assert_eq!(decode(&bytes), ("plain ascii\n".to_string(), *enc, false), "{:?}", enc);
+        }
+        // C1 bytes rule out Latin-1, so the bytes are kept as they are
+        let (text, enc, _) = decode(b"ok\x81\xff");
+        assert_eq!(enc, Encoding::Utf8);
+        assert_eq!(grapheme::encode(&text), b"ok\x81\xff");
+    }
+
+    #[test]
+    fn line_ending_detection() {
+        assert_eq!(line_endings("a\r\nb\r\nc"), Some(FileFormat::Dos));
+        assert_eq!(line_endings("a\rb\rc\n"), Some(FileFormat::Mac));
+        assert_eq!(line_endings("a\nb\r\nc\n"), Some(FileFormat::Unix));
+        assert_eq!(line_endings("one line"), None);
+        assert_eq!(normalize("a\r\nb\r".into(), FileFormat::Dos), "a\nb\r");
+    }
+}
@@ -0,0 +1,150 @@
+use std::str;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Bytes of a file which aren't valid UTF-8 are kept in the text as chars from this private
+/// use range, so they can be shown and written back unchanged
+const RAW_BYTE: u32 = 0x10FF00;
+
+/// Decodes file contents as UTF-8, keeping invalid bytes as raw byte chars
+pub fn decode(mut bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    loop {
+        match str::from_utf8(bytes) {
+            Ok(valid) => {
+                text.push_str(valid);
+                return text;
+            },
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                text.push_str(str::from_utf8(valid).unwrap_or_default());
+                let bad = e.error_len().unwrap_or(rest.len());
+                text.extend(rest[..bad].iter().map(|b| raw_char(*b)));
+                bytes = &rest[bad..];
+            },
+        }
+    }
+}
+
+/// Encodes text as UTF-8, turning raw byte chars back into the bytes they stand for
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut buf = [0; 4];
+    for c in text.chars() {
+        match raw_byte(c) {
+            Some(b) => bytes.push(b),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes()),
+        }
+    }
+    bytes
+}
+
+fn raw_char(b: u8) -> char {
+    char::from_u32(RAW_BYTE + b as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// The invalid byte `c` stands for, if it is a raw byte char
+pub fn raw_byte(c: char) -> Option<u8> {
+    (c as u32).checked_sub(RAW_BYTE).map(|b| b as u8)
+}
+
+/// What is drawn instead of grapheme `g` at display column `vcol`, if it can't be drawn as
+/// it is: tabs expand to spaces up to the next tab stop, control chars show as `^X` and
+/// invalid bytes and graphemes with no width as hex codes in angle brackets
+pub fn replacement(g: &str, vcol: usize, tabstop: usize) -> Option<String> {
+    let c = g.chars().next()?;
+    if let Some(b) = raw_byte(c) {
+        return Some(format!("<{:02x}>", b));
+    }
+    match c {
+        '\t' => Some(" ".repeat(tabstop.max(1) - vcol % tabstop.max(1))),
+        '\x7f' => Some("^?".to_string()),
+        '\0'..='\x1f' => Some(format!("^{}", (c as u8 + b'@') as char)),
+        '\u{80}'..='\u{9f}' => Some(format!("<{:02x}>", c as u32)),
+        _ if g.width() == 0 => Some(g.chars().map(|c| format!("<{:04x}>", c as u32)).collect()),
+        _ => None,
+    }
+}
+
+/// Columns grapheme `g` takes when drawn at display column `vcol`
+pub fn width(g: &str, vcol: usize, tabstop: usize) -> usize {
+    match g.as_bytes() {
+        [b' '..=b'~'] => 1,
+        _ => replacement(g, vcol, tabstop).map_or_else(|| g.width(), |r| r.len()),
+    }
+}
+
+/// The graphemes of `line` with the display column each starts at
+pub fn columns(line: &str, tabstop: usize) -> impl Iterator<Item = (usize, &str)> {
+    line.graphemes(true).scan(0, move |vcol, g| {
+        let at = *vcol;
+        *vcol += width(g, at, tabstop);
+        Some((at, g))
+    })
+}
+
+/// Display column grapheme `col` of `line` starts at, or the line's width past its end
+pub fn display_col(line: &str, col: usize, tabstop: usize) -> usize {
+    columns(line, tabstop).take(col).fold(0, |_, (vcol, g)| vcol + width(g, vcol, tabstop))
+}
+
+/// Columns all of `line` takes
+pub fn line_width(line: &str, tabstop: usize) -> usize {
+    display_col(line, usize::MAX, tabstop)
+}
+
+/// Index of the grapheme of `line` drawn at display column `vcol`, or the number of
+/// graphemes past its end
+pub fn col_at_display(line: &str, vcol: usize, tabstop: usize) -> usize {
+    let mut col = 0;
+    for (start, g) in columns(line, tabstop) {
+        if vcol < start + width(g, start, tabstop) {
+            return col;
+        }
+        col += 1;
+    }
+    col
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_width_columns() {
+        // Wide CJK, a combining accent, an emoji ZWJ sequence and a tab
+        let line = "a漢e\u{301}👩\u{200d}💻\tz";
+        let graphemes: Vec<(usize, &str)> = columns(line, 4).collect();
+        assert_eq!(graphemes.len(), 6);
+        assert_eq!(graphemes.iter().map(|(v, _)| *v).collect::<Vec<_>>(), vec![0, 1, 3, 4, 8, 12]);
+        assert_eq!(graphemes[2].1, "e\u{301}");
+        assert_eq!(line_width(line, 4), 13);
+        assert_eq!(display_col(line, 2, 4), 3);
+        assert_eq!(col_at_display(line, 2, 4), 1);
+        assert_eq!(col_at_display(line, 10, 4), 4);
+        assert_eq!(col_at_display(line, 99, 4), 6);
+    }
+
+    #[test]
+    fn tabs_expand_to_next_stop() {
+        assert_eq!(width("\t", 0, 8), 8);
+        assert_eq!(width("\t", 5, 8), 3);
+        assert_eq!(width("\t", 8, 8), 8);
+        assert_eq!(line_width("ab\tc\t", 4), 8);
+    }
+
+    #[test]
+    fn control_chars_and_invalid_bytes_are_visible() {
+        assert_eq!(replacement("\x01", 0, 8).as_deref(), Some("^A"));
+        assert_eq!(replacement("\x7f", 0, 8).as_deref(), Some("^?"));
+        assert_eq!(replacement("\u{200b}", 0, 8).as_deref(), Some("<200b>"));
+        assert_eq!(replacement("é", 0, 8), None);
+        let bytes = b"ok\xff\xfe and \xe2\x82";
+        let text = decode(bytes);
+        assert_eq!(text.chars().filter(|c| raw_byte(*c).is_some()).count(), 4);
+        assert_eq!(encode(&text), bytes);
+        let g = text.graphemes(true).nth(2).unwrap_or_default();
+        assert_eq!(replacement(g, 0, 8).as_deref(), Some("<ff>"));
+        assert_eq!(line_width(&text, 8), 2 + 4 + 4 + 5 + 4 + 4);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::options::Indent;
+
+/// Lines looked at to detect how a file is indented
+const DETECT_LINES: usize = 1000;
+
+/// The blanks `line` starts with
+pub fn leading(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Columns the blanks `line` starts with take
+pub fn width(line: &str, tabstop: usize) -> usize {
+    leading(line).chars().fold(0, |w, c| if c == '\t' { w + tabstop - w % tabstop } else { w + 1 })
+}
+
+/// Works out how `text` is indented from its first lines, starting from the `global`
+/// settings. Gives `None` when no lines are indented. Lines indented with spaces vote for
+/// the difference in indent from the line before as the shift width; a file indenting
+/// with both tabs and spaces is taken to have tab stops every 8 columns.
+pub fn detect(text: &str, global: &Indent) -> Option<Indent> {
+    let (mut tabs, mut spaces) = (0, 0);
+    let mut deltas = [0; 9];
+    let mut prev = 0;
+    for line in text.lines().take(DETECT_LINES) {
+        let rest = line.trim_start_matches([' ', '\t']);
+        // Blank lines, and the ` * ` continuing a block comment, say nothing
+        if rest.is_empty() || rest.starts_with('*') {
+            continue;
+        }
+        let lead = leading(line);
+        if lead.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+        if !lead.is_empty() {
+            spaces += 1;
+        }
+        let delta = if lead.len() > prev { lead.len() - prev } else { prev - lead.len() };
+        if (2..=8).contains(&delta) {
+            deltas[delta] += 1;
+        }
+        prev = lead.len();
+    }
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+    // The most common step wins, the smaller one on a tie
+    let step = (2..=8).rev().max_by_key(|d| deltas[*d]).filter(|d| deltas[*d] > 0);
+    let mut indent = global.clone();
+    indent.expandtab = spaces > tabs;
+    match (step, indent.expandtab) {
+        (Some(step), true) => indent.shiftwidth = step,
+        (Some(step), false) => {
+            indent.shiftwidth = step;
+            indent.tabstop = 8;
+        },
+        (None, false) => indent.shiftwidth = 0,
+        (None, true) => {},
+    }
+    Some(indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(text: &str) -> Option<(bool, usize, usize)> {
+        detect(text, &Indent::default()).map(|i| (i.expandtab, i.shiftwidth, i.tabstop))
+    }
+
+    #[test]
+    fn leading_blanks() {
+        assert_eq!(leading(" \t x "), " \t ");
+        assert_eq!(width(" \t x", 4), 5);
+        assert_eq!(width("\t\tx", 8), 16);
+        assert_eq!(width("x", 8), 0);
+    }
+
+    #[test]
+    fn indentation_is_detected() {
+        assert_eq!(detected("a\nb\n"), None);
+        assert_eq!(detected("fn a() {\n    b\n    if c {\n        d\n    }\n}\n"), Some((true, 4, 8)));
+        assert_eq!(detected("a:\n  b:\n    c: 1\n  d: 2\n"), Some((true, 2, 8)));
+        assert_eq!(detected("a {\n\tb\n\t\tc\n}\n"), Some((false, 0, 8)));
+        // Spaces stepping by 4 between tab-indented lines, as in old C code
+        assert_eq!(detected("a\n    b\n\tc\n\t    d\n\te\n\tf\n"), Some((false, 4, 8)));
+        // Blank lines and block comments don't count
+        assert_eq!(detected("/**\n * doc\n */\nfn a() {\n\n  b\n}\n"), Some((true, 2, 8)));
+    }
+}
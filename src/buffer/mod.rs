@@ -0,0 +1,570 @@
+pub mod encoding;
+pub mod grapheme;
+pub mod indent;
+pub mod reflow;
+pub mod sign;
+
+use std::{fs, io, path::{Path, PathBuf}, str::Split, time::Instant};
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{error::LxConfigError, fold::Folds, mark::BufferMarks, options::{FileOptions, FoldOptions, Indent, Options}, swap, watch::Stamp, syntax::{Highlighter, grammar::IndentRules}, types::{Cursor, Direction}};
+pub use sign::Signs;
+
+/// Byte offset of column `col` in `line`, or the line's length past its end. Columns count
+/// grapheme clusters, so a letter and its combining accents are one column.
+pub fn col_offset(line: &str, col: usize) -> usize {
+    line.grapheme_indices(true).nth(col).map_or(line.len(), |(i, _)| i)
+}
+
+/// Column of the byte offset `offset` in `line`
+pub fn col_at(line: &str, offset: usize) -> usize {
+    line[..offset.min(line.len())].graphemes(true).count()
+}
+
+/// Text being edited, optionally backed by a file on disk.
+#[derive(Debug, Default, Clone)]
+pub struct Buffer {
+    pub text: String,
+    pub path: Option<PathBuf>,
+    pub modified: bool,
+    /// Bumped on every change to `text`, so derived state knows when it is stale
+    pub version: u64,
+    pub syntax: Option<Highlighter>,
+    /// Whether the file had no newline after its last line. Otherwise one is stripped on
+    /// open and added back on save.
+    pub no_eol: bool,
+    /// Whether the file started with a byte order mark, which a UTF-16 file is written
+    /// back with
+    pub bom: bool,
+    pub signs: Signs,
+    pub marks: BufferMarks,
+    pub folds: Folds,
+    /// Git branch of the work tree holding the file, if it is in one
+    pub branch: Option<String>,
+    /// Indent options of this buffer, when they differ from the global ones
+    pub indent: Option<Indent>,
+    pub file: FileOptions,
+    /// Fold options of this buffer, when they differ from the global ones
+    pub fold: Option<FoldOptions>,
+    /// Line width of this buffer for `gq`, when it differs from the global one
+    pub textwidth: Option<usize>,
+    /// Whether writing the file is refused, as when another editor has it open
+    pub readonly: bool,
+    /// Directory the unsaved text is journaled to, if it is journaled at all
+    pub swap_dir: Option<PathBuf>,
+    /// Version of the text last journaled to the buffer's swap file, if it has one
+    pub swapped: Option<u64>,
+    /// Version of the text last diffed against version control
+    pub diffed: Option<u64>,
+    /// The file as it was when last read or written
+    pub stamp: Option<Stamp>,
+    /// Whether writing the buffer sends it to standard output as the editor exits, as in
+    /// filter mode, rather than to a file
+    pub stdout: bool,
+}
+
+impl Buffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Reads a buffer from `path`, detecting the file's encoding and line endings, which it
+    /// is written back with. Otherwise `file` gives the file options. A path that doesn't
+    /// exist yet gives an empty buffer which will create the file when saved.
+    pub fn open(path: &Path, file: FileOptions) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let stamp = Stamp::of(path);
+        Ok(Self { path: Some(path.to_path_buf()), stamp, ..Self::from_bytes(&bytes, file) })
+    }
+    /// A buffer without a file holding `bytes`, as read from standard input, with their
+    /// encoding and line endings detected as `open` does
+    pub fn from_bytes(bytes: &[u8], mut file: FileOptions) -> Self {
+        let (mut text, mut bom) = (String::new(), false);
+        if !bytes.is_empty() {
+            let (decoded, enc, had_bom) = encoding::decode(bytes);
+            text = decoded;
+            bom = had_bom;
+            file.fileencoding = enc;
+        }
+        if let Some(format) = encoding::line_endings(&text) {
+            file.fileformat = format;
+            text = encoding::normalize(text, format);
+        }
+        let no_eol = !text.is_empty() && !text.ends_with('\n');
+        if !no_eol {
+            text.pop();
+        }
+        Self { text, no_eol, bom, file, ..Self::default() }
+    }
+    /// Reads the file again after something else changed it. Options given to the buffer
+    /// are kept, but the file's encoding and line endings are detected again.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let path = self.path.clone().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No file name"))?;
+        let file = Self::open(&path, self.file.clone())?;
+        let lines: Vec<String> = file.lines().map(String::from).collect();
+        self.replace_lines(0, self.line_count() - 1, &lines);
+        self.no_eol = file.no_eol;
+        self.bom = file.bom;
+        self.file = file.file;
+        self.stamp = file.stamp;
+        self.modified = false;
+        self.remove_swap()
+    }
+    /// Writes the buffer to its file as its file options say, first trimming trailing
+    /// blanks or adding the missing final line ending if they ask for it
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "No file name")),
+        };
+        if self.readonly {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "Buffer is read-only (add ! to override)"));
+        }
+        let bytes = self.encode()?;
+        fs::write(&path, bytes)?;
+        self.stamp = Stamp::of(&path);
+        self.modified = false;
+        self.remove_swap()
+    }
+    /// The buffer as it is written out, in its encoding and with its line endings, once
+    /// trailing blanks are trimmed or the missing final line ending added if its file
+    /// options ask for it
+    pub fn encode(&mut self) -> io::Result<Vec<u8>> {
+        if self.file.trimtrailing {
+            self.trim_trailing();
+        }
+        if self.file.fixendofline {
+            self.no_eol = false;
+        }
+        let eol = self.file.fileformat.eol();
+        let end = if self.no_eol || self.text.is_empty() { "" } else { eol };
+        encoding::encode(&format!("{}{}", self.text.replace('\n', eol), end), self.file.fileencoding, self.bom)
+    }
+    /// Journals the unsaved text to the buffer's swap file, when it changed since last time
+    pub fn journal(&mut self) -> io::Result<()> {
+        match (&self.swap_dir, &self.path) {
+            (Some(dir), Some(path)) if self.modified && !self.readonly && self.swapped != Some(self.version) => {
+                swap::write(dir, path, &self.text)?;
+                self.swapped = Some(self.version);
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+    /// Removes the buffer's swap file, once its changes are saved or thrown away
+    pub fn remove_swap(&mut self) -> io::Result<()> {
+        match (&self.swap_dir, &self.path, self.swapped.take()) {
+            (Some(dir), Some(path), Some(_)) => swap::remove(dir, path),
+            _ => Ok(()),
+        }
+    }
+    /// Removes blanks from the ends of lines
+    fn trim_trailing(&mut self) {
+        for n in 0..self.line_count() {
+            let line = self.line(n);
+            let trimmed = line.trim_end_matches([' ', '\t']).len();
+            if trimmed < line.len() {
+                let (from, to) = (col_at(line, trimmed), col_at(line, line.len()));
+                self.delete(Cursor::new(n, from), Cursor::new(n, to));
+            }
+        }
+    }
+    /// Marks the text as changed
+    pub fn touch(&mut self) {
+        self.modified = true;
+        self.version += 1;
+    }
+    pub fn lines(&self) -> Split<'_, char> {
+        self.text.split('\n')
+    }
+    /// Number of lines, which is never zero: an empty buffer has one empty line
+    pub fn line_count(&self) -> usize {
+        self.text.bytes().filter(|b| *b == b'\n').count() + 1
+    }
+    /// Byte offset where line `n` starts, or the end of the text past the last line
+    pub fn line_offset(&self, n: usize) -> usize {
+        match n {
+            0 => 0,
+            n => self.text.match_indices('\n').nth(n - 1).map_or(self.text.len(), |(i, _)| i + 1),
+        }
+    }
+    /// Text of line `n` without its newline, empty past the last line
+    pub fn line(&self, n: usize) -> &str {
+        let start = self.line_offset(n).min(self.text.len());
+        let rest = &self.text[start..];
+        match rest.find('\n') {
+            Some(end) => &rest[..end],
+            None if n < self.line_count() => rest,
+            None => "",
+        }
+    }
+    /// Number of columns in line `n`
+    pub fn line_len(&self, n: usize) -> usize {
+        col_at(self.line(n), usize::MAX)
+    }
+    /// Byte offset of `at`, clamped to the text
+    pub fn offset(&self, at: Cursor) -> usize {
+        let start = self.line_offset(at.line);
+        (start + col_offset(self.line(at.line), at.col)).min(self.text.len())
+    }
+    /// Position of the byte offset `offset`
+    pub fn cursor_at(&self, offset: usize) -> Cursor {
+        let offset = offset.min(self.text.len());
+        let line = self.text[..offset].bytes().filter(|b| *b == b'\n').count();
+        let start = self.line_offset(line);
+        Cursor::new(line, col_at(self.line(line), offset - start))
+    }
+    /// Start of the first match of `re` after `at`, wrapping around past the end
+    pub fn find(&self, re: &Regex, at: Cursor) -> Option<Cursor> {
+        let from = self.offset(self.next_char(at, 1));
+        re.find_at(&self.text, from).or_else(|| re.find(&self.text))
+            .map(|m| self.cursor_at(m.start()))
+    }
+    /// Moves `at` onto the text. Edit mode keeps the cursor on a character, so unless
+    /// `past_end` the column stops at the last one.
+    pub fn clamp(&self, at: Cursor, past_end: bool) -> Cursor {
+        let line = at.line.min(self.line_count() - 1);
+        let len = self.line_len(line);
+        let max = if past_end || len == 0 { len } else { len - 1 };
+        Cursor::new(line, at.col.min(max))
+    }
+    /// Position `n` characters before `at`, crossing line breaks
+    pub fn prev_char(&self, at: Cursor, n: usize) -> Cursor {
+        let offset = self.offset(at);
+        let to = self.text[..offset].grapheme_indices(true).rev().take(n).last().map_or(offset, |(i, _)| i);
+        self.cursor_at(to)
+    }
+    /// Position `n` characters after `at`, crossing line breaks
+    pub fn next_char(&self, at: Cursor, n: usize) -> Cursor {
+        let offset = self.offset(at);
+        let to = self.text[offset..].grapheme_indices(true).nth(n).map_or(self.text.len(), |(i, _)| offset + i);
+        self.cursor_at(to)
+    }
+    /// Inserts `s` at `at`, returning the position just after it
+    pub fn insert(&mut self, at: Cursor, s: &str) -> Cursor {
+        let offset = self.offset(at);
+        self.text.insert_str(offset, s);
+        let lines = s.bytes().filter(|b| *b == b'\n').count();
+        self.signs.shift(at.line, 0, lines);
+        self.marks.shift(at.line, 0, lines);
+        self.folds.shift(at.line, 0, lines);
+        self.marks.changed(at);
+        if let Some(h) = self.syntax.as_mut() {
+            h.edited(at.line);
+        }
+        self.touch();
+        self.cursor_at(offset + s.len())
+    }
+    /// Removes the text between `from` and `to`, returning it
+    pub fn delete(&mut self, from: Cursor, to: Cursor) -> String {
+        let (from, to) = if from <= to { (from, to) } else { (to, from) };
+        let (start, end) = (self.offset(from), self.offset(to));
+        let removed: String = self.text.drain(start..end).collect();
+        let lines = removed.bytes().filter(|b| *b == b'\n').count();
+        self.signs.shift(from.line, lines, 0);
+        self.marks.shift(from.line, lines, 0);
+        self.folds.shift(from.line, lines, 0);
+        if !removed.is_empty() {
+            self.marks.changed(from);
+            if let Some(h) = self.syntax.as_mut() {
+                h.edited(from.line);
+            }
+            self.touch();
+        }
+        removed
+    }
+    /// Deletes lines `first` to `last`, inclusive, returning them with a newline after each
+    pub fn delete_lines(&mut self, first: usize, last: usize) -> String {
+        let (from, to) = if last + 1 < self.line_count() {
+            (Cursor::new(first, 0), Cursor::new(last + 1, 0))
+        } else if first > 0 {
+            // The last line has no newline after it to take, so the one before it goes
+            (Cursor::new(first - 1, self.line_len(first - 1)), Cursor::new(last, self.line_len(last)))
+        } else {
+            (Cursor::new(0, 0), Cursor::new(last, self.line_len(last)))
+        };
+        let removed = self.delete(from, to);
+        match removed.strip_prefix('\n') {
+            Some(lines) => format!("{}\n", lines),
+            None if removed.ends_with('\n') => removed,
+            None => format!("{}\n", removed),
+        }
+    }
+    /// Replaces lines `first` to `last`, inclusive, with `lines`
+    pub fn replace_lines(&mut self, first: usize, last: usize, lines: &[String]) {
+        let end = Cursor::new(last, self.line_len(last));
+        if !self.lines().skip(first).take(last + 1 - first).eq(lines.iter().map(String::as_str)) {
+            self.delete(Cursor::new(first, 0), end);
+            self.insert(Cursor::new(first, 0), &lines.join("\n"));
+        }
+    }
+    pub fn is_blank(&self, n: usize) -> bool {
+        self.line(n).trim().is_empty()
+    }
+    /// The line `}` or `{` moves to from `line`: the blank line after or before the `n`th
+    /// paragraph, or the last or first line
+    pub fn paragraph_edge(&self, line: usize, dir: Direction, n: usize) -> usize {
+        let last = self.line_count() - 1;
+        let mut l = line;
+        for _ in 0..n {
+            match dir {
+                Direction::Next => {
+                    while l < last && self.is_blank(l) { l += 1; }
+                    while l < last && !self.is_blank(l) { l += 1; }
+                },
+                Direction::Prev => {
+                    while l > 0 && self.is_blank(l) { l -= 1; }
+                    while l > 0 && !self.is_blank(l) { l -= 1; }
+                },
+            }
+        }
+        l
+    }
+    /// First and last line of the `n` paragraphs from `line`, counting runs of blank lines
+    /// as paragraphs too. `around` adds the blank lines after them, or before them if there
+    /// are none after.
+    pub fn paragraph(&self, line: usize, n: usize, around: bool) -> (usize, usize) {
+        let count = self.line_count();
+        let run_end = |l: usize| {
+            let blank = self.is_blank(l);
+            (l..count).take_while(|i| self.is_blank(*i) == blank).last().unwrap_or(l)
+        };
+        let blank = self.is_blank(line);
+        let first = (0..=line).rev().take_while(|i| self.is_blank(*i) == blank).last().unwrap_or(line);
+        let mut last = run_end(line);
+        for _ in 1..n {
+            if last + 1 < count {
+                last = run_end(last + 1);
+            }
+        }
+        if !around {
+            return (first, last);
+        }
+        if last + 1 < count {
+            (first, run_end(last + 1))
+        } else if !self.is_blank(first) && first > 0 && self.is_blank(first - 1) {
+            ((0..first).rev().take_while(|i| self.is_blank(*i)).last().unwrap_or(first), last)
+        } else {
+            (first, last)
+        }
+    }
+    /// Start and end, just past it, of the `n` words from `at` on its line, as `iw` takes
+    /// them: runs of word characters, of other marks and of blanks each count as one.
+    /// `around` adds the blanks after them, or before them if there are none after.
+    pub fn word(&self, at: Cursor, n: usize, around: bool) -> (Cursor, Cursor) {
+        let graphemes: Vec<&str> = self.line(at.line).graphemes(true).collect();
+        let class = |i: usize| match graphemes[i] {
+            g if g.trim().is_empty() => 0,
+            g if g.chars().all(|c| c.is_alphanumeric() || c == '_') => 1,
+            _ => 2,
+        };
+        let len = graphemes.len();
+        if len == 0 {
+            return (at, at);
+        }
+        let run_end = |i: usize| (i..len).take_while(|j| class(*j) == class(i)).last().unwrap_or(i) + 1;
+        let run_start = |i: usize| (0..=i).rev().take_while(|j| class(*j) == class(i)).last().unwrap_or(i);
+        let col = at.col.min(len - 1);
+        let mut start = run_start(col);
+        let mut end = run_end(col);
+        for _ in 1..n {
+            if end < len {
+                end = run_end(end);
+            }
+        }
+        if around {
+            if end < len && class(end) == 0 {
+                end = run_end(end);
+            } else if start > 0 && class(start - 1) == 0 {
+                start = run_start(start - 1);
+            }
+        }
+        (Cursor::new(at.line, start), Cursor::new(at.line, end))
+    }
+    /// Whether `name` is an option buffers can have their own value of
+    pub fn is_option(name: &str) -> bool {
+        let name = name.split_once('=').map_or(name, |(n, _)| n).trim_end_matches(['!', '?']);
+        Indent::is_option(name) || FileOptions::is_option(name) || FoldOptions::is_option(name) || matches!(name, "textwidth" | "tw")
+    }
+    /// Applies one `:setlocal` argument naming a buffer-local option. Options the buffer
+    /// doesn't have its own value of yet start from their `global` value.
+    pub fn set_option(&mut self, arg: &str, global: &Options) -> Result<(), LxConfigError> {
+        if Indent::is_option(arg) {
+            self.indent.get_or_insert_with(|| global.indent.clone()).set(arg)
+        } else if FileOptions::is_option(arg) {
+            let old = self.file.clone();
+            self.file.set(arg)?;
+            // Converting the file changes what saving it writes
+            if (self.file.fileformat, self.file.fileencoding) != (old.fileformat, old.fileencoding) {
+                self.modified = true;
+            }
+            Ok(())
+        } else if FoldOptions::is_option(arg) {
+            self.fold.get_or_insert_with(|| global.fold.clone()).set(arg)
+        } else {
+            let mut options = Options { textwidth: self.textwidth(global), ..Options::default() };
+            options.set(arg)?;
+            self.textwidth = Some(options.textwidth);
+            Ok(())
+        }
+    }
+    /// Value of buffer-local option `name` in this buffer, as `:set` would take it
+    pub fn get_option(&self, name: &str, global: &Options) -> Option<String> {
+        match name {
+            "textwidth" | "tw" => Some(format!("textwidth={}", self.textwidth(global))),
+            _ => self.indent(global).get(name).or_else(|| self.file.get(name))
+                .or_else(|| self.fold_options(global).get(name)),
+        }
+    }
+    /// Line width `gq` reflows this buffer's text to, zero for the window's width
+    pub fn textwidth(&self, options: &Options) -> usize {
+        self.textwidth.unwrap_or(options.textwidth)
+    }
+    /// The indent options in effect in this buffer
+    pub fn indent<'a>(&'a self, options: &'a Options) -> &'a Indent {
+        self.indent.as_ref().unwrap_or(&options.indent)
+    }
+    /// The fold options in effect in this buffer
+    pub fn fold_options<'a>(&'a self, options: &'a Options) -> &'a FoldOptions {
+        self.fold.as_ref().unwrap_or(&options.fold)
+    }
+    fn indent_rules(&self) -> IndentRules {
+        self.syntax.as_ref().map(|h| h.grammar.indent.clone()).unwrap_or_default()
+    }
+    /// Columns a line following `line` is indented: as far as `line` is, and a level more
+    /// if it opens a block
+    fn indent_after(&self, line: &str, indent: &Indent) -> usize {
+        let opens = self.indent_rules().increase.is_some_and(|re| re.is_match(line));
+        indent::width(line, indent.tabstop) + if opens { indent.width() } else { 0 }
+    }
+    /// Indents line `n` by `width` columns. Returns the lengths of its old and new indent.
+    pub fn set_indent(&mut self, n: usize, width: usize, indent: &Indent) -> (usize, usize) {
+        let old = indent::leading(self.line(n)).len();
+        let new = indent.indentation(width);
+        if self.line(n)[..old] != new {
+            self.delete(Cursor::new(n, 0), Cursor::new(n, old));
+            self.insert(Cursor::new(n, 0), &new);
+        }
+        (old, new.len())
+    }
+    /// Splits the line at `at` as Return does in Insert mode, returning the start of the new
+    /// line's text. The new line is indented like the one split, a level more after a line
+    /// opening a block and a level less when it starts with a closer. Between an opener and
+    /// its closer, as in `{|}`, the closer gets a line of its own. Blanks around the split
+    /// are dropped, so a line holding only indent is left empty.
+    pub fn newline(&mut self, at: Cursor, indent: &Indent) -> Cursor {
+        let rules = self.indent_rules();
+        let line = self.line(at.line);
+        let (before, after) = line.split_at(col_offset(line, at.col));
+        let width = indent::width(line, indent.tabstop);
+        let opens = rules.increase.as_ref().is_some_and(|re| re.is_match(before)) && !before.trim().is_empty();
+        let closes = rules.decrease.as_ref().is_some_and(|re| re.is_match(after));
+        let new_width = match (opens, closes) {
+            (true, _) => width + indent.width(),
+            (false, true) => width.saturating_sub(indent.width()),
+            (false, false) => width,
+        };
+        let from = Cursor::new(at.line, col_at(line, before.trim_end_matches([' ', '\t']).len()));
+        let to = Cursor::new(at.line, col_at(line, line.len() - after.trim_start_matches([' ', '\t']).len()));
+        self.delete(from, to);
+        let cursor = self.insert(from, &format!("\n{}", indent.indentation(new_width)));
+        if opens && closes {
+            self.insert(cursor, &format!("\n{}", indent.indentation(width)));
+        }
+        cursor
+    }
+    /// Dedents line `at.line` if the text typed up to `at` has just made it start with a
+    /// closer, returning where `at` ends up
+    pub fn dedent_closer(&mut self, at: Cursor, indent: &Indent) -> Cursor {
+        let decrease = match self.indent_rules().decrease {
+            Some(re) => re,
+            None => return at,
+        };
+        let line = self.line(at.line);
+        let typed = &line[..col_offset(line, at.col)];
+        let before = &line[..col_offset(line, at.col.saturating_sub(1))];
+        if !decrease.is_match(typed) || decrease.is_match(before) {
+            return at;
+        }
+        let width = indent::width(line, indent.tabstop);
+        let expected = (0..at.line).rev().find(|l| !self.is_blank(*l))
+            .map_or(0, |l| self.indent_after(self.line(l), indent));
+        let target = expected.saturating_sub(indent.width());
+        if width <= target {
+            return at;
+        }
+        let (old, new) = self.set_indent(at.line, target, indent);
+        Cursor::new(at.line, (at.col + new).saturating_sub(old))
+    }
+    /// Opens a line below or above line `line`, as `o` and `O` do, indented for the text
+    /// around it. Returns the end of the line opened.
+    pub fn open_line(&mut self, line: usize, dir: Direction, indent: &Indent) -> Cursor {
+        let text = self.line(line);
+        let width = match dir {
+            Direction::Next => self.indent_after(text, indent),
+            Direction::Prev => {
+                let closes = self.indent_rules().decrease.is_some_and(|re| re.is_match(text));
+                indent::width(text, indent.tabstop) + if closes { indent.width() } else { 0 }
+            },
+        };
+        let lead = indent.indentation(width);
+        match dir {
+            Direction::Next => self.insert(Cursor::new(line, self.line_len(line)), &format!("\n{}", lead)),
+            Direction::Prev => {
+                self.insert(Cursor::new(line, 0), &format!("{}\n", lead));
+                Cursor::new(line, lead.len())
+            },
+        }
+    }
+    /// Shifts the non-blank lines from `first` to `last` a level right or left, as `>` and
+    /// `<` do
+    pub fn shift_lines(&mut self, first: usize, last: usize, dir: Direction, indent: &Indent) {
+        for n in first..=last {
+            if self.is_blank(n) {
+                continue;
+            }
+            let width = indent::width(self.line(n), indent.tabstop);
+            let width = match dir {
+                Direction::Next => width + indent.width(),
+                Direction::Prev => width.saturating_sub(indent.width()),
+            };
+            self.set_indent(n, width, indent);
+        }
+    }
+    /// Brings syntax highlighting of the first `upto` lines up to date, stopping early at
+    /// `deadline`. Returns whether it finished.
+    pub fn highlight(&mut self, upto: usize, deadline: Instant) -> bool {
+        match self.syntax.as_mut() {
+            Some(h) => h.update(&self.text, self.version, upto, deadline),
+            None => true,
+        }
+    }
+    /// Name of the buffer's filetype, if it has a grammar
+    pub fn filetype(&self) -> Option<&str> {
+        self.syntax.as_ref().map(|h| h.grammar.name.as_str())
+    }
+    /// Line comment leaders of the buffer's filetype, which `gq` keeps at the start of lines
+    pub fn comments(&self) -> Vec<String> {
+        self.syntax.as_ref().map(|h| h.grammar.comments.clone()).unwrap_or_default()
+    }
+    /// Name shown for the buffer in the UI
+    pub fn name(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None if self.stdout => "[stdout]".to_string(),
+            None => "[No Name]".to_string(),
+        }
+    }
+    /// Whether this buffer is backed by `path`, comparing canonical paths when possible
+    pub fn is_path(&self, path: &Path) -> bool {
+        match &self.path {
+            Some(p) => p == path || matches!(
+                (p.canonicalize(), path.canonicalize()), (Ok(a), Ok(b)) if a == b),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use unicode_width::UnicodeWidthStr;
+
+/// A line split into the prefix kept at the start of every line of its paragraph, a list
+/// marker and the text that gets reflowed
+struct Parts<'a> {
+    /// Indent and comment leader, with the blanks after the leader
+    prefix: &'a str,
+    /// The comment leader alone, which lines must share to be joined
+    leader: &'a str,
+    /// A Markdown list marker such as `- ` or `1. `, with the blanks after it
+    marker: &'a str,
+    body: &'a str,
+}
+
+/// Length of the list marker `s` starts with: a bullet or a number followed by `.` or `)`,
+/// then blanks and some text
+fn list_marker(s: &str) -> usize {
+    let digits = s.bytes().take_while(u8::is_ascii_digit).count();
+    let len = match s[digits..].chars().next() {
+        Some('-' | '*' | '+') if digits == 0 => 1,
+        Some('.' | ')') if (1..=9).contains(&digits) => digits + 1,
+        _ => return 0,
+    };
+    let rest = &s[len..];
+    let blanks = rest.len() - rest.trim_start().len();
+    if blanks > 0 && blanks < rest.len() { len + blanks } else { 0 }
+}
+
+/// Splits `line` using `comments`, the leaders of line comments, longest first
+fn split<'a>(line: &'a str, comments: &[String]) -> Parts<'a> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let leader = comments.iter().find(|c| rest.starts_with(c.as_str())).map_or("", |c| &rest[..c.len()]);
+    let after = &rest[leader.len()..];
+    let prefix = &line[..line.len() - after.trim_start().len()];
+    let rest = &line[prefix.len()..];
+    let marker = &rest[..list_marker(rest)];
+    Parts { prefix, leader, marker, body: rest[marker.len()..].trim_end() }
+}
+
+/// Appends `words` to `out` as lines of at most `width` columns, where they fit. The first
+/// line starts with `first` and the rest with `rest`.
+fn fill(words: &[&str], first: &str, rest: &str, width: usize, out: &mut Vec<String>) {
+    let mut line = first.to_string();
+    let mut empty = true;
+    for word in words {
+        if !empty && line.width() + 1 + word.width() > width {
+            out.push(line);
+            line = rest.to_string();
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        empty = false;
+    }
+    out.push(line);
+}
+
+/// Reflows `lines` into lines of at most `width` columns, as `gq` does. Blank lines, and
+/// lines with nothing after their comment leader, separate paragraphs and are kept. A
+/// paragraph also ends where the comment leader changes and before a list item, whose
+/// following lines are indented to line up with the text after its marker.
+pub fn reflow(lines: &[&str], width: usize, comments: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let first = split(lines[i], comments);
+        i += 1;
+        if first.body.is_empty() {
+            out.push(lines[i - 1].trim_end().to_string());
+            continue;
+        }
+        let mut words: Vec<&str> = first.body.split_whitespace().collect();
+        while let Some(next) = lines.get(i).map(|l| split(l, comments)) {
+            if next.body.is_empty() || !next.marker.is_empty() || next.leader != first.leader {
+                break;
+            }
+            words.extend(next.body.split_whitespace());
+            i += 1;
+        }
+        let lead = format!("{}{}", first.prefix, first.marker);
+        let hang = format!("{}{}", first.prefix, " ".repeat(first.marker.width()));
+        fill(&words, &lead, &hang, width, &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gq(text: &str, width: usize) -> String {
+        let lines: Vec<&str> = text.split('\n').collect();
+        reflow(&lines, width, &["//".to_string(), "#".to_string()]).join("\n")
+    }
+
+    #[test]
+    fn paragraphs_are_filled() {
+        assert_eq!(gq("one two\nthree four five six", 14), "one two three\nfour five six");
+        assert_eq!(gq("one\n\ntwo\nthree", 20), "one\n\ntwo three");
+        assert_eq!(gq("  indented words go here", 12), "  indented\n  words go\n  here");
+        assert_eq!(gq("unbreakablelongword x", 5), "unbreakablelongword\nx");
+        assert_eq!(gq("trailing   \n", 20), "trailing\n");
+    }
+
+    #[test]
+    fn comments_and_lists_keep_their_leaders() {
+        assert_eq!(gq("// one two three\n// four", 13), "// one two\n// three four");
+        assert_eq!(gq("// comment\n#\n# other", 20), "// comment\n#\n# other");
+        assert_eq!(gq("// comment\ncode", 20), "// comment\ncode");
+        assert_eq!(gq("- one two three\n- four", 10), "- one two\n  three\n- four");
+        assert_eq!(gq("10. one two three", 12), "10. one two\n    three");
+        assert_eq!(gq("-not a list", 20), "-not a list");
+    }
+}
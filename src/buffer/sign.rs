@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+/// Severity of a diagnostic shown in the gutter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+/// How a line differs from the version control base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VcsStatus {
+    Added,
+    Modified,
+    /// Lines were removed just below this one
+    Removed,
+}
+
+impl Severity {
+    pub fn symbol(&self) -> char {
+        match self {
+            Self::Error => 'E',
+            Self::Warning => 'W',
+            Self::Info => 'I',
+            Self::Hint => 'H',
+        }
+    }
+    /// Theme key used to style the sign
+    pub fn style_key(&self) -> &'static str {
+        match self {
+            Self::Error => "ui.gutter.error",
+            Self::Warning => "ui.gutter.warning",
+            Self::Info => "ui.gutter.info",
+            Self::Hint => "ui.gutter.hint",
+        }
+    }
+}
+
+impl VcsStatus {
+    pub fn symbol(&self) -> char {
+        match self {
+            Self::Added => '+',
+            Self::Modified => '~',
+            Self::Removed => '_',
+        }
+    }
+    /// Theme key used to style the sign
+    pub fn style_key(&self) -> &'static str {
+        match self {
+            Self::Added => "ui.gutter.added",
+            Self::Modified => "ui.gutter.modified",
+            Self::Removed => "ui.gutter.removed",
+        }
+    }
+}
+
+/// Per-line marks shown in the gutter sign columns, keyed by line index
+#[derive(Debug, Default, Clone)]
+pub struct Signs {
+    /// The most severe diagnostic on each line
+    pub diagnostics: BTreeMap<usize, Severity>,
+    pub vcs: BTreeMap<usize, VcsStatus>,
+}
+
+impl Signs {
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty() && self.vcs.is_empty()
+    }
+    /// Adds a diagnostic, keeping the more severe one if the line already has one
+    pub fn add_diagnostic(&mut self, line: usize, severity: Severity) {
+        let entry = self.diagnostics.entry(line).or_insert(severity);
+        *entry = (*entry).min(severity);
+    }
+    /// Keeps signs on the lines they were placed on when `removed` lines after `line` are
+    /// deleted and `added` lines are inserted there
+    pub fn shift(&mut self, line: usize, removed: usize, added: usize) {
+        fn shift_map<T>(map: &mut BTreeMap<usize, T>, line: usize, removed: usize, added: usize) {
+            let moved = map.split_off(&(line + 1));
+            for (l, v) in moved {
+                if l > line + removed {
+                    map.insert(l - removed + added, v);
+                }
+            }
+        }
+        if removed != added {
+            shift_map(&mut self.diagnostics, line, removed, added);
+            shift_map(&mut self.vcs, line, removed, added);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Changes made to buffers in Edit and Insert mode, kept as the operations which made them
+//! rather than as keys, so `.` can make the last one again with a new count.
+
+use tui::backend::Backend;
+
+use crate::{Lx, LxResult, mode::Mode, op::{EditOp, InsertOp, Operation}, types::Direction};
+
+/// Changes kept in the log
+const LOG_LENGTH: usize = 100;
+
+/// One change: an Edit mode operation such as `o` or `>j`, and what was typed in the Insert
+/// mode session after it. A change made only by typing has no operation.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Change {
+    pub edit: Option<EditOp>,
+    pub inserted: Vec<InsertOp>,
+}
+
+impl Change {
+    /// The change made with count `n`: its operation's count, or for a change made only by
+    /// typing, the times the text is typed
+    pub fn with_count(self, n: usize) -> Self {
+        match self.edit {
+            Some(edit) => Self { edit: Some(edit.with_count(n)), inserted: self.inserted },
+            None => {
+                let typed = self.inserted.len();
+                Self { edit: None, inserted: self.inserted.into_iter().cycle().take(typed * n).collect() }
+            },
+        }
+    }
+}
+
+/// Changes made this session, oldest first
+#[derive(Debug, Default)]
+pub struct ChangeLog {
+    changes: Vec<Change>,
+    /// The change of the Insert mode session under way
+    inserting: Option<Change>,
+}
+
+impl ChangeLog {
+    pub fn push(&mut self, change: Change) {
+        if self.changes.len() == LOG_LENGTH {
+            self.changes.remove(0);
+        }
+        self.changes.push(change);
+    }
+    /// The last change made, which `.` repeats
+    pub fn last(&self) -> Option<&Change> {
+        self.changes.last()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Change> {
+        self.changes.iter()
+    }
+    /// Starts the change of an Insert mode session entered with `edit`, as `o` enters one
+    pub fn begin_insert(&mut self, edit: EditOp) {
+        self.end_insert();
+        self.inserting = Some(Change { edit: Some(edit), inserted: Vec::new() });
+    }
+    /// Adds what was typed in Insert mode to the change of the session, starting one when
+    /// there is none
+    pub fn insert(&mut self, op: InsertOp) {
+        self.inserting.get_or_insert_with(Change::default).inserted.push(op);
+    }
+    /// Ends the change of the Insert mode session, on leaving Insert mode or moving in it,
+    /// giving the change if there was one
+    pub fn end_insert(&mut self) -> Option<&Change> {
+        let change = self.inserting.take()?;
+        self.push(change);
+        self.last()
+    }
+}
+
+impl<W: Backend> Lx<W> {
+    /// Makes a change with an Edit mode operation and logs it. A change entering Insert
+    /// mode takes in what is typed there until Insert mode is left.
+    pub fn make_change(&mut self, edit: EditOp) -> LxResult<()> {
+        edit.exec_app(self)?;
+        if matches!(self.mode, Mode::Insert(_)) {
+            self.changes.begin_insert(edit);
+        } else {
+            self.changes.push(Change { edit: Some(edit), inserted: Vec::new() });
+        }
+        Ok(())
+    }
+    /// `.`: makes the last change again at the cursor, with `count` in place of the count
+    /// it was made with, which later repeats then keep
+    pub fn repeat_change(&mut self, count: Option<usize>) -> LxResult<()> {
+        let change = match (self.changes.last(), count) {
+            (Some(change), Some(n)) => change.clone().with_count(n),
+            (Some(change), None) => change.clone(),
+            (None, _) => return Ok(()),
+        };
+        if let Some(edit) = &change.edit {
+            edit.exec_app(self)?;
+        }
+        if !change.inserted.is_empty() {
+            self.mode = Mode::insert();
+            for op in &change.inserted {
+                op.exec_app(self)?;
+            }
+        }
+        self.mode_switch(Mode::edit())?;
+        self.repeat_insert(&change)?;
+        self.changes.push(change);
+        Ok(())
+    }
+    /// Types what was typed after `o` or `O` with a count again on lines of its own below,
+    /// until there is a copy for each of the count
+    pub fn repeat_insert(&mut self, change: &Change) -> LxResult<()> {
+        let n = match change.edit {
+            Some(EditOp::InsertLine(_, n)) => n,
+            _ => return Ok(()),
+        };
+        for _ in 1..n {
+            EditOp::InsertLine(Direction::Next, 1).exec_app(self)?;
+            for op in &change.inserted {
+                op.exec_app(self)?;
+            }
+            self.mode_switch(Mode::edit())?;
+        }
+        Ok(())
+    }
+}
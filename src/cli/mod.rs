@@ -0,0 +1,263 @@
+use std::{io::{self, Read}, path::{Path, PathBuf}};
+use regex::Regex;
+use tui::backend::Backend;
+
+use crate::{Lx, LxResult, config, mark::JumpList, types::Cursor, window::{OpenIn, SplitDir}};
+
+/// What `--help` prints after the usage line
+const HELP: &str = "
+Edits FILES, or standard input when FILES is `-`.
+
+Options:
+  +N              Start on line N of the first file
+  +               Start on the last line
+  +/PATTERN       Start at the first match of PATTERN
+  +CMD            Run CMD once the files are open, as -c does
+  --filter        Edit standard input, and write the buffer to standard output on
+                  exit if it was written with :w, :wq or :x. Exits with 1 otherwise.
+  -R              Open the files read-only
+  -o              Open the files in windows stacked on top of each other
+  -O              Open the files in windows side by side
+  -p              Open the files in tab pages
+  -c CMD          Run CMD once the files are open. May be given more than once.
+  --batch         Run the commands on each file in turn without a terminal, then
+                  exit, failing if any command does
+  -s FILE         Run the commands in FILE as -c does, in batch mode
+  --config FILE   Run the commands in FILE at startup instead of the config file
+  --clean         Start without running the config file, and without reading or
+                  saving marks, jumps and folds
+  -h, --help      Print this help and exit
+  --version       Print the version and exit
+";
+
+/// Where the cursor starts in the first file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Jump {
+    /// A line, counting from 1
+    Line(usize),
+    LastLine,
+    Pattern(String),
+}
+
+/// How the files given are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Layout {
+    /// One window showing the first file, with the rest in the buffer list
+    #[default]
+    Buffers,
+    Split(SplitDir),
+    Tabs,
+}
+
+/// The files to edit and how to start editing them
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Args {
+    pub files: Vec<PathBuf>,
+    /// Whether the first buffer is read from standard input
+    pub stdin: bool,
+    /// Whether the buffer read from standard input is written to standard output
+    pub filter: bool,
+    pub jump: Option<Jump>,
+    pub readonly: bool,
+    pub layout: Layout,
+    /// Commands file run at startup in place of `config::config_file()`
+    pub config: Option<PathBuf>,
+    /// Whether to skip the commands file
+    pub clean: bool,
+    /// Commands run once the files are open, in order
+    pub commands: Vec<String>,
+    /// Whether to run the commands on each file without a terminal, as `batch::run` does
+    pub batch: bool,
+}
+
+/// What the command line asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Edit(Args),
+    Help,
+    Version,
+}
+
+/// The name the program was run as, from the first of its arguments
+pub fn program(arg0: Option<String>) -> String {
+    arg0.as_deref()
+        .and_then(|arg| Path::new(arg).file_name())
+        .map_or_else(|| env!("CARGO_PKG_NAME").to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+pub fn usage(program: &str) -> String {
+    format!("Usage: {} [OPTIONS] [FILES]...", program)
+}
+
+pub fn help(program: &str) -> String {
+    format!("{}\n{}", usage(program), HELP)
+}
+
+pub fn version() -> String {
+    format!("lx {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Reads the command line, without the program name. Arguments after `--` are all files.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Action, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+    let mut options = true;
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("Missing argument for {}", name));
+        match arg.as_str() {
+            _ if !options => parsed.files.push(arg.into()),
+            "--" => options = false,
+            "-" => parsed.stdin = true,
+            "--filter" => {
+                parsed.stdin = true;
+                parsed.filter = true;
+            },
+            "-h" | "--help" => return Ok(Action::Help),
+            "--version" => return Ok(Action::Version),
+            "-R" => parsed.readonly = true,
+            "-o" => parsed.layout = Layout::Split(SplitDir::Horizontal),
+            "-O" => parsed.layout = Layout::Split(SplitDir::Vertical),
+            "-p" => parsed.layout = Layout::Tabs,
+            "-c" => parsed.commands.push(value("-c")?),
+            "--config" => parsed.config = Some(value("--config")?.into()),
+            "--clean" => parsed.clean = true,
+            "--batch" => parsed.batch = true,
+            "-s" => {
+                parsed.commands.push(format!("source {}", value("-s")?));
+                parsed.batch = true;
+            },
+            "+" => parsed.jump = Some(Jump::LastLine),
+            _ if arg.starts_with('+') => {
+                let rest = &arg[1..];
+                if let Some(pattern) = rest.strip_prefix('/') {
+                    parsed.jump = Some(Jump::Pattern(pattern.to_string()));
+                } else if let Ok(line) = rest.parse() {
+                    parsed.jump = Some(Jump::Line(line));
+                } else {
+                    parsed.commands.push(rest.to_string());
+                }
+            },
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ => parsed.files.push(arg.into()),
+        }
+    }
+    Ok(Action::Edit(parsed))
+}
+
+impl<W: Backend> Lx<W> {
+    /// Sets the editor up as the command line asks: runs the config file, opens the files
+    /// and moves to where the cursor should start, then runs the commands given. Problems
+    /// show as messages rather than stopping the editor from starting.
+    pub fn start(&mut self, args: Args) -> LxResult<()> {
+        let config = args.config.clone().unwrap_or_else(config::config_file);
+        // A missing config file is only worth a message when it was asked for
+        if !args.clean && (args.config.is_some() || config.exists()) {
+            if let Err(e) = self.source(&config) {
+                self.error(format!("{}: {}", config.display(), e));
+            }
+        }
+        let empty = self.buf_idx();
+        let opened = self.buf.len();
+        if args.stdin {
+            let mut bytes = Vec::new();
+            match io::stdin().read_to_end(&mut bytes) {
+                Ok(_) => {
+                    self.open_bytes(&bytes);
+                    let idx = self.buf_idx();
+                    self.buf[idx].stdout = args.filter;
+                },
+                Err(e) => self.error(format!("Reading standard input: {}", e)),
+            }
+        }
+        for (i, file) in args.files.iter().enumerate() {
+            let first = i == 0 && !args.stdin;
+            let shown = self.buf_idx();
+            let open_in = match args.layout {
+                Layout::Split(dir) if !first => OpenIn::Split(dir),
+                Layout::Tabs if !first => OpenIn::Tab,
+                _ => OpenIn::Current,
+            };
+            match self.open(file, open_in) {
+                // Buffers of the other files are added without showing them
+                Ok(()) if !first && args.layout == Layout::Buffers => self.switch_buf(shown),
+                Ok(()) => {},
+                Err(e) => self.error(format!("{}: {}", file.display(), e)),
+            }
+        }
+        if args.readonly {
+            for b in self.buf[opened..].iter_mut() {
+                b.readonly = true;
+            }
+        }
+        // The empty buffer the editor starts with gives way to the files
+        if self.buf.len() > opened && self.buf[empty].path.is_none() && !self.buf[empty].modified {
+            self.close_buf(empty);
+        }
+        self.switch_tab(0);
+        self.tabs[self.tab_idx].focus = 0;
+        // Opening the files isn't a jump, and the last session's jumps come back
+        self.jumps = JumpList::default();
+        if !args.clean {
+            if let Err(e) = self.load_marks() {
+                self.error(format!("Reading marks: {}", e));
+            }
+            if let Err(e) = self.load_folds() {
+                self.error(format!("Reading folds: {}", e));
+            }
+        }
+        match args.jump {
+            Some(Jump::Line(line)) => self.move_vertical(line.saturating_sub(1)),
+            Some(Jump::LastLine) => self.move_vertical(usize::MAX),
+            Some(Jump::Pattern(pattern)) => match Regex::new(&pattern) {
+                Ok(re) => {
+                    let at = Cursor::new(0, 0);
+                    match self.buf[self.buf_idx()].find(&re, at) {
+                        Some(to) => self.set_cursor(to),
+                        None => self.error(format!("Pattern not found: {}", pattern)),
+                    }
+                },
+                Err(e) => self.error(e),
+            },
+            None => {},
+        }
+        for cmd in args.commands {
+            self.run_cmd(&cmd)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Action, String> {
+        parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_options_and_files() {
+        assert_eq!(args(&["-R", "-O", "+12", "-c", "set ts=4", "a.rs", "-", "--", "-p"]), Ok(Action::Edit(Args {
+            files: vec!["a.rs".into(), "-p".into()],
+            stdin: true,
+            jump: Some(Jump::Line(12)),
+            readonly: true,
+            layout: Layout::Split(SplitDir::Vertical),
+            commands: vec!["set ts=4".into()],
+            ..Args::default()
+        })));
+        assert_eq!(args(&["+/fn main"]), Ok(Action::Edit(Args { jump: Some(Jump::Pattern("fn main".into())), ..Args::default() })));
+        assert_eq!(args(&["--filter"]), Ok(Action::Edit(Args { stdin: true, filter: true, ..Args::default() })));
+        assert_eq!(args(&["a", "--help"]), Ok(Action::Help));
+        assert!(args(&["-c"]).is_err());
+        assert!(args(&["--bogus"]).is_err());
+    }
+
+    #[test]
+    fn help_names_the_program_as_it_was_run() {
+        assert_eq!(program(Some("/usr/local/bin/vi".into())), "vi");
+        assert_eq!(program(Some("view".into())), "view");
+        assert_eq!(program(None), "vi");
+        assert!(help("vi").starts_with("Usage: vi [OPTIONS] [FILES]...\n\nEdits FILES"));
+    }
+}
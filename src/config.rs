@@ -0,0 +1,27 @@
+use std::{env, path::PathBuf};
+
+/// Resolves an XDG base directory, falling back to the given path under `$HOME`
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    match env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(env::temp_dir)
+            .join(fallback),
+    }
+}
+
+/// Directory holding user configuration, `$XDG_CONFIG_HOME/lx`
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config").join("lx")
+}
+
+/// Directory holding state kept between sessions, `$XDG_STATE_HOME/lx`
+pub fn state_dir() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state").join("lx")
+}
+
+/// File of commands run at startup, `$XDG_CONFIG_HOME/lx/lxrc`
+pub fn config_file() -> PathBuf {
+    config_dir().join("lxrc")
+}
@@ -0,0 +1,294 @@
+use std::{collections::HashMap, env, fs, io, path::{Path, PathBuf}};
+use regex::Regex;
+
+use crate::error::LxConfigError;
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// The properties EditorConfig files give one file, with the names and values lowercased.
+/// See <https://editorconfig.org> for the properties and what they mean.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Properties(HashMap<String, String>);
+
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str).filter(|v| *v != "unset")
+    }
+    /// The `:setlocal` arguments the properties stand for. `tab_width` defaults to
+    /// `indent_size`, and unknown values are skipped.
+    pub fn options(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        match self.get("indent_style") {
+            Some("tab") => args.push("noexpandtab".to_string()),
+            Some("space") => args.push("expandtab".to_string()),
+            _ => {},
+        }
+        let indent_size = self.get("indent_size").filter(|s| *s == "tab" || s.parse::<usize>().is_ok());
+        let tab_width = self.get("tab_width").or_else(|| indent_size.filter(|s| *s != "tab"));
+        if let Some(size) = indent_size {
+            args.push(format!("shiftwidth={}", if size == "tab" { "0" } else { size }));
+        }
+        if let Some(width) = tab_width.filter(|w| w.parse::<usize>().is_ok()) {
+            args.push(format!("tabstop={}", width));
+        }
+        match self.get("end_of_line") {
+            Some("lf") => args.push("fileformat=unix".to_string()),
+            Some("crlf") => args.push("fileformat=dos".to_string()),
+            Some("cr") => args.push("fileformat=mac".to_string()),
+            _ => {},
+        }
+        if let Some(charset) = self.get("charset") {
+            args.push(format!("fileencoding={}", charset));
+        }
+        match self.get("trim_trailing_whitespace") {
+            Some("true") => args.push("trimtrailing".to_string()),
+            Some("false") => args.push("notrimtrailing".to_string()),
+            _ => {},
+        }
+        match self.get("insert_final_newline") {
+            Some("true") => args.push("fixendofline".to_string()),
+            Some("false") => args.push("nofixendofline".to_string()),
+            _ => {},
+        }
+        match self.get("max_line_length") {
+            Some("off") => args.push("textwidth=0".to_string()),
+            Some(n) if n.parse::<usize>().is_ok() => args.push(format!("textwidth={}", n)),
+            _ => {},
+        }
+        args
+    }
+}
+
+/// A parsed EditorConfig file: its sections' globs and properties, in order
+struct File {
+    root: bool,
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+fn parse(src: &str) -> File {
+    let mut file = File { root: false, sections: Vec::new() };
+    for line in src.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            file.sections.push((glob.to_string(), Vec::new()));
+        } else if let Some((key, value)) = line.split_once('=') {
+            let (key, value) = (key.trim().to_lowercase(), value.trim().to_lowercase());
+            match file.sections.last_mut() {
+                Some((_, props)) => props.push((key, value)),
+                None if key == "root" => file.root = value == "true",
+                None => {},
+            }
+        }
+    }
+    file
+}
+
+/// Index just past the `}` closing the brace at the start of `glob`
+fn closing_brace(glob: &[char]) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in glob.iter().enumerate() {
+        if std::mem::take(&mut escaped) {
+            continue;
+        }
+        match c {
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' if depth == 1 => return Some(i + 1),
+            '}' => depth -= 1,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// Translates an EditorConfig glob into a regex matching the paths it covers. Globs
+/// without a `/` match files of that name in any directory.
+fn glob_regex(glob: &str) -> String {
+    let glob = if glob.contains('/') { glob.trim_start_matches('/').to_string() } else { format!("**/{}", glob) };
+    let chars: Vec<char> = glob.chars().collect();
+    let mut re = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                // `**/` also matches no directories at all
+                if chars.get(i + 2) == Some(&'/') {
+                    re.push_str("(?:.*/)?");
+                    i += 1;
+                } else {
+                    re.push_str(".*");
+                }
+                i += 1;
+            },
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            '[' => match chars[i..].iter().position(|c| *c == ']') {
+                Some(end) => {
+                    let class: String = chars[i + 1..i + end].iter().collect();
+                    let class = class.strip_prefix('!').map_or_else(|| class.clone(), |c| format!("^{}", c));
+                    re.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                    i += end;
+                },
+                None => re.push_str("\\["),
+            },
+            '{' => match closing_brace(&chars[i..]) {
+                Some(end) => {
+                    let inner: String = chars[i + 1..i + end - 1].iter().collect();
+                    let range = inner.split_once("..")
+                        .and_then(|(a, b)| Some((a.parse::<i64>().ok()?, b.parse::<i64>().ok()?)));
+                    match range {
+                        Some((a, b)) => re.push_str(&format!("(?:{})",
+                            (a.min(b)..=a.max(b)).map(|n| n.to_string()).collect::<Vec<_>>().join("|"))),
+                        None if inner.contains(',') => {
+                            let alts: Vec<String> = split_alternatives(&inner).iter()
+                                .map(|alt| glob_regex_part(alt))
+                                .collect();
+                            re.push_str(&format!("(?:{})", alts.join("|")));
+                        },
+                        None => re.push_str(&regex::escape(&format!("{{{}}}", inner))),
+                    }
+                    i += end - 1;
+                },
+                None => re.push_str("\\{"),
+            },
+            '\\' if i + 1 < chars.len() => {
+                re.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 1;
+            },
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    re.push('$');
+    re
+}
+
+/// The regex for part of a glob, such as one alternative of `{a,b}`
+fn glob_regex_part(glob: &str) -> String {
+    let re = glob_regex(&format!("/{}", glob));
+    re[1..re.len() - 1].to_string()
+}
+
+/// Splits the alternatives of a brace group at the commas outside nested braces
+fn split_alternatives(inner: &str) -> Vec<String> {
+    let mut alts = Vec::new();
+    let mut alt = String::new();
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                alts.push(std::mem::take(&mut alt));
+                continue;
+            },
+            _ => {},
+        }
+        alt.push(c);
+    }
+    alts.push(alt);
+    alts
+}
+
+/// Whether the section `glob` of the EditorConfig file in `dir` covers `path`
+fn matches(glob: &str, dir: &Path, path: &Path) -> bool {
+    let rel = match path.strip_prefix(dir) {
+        Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
+        Err(_) => return false,
+    };
+    Regex::new(&glob_regex(glob)).is_ok_and(|re| re.is_match(&rel))
+}
+
+/// The properties given to the file at `path` by the `.editorconfig` files in its directory
+/// and the ones above it, up to one marked `root = true`. Closer files win, as do later
+/// sections within a file.
+pub fn properties(path: &Path) -> Result<Properties, LxConfigError> {
+    let path = env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf());
+    let mut files: Vec<(PathBuf, File)> = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let src = match fs::read_to_string(dir.join(FILE_NAME)) {
+            Ok(src) => src,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(LxConfigError::InvalidEditorConfig(format!("{}: {}", dir.join(FILE_NAME).display(), e))),
+        };
+        let file = parse(&src);
+        let root = file.root;
+        files.push((dir.to_path_buf(), file));
+        if root {
+            break;
+        }
+    }
+    let mut props = Properties::default();
+    for (dir, file) in files.iter().rev() {
+        for (glob, section) in &file.sections {
+            if matches(glob, dir, &path) {
+                props.0.extend(section.iter().cloned());
+            }
+        }
+    }
+    Ok(props)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    fn covers(glob: &str, path: &str) -> bool {
+        matches(glob, Path::new("/p"), &Path::new("/p").join(path))
+    }
+
+    #[test]
+    fn globs() {
+        assert!(covers("*", "a.rs") && covers("*", "src/a.rs"));
+        assert!(covers("*.rs", "src/deep/a.rs") && !covers("*.rs", "a.rs.bak"));
+        assert!(covers("src/*.rs", "src/a.rs") && !covers("src/*.rs", "src/deep/a.rs"));
+        assert!(covers("/src/*.rs", "src/a.rs") && !covers("src/*.rs", "lib/src/a.rs"));
+        assert!(covers("src/**.rs", "src/deep/a.rs"));
+        assert!(covers("src/**/a.rs", "src/a.rs") && covers("src/**/a.rs", "src/x/y/a.rs"));
+        assert!(covers("*.{js,ts}", "a.ts") && !covers("*.{js,ts}", "a.rs"));
+        assert!(covers("{Makefile,*.{c,h}}", "Makefile") && covers("{Makefile,*.{c,h}}", "lib/x.h"));
+        assert!(covers("v{1..10}.txt", "v7.txt") && !covers("v{1..10}.txt", "v11.txt"));
+        assert!(covers("{single}", "{single}"));
+        assert!(covers("[abc].rs", "b.rs") && !covers("[abc].rs", "d.rs"));
+        assert!(covers("[!abc].rs", "d.rs") && !covers("[!abc].rs", "a.rs"));
+        assert!(covers("a?c", "abc") && !covers("a?c", "a/c"));
+        assert!(covers("\\*.rs", "*.rs") && !covers("\\*.rs", "a.rs"));
+        assert!(!matches("*", Path::new("/p"), Path::new("/q/a.rs")));
+    }
+
+    #[test]
+    fn properties_become_options() {
+        let file = parse("root = true\n; comment\n[*]\nIndent_Style = Space\nindent_size = 4\n[*.mk]\nindent_style = tab\nindent_size = tab\nmax_line_length = off\n");
+        assert!(file.root);
+        assert_eq!(file.sections.len(), 2);
+        let props = |section: &[(String, String)]| Properties(section.iter().cloned().collect());
+        assert_eq!(props(&file.sections[0].1).options(), ["expandtab", "shiftwidth=4", "tabstop=4"]);
+        assert_eq!(props(&file.sections[1].1).options(), ["noexpandtab", "shiftwidth=0", "textwidth=0"]);
+        let props = parse("[*]\nend_of_line = crlf\ntrim_trailing_whitespace = true\ninsert_final_newline = false\nmax_line_length = 80\nindent_size = unset\ntab_width = x\n");
+        assert_eq!(Properties(props.sections[0].1.iter().cloned().collect()).options(),
+            ["fileformat=dos", "trimtrailing", "nofixendofline", "textwidth=80"]);
+    }
+
+    #[test]
+    fn closer_files_and_later_sections_win() {
+        let top = TempDir::new("editorconfig");
+        let dir = top.join("repo/src");
+        top.file(FILE_NAME, "root = true\n[*]\ncharset = latin1\n");
+        top.file("repo/.editorconfig", "[*]\nindent_size = 2\ntab_width = 8\n[*.rs]\nindent_size = 4\n");
+        top.file("repo/src/.editorconfig", "[*.rs]\ntab_width = 4\n[lib.rs]\ntab_width = 3\n");
+        let props = properties(&dir.join("lib.rs")).unwrap();
+        assert_eq!(props.get("indent_size"), Some("4"));
+        assert_eq!(props.get("tab_width"), Some("3"));
+        assert_eq!(props.get("charset"), Some("latin1"));
+        assert_eq!(properties(&dir.join("main.rs")).unwrap().get("tab_width"), Some("4"));
+        // Files above the one marked as the root are not read
+        fs::write(top.join("repo").join(FILE_NAME), "root = true\n[*]\nindent_size = 2\n").unwrap();
+        let props = properties(&dir.join("main.rs")).unwrap();
+        assert_eq!(props.get("indent_size"), Some("2"));
+        assert_eq!(props.get("charset"), None);
+    }
+}
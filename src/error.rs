@@ -1,13 +1,10 @@
 use std::{fmt, error, io};
-use tokio::io as TokioIo;
-use crossterm::ErrorKind;
 
 pub type LxResult<T> = Result<T, LxError>;
 
 #[derive(Debug)]
 pub enum LxError {
     IoError(io::Error),
-    CrosstermError(crossterm::ErrorKind),
     ConfigError(LxConfigError),
 
 }
@@ -15,6 +12,9 @@ pub enum LxError {
 pub enum LxConfigError {
     InvalidKeymap(String),
     InvalidColor(String),
+    InvalidGrammar(String),
+    InvalidTheme(String),
+    InvalidEditorConfig(String),
     UnrecognizedKeyword(String),
     IoError(io::Error),
 }
@@ -26,9 +26,12 @@ impl error::Error for LxConfigError {
 impl fmt::Display for LxConfigError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::IoError(e) => f.write_fmt(format_args!("{}", e.to_string())),
+            Self::IoError(e) => f.write_fmt(format_args!("{}", e)),
             Self::UnrecognizedKeyword(e) => f.write_fmt(format_args!("{}", e)),
             Self::InvalidColor(e) => f.write_fmt(format_args!("{}", e)),
+            Self::InvalidGrammar(e) => f.write_fmt(format_args!("{}", e)),
+            Self::InvalidTheme(e) => f.write_fmt(format_args!("{}", e)),
+            Self::InvalidEditorConfig(e) => f.write_fmt(format_args!("{}", e)),
             Self::InvalidKeymap(e) => f.write_fmt(format_args!("{}", e)),
         }
     }
@@ -37,8 +40,7 @@ impl fmt::Display for LxError {
 
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::IoError(e) => f.write_fmt(format_args!("{}", e.to_string())),
-            Self::CrosstermError(e) => f.write_fmt(format_args!("{}", e.to_string())),
+            Self::IoError(e) => f.write_fmt(format_args!("{}", e)),
             Self::ConfigError(e) => f.write_fmt(format_args!("{}", e)),
         }
     }
@@ -0,0 +1,313 @@
+//! Commands working on lines, as ex has them: a range of lines and then the command, as in
+//! `%s/foo/bar/g`, `2,$d` or `g/TODO/d`. Patterns are regular expressions as the `regex`
+//! crate reads them.
+
+use regex::{Regex, RegexBuilder};
+use tui::backend::Backend;
+
+use crate::{Lx, LxResult, buffer::col_at, register, types::{Cursor, Level}};
+
+/// Lines a command applies to, counting from 0, both ends included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl Range {
+    pub fn line(n: usize) -> Self {
+        Self { first: n, last: n }
+    }
+}
+
+/// Reads a line address off the front of `s`: a line number, `.` for `current` or `$` for
+/// `last`, then any `+N` and `-N` offsets. Gives the line counted from 0, which may be off
+/// the text, and the rest of `s`.
+fn address(s: &str, current: usize, last: usize) -> Option<(isize, &str)> {
+    let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (mut line, mut rest) = match s.chars().next()? {
+        '.' => (current as isize, &s[1..]),
+        '$' => (last as isize, &s[1..]),
+        '+' | '-' => (current as isize, s),
+        c if c.is_ascii_digit() => {
+            let n = digits(s);
+            (s[..n].parse::<isize>().ok()?.max(1) - 1, &s[n..])
+        },
+        _ => return None,
+    };
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let n = digits(&rest[1..]);
+        let by: isize = if n == 0 { 1 } else { rest[1..=n].parse().ok()? };
+        line += if sign == '+' { by } else { -by };
+        rest = &rest[1 + n..];
+    }
+    Some((line, rest))
+}
+
+/// Splits the range off the front of `cmd`: `%` for every line, or one address or two
+/// separated by `,`. The range is `None` when `cmd` doesn't start with one.
+pub fn range(cmd: &str, current: usize, last: usize) -> Result<(Option<Range>, &str), String> {
+    if let Some(rest) = cmd.strip_prefix('%') {
+        return Ok((Some(Range { first: 0, last }), rest));
+    }
+    let (first, rest) = match address(cmd, current, last) {
+        Some(address) => address,
+        None => return Ok((None, cmd)),
+    };
+    let (second, rest) = match rest.strip_prefix(',') {
+        Some(rest) => address(rest, current, last).ok_or("Invalid range")?,
+        None => (first, rest),
+    };
+    let on_text = |l: isize| (0..=last as isize).contains(&l);
+    if !on_text(first) || !on_text(second) {
+        return Err("Invalid range".into());
+    }
+    Ok((Some(Range { first: first.min(second) as usize, last: first.max(second) as usize }), rest))
+}
+
+/// Splits a command into its name, with any `!` after it, and its argument
+pub fn name(cmd: &str) -> (&str, &str) {
+    let mut end = cmd.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(cmd.len());
+    if cmd[end..].starts_with('!') {
+        end += 1;
+    }
+    (&cmd[..end], cmd[end..].trim_start())
+}
+
+/// Reads `n` fields separated by the character `s` starts with, as in `/pattern/text/`,
+/// giving them and what follows the last one. A backslash before the separator makes it
+/// part of a field, and separators at the end may be left off.
+fn fields(s: &str, n: usize) -> Result<(Vec<String>, &str), String> {
+    let delim = s.chars().next()
+        .filter(|c| !c.is_alphanumeric() && !c.is_whitespace() && !matches!(c, '\\' | '"' | '|'))
+        .ok_or("Missing pattern")?;
+    let body = &s[delim.len_utf8()..];
+    let mut fields = vec![String::new()];
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == delim && fields.len() == n {
+            return Ok((fields, &body[i + c.len_utf8()..]));
+        }
+        let field = fields.last_mut().expect("there is always a field");
+        match c {
+            '\\' => match chars.next() {
+                Some((_, d)) if d == delim => field.push(d),
+                Some((_, d)) => {
+                    field.push('\\');
+                    field.push(d);
+                },
+                None => field.push('\\'),
+            },
+            c if c == delim => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields.resize(n, String::new());
+    Ok((fields, ""))
+}
+
+/// Reads the register an argument such as the `a` of `:d a` names: the unnamed register
+/// when it is empty. Only registers text can be put in are taken when `writing`.
+fn register_arg(arg: &str, writing: bool) -> Result<char, String> {
+    let mut chars = arg.trim().chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok(register::UNNAMED),
+        (Some(reg), None) if !writing || register::is_writable(reg) => Ok(reg),
+        _ => Err(format!("Invalid register: {}", arg.trim())),
+    }
+}
+
+/// Turns a replacement written the way vim writes them, with `&` or `\0` for the match and
+/// `\1` to `\9` for groups, into the way `Regex::replace` takes them
+fn replacement(rep: &str) -> String {
+    let mut out = String::new();
+    let mut chars = rep.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(d) if d.is_ascii_digit() => out.push_str(&format!("${{{}}}", d)),
+                Some('n') | Some('r') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('$') => out.push_str("$$"),
+                Some(d) => out.push(d),
+                None => out.push('\\'),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl<W: Backend> Lx<W> {
+    /// The range a command without one applies to: the cursor's line
+    pub fn current_line(&self) -> Range {
+        Range::line(self.cursor().line)
+    }
+    /// Every line of the focused buffer
+    pub fn all_lines(&self) -> Range {
+        Range { first: 0, last: self.buf[self.buf_idx()].line_count() - 1 }
+    }
+    /// Moves the cursor to the first non-blank of `line`
+    pub fn go_to_line(&mut self, line: usize) {
+        let text = self.buf[self.buf_idx()].line(line);
+        let col = col_at(text, text.len() - text.trim_start().len());
+        self.set_cursor(Cursor::new(line, col));
+    }
+    /// `:d [x]`: deletes the lines of `range` into register `x`, or the unnamed register
+    pub fn delete_lines(&mut self, range: Range, arg: &str) {
+        let reg = match register_arg(arg, true) {
+            Ok(reg) => reg,
+            Err(e) => return self.error(e),
+        };
+        let idx = self.buf_idx();
+        let removed = self.buf[idx].delete_lines(range.first, range.last);
+        self.registers.store(reg, removed);
+        self.go_to_line(range.first.min(self.buf[idx].line_count() - 1));
+    }
+    /// `:y [x]`: copies the lines of `range` into register `x`, or the unnamed register
+    pub fn yank_lines(&mut self, range: Range, arg: &str) {
+        let reg = match register_arg(arg, true) {
+            Ok(reg) => reg,
+            Err(e) => return self.error(e),
+        };
+        let buf = &self.buf[self.buf_idx()];
+        let lines: String = buf.lines().skip(range.first).take(range.last + 1 - range.first)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        self.registers.store(reg, lines);
+    }
+    /// `:put [x]`: puts the text of register `x`, or the unnamed register, in lines after
+    /// `line`, moving to the last of them
+    pub fn put_lines(&mut self, line: usize, arg: &str) {
+        let reg = match register_arg(arg, false) {
+            Ok(reg) => reg,
+            Err(e) => return self.error(e),
+        };
+        let text = match self.registers.get(reg) {
+            Some(text) => format!("\n{}", text.strip_suffix('\n').unwrap_or(text)),
+            None => return self.error(format!("Register {} is empty", reg)),
+        };
+        let idx = self.buf_idx();
+        let end = Cursor::new(line, self.buf[idx].line_len(line));
+        let to = self.buf[idx].insert(end, &text);
+        self.go_to_line(to.line);
+    }
+    /// `:s/pattern/replacement/flags`: replaces the first match of the pattern in each line
+    /// of `range`, or every match with the `g` flag. `i` ignores case, and `e` doesn't count
+    /// finding no match as an error.
+    pub fn substitute(&mut self, range: Range, arg: &str) {
+        let (fields, flags) = match fields(arg, 2) {
+            Ok(parsed) => parsed,
+            Err(e) => return self.error(e),
+        };
+        let re = RegexBuilder::new(&fields[0]).case_insensitive(flags.contains('i')).build();
+        let re = match re {
+            Ok(re) => re,
+            Err(e) => return self.error(e),
+        };
+        let rep = replacement(&fields[1]);
+        let idx = self.buf_idx();
+        let mut lines: Vec<String> = self.buf[idx].lines().skip(range.first).take(range.last + 1 - range.first)
+            .map(String::from)
+            .collect();
+        let mut changed = None;
+        for (i, line) in lines.iter_mut().enumerate() {
+            if re.is_match(line) {
+                let replaced = if flags.contains('g') { re.replace_all(line, rep.as_str()) } else { re.replace(line, rep.as_str()) };
+                *line = replaced.into_owned();
+                changed = Some(i);
+            }
+        }
+        match changed {
+            Some(i) => {
+                self.buf[idx].replace_lines(range.first, range.last, &lines);
+                // Replacements may split lines, which moves the last one changed down
+                let split: usize = lines[..=i].iter().map(|l| l.matches('\n').count()).sum();
+                self.go_to_line(range.first + i + split);
+            },
+            None if flags.contains('e') => {},
+            // Not an error, so `:g` and batch mode go on to what comes next, though it
+            // stops a macro as a failed motion does
+            None => {
+                self.abort_macro();
+                self.info(format!("Pattern not found: {}", fields[0]));
+            },
+        }
+    }
+    /// `:g/pattern/command`: runs the command on each line of `range` matching the pattern,
+    /// or with `matching` false on each line that doesn't, as `:v` does. Stops at the first
+    /// command which fails.
+    pub fn global(&mut self, range: Range, arg: &str, matching: bool) -> LxResult<()> {
+        let (fields, cmd) = match fields(arg, 1) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.error(e);
+                return Ok(());
+            },
+        };
+        let re = match Regex::new(&fields[0]) {
+            Ok(re) => re,
+            Err(e) => {
+                self.error(e);
+                return Ok(());
+            },
+        };
+        if matches!(name(cmd.trim_start()).0, "g" | "global" | "g!" | "global!" | "v" | "vglobal") {
+            self.error("Cannot nest :global");
+            return Ok(());
+        }
+        let idx = self.buf_idx();
+        let lines: Vec<usize> = (range.first..=range.last)
+            .filter(|n| re.is_match(self.buf[idx].line(*n)) == matching)
+            .collect();
+        if lines.is_empty() {
+            self.abort_macro();
+            self.info(format!("Pattern not found: {}", fields[0]));
+            return Ok(());
+        }
+        // Lines the commands add or delete move the lines after them
+        let mut shift = 0;
+        self.message = None;
+        for n in lines {
+            let line = n as isize + shift;
+            let count = self.buf[idx].line_count() as isize;
+            if line < 0 || line >= count {
+                continue;
+            }
+            self.go_to_line(line as usize);
+            self.run_cmd(cmd)?;
+            shift += self.buf[idx].line_count() as isize - count;
+            if self.quit || self.message.as_ref().is_some_and(|m| m.level == Level::Error) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(range("%d", 3, 9), Ok((Some(Range { first: 0, last: 9 }), "d")));
+        assert_eq!(range("2,$s/a/b/", 3, 9), Ok((Some(Range { first: 1, last: 9 }), "s/a/b/")));
+        assert_eq!(range(".-1,.+2d", 3, 9), Ok((Some(Range { first: 2, last: 5 }), "d")));
+        assert_eq!(range("set ts=4", 3, 9), Ok((None, "set ts=4")));
+        assert!(range("12", 3, 9).is_err());
+    }
+
+    #[test]
+    fn fields_and_replacements() {
+        assert_eq!(fields("/a\\/b/c", 2), Ok((vec!["a/b".to_string(), "c".to_string()], "")));
+        assert_eq!(fields("#x#y#gi", 2), Ok((vec!["x".to_string(), "y".to_string()], "gi")));
+        assert_eq!(fields("/TODO/d", 1), Ok((vec!["TODO".to_string()], "d")));
+        assert_eq!(replacement("<&> \\1 $5"), "<${0}> ${1} $$5");
+        assert_eq!(register_arg(" a", true), Ok('a'));
+        assert_eq!(register_arg("", true), Ok(register::UNNAMED));
+        assert!(register_arg(":", true).is_err() && register_arg(":", false).is_ok());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+/// One pattern line of a `.gitignore` file
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Vec<char>,
+    negate: bool,
+    dir_only: bool,
+    /// Patterns containing a slash match against the path relative to the `.gitignore`,
+    /// others match against the file name at any depth
+    anchored: bool,
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self { pattern: line.chars().collect(), negate, dir_only, anchored })
+    }
+
+    fn matches(&self, rel: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let target = if self.anchored {
+            rel
+        } else {
+            rel.rsplit('/').next().unwrap_or(rel)
+        };
+        glob_match(&self.pattern, &target.chars().collect::<Vec<char>>())
+    }
+}
+
+/// Matches a gitignore-style glob. `*` and `?` don't cross `/`, `**` does, and `[...]`
+/// matches a character class.
+pub fn glob_match(pat: &[char], s: &[char]) -> bool {
+    match pat.first() {
+        None => s.is_empty(),
+        Some('*') if pat.get(1) == Some(&'*') => {
+            // `**/` may also match nothing at all
+            let rest = if pat.get(2) == Some(&'/') { &pat[3..] } else { &pat[2..] };
+            (0..=s.len()).any(|i| glob_match(rest, &s[i..]))
+        },
+        Some('*') => {
+            let rest = &pat[1..];
+            for i in 0..=s.len() {
+                if glob_match(rest, &s[i..]) {
+                    return true;
+                }
+                if s.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        },
+        Some('?') => matches!(s.first(), Some(c) if *c != '/') && glob_match(&pat[1..], &s[1..]),
+        Some('[') => {
+            let end = match pat.iter().skip(1).position(|c| *c == ']') {
+                Some(end) => end + 1,
+                None => return s.first() == Some(&'[') && glob_match(&pat[1..], &s[1..]),
+            };
+            let c = match s.first() {
+                Some(c) => *c,
+                None => return false,
+            };
+            let class = &pat[1..end];
+            let (negated, class) = match class.first() {
+                Some('!') | Some('^') => (true, &class[1..]),
+                _ => (false, class),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    found |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    found |= class[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && glob_match(&pat[end + 1..], &s[1..])
+        },
+        Some(p) => s.first() == Some(p) && glob_match(&pat[1..], &s[1..]),
+    }
+}
+
+/// Answers whether paths are ignored by the `.gitignore` files of the directories above
+/// them. Rule files, and the directories to look in for each parent, are read once and
+/// cached.
+#[derive(Debug, Default)]
+pub struct GitIgnore {
+    rules: HashMap<PathBuf, Vec<Rule>>,
+    /// For a parent directory as given, its canonical form followed by the directories
+    /// whose rules apply to its children, from the repository root down
+    chains: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl GitIgnore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Forgets cached rules, so edited `.gitignore` files are picked up
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.chains.clear();
+    }
+    fn rules_for(&mut self, dir: &Path) -> &[Rule] {
+        self.rules.entry(dir.to_path_buf()).or_insert_with(|| {
+            fs::read_to_string(dir.join(".gitignore"))
+                .map(|s| s.lines().filter_map(Rule::parse).collect())
+                .unwrap_or_default()
+        })
+    }
+    fn chain_for(&mut self, parent: &Path) -> Vec<PathBuf> {
+        self.chains.entry(parent.to_path_buf()).or_insert_with(|| {
+            let canon = parent.canonicalize().unwrap_or_else(|_| parent.to_path_buf());
+            let mut dirs = Vec::new();
+            for dir in canon.ancestors() {
+                dirs.push(dir.to_path_buf());
+                if dir.join(".git").exists() {
+                    break;
+                }
+            }
+            dirs.reverse();
+            dirs.insert(0, canon);
+            dirs
+        }).clone()
+    }
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> bool {
+        let name = match path.file_name() {
+            Some(name) => name,
+            None => return false,
+        };
+        if name == ".git" {
+            return true;
+        }
+        let chain = self.chain_for(path.parent().unwrap_or_else(|| Path::new(".")));
+        let path = chain[0].join(name);
+        let mut ignored = false;
+        for dir in &chain[1..] {
+            let rel = match path.strip_prefix(dir) {
+                Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
+                Err(_) => continue,
+            };
+            for rule in self.rules_for(dir) {
+                if rule.matches(&rel, is_dir) {
+                    ignored = !rule.negate;
+                }
+            }
+        }
+        ignored
+    }
+}
@@ -0,0 +1,325 @@
+pub mod ignore;
+
+use std::{fmt, fs, io, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+use crate::config;
+
+pub use self::ignore::GitIgnore;
+
+/// Directory deleted files are moved to, so that deletions can be undone
+pub fn trash_dir() -> PathBuf {
+    config::state_dir().join("trash")
+}
+
+/// A filesystem change made from the editor. Every operation records what is needed to
+/// reverse it, `made` being the outermost directory created to hold its result, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileOp {
+    Create { path: PathBuf, dir: bool, made: Option<PathBuf> },
+    /// Renames and moves
+    Move { from: PathBuf, to: PathBuf, made: Option<PathBuf> },
+    Copy { from: PathBuf, to: PathBuf, made: Option<PathBuf> },
+    Trash { path: PathBuf, trashed: PathBuf },
+}
+
+impl FileOp {
+    pub fn create(path: PathBuf, dir: bool) -> Self {
+        Self::Create { made: first_missing(&path), path, dir }
+    }
+    pub fn rename(from: PathBuf, to: PathBuf) -> Self {
+        Self::Move { made: first_missing(&to), from, to }
+    }
+    pub fn copy(from: PathBuf, to: PathBuf) -> Self {
+        Self::Copy { made: first_missing(&to), from, to }
+    }
+    /// Deletion of `path` by moving it to a uniquely-named entry in the trash directory
+    pub fn trash(path: PathBuf) -> Self {
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let trashed = trash_dir().join(format!("{}-{}", stamp, name));
+        Self::Trash { path, trashed }
+    }
+
+    pub fn apply(&self) -> io::Result<()> {
+        match self {
+            Self::Create { path, dir, .. } => {
+                refuse_existing(path)?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if *dir {
+                    fs::create_dir(path)
+                } else {
+                    fs::File::create(path).map(|_| ())
+                }
+            },
+            Self::Move { from, to, .. } => {
+                refuse_existing(to)?;
+                move_path(from, to)
+            },
+            Self::Copy { from, to, .. } => {
+                refuse_existing(to)?;
+                copy_path(from, to)
+            },
+            Self::Trash { path, trashed } => {
+                if let Some(parent) = trashed.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                move_path(path, trashed)
+            },
+        }
+    }
+
+    /// Reverses the operation, refusing to delete anything that changed since it was made
+    pub fn undo(&self) -> io::Result<()> {
+        match self {
+            Self::Create { path, dir: true, made } => {
+                fs::remove_dir(path)?;
+                remove_made(path, made);
+                Ok(())
+            },
+            Self::Create { path, dir: false, made } => {
+                if fs::metadata(path)?.len() > 0 {
+                    return Err(changed(path));
+                }
+                fs::remove_file(path)?;
+                remove_made(path, made);
+                Ok(())
+            },
+            Self::Move { from, to, made } => {
+                refuse_existing(from)?;
+                move_path(to, from)?;
+                remove_made(to, made);
+                Ok(())
+            },
+            Self::Copy { from, to, made } => {
+                if !same_contents(from, to)? {
+                    return Err(changed(to));
+                }
+                if to.is_dir() { fs::remove_dir_all(to)? } else { fs::remove_file(to)? }
+                remove_made(to, made);
+                Ok(())
+            },
+            Self::Trash { path, trashed } => {
+                refuse_existing(path)?;
+                move_path(trashed, path)
+            },
+        }
+    }
+
+    /// The move made by the operation, as `(from, to)`
+    pub fn moved(&self) -> Option<(&Path, &Path)> {
+        match self {
+            Self::Move { from, to, .. } => Some((from, to)),
+            _ => None,
+        }
+    }
+    /// The path the operation deleted
+    pub fn trashed(&self) -> Option<&Path> {
+        match self {
+            Self::Trash { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Create { path, .. } => write!(f, "create {}", path.display()),
+            Self::Move { from, to, .. } => write!(f, "move {} -> {}", from.display(), to.display()),
+            Self::Copy { from, to, .. } => write!(f, "copy {} -> {}", from.display(), to.display()),
+            Self::Trash { path, .. } => write!(f, "delete {}", path.display()),
+        }
+    }
+}
+
+fn refuse_existing(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        Err(io::Error::new(io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display())))
+    } else {
+        Ok(())
+    }
+}
+
+fn changed(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{} has changed since, so it is kept", path.display()))
+}
+
+/// The outermost of the directories that would have to be created to hold `path`
+fn first_missing(path: &Path) -> Option<PathBuf> {
+    path.ancestors().skip(1).take_while(|p| !p.as_os_str().is_empty() && !p.exists()).last().map(Path::to_path_buf)
+}
+
+/// Removes the directories created to hold `path`, up to `made`, as long as they are empty
+fn remove_made(path: &Path, made: &Option<PathBuf>) {
+    let made = match made {
+        Some(made) => made,
+        None => return,
+    };
+    for dir in path.ancestors().skip(1) {
+        if fs::remove_dir(dir).is_err() || dir == made {
+            break;
+        }
+    }
+}
+
+/// Whether `a` and `b` hold the same files with the same contents
+fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    if !a.is_dir() {
+        return Ok(!b.is_dir() && fs::read(a)? == fs::read(b)?);
+    }
+    if !b.is_dir() || fs::read_dir(a)?.count() != fs::read_dir(b)?.count() {
+        return Ok(false);
+    }
+    for entry in fs::read_dir(a)? {
+        let entry = entry?;
+        if !same_contents(&entry.path(), &b.join(entry.file_name()))? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Renames `from` to `to`, falling back to copy and delete across filesystems
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_path(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Where `path` ends up after everything at `from` is moved to `to`, if it was affected
+pub fn relocate(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from).ok().map(|rest| {
+        if rest.as_os_str().is_empty() { to.to_path_buf() } else { to.join(rest) }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    #[test]
+    fn create_and_undo() {
+        let dir = TempDir::new("files-create");
+        let op = FileOp::create(dir.join("a/b/new.rs"), false);
+        op.apply().unwrap();
+        assert!(dir.join("a/b/new.rs").is_file());
+        assert!(op.apply().is_err());
+        op.undo().unwrap();
+        // The directories made for it go too
+        assert!(!dir.join("a").exists());
+        assert!(dir.exists());
+
+        let op = FileOp::create(dir.join("sub"), true);
+        op.apply().unwrap();
+        assert!(dir.join("sub").is_dir());
+        op.undo().unwrap();
+        assert!(!dir.join("sub").exists());
+    }
+
+    #[test]
+    fn undo_keeps_what_changed() {
+        let dir = TempDir::new("files-changed");
+        let op = FileOp::create(dir.join("a/new.rs"), false);
+        op.apply().unwrap();
+        fs::write(dir.join("a/new.rs"), "written").unwrap();
+        assert!(op.undo().is_err());
+        assert_eq!(fs::read_to_string(dir.join("a/new.rs")).unwrap(), "written");
+
+        let op = FileOp::create(dir.join("d"), true);
+        op.apply().unwrap();
+        fs::write(dir.join("d/inside"), "").unwrap();
+        assert!(op.undo().is_err());
+        assert!(dir.join("d/inside").exists());
+
+        let op = FileOp::copy(dir.join("d"), dir.join("e"));
+        op.apply().unwrap();
+        fs::write(dir.join("e/inside"), "edited").unwrap();
+        assert!(op.undo().is_err());
+        assert!(dir.join("e/inside").exists());
+    }
+
+    #[test]
+    fn move_and_undo() {
+        let dir = TempDir::new("files-move");
+        fs::write(dir.join("old.rs"), "text").unwrap();
+        let op = FileOp::rename(dir.join("old.rs"), dir.join("x/y/new.rs"));
+        op.apply().unwrap();
+        assert!(!dir.join("old.rs").exists());
+        assert_eq!(fs::read_to_string(dir.join("x/y/new.rs")).unwrap(), "text");
+        assert_eq!(op.moved(), Some((dir.join("old.rs").as_path(), dir.join("x/y/new.rs").as_path())));
+        op.undo().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("old.rs")).unwrap(), "text");
+        assert!(!dir.join("x").exists());
+
+        fs::write(dir.join("taken.rs"), "").unwrap();
+        assert!(FileOp::rename(dir.join("old.rs"), dir.join("taken.rs")).apply().is_err());
+        assert_eq!(fs::read_to_string(dir.join("old.rs")).unwrap(), "text");
+    }
+
+    #[test]
+    fn copy_and_undo() {
+        let dir = TempDir::new("files-copy");
+        fs::create_dir_all(dir.join("src/inner")).unwrap();
+        fs::write(dir.join("src/inner/f.rs"), "f").unwrap();
+        let op = FileOp::copy(dir.join("src"), dir.join("to/dst"));
+        op.apply().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("to/dst/inner/f.rs")).unwrap(), "f");
+        assert!(dir.join("src/inner/f.rs").exists());
+        op.undo().unwrap();
+        assert!(!dir.join("to").exists());
+        assert!(dir.join("src/inner/f.rs").exists());
+
+        let op = FileOp::copy(dir.join("src/inner/f.rs"), dir.join("g.rs"));
+        op.apply().unwrap();
+        op.undo().unwrap();
+        assert!(!dir.join("g.rs").exists());
+    }
+
+    #[test]
+    fn trash_and_undo() {
+        let dir = TempDir::new("files-trash");
+        fs::write(dir.join("gone.rs"), "text").unwrap();
+        let op = FileOp::Trash { path: dir.join("gone.rs"), trashed: dir.join("trash/1-gone.rs") };
+        op.apply().unwrap();
+        assert!(!dir.join("gone.rs").exists());
+        assert_eq!(op.trashed(), Some(dir.join("gone.rs").as_path()));
+        assert_eq!(op.moved(), None);
+        op.undo().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("gone.rs")).unwrap(), "text");
+        assert!(!dir.join("trash/1-gone.rs").exists());
+
+        op.apply().unwrap();
+        fs::write(dir.join("gone.rs"), "new").unwrap();
+        assert!(op.undo().is_err());
+        assert_eq!(fs::read_to_string(dir.join("gone.rs")).unwrap(), "new");
+    }
+}
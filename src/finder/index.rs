@@ -0,0 +1,49 @@
+use std::{fs, path::PathBuf, thread};
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use crate::files::GitIgnore;
+
+/// Number of paths collected before a batch is sent to the finder
+const BATCH: usize = 512;
+
+/// Walks the tree below `root` on a background thread, streaming batches of file paths
+/// (relative to `root`) as they are found. Hidden and gitignored entries are skipped. The
+/// walk stops early once the receiver is dropped.
+pub fn spawn(root: PathBuf) -> UnboundedReceiver<Vec<PathBuf>> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let mut ignore = GitIgnore::new();
+        let mut stack = vec![root.clone()];
+        let mut batch = Vec::with_capacity(BATCH);
+        while let Some(dir) = stack.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+            entries.sort_by_key(|e| e.file_name());
+            for entry in entries.into_iter().rev() {
+                let path = entry.path();
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                if ignore.is_ignored(&path, is_dir) {
+                    continue;
+                }
+                if is_dir {
+                    stack.push(path);
+                } else {
+                    batch.push(path.strip_prefix(&root).map(PathBuf::from).unwrap_or(path));
+                    if batch.len() >= BATCH
+                        && tx.unbounded_send(std::mem::replace(&mut batch, Vec::with_capacity(BATCH))).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let _ = tx.unbounded_send(batch);
+        }
+    });
+    rx
+}
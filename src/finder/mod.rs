@@ -0,0 +1,365 @@
+pub mod score;
+pub mod index;
+
+use std::{collections::BTreeSet, fs, io::{BufRead, BufReader}, path::PathBuf};
+use futures::{StreamExt, channel::mpsc::UnboundedReceiver, future};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+};
+use crate::{buffer, prefix::FindTarget, theme::Theme};
+
+pub use self::score::{score, Match};
+
+/// Most matches kept for display
+const MAX_SHOWN: usize = 500;
+
+/// Something that can be picked in the finder
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinderItem {
+    File(PathBuf),
+    Buffer(usize),
+    History(String),
+}
+
+/// The floating fuzzy finder over files, buffers or command history.
+///
+/// Items are matched against the query as it is typed. File items are indexed in the
+/// background and arrive in batches, so results fill in while large trees are walked.
+/// A file or buffer query may end in `:<line>`, as in `main.rs:40`, to preview and open
+/// the result at that line.
+pub struct Finder {
+    pub target: FindTarget,
+    pub query: String,
+    root: PathBuf,
+    items: Vec<(String, FinderItem)>,
+    /// Indices into `items` of the current matches, best first
+    matches: Vec<(usize, Match)>,
+    /// Items marked with `<Tab>` for opening together
+    marked: BTreeSet<usize>,
+    list: ListState,
+    index: Option<UnboundedReceiver<Vec<PathBuf>>>,
+    /// The preview last shown, as the item, its first line and its text
+    preview: Option<(usize, usize, String)>,
+}
+
+/// Text of the lines from `first` on read from `lines`, `rows` of them at most
+fn lines_from(lines: impl Iterator<Item = Vec<u8>>, first: usize, rows: usize) -> String {
+    let lines: Vec<Vec<u8>> = lines.skip(first).take(rows).collect();
+    if lines.iter().any(|l| l.contains(&0)) {
+        return "<binary file>".to_string();
+    }
+    lines.iter().map(|l| String::from_utf8_lossy(l)).collect::<Vec<_>>().join("\n")
+}
+
+impl Finder {
+    pub fn new(target: FindTarget, buffers: &[buffer::Buffer], history: &[String]) -> Self {
+        let mut items = Vec::new();
+        let mut index = None;
+        let mut root = PathBuf::from(".");
+        match &target {
+            FindTarget::Files(dir) => {
+                root = dir.clone();
+                index = Some(index::spawn(dir.clone()));
+            },
+            FindTarget::Buffers => {
+                items = buffers.iter().enumerate()
+                    .map(|(i, b)| (b.name(), FinderItem::Buffer(i)))
+                    .collect();
+            },
+            FindTarget::History => {
+                let mut seen = BTreeSet::new();
+                items = history.iter().rev()
+                    .filter(|cmd| seen.insert(cmd.as_str()))
+                    .map(|cmd| (cmd.clone(), FinderItem::History(cmd.clone())))
+                    .collect();
+            },
+        }
+        let mut finder = Self {
+            target,
+            query: String::new(),
+            root,
+            items,
+            matches: Vec::new(),
+            marked: BTreeSet::new(),
+            list: ListState::default(),
+            index,
+            preview: None,
+        };
+        finder.rematch();
+        finder
+    }
+
+    pub fn indexing(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Takes in any paths found by the background indexer since the last call, matching
+    /// only the new items against the query. Returns whether anything arrived.
+    pub fn poll(&mut self) -> bool {
+        let mut arrived = false;
+        while let Some(rx) = self.index.as_mut() {
+            match rx.try_next() {
+                Ok(Some(batch)) => {
+                    self.add(batch);
+                    arrived = true;
+                },
+                Ok(None) => { self.index = None; },
+                Err(_) => break,
+            }
+        }
+        if arrived {
+            self.sort_matches();
+        }
+        arrived
+    }
+
+    /// Waits for the background indexer to find more paths, then takes them in along with
+    /// any others already found. Never finishes once indexing is done.
+    pub async fn indexed(&mut self) {
+        match self.index.as_mut() {
+            Some(rx) => match rx.next().await {
+                Some(batch) => {
+                    self.add(batch);
+                    if !self.poll() {
+                        self.sort_matches();
+                    }
+                },
+                None => self.index = None,
+            },
+            None => future::pending().await,
+        }
+    }
+
+    /// Adds paths found by the indexer, matching them against the query
+    fn add(&mut self, batch: Vec<PathBuf>) {
+        let start = self.items.len();
+        let root = &self.root;
+        self.items.extend(batch.into_iter().map(|p| {
+            let label = p.display().to_string();
+            (label, FinderItem::File(root.join(p)))
+        }));
+        for idx in start..self.items.len() {
+            if let Some(m) = score(self.pattern(), &self.items[idx].0) {
+                self.matches.push((idx, m));
+            }
+        }
+    }
+
+    fn sort_matches(&mut self) {
+        let items = &self.items;
+        self.matches.sort_by(|(a, ma), (b, mb)| mb.score.cmp(&ma.score)
+            .then_with(|| items[*a].0.len().cmp(&items[*b].0.len())));
+        if self.list.selected().is_none() && !self.matches.is_empty() {
+            self.list.select(Some(0));
+        }
+    }
+
+    /// Recomputes matches after the query changed. When characters were only appended,
+    /// just the previous matches need rechecking.
+    fn rematch_from(&mut self, narrowed: bool) {
+        if narrowed {
+            let (query, items) = (self.pattern(), &self.items);
+            self.matches = self.matches.iter()
+                .filter_map(|(idx, _)| score(query, &items[*idx].0).map(|m| (*idx, m)))
+                .collect();
+        } else {
+            let query = self.pattern();
+            self.matches = self.items.iter().enumerate()
+                .filter_map(|(idx, (label, _))| score(query, label).map(|m| (idx, m)))
+                .collect();
+        }
+        self.list.select(None);
+        self.sort_matches();
+    }
+    fn rematch(&mut self) {
+        self.rematch_from(false);
+    }
+    /// The query less any `:<line>` ending it, and the 0-based line it asks for
+    fn split_query(&self) -> (&str, Option<usize>) {
+        if matches!(self.target, FindTarget::History) {
+            return (&self.query, None);
+        }
+        match self.query.rsplit_once(':') {
+            Some((pattern, line)) if line.bytes().all(|b| b.is_ascii_digit()) => {
+                (pattern, line.parse::<usize>().ok().map(|n| n.saturating_sub(1)))
+            },
+            _ => (&self.query, None),
+        }
+    }
+    /// The part of the query matched against items
+    fn pattern(&self) -> &str {
+        self.split_query().0
+    }
+    /// The line asked for with `:<line>` at the end of the query, 0-based
+    pub fn line(&self) -> Option<usize> {
+        self.split_query().1
+    }
+    pub fn push(&mut self, ch: char) {
+        self.query.push(ch);
+        self.rematch_from(true);
+    }
+    pub fn pop(&mut self) {
+        if self.query.pop().is_some() {
+            self.rematch();
+        }
+    }
+
+    pub fn next(&mut self) {
+        let sel = self.list.selected().map_or(0, |i| i + 1);
+        if sel < self.matches.len().min(MAX_SHOWN) {
+            self.list.select(Some(sel));
+        }
+    }
+    pub fn prev(&mut self) {
+        let sel = self.list.selected().unwrap_or(0).saturating_sub(1);
+        if !self.matches.is_empty() {
+            self.list.select(Some(sel));
+        }
+    }
+    /// Marks or unmarks the current item, then moves to the next one
+    pub fn toggle_mark(&mut self) {
+        if let Some(&(idx, _)) = self.list.selected().and_then(|i| self.matches.get(i)) {
+            if !self.marked.remove(&idx) {
+                self.marked.insert(idx);
+            }
+            self.next();
+        }
+    }
+
+    /// The marked items, or the current one if none are marked
+    pub fn chosen(&self) -> Vec<FinderItem> {
+        if self.marked.is_empty() {
+            self.list.selected()
+                .and_then(|i| self.matches.get(i))
+                .map(|(idx, _)| vec![self.items[*idx].1.clone()])
+                .unwrap_or_default()
+        } else {
+            self.marked.iter().map(|idx| self.items[*idx].1.clone()).collect()
+        }
+    }
+
+    /// `rows` lines of the current result, centred on the line the query asks for
+    fn preview_text(&mut self, buffers: &[buffer::Buffer], rows: usize) -> String {
+        let idx = match self.list.selected().and_then(|i| self.matches.get(i)) {
+            Some((idx, _)) => *idx,
+            None => return String::new(),
+        };
+        let first = self.line().map_or(0, |line| line.saturating_sub(rows / 2));
+        if let Some((cached, cached_first, text)) = &self.preview {
+            if (*cached, *cached_first) == (idx, first) {
+                return text.clone();
+            }
+        }
+        let text = match &self.items[idx].1 {
+            // Only as much of the file is read as is shown
+            FinderItem::File(path) => match fs::File::open(path) {
+                Ok(f) => lines_from(BufReader::new(f).split(b'\n').map_while(Result::ok), first, rows),
+                Err(e) => e.to_string(),
+            },
+            FinderItem::Buffer(i) => {
+                let lines = buffers.get(*i).into_iter().flat_map(|b| b.lines()).map(|l| l.as_bytes().to_vec());
+                lines_from(lines, first, rows)
+            },
+            FinderItem::History(cmd) => format!(":{}", cmd),
+        };
+        self.preview = Some((idx, first, text.clone()));
+        text
+    }
+
+    /// Draws the finder as a popup over `area`: the query and results on the left and a
+    /// preview of the current result on the right.
+    pub fn render(&mut self, buffers: &[buffer::Buffer], theme: &Theme, area: Rect, buf: &mut Buffer) {
+        Clear.render(area, buf);
+        let title = match &self.target {
+            FindTarget::Files(dir) => format!("Find files in {}", dir.display()),
+            FindTarget::Buffers => "Find buffers".to_string(),
+            FindTarget::History => "Find history".to_string(),
+        };
+        let block = Block::default().borders(Borders::ALL).title(title)
+            .style(theme.get("ui.popup"))
+            .border_style(theme.get("ui.popup.border"));
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let halves = Layout::default()
+            .direction(tui::layout::Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(inner);
+        let left = Layout::default()
+            .direction(tui::layout::Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(1)].as_ref())
+            .split(halves[0]);
+
+        Paragraph::new(format!("> {}", self.query)).render(left[0], buf);
+        let status = format!("{}/{}{}{}",
+            self.matches.len(),
+            self.items.len(),
+            if self.marked.is_empty() { String::new() } else { format!(" ({} marked)", self.marked.len()) },
+            if self.indexing() { " indexing..." } else { "" });
+        Paragraph::new(status)
+            .style(theme.get("ui.hint"))
+            .render(left[1], buf);
+
+        let bold = theme.get("ui.match");
+        let items: Vec<ListItem> = self.matches.iter().take(MAX_SHOWN)
+            .map(|(idx, m)| {
+                let mark = if self.marked.contains(idx) { "* " } else { "  " };
+                let mut spans = vec![Span::raw(mark)];
+                let mut pos = m.positions.iter().peekable();
+                for (i, c) in self.items[*idx].0.chars().enumerate() {
+                    if pos.peek() == Some(&&i) {
+                        pos.next();
+                        spans.push(Span::styled(c.to_string(), bold));
+                    } else {
+                        spans.push(Span::raw(c.to_string()));
+                    }
+                }
+                ListItem::new(Spans::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(theme.get("ui.selection"));
+        StatefulWidget::render(list, left[2], buf, &mut self.list);
+
+        let preview = self.preview_text(buffers, halves[1].height as usize);
+        Paragraph::new(preview)
+            .block(Block::default().borders(Borders::LEFT))
+            .render(halves[1], buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_is_centred_on_the_line_asked_for() {
+        let text = (1..=100).map(|n| format!("line {}", n)).collect::<Vec<_>>().join("\n");
+        let buffers = [buffer::Buffer { text, path: Some(PathBuf::from("src/lib.rs")), ..Default::default() }];
+        let mut finder = Finder::new(FindTarget::Buffers, &buffers, &[]);
+        for c in "lib:50".chars() {
+            finder.push(c);
+        }
+        assert_eq!(finder.line(), Some(49));
+        assert_eq!(finder.chosen(), [FinderItem::Buffer(0)]);
+        assert_eq!(finder.preview_text(&buffers, 5), "line 48\nline 49\nline 50\nline 51\nline 52");
+        // Near the top it starts at the first line
+        finder.pop();
+        assert_eq!(finder.preview_text(&buffers, 3), "line 4\nline 5\nline 6");
+        finder.pop();
+        assert_eq!(finder.line(), None);
+        assert_eq!(finder.preview_text(&buffers, 2), "line 1\nline 2");
+    }
+
+    #[test]
+    fn history_keeps_colons_in_the_query() {
+        let mut finder = Finder::new(FindTarget::History, &[], &["s/a:1/b".to_string()]);
+        for c in "a:1".chars() {
+            finder.push(c);
+        }
+        assert_eq!(finder.line(), None);
+        assert_eq!(finder.chosen(), [FinderItem::History("s/a:1/b".to_string())]);
+    }
+}
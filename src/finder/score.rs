@@ -0,0 +1,157 @@
+//! Fuzzy matching of a query against candidate strings.
+//!
+//! The query must appear in the candidate as a subsequence. Among all ways of matching it,
+//! the best scoring one is kept: matched characters score more when they start a path
+//! segment, a word after `_`/`-`/`.`/space, or a camelCase hump, and when they follow the
+//! previous match directly. Skipped characters cost a small penalty.
+
+const MATCH: i32 = 16;
+const CONSECUTIVE: i32 = 16;
+const BOUNDARY_PATH: i32 = 32;
+const BOUNDARY_WORD: i32 = 24;
+const BOUNDARY_CAMEL: i32 = 20;
+const BOUNDARY_START: i32 = 28;
+const GAP: i32 = 1;
+/// Bonus for matches inside the last path segment, i.e. the file name
+const FILE_NAME: i32 = 4;
+
+/// A successful match, with the char indices of the candidate that were matched
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i32,
+    pub positions: Vec<usize>,
+}
+
+fn bonus(chars: &[char], j: usize, name_start: usize) -> i32 {
+    let cur = chars[j];
+    let b = match j.checked_sub(1).map(|p| chars[p]) {
+        None => BOUNDARY_START,
+        Some('/') | Some('\\') => BOUNDARY_PATH,
+        Some('_') | Some('-') | Some('.') | Some(' ') => BOUNDARY_WORD,
+        Some(prev) if prev.is_lowercase() && cur.is_uppercase() => BOUNDARY_CAMEL,
+        Some(prev) if !prev.is_alphanumeric() && cur.is_alphanumeric() => BOUNDARY_WORD,
+        Some(prev) if prev.is_alphabetic() && cur.is_numeric() => BOUNDARY_CAMEL,
+        _ => 0,
+    };
+    if j >= name_start { b + FILE_NAME } else { b }
+}
+
+/// Scores `candidate` against `query`. Matching is case-insensitive unless the query
+/// contains an uppercase letter. Returns `None` if the query isn't a subsequence.
+pub fn score(query: &str, candidate: &str) -> Option<Match> {
+    let q: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if q.is_empty() {
+        return Some(Match { score: 0, positions: Vec::new() });
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    let (m, n) = (q.len(), chars.len());
+    if m > n {
+        return None;
+    }
+    let smart_case = q.iter().any(|c| c.is_uppercase());
+    let eq = |a: char, b: char| if smart_case { a == b } else {
+        a == b || a.to_lowercase().eq(b.to_lowercase())
+    };
+    let name_start = chars.iter().rposition(|c| *c == '/' || *c == '\\').map_or(0, |i| i + 1);
+
+    // Quick rejection before the quadratic pass
+    let mut qi = 0;
+    for c in &chars {
+        if qi < m && eq(q[qi], *c) {
+            qi += 1;
+        }
+    }
+    if qi < m {
+        return None;
+    }
+
+    // best[i * n + j]: best score with q[i] matched at chars[j]; back holds the position
+    // q[i - 1] was matched at on that path
+    let mut best: Vec<Option<i32>> = vec![None; m * n];
+    let mut back: Vec<usize> = vec![0; m * n];
+    for i in 0..m {
+        // Best score of the previous row ending strictly before j - 1, less gap penalties
+        let mut run: Option<(i32, usize)> = None;
+        for j in i..n {
+            if i > 0 && j >= 2 {
+                let k = j - 2;
+                run = match (run, best[(i - 1) * n + k]) {
+                    (Some((s, at)), Some(p)) if s - GAP >= p - GAP => Some((s - GAP, at)),
+                    (_, Some(p)) => Some((p - GAP, k)),
+                    (Some((s, at)), None) => Some((s - GAP, at)),
+                    (None, None) => None,
+                };
+            }
+            if !eq(q[i], chars[j]) {
+                continue;
+            }
+            let here = MATCH + bonus(&chars, j, name_start);
+            if i == 0 {
+                // Leading skipped characters are penalised lightly, capped
+                best[j] = Some(here - (j as i32).min(8) * GAP);
+                continue;
+            }
+            let consecutive = best[(i - 1) * n + j - 1].map(|p| (p + CONSECUTIVE, j - 1));
+            let gapped = run;
+            let choice = match (consecutive, gapped) {
+                (Some(c), Some(g)) => Some(if c.0 >= g.0 { c } else { g }),
+                (c, g) => c.or(g),
+            };
+            if let Some((prev, at)) = choice {
+                best[i * n + j] = Some(prev + here);
+                back[i * n + j] = at;
+            }
+        }
+    }
+    let (mut j, total) = (0..n)
+        .filter_map(|j| best[(m - 1) * n + j].map(|s| (j, s)))
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))?;
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        if i > 0 {
+            j = back[i * n + j];
+        }
+    }
+    // Prefer shorter candidates among otherwise equal matches
+    let score = total - (n as i32 - m as i32).min(32) / 4;
+    Some(Match { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(query: &str, candidate: &str) -> i32 {
+        score(query, candidate).unwrap_or_else(|| panic!("{} should match {}", query, candidate)).score
+    }
+
+    #[test]
+    fn path_segments_rank_first() {
+        assert!(points("fb", "foo/bar.rs") > points("fb", "fooba.rs"));
+        assert_eq!(score("fb", "foo/bar.rs").unwrap().positions, [0, 4]);
+        // The file name counts for more than directories above it
+        assert!(points("main", "src/main.rs") > points("main", "main/src.rs"));
+        assert_eq!(score("bf", "foo/bar.rs"), None);
+    }
+
+    #[test]
+    fn camel_humps_and_words() {
+        assert!(points("fb", "FooBar.rs") > points("fb", "Fabric.rs"));
+        assert_eq!(score("gtc", "getTextContent").unwrap().positions, [0, 3, 7]);
+        assert_eq!(score("sl", "status_line.rs").unwrap().positions, [0, 7]);
+    }
+
+    #[test]
+    fn case_and_ties() {
+        assert!(score("fb", "FooBar").is_some());
+        // An uppercase letter makes the query case-sensitive
+        assert_eq!(score("FB", "foobar"), None);
+        assert!(score("FB", "FooBar").is_some());
+        // Equal matches favour the shorter candidate, and the earliest place in it
+        assert!(points("lib", "lib.rs") > points("lib", "lib.rs.orig"));
+        assert_eq!(score("a", "a/a").unwrap().positions, [2]);
+        assert_eq!(score("a", "xxxxxxxx_a_a").unwrap().positions, [9]);
+        assert_eq!(score("", "any"), Some(Match { score: 0, positions: Vec::new() }));
+    }
+}
@@ -0,0 +1,539 @@
+//! Folds: ranges of lines which, closed, show as a single summary line that vertical
+//! motions and operators on lines take as one line. Folds are made by hand with `zf`, or
+//! found from the indentation, markers or syntax of the text as `foldmethod` says. Each
+//! buffer keeps its own, which follow lines added and deleted above them. Folds of files
+//! are written to the state directory on exit and read back at startup.
+
+use std::{cmp::Reverse, collections::BTreeMap, fs, io, path::PathBuf, time::{Duration, Instant}};
+use tui::backend::Backend;
+
+use crate::{Lx, buffer::{Buffer, indent}, config, ex::Range, mark::absolute, mode::Mode, options::{FoldMethod, FoldOptions, Indent}};
+
+/// Time spent highlighting a buffer to find its syntax folds, which are found once the
+/// whole buffer is highlighted
+const SYNTAX_BUDGET: Duration = Duration::from_millis(8);
+
+/// Lines `start` to `end` of a buffer, inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+    pub closed: bool,
+}
+
+/// The `z` commands opening and closing folds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FoldOp {
+    /// `zo`: open `n` levels of closed folds at the cursor
+    Open(usize),
+    /// `zc`: close `n` levels of open folds at the cursor
+    Close(usize),
+    /// `za`: open the closed fold at the cursor, or close the open one
+    Toggle(usize),
+    /// `zR`
+    OpenAll,
+    /// `zM`
+    CloseAll,
+    /// `zd`: delete the fold at the cursor, keeping those it holds
+    Delete,
+    /// `zE`
+    DeleteAll,
+}
+
+/// The folds of a buffer
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Folds {
+    /// By first line, with folds before those they hold
+    folds: Vec<Fold>,
+    /// Version of the text and method the folds were found for, unless made by hand
+    found: Option<(u64, FoldMethod)>,
+}
+
+impl Folds {
+    pub fn iter(&self) -> impl Iterator<Item = &Fold> {
+        self.folds.iter()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+    /// Folds holding `line`, outermost first
+    fn holding(&self, line: usize) -> impl Iterator<Item = &Fold> {
+        self.folds.iter().filter(move |f| f.start <= line && line <= f.end)
+    }
+    /// First and last line of the outermost closed fold holding `line`, which all show as
+    /// one line
+    pub fn closed_at(&self, line: usize) -> Option<(usize, usize)> {
+        self.holding(line).find(|f| f.closed).map(|f| (f.start, f.end))
+    }
+    /// The line `line` shows on: the first of the closed fold holding it, or itself
+    pub fn shown_line(&self, line: usize) -> usize {
+        self.closed_at(line).map_or(line, |(start, _)| start)
+    }
+    /// The line shown after `line`, past the closed fold holding it
+    pub fn next_line(&self, line: usize) -> usize {
+        self.closed_at(line).map_or(line, |(_, end)| end) + 1
+    }
+    /// `n` shown lines down from `line`, stopping at `last`
+    pub fn down(&self, line: usize, n: usize, last: usize) -> usize {
+        let mut line = self.shown_line(line);
+        for _ in 0..n {
+            match self.next_line(line) {
+                next if next > last => break,
+                next => line = next,
+            }
+        }
+        line
+    }
+    /// `n` shown lines up from `line`, stopping at the first
+    pub fn up(&self, line: usize, n: usize) -> usize {
+        let mut line = self.shown_line(line);
+        for _ in 0..n {
+            if line == 0 {
+                break;
+            }
+            line = self.shown_line(line - 1);
+        }
+        line
+    }
+    /// Lines `first` to `last` taking in the whole of closed folds at either end
+    pub fn widen(&self, first: usize, last: usize) -> (usize, usize) {
+        (self.shown_line(first), self.closed_at(last).map_or(last, |(_, end)| end))
+    }
+    /// Number of folds holding `line`
+    pub fn level(&self, line: usize) -> usize {
+        self.holding(line).count()
+    }
+    fn sort(&mut self) {
+        self.folds.sort_by_key(|f| (f.start, Reverse(f.end)));
+    }
+    /// Adds a closed fold made by hand, taking in any fold it overlaps
+    pub fn add(&mut self, start: usize, end: usize) {
+        let (mut start, mut end) = (start.min(end), start.max(end));
+        while let Some(f) = self.folds.iter().find(|f| (f.start < start && start <= f.end && f.end < end)
+            || (start < f.start && f.start <= end && end < f.end)) {
+            (start, end) = (start.min(f.start), end.max(f.end));
+        }
+        self.folds.retain(|f| (f.start, f.end) != (start, end));
+        self.folds.push(Fold { start, end, closed: true });
+        self.sort();
+    }
+    /// Opens `n` levels of the closed folds holding `line`, outermost first
+    pub fn open(&mut self, line: usize, n: usize) {
+        for f in self.folds.iter_mut().filter(|f| f.closed && f.start <= line && line <= f.end).take(n) {
+            f.closed = false;
+        }
+    }
+    /// Closes `n` levels of the open folds holding `line`, starting from the innermost, or
+    /// from the one holding the outermost closed fold
+    pub fn close(&mut self, line: usize, n: usize) {
+        for _ in 0..n {
+            let holding: Vec<usize> = (0..self.folds.len())
+                .filter(|i| self.folds[*i].start <= line && line <= self.folds[*i].end)
+                .collect();
+            let closed = holding.iter().position(|i| self.folds[*i].closed).unwrap_or(holding.len());
+            match closed.checked_sub(1) {
+                Some(k) => self.folds[holding[k]].closed = true,
+                None => break,
+            }
+        }
+    }
+    /// Opens the closed fold holding `line`, or closes the innermost open one
+    pub fn toggle(&mut self, line: usize, n: usize) {
+        if self.closed_at(line).is_some() {
+            self.open(line, n);
+        } else {
+            self.close(line, n);
+        }
+    }
+    /// Opens every fold holding `line`, so it shows
+    pub fn reveal(&mut self, line: usize) {
+        self.open(line, usize::MAX);
+    }
+    pub fn set_all(&mut self, closed: bool) {
+        self.set_within(0, usize::MAX, closed);
+    }
+    /// Opens or closes the folds with lines from `first` to `last`
+    pub fn set_within(&mut self, first: usize, last: usize, closed: bool) {
+        for f in self.folds.iter_mut().filter(|f| f.start <= last && first <= f.end) {
+            f.closed = closed;
+        }
+    }
+    /// Deletes the closed fold holding `line`, or else the innermost fold holding it.
+    /// Returns whether there was one.
+    pub fn delete(&mut self, line: usize) -> bool {
+        let at = |f: &Fold| f.start <= line && line <= f.end;
+        let idx = self.folds.iter().position(|f| f.closed && at(f)).or_else(|| self.folds.iter().rposition(at));
+        idx.map(|i| self.folds.remove(i)).is_some()
+    }
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+    /// Keeps folds on the lines they were made on when `removed` lines after `line` are
+    /// deleted and `added` lines are inserted there, as `BufferMarks::shift` does. Folds
+    /// left without lines are deleted.
+    pub fn shift(&mut self, line: usize, removed: usize, added: usize) {
+        if removed == added {
+            return;
+        }
+        let moved = |l: usize, start: bool| match l {
+            l if l <= line => l,
+            l if l > line + removed => l - removed + added,
+            _ if start => line + 1,
+            _ => line,
+        };
+        for f in self.folds.iter_mut() {
+            (f.start, f.end) = (moved(f.start, true), moved(f.end, false));
+        }
+        self.folds.retain(|f| f.start <= f.end);
+    }
+    /// Whether the folds were found for text `version` with `method`
+    fn is_current(&self, version: u64, method: FoldMethod) -> bool {
+        self.found == Some((version, method))
+    }
+    /// Takes the folds found for text `version` with `method` in place of the old ones.
+    /// Folds start closed where closed folds started before.
+    fn set_found(&mut self, found: Vec<(usize, usize)>, version: u64, method: FoldMethod) {
+        let closed: Vec<usize> = self.folds.iter().filter(|f| f.closed).map(|f| f.start).collect();
+        self.folds = found.into_iter().map(|(start, end)| Fold { start, end, closed: closed.contains(&start) }).collect();
+        self.found = Some((version, method));
+    }
+    /// Keeps the folds found so far as folds made by hand, once `foldmethod` is manual
+    fn keep_found(&mut self) {
+        self.found = None;
+    }
+}
+
+/// The summary a closed fold of `lines` lines shows: its level in dashes, the number of
+/// lines and the text of its first line `first`
+pub fn summary(first: &str, lines: usize, level: usize) -> String {
+    format!("+-{} {:>3} lines: {}", "-".repeat(level), lines, first.trim().replace('\t', " "))
+}
+
+/// The folds of `buf` as its fold method finds them, nested in one another
+pub fn find(buf: &Buffer, options: &FoldOptions, indent: &Indent) -> Vec<(usize, usize)> {
+    nest(match options.foldmethod {
+        FoldMethod::Manual => Vec::new(),
+        FoldMethod::Indent => by_indent(buf, indent),
+        FoldMethod::Marker => by_marker(buf, &options.foldmarker),
+        FoldMethod::Syntax => by_syntax(buf),
+    })
+}
+
+/// Runs of lines indented a level or more further than `shiftwidth` steps, each level
+/// folded inside the one before it. Blank lines take the shallower level of the lines
+/// around them, so those between blocks stay out of their folds.
+fn by_indent(buf: &Buffer, indent: &Indent) -> Vec<(usize, usize)> {
+    let levels: Vec<Option<usize>> = buf.lines()
+        .map(|l| (!l.trim().is_empty()).then(|| indent::width(l, indent.tabstop) / indent.width()))
+        .collect();
+    let mut after = vec![0; levels.len()];
+    let mut next = 0;
+    for (n, level) in levels.iter().enumerate().rev() {
+        after[n] = next;
+        next = level.unwrap_or(next);
+    }
+    let mut found = Vec::new();
+    // Where the fold of each level from the first is open from
+    let mut open: Vec<usize> = Vec::new();
+    let mut before = 0;
+    for (n, level) in levels.iter().enumerate() {
+        let level = level.unwrap_or_else(|| before.min(after[n]));
+        before = levels[n].unwrap_or(before);
+        while open.len() > level {
+            found.extend(open.pop().map(|start| (start, n - 1)));
+        }
+        while open.len() < level {
+            open.push(n);
+        }
+    }
+    found.extend(open.into_iter().map(|start| (start, levels.len() - 1)));
+    found
+}
+
+/// From each line holding the start marker to the line holding its end marker. A start
+/// without an end runs to the last line.
+fn by_marker(buf: &Buffer, (start, end): &(String, String)) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut open = Vec::new();
+    for (n, line) in buf.lines().enumerate() {
+        let mut markers: Vec<(usize, bool)> = line.match_indices(start.as_str()).map(|(i, _)| (i, true))
+            .chain(line.match_indices(end.as_str()).map(|(i, _)| (i, false)))
+            .collect();
+        markers.sort();
+        for (_, starts) in markers {
+            match starts {
+                true => open.push(n),
+                false => found.extend(open.pop().map(|s| (s, n))),
+            }
+        }
+    }
+    let last = buf.line_count() - 1;
+    found.extend(open.into_iter().map(|s| (s, last)));
+    found
+}
+
+/// Brackets and highlighted regions, such as block comments, spanning lines. Brackets
+/// in strings and comments don't count.
+fn by_syntax(buf: &Buffer) -> Vec<(usize, usize)> {
+    let h = match &buf.syntax {
+        Some(h) => h,
+        None => return Vec::new(),
+    };
+    let mut found = h.regions();
+    let mut open: Vec<(char, usize)> = Vec::new();
+    for (n, line) in buf.lines().enumerate() {
+        for (i, c) in line.char_indices() {
+            let opener = match c {
+                '}' => '{',
+                ')' => '(',
+                ']' => '[',
+                '{' | '(' | '[' if h.is_code(n, i) => {
+                    open.push((c, n));
+                    continue;
+                },
+                _ => continue,
+            };
+            if !h.is_code(n, i) {
+                continue;
+            }
+            if let Some(k) = open.iter().rposition(|(o, _)| *o == opener) {
+                let start = open[k].1;
+                open.truncate(k);
+                if n > start {
+                    found.push((start, n));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// `found` nested in one another, with folds of a single line dropped. A fold which
+/// starts inside another and ends after it, as the `else` block in `} else {` does,
+/// ends the other on the line before it.
+fn nest(mut found: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    found.sort_by_key(|(start, end)| (*start, Reverse(*end)));
+    found.dedup();
+    let mut nested: Vec<(usize, usize)> = Vec::with_capacity(found.len());
+    // Folds in `nested` holding the one being added, outermost first
+    let mut holding: Vec<usize> = Vec::new();
+    for (start, end) in found {
+        while let Some(&i) = holding.last() {
+            let outer = &mut nested[i];
+            if outer.1 >= end {
+                break;
+            }
+            if outer.1 >= start {
+                outer.1 = start - 1;
+            }
+            holding.pop();
+        }
+        holding.push(nested.len());
+        nested.push((start, end));
+    }
+    nested.retain(|(start, end)| end > start);
+    nested.sort_by_key(|(start, end)| (*start, Reverse(*end)));
+    nested.dedup();
+    nested
+}
+
+/// Folds of files which aren't open, by file
+#[derive(Debug, Default)]
+pub struct FileFolds(BTreeMap<PathBuf, Vec<Fold>>);
+
+impl FileFolds {
+    /// Reads lines of `fold START END open|closed PATH`, skipping any that don't read
+    pub fn parse(text: &str) -> Self {
+        let mut files = Self::default();
+        for line in text.lines() {
+            let mut fields = line.splitn(5, ' ');
+            let kind = fields.next();
+            let (start, end) = (fields.next().and_then(|s| s.parse().ok()), fields.next().and_then(|e| e.parse().ok()));
+            let closed = match fields.next() {
+                Some("closed") => Some(true),
+                Some("open") => Some(false),
+                _ => None,
+            };
+            if let (Some("fold"), Some(start), Some(end), Some(closed), Some(path)) = (kind, start, end, closed, fields.next()) {
+                files.0.entry(PathBuf::from(path)).or_default().push(Fold { start, end, closed });
+            }
+        }
+        files
+    }
+    pub fn write(&self) -> String {
+        self.0.iter().flat_map(|(path, folds)| folds.iter().map(move |f| {
+            format!("fold {} {} {} {}\n", f.start, f.end, if f.closed { "closed" } else { "open" }, path.display())
+        })).collect()
+    }
+    /// Keeps `folds` for the file at `path`, dropping what was kept if there are none
+    fn set(&mut self, path: PathBuf, folds: &Folds) {
+        if folds.is_empty() {
+            self.0.remove(&path);
+        } else {
+            self.0.insert(path, folds.iter().copied().collect());
+        }
+    }
+}
+
+/// File the folds are kept in between sessions
+fn state_file() -> PathBuf {
+    config::state_dir().join("folds")
+}
+
+impl<W: Backend> Lx<W> {
+    /// Finds the folds of the buffers shown in the current tab again where their text or
+    /// fold method changed. In Insert mode the folds at the cursor open.
+    pub fn update_folds(&mut self) {
+        let deadline = Instant::now() + SYNTAX_BUDGET;
+        let shown: Vec<usize> = self.tabs[self.tab_idx].windows.iter().map(|w| w.buf_idx).collect();
+        for idx in shown {
+            let buf = &mut self.buf[idx];
+            let options = buf.fold_options(&self.options).clone();
+            let method = options.foldmethod;
+            if method == FoldMethod::Manual {
+                buf.folds.keep_found();
+                continue;
+            }
+            if buf.folds.is_current(buf.version, method) || (method == FoldMethod::Syntax && !buf.highlight(usize::MAX, deadline)) {
+                continue;
+            }
+            let found = find(buf, &options, buf.indent(&self.options));
+            let version = buf.version;
+            buf.folds.set_found(found, version, method);
+        }
+        if matches!(self.mode, Mode::Insert(_)) {
+            let (idx, line) = (self.buf_idx(), self.cursor().line);
+            self.buf[idx].folds.reveal(line);
+        }
+    }
+    /// Opens or closes folds at the cursor, or deletes them
+    pub fn fold_op(&mut self, op: FoldOp) {
+        self.update_folds();
+        let (idx, line) = (self.buf_idx(), self.cursor().line);
+        let manual = self.buf[idx].fold_options(&self.options).foldmethod == FoldMethod::Manual;
+        let folds = &mut self.buf[idx].folds;
+        match op {
+            FoldOp::Open(n) => folds.open(line, n),
+            FoldOp::Close(n) => folds.close(line, n),
+            FoldOp::Toggle(n) => folds.toggle(line, n),
+            FoldOp::OpenAll => folds.set_all(false),
+            FoldOp::CloseAll => folds.set_all(true),
+            FoldOp::Delete | FoldOp::DeleteAll if !manual => {
+                return self.error("Cannot delete fold with current foldmethod");
+            },
+            FoldOp::Delete => {
+                if !folds.delete(line) {
+                    return self.error("No fold found");
+                }
+            },
+            FoldOp::DeleteAll => folds.clear(),
+        }
+        self.scroll_to_cursor();
+    }
+    /// `zf` and `:fold`: folds lines `first` to `last` by hand, closed
+    pub fn fold_lines(&mut self, first: usize, last: usize) {
+        let idx = self.buf_idx();
+        if self.buf[idx].fold_options(&self.options).foldmethod != FoldMethod::Manual {
+            return self.error("Cannot create fold with current foldmethod");
+        }
+        self.buf[idx].folds.add(first, last);
+        self.set_cursor(self.cursor());
+    }
+    /// `:foldopen` and `:foldclose`: opens or closes the folds with lines in `range`
+    pub fn set_folds(&mut self, range: Range, closed: bool) {
+        self.update_folds();
+        let idx = self.buf_idx();
+        self.buf[idx].folds.set_within(range.first, range.last, closed);
+        self.scroll_to_cursor();
+    }
+    /// Gives buffer `idx` the folds kept for its file, as it is opened
+    pub fn adopt_folds(&mut self, idx: usize) {
+        let path = match self.buf[idx].path.as_deref() {
+            Some(path) => absolute(path),
+            None => return,
+        };
+        if let Some(folds) = self.file_folds.0.remove(&path) {
+            let buf = &mut self.buf[idx];
+            let last = buf.line_count() - 1;
+            buf.folds = Folds { folds: folds.into_iter().filter(|f| f.start <= f.end && f.end <= last).collect(), found: None };
+            buf.folds.sort();
+        }
+    }
+    /// Keeps the folds of buffer `idx` as it is closed
+    pub fn keep_folds(&mut self, idx: usize) {
+        if let Some(path) = self.buf[idx].path.as_deref().map(absolute) {
+            self.file_folds.set(path, &self.buf[idx].folds);
+        }
+    }
+    /// Writes the folds of files, open or not, to the state file
+    pub fn save_folds(&mut self) -> io::Result<()> {
+        for idx in 0..self.buf.len() {
+            self.keep_folds(idx);
+        }
+        fs::create_dir_all(config::state_dir())?;
+        fs::write(state_file(), self.file_folds.write())
+    }
+    /// Reads back the folds of the last session, giving open buffers theirs. A missing
+    /// state file is no error.
+    pub fn load_folds(&mut self) -> io::Result<()> {
+        let text = match fs::read_to_string(state_file()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        self.file_folds = FileFolds::parse(&text);
+        for idx in 0..self.buf.len() {
+            if self.buf[idx].folds.is_empty() {
+                self.adopt_folds(idx);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer { text: text.to_string(), ..Buffer::new() }
+    }
+
+    #[test]
+    fn indent_folds_nest() {
+        let buf = buffer("fn a() {\n    if x {\n        y\n    }\n\n    z\n}\n\nfn b() {}");
+        let indent = Indent { shiftwidth: 4, ..Indent::default() };
+        assert_eq!(find(&buf, &FoldOptions { foldmethod: FoldMethod::Indent, ..FoldOptions::default() }, &indent), vec![(1, 5)]);
+        let buf = buffer("a\n  b\n    c\n    d\n  e");
+        let indent = Indent { shiftwidth: 2, ..Indent::default() };
+        assert_eq!(by_indent(&buf, &indent), vec![(2, 3), (1, 4)]);
+        assert_eq!(nest(by_indent(&buf, &indent)), vec![(1, 4), (2, 3)]);
+    }
+
+    #[test]
+    fn overlapping_folds_nest() {
+        // `if {` on 0, `} else {` on 3, `}` on 6
+        assert_eq!(nest(vec![(0, 3), (3, 6), (1, 2)]), vec![(0, 2), (1, 2), (3, 6)]);
+        let buf = buffer("a {{{\nb\n{{{ c }}}\nd }}}\ne {{{\nf");
+        assert_eq!(find(&buf, &FoldOptions { foldmethod: FoldMethod::Marker, ..FoldOptions::default() }, &Indent::default()),
+            vec![(0, 3), (4, 5)]);
+    }
+
+    #[test]
+    fn folds_open_close_and_shift() {
+        let mut folds = Folds::default();
+        folds.add(2, 8);
+        folds.add(4, 5);
+        assert_eq!(folds.closed_at(5), Some((2, 8)));
+        assert_eq!((folds.down(1, 1, 20), folds.down(1, 2, 20), folds.up(9, 1)), (2, 9, 2));
+        folds.open(5, 1);
+        assert_eq!(folds.closed_at(5), Some((4, 5)));
+        folds.close(5, 1);
+        assert_eq!(folds.closed_at(5), Some((2, 8)));
+        // Deleting lines 3 to 4 takes the inner fold's start with them
+        folds.shift(2, 2, 0);
+        assert_eq!(folds.iter().map(|f| (f.start, f.end)).collect::<Vec<_>>(), vec![(2, 6), (3, 3)]);
+        let kept = FileFolds::parse(&format!("fold 2 6 closed /a b\njunk\n{}", "fold 1 x open /c"));
+        assert_eq!(kept.write(), "fold 2 6 closed /a b\n");
+    }
+}
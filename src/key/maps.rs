@@ -1,14 +1,12 @@
-use crate::error::{LxConfigError, LxError};
-use serde::{Serializer, Deserializer, Serialize, Deserialize};
-use std::convert::TryFrom;
-use crossterm::{
-    event::{Event, KeyEvent, KeyModifiers, KeyCode},
-};
+use crate::error::LxConfigError;
+use serde::{Serializer, Deserializer, Serialize, Deserialize, de::Error as DeError};
+use std::{convert::TryFrom, fmt};
+use super::{KeyEvent, KeyModifiers, KeyCode};
 
 #[derive(Debug, PartialEq)]
 pub struct KeyEv(KeyEvent);
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum KeyMap {
     Char(char),
     Ctrl(char),
@@ -24,7 +22,7 @@ impl TryFrom<String> for KeyMap {
     fn try_from(map: String) -> Result<Self, Self::Error> {
         let map_lc = map.to_lowercase();
         if map.len() == 1 {
-            let ch = map.chars().nth(0).unwrap();
+            let ch = map.chars().next().unwrap();
             return Ok( KeyMap::Char(ch) );
         } else if map.len() == 3 {
             if map_lc.starts_with("c-") {
@@ -46,7 +44,27 @@ impl TryFrom<String> for KeyMap {
                 return Ok( KeyMap::CtrlAlt(ch))
             }
         }
-        return Err(LxConfigError::InvalidKeymap(map.to_string()));
+        Err(LxConfigError::InvalidKeymap(map))
+    }
+}
+
+impl fmt::Display for KeyMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyMap::Char(c) => write!(f, "{}", c),
+            KeyMap::Ctrl(c) => write!(f, "c-{}", c),
+            KeyMap::Alt(c) => write!(f, "a-{}", c),
+            KeyMap::CtrlAlt(c) => write!(f, "c-a-{}", c),
+            KeyMap::CtrlShift(c) => write!(f, "c-s-{}", c),
+            KeyMap::AltShift(c) => write!(f, "a-s-{}", c),
+        }
+    }
+}
+
+impl KeyMap {
+    /// Whether the given key event is the one produced by this mapping
+    pub fn matches(&self, ke: &KeyEvent) -> bool {
+        KeyEvent::from(*self) == *ke
     }
 }
 
@@ -55,15 +73,7 @@ impl Serialize for KeyMap {
     where
         S: Serializer
     {
-        match self {
-            KeyMap::Char(c) => s.serialize_newtype_variant("map", 0, "", &c),
-            KeyMap::Ctrl(c) => s.serialize_newtype_variant("map", 0, "c-", &c),
-            KeyMap::Alt(c) => s.serialize_newtype_variant("map", 0, "a-", &c),
-            KeyMap::CtrlAlt(c) => s.serialize_newtype_variant("map", 0, "c-a-", &c),
-            KeyMap::CtrlShift(c) => s.serialize_newtype_variant("map", 0, "c-s-", &c),
-            KeyMap::AltShift(c) => s.serialize_newtype_variant("map", 0, "a-s-", &c),
-        }
-
+        s.serialize_str(&self.to_string())
     }
 }
 
@@ -72,19 +82,22 @@ impl<'de> Deserialize<'de> for KeyMap {
     where
         D: Deserializer<'de>
     {
-        unimplemented!();
-        Ok(KeyMap::Char('c'))
+        let map = String::deserialize(de)?;
+        KeyMap::try_from(map).map_err(|e| D::Error::custom(e.to_string()))
     }
 }
 
+/// Letters with Shift held come in upper case, as terminals report them
 impl From<KeyMap> for KeyEvent {
     fn from(map: KeyMap) -> KeyEvent {
         match map {
+            KeyMap::Char('\t') => KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Tab },
+            KeyMap::Char('\n') => KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter },
             KeyMap::Char(c) => KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Char(c) },
             KeyMap::Ctrl(c) => KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char(c) },
             KeyMap::Alt(c) => KeyEvent { modifiers: KeyModifiers::ALT, code: KeyCode::Char(c) },
-            KeyMap::CtrlShift(c) => KeyEvent { modifiers: KeyModifiers::CONTROL|KeyModifiers::SHIFT, code: KeyCode::Char(c) },
-            KeyMap::AltShift(c) => KeyEvent{ modifiers: KeyModifiers::ALT|KeyModifiers::SHIFT, code: KeyCode::Char(c) },
+            KeyMap::CtrlShift(c) => KeyEvent { modifiers: KeyModifiers::CONTROL|KeyModifiers::SHIFT, code: KeyCode::Char(c.to_ascii_uppercase()) },
+            KeyMap::AltShift(c) => KeyEvent{ modifiers: KeyModifiers::ALT|KeyModifiers::SHIFT, code: KeyCode::Char(c.to_ascii_uppercase()) },
             KeyMap::CtrlAlt(c) => KeyEvent{ modifiers: KeyModifiers::CONTROL|KeyModifiers::ALT, code: KeyCode::Char(c) }
         }
     }
@@ -92,11 +105,11 @@ impl From<KeyMap> for KeyEvent {
 
 #[derive(Serialize, Deserialize)]
 pub struct KeyMaps {
-    cursor: DirectionKeys,
-    navigation: CycleKeys,
-    edit_toggle: KeyMap,
-    focus_toggle: KeyMap,
-    overview_toggle: KeyMap,
+    pub cursor: DirectionKeys,
+    pub navigation: CycleKeys,
+    pub edit_toggle: KeyMap,
+    pub focus_toggle: KeyMap,
+    pub overview_toggle: KeyMap,
 }
 
 impl Default for KeyMaps {
@@ -123,19 +136,13 @@ impl Default for KeyMaps {
 
 #[derive(Serialize, Deserialize)]
 pub struct DirectionKeys {
-    up: KeyMap,
-    down: KeyMap,
-    left: KeyMap,
-    right: KeyMap,
+    pub up: KeyMap,
+    pub down: KeyMap,
+    pub left: KeyMap,
+    pub right: KeyMap,
 }
 #[derive(Serialize, Deserialize)]
 pub struct CycleKeys {
-    next: KeyMap,
-    previous: KeyMap
-}
-
-macro_rules! event {
-    ($key:expr) => {
-        Event { 0: $key }
-    }
+    pub next: KeyMap,
+    pub previous: KeyMap
 }
@@ -1,7 +1,9 @@
+pub mod event;
 pub mod maps;
+pub mod notation;
 
-use crossterm::event::{KeyModifiers, KeyCode, KeyEvent};
-use crate::op::{Operation, GlobalOp, EditOp, InsertOp, CommandOp};
+pub use self::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::op::Operation;
 
 pub trait EditPrefixKey: Default {
     type Op: Operation + Default;
@@ -14,11 +16,8 @@ pub trait EditPrefixKey: Default {
     }
     /// Matches the key event immediately following the triggering of this prefix to the
     /// corresponding operation. ONLY applies for prefix keys.
-    fn match_key_op(ke: KeyEvent) -> Option<Self::Op> {
-        match ke {
-            KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Char(' ') }=>  { return Some(Self::Op::default()); }
-            _ => { return Some(Self::Op::default()); }
-        }
+    fn match_key_op(_ke: KeyEvent) -> Option<Self::Op> {
+        Some(Self::Op::default())
     }
 }
 
@@ -46,11 +45,8 @@ pub trait GlobalPrefixKey: Default {
     }
     /// Matches the key event immediately following the triggering of this prefix to the
     /// corresponding operation
-    fn match_key_op(ke: KeyEvent) -> Option<Self::Op> {
-        match ke {
-            KeyEvent{modifiers: KeyModifiers::NONE, code: KeyCode::Char(' ') }=>  { return Some(Self::Op::default()); }
-            _ => { return Some(Self::Op::default()); }
-        }
+    fn match_key_op(_ke: KeyEvent) -> Option<Self::Op> {
+        Some(Self::Op::default())
     }
 }
 
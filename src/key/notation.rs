@@ -0,0 +1,210 @@
+use super::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The key a `<name>` stands for, without modifiers
+fn named(name: &str) -> Option<KeyCode> {
+    Some(match name {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "del" | "delete" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "gt" => KeyCode::Char('>'),
+        "bar" => KeyCode::Char('|'),
+        "bslash" => KeyCode::Char('\\'),
+        _ => match name.strip_prefix('f').map(str::parse) {
+            Some(Ok(n)) => KeyCode::F(n),
+            _ => return None,
+        },
+    })
+}
+
+/// The name a key is written with inside `<...>`, the other way from `named`
+fn name(code: KeyCode) -> Option<String> {
+    Some(match code {
+        KeyCode::Esc => "esc".into(),
+        KeyCode::Enter => "cr".into(),
+        KeyCode::Tab | KeyCode::BackTab => "tab".into(),
+        KeyCode::Backspace => "bs".into(),
+        KeyCode::Delete => "del".into(),
+        KeyCode::Insert => "insert".into(),
+        KeyCode::Up => "up".into(),
+        KeyCode::Down => "down".into(),
+        KeyCode::Left => "left".into(),
+        KeyCode::Right => "right".into(),
+        KeyCode::Home => "home".into(),
+        KeyCode::End => "end".into(),
+        KeyCode::PageUp => "pageup".into(),
+        KeyCode::PageDown => "pagedown".into(),
+        KeyCode::F(n) => format!("f{}", n),
+        KeyCode::Char(' ') => "space".into(),
+        KeyCode::Char('<') => "lt".into(),
+        KeyCode::Char('>') => "gt".into(),
+        KeyCode::Char('|') => "bar".into(),
+        KeyCode::Char('\\') => "bslash".into(),
+        KeyCode::Char(c) => c.to_lowercase().to_string(),
+        KeyCode::Null => return None,
+    })
+}
+
+/// The key a character is typed with. Terminals report capitals with Shift held.
+fn char_key(c: char) -> KeyEvent {
+    let modifiers = if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+    KeyEvent { modifiers, code: KeyCode::Char(c) }
+}
+
+/// The key inside `<...>`: a name or a character, after any of the `c-`, `a-` (or `m-`) and
+/// `s-` modifiers
+fn special_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+        modifiers |= match rest.as_bytes()[0].to_ascii_lowercase() {
+            b'c' => KeyModifiers::CONTROL,
+            b'a' | b'm' => KeyModifiers::ALT,
+            b's' => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        rest = &rest[2..];
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        // `<x>` is just those three keys
+        (Some(_), None) if modifiers.is_empty() => return None,
+        // Terminals report letters in upper case with Shift held, otherwise in lower case
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+        (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+        _ => named(&rest.to_lowercase())?,
+    };
+    Some(match code {
+        KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyEvent { modifiers, code: KeyCode::BackTab },
+        code => KeyEvent { modifiers, code },
+    })
+}
+
+/// Parses keys written the way vim writes them: characters stand for themselves, and
+/// special keys go in angle brackets, as in `hello<esc>`, `<c-w>l` or `<lt>` for `<`.
+/// A `<` not starting a known key is just a `<`.
+pub fn parse(keys: &str) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        let special = rest.strip_prefix('<')
+            .and_then(|r| r.find('>').map(|end| &r[..end]))
+            .and_then(|name| Some((special_key(name)?, name.len() + 2)));
+        match special {
+            Some((key, len)) => {
+                events.push(key);
+                rest = &rest[len..];
+            },
+            None => {
+                events.push(char_key(c));
+                rest = &rest[c.len_utf8()..];
+            },
+        }
+    }
+    events
+}
+
+/// Writes keys the way `parse` reads them, so `parse(&format(keys))` gives `keys` back.
+/// Keys with modifiers are written as `KeyMap` writes them, in angle brackets, as in
+/// `<c-w>` or `<c-s-a>`. Keys with no name are left out. Letters are written the way
+/// terminals report them, in capitals with Shift held, whichever of the two a key came with.
+pub fn format(keys: &[KeyEvent]) -> String {
+    let mut out = String::new();
+    for ke in keys {
+        let mut ke = *ke;
+        match ke.code {
+            KeyCode::Char(c) if c.is_uppercase() => ke.modifiers |= KeyModifiers::SHIFT,
+            KeyCode::Char(c) if c.is_lowercase() && ke.modifiers.contains(KeyModifiers::SHIFT) => {
+                ke.code = KeyCode::Char(c.to_ascii_uppercase());
+            },
+            _ => {},
+        }
+        match ke.code {
+            KeyCode::Char('<') if ke.modifiers.is_empty() => out.push_str("<lt>"),
+            KeyCode::Char(c) if ke == char_key(c) => out.push(c),
+            code => {
+                let name = match name(code) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let held = [(KeyModifiers::CONTROL, "c-"), (KeyModifiers::ALT, "a-"), (KeyModifiers::SHIFT, "s-")];
+                out.push('<');
+                for (modifier, prefix) in held.iter() {
+                    if ke.modifiers.contains(*modifier) || (*modifier == KeyModifiers::SHIFT && code == KeyCode::BackTab) {
+                        out.push_str(prefix);
+                    }
+                }
+                out.push_str(&name);
+                out.push('>');
+            },
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::maps::KeyMap;
+
+    #[test]
+    fn parses_special_keys() {
+        let key = |modifiers, code| KeyEvent { modifiers, code };
+        assert_eq!(parse("iA<esc>"), vec![
+            key(KeyModifiers::NONE, KeyCode::Char('i')),
+            key(KeyModifiers::SHIFT, KeyCode::Char('A')),
+            key(KeyModifiers::NONE, KeyCode::Esc),
+        ]);
+        assert_eq!(parse("<C-Space><c-w><S-Tab>"), vec![
+            key(KeyModifiers::CONTROL, KeyCode::Char(' ')),
+            key(KeyModifiers::CONTROL, KeyCode::Char('w')),
+            key(KeyModifiers::SHIFT, KeyCode::BackTab),
+        ]);
+        assert_eq!(parse("<b><lt>"), "<b><".chars().map(char_key).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn formats_keys_back() {
+        let keys = "qaA<lt>b<esc>:s/x/y/<cr><c-w>l<a-s-x><f5> @a";
+        assert_eq!(format(&parse(keys)), keys);
+        let maps = [KeyMap::Ctrl('w'), KeyMap::CtrlShift('a'), KeyMap::Alt('>'), KeyMap::Char('\n')];
+        for map in maps.iter() {
+            let key = KeyEvent::from(*map);
+            assert_eq!(parse(&format(&[key])), vec![key], "{}", map);
+        }
+    }
+
+    #[test]
+    fn formats_every_printable_key_back() {
+        let held = [KeyModifiers::SHIFT, KeyModifiers::CONTROL, KeyModifiers::ALT];
+        for c in ' '..='~' {
+            for bits in 0..1 << held.len() {
+                let modifiers = (0..held.len()).filter(|i| bits & 1 << i != 0)
+                    .fold(KeyModifiers::NONE, |all, i| all | held[i]);
+                let key = KeyEvent::new(KeyCode::Char(c), modifiers);
+                // Capitals come back with Shift held, and letters with Shift held in upper case
+                let shifted = c.is_ascii_uppercase() || (c.is_ascii_lowercase() && modifiers.contains(KeyModifiers::SHIFT));
+                let expected = match shifted {
+                    true => KeyEvent::new(KeyCode::Char(c.to_ascii_uppercase()), modifiers | KeyModifiers::SHIFT),
+                    false => key,
+                };
+                let formatted = format(&[key]);
+                assert_eq!(parse(&formatted), vec![expected], "{:?} as {:?}", key, formatted);
+                assert_eq!(format(&[expected]), formatted, "{:?}", key);
+            }
+        }
+        assert_eq!(format(&[KeyEvent::from(KeyMap::Char('A'))]), "A");
+    }
+}
@@ -0,0 +1,49 @@
+//! The lx editor: buffers, operations, keymaps, modes and commands, drawn with tui to any
+//! backend and driven by `key::KeyEvent`s. `term` runs it on a crossterm terminal, which
+//! the `vi` binary does.
+
+pub mod prefix;
+pub mod app;
+pub mod config;
+pub mod error;
+pub mod key;
+pub mod types;
+pub mod mode;
+pub mod op;
+pub mod ui;
+pub mod overview;
+pub mod window;
+pub mod register;
+pub mod mark;
+pub mod fold;
+pub mod pair;
+pub mod buffer;
+pub mod change;
+pub mod files;
+pub mod finder;
+pub mod syntax;
+pub mod theme;
+pub mod options;
+pub mod vcs;
+pub mod editorconfig;
+pub mod swap;
+pub mod prompt;
+pub mod cli;
+pub mod ex;
+pub mod macros;
+pub mod batch;
+pub mod watch;
+#[cfg(feature = "term")]
+pub mod term;
+
+pub use self::{
+    app::Lx,
+    error::{LxError, LxResult},
+    types::Direction,
+    mode::Mode,
+    key::{EditKey, GlobalKey, GlobalPrefixKey, EditPrefixKey},
+    prefix::{Prefix, MotionPre, WindowPre, SearchPre, TabPre, LeaderPre, BufferPre},
+};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,95 @@
+//! Macros: keys recorded into a register with `q{register}` and played back with
+//! `@{register}`. Registers hold the keys written the way `key::notation` writes them, so
+//! a macro can be put in a buffer with `:put`, edited there and deleted back with `:d`.
+
+use tui::backend::Backend;
+
+use crate::{Lx, LxResult, key::notation, register};
+
+/// Keys one `@` may play, counting those of the macros it plays in turn, so a macro which
+/// calls itself and never fails still stops
+const MAX_REPLAYED: usize = 1 << 20;
+
+/// A macro being recorded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recording {
+    pub reg: char,
+    /// Index in `Lx::prev_keys` of the first key recorded
+    pub from: usize,
+}
+
+impl<W: Backend> Lx<W> {
+    /// `q{reg}`: records the keys typed from now on into `reg`
+    pub fn start_recording(&mut self, reg: char) {
+        // The register's key is added to `prev_keys` once it is handled
+        self.recording = Some(Recording { reg, from: self.prev_keys.len() + 1 });
+    }
+    /// `q` while recording: puts the keys typed since recording started in the register
+    pub fn stop_recording(&mut self) {
+        if let Some(rec) = self.recording.take() {
+            let from = rec.from.min(self.prev_keys.len());
+            self.registers.store(rec.reg, notation::format(&self.prev_keys[from..]));
+        }
+    }
+    /// `@{reg}`: handles the keys in `reg` as if typed, `count` times over. `@@` plays the
+    /// last macro played again, and `@:` runs the last command. Playing stops at the first
+    /// error or motion which can't move.
+    pub fn play_macro(&mut self, reg: char, count: usize) -> LxResult<()> {
+        let reg = match (reg, self.last_macro) {
+            ('@', Some(last)) => last,
+            ('@', None) => {
+                self.error("No previous macro");
+                return Ok(());
+            },
+            (reg, _) => reg.to_ascii_lowercase(),
+        };
+        let text = match self.registers.get(reg) {
+            Some(text) => text.to_string(),
+            None => {
+                self.error(format!("Register {} is empty", reg));
+                return Ok(());
+            },
+        };
+        self.last_macro = Some(reg);
+        if reg == register::COMMAND {
+            for _ in 0..count {
+                self.run_cmd(&text)?;
+            }
+            return Ok(());
+        }
+        // A line break ending the register, as `:d` leaves one, isn't played
+        let keys = notation::parse(text.strip_suffix('\n').unwrap_or(&text));
+        // The keys go before those left of a macro playing this one, which carries on after
+        for _ in 0..count {
+            for ke in keys.iter().rev() {
+                self.replay.push_front(*ke);
+            }
+        }
+        if self.replaying {
+            return Ok(());
+        }
+        self.replaying = true;
+        let mut played = 0;
+        let result = loop {
+            let ke = match self.replay.pop_front() {
+                Some(ke) if !self.quit => ke,
+                _ => break Ok(()),
+            };
+            played += 1;
+            if played > MAX_REPLAYED {
+                self.error(format!("Macro stopped after {} keys", MAX_REPLAYED));
+                break Ok(());
+            }
+            if let Err(e) = self.match_key_event(ke) {
+                break Err(e);
+            }
+        };
+        self.replay.clear();
+        self.replaying = false;
+        result
+    }
+    /// Drops the keys of the macros playing, for a motion which fails
+    pub fn abort_macro(&mut self) {
+        self.replay.clear();
+    }
+}
@@ -1,41 +1,48 @@
-pub mod prefix;
-pub mod app;
-pub mod config;
-pub mod error;
-pub mod key;
-pub mod types;
-pub mod mode;
-pub mod op;
-pub mod ui;
-
-pub use self::{
-    app::Lx,
-    error::{LxError, LxResult},
-    types::Direction,
-    mode::Mode,
-    key::{EditKey, GlobalKey, GlobalPrefixKey, EditPrefixKey},
-    prefix::{Prefix, MotionPre, WindowPre, SearchPre, TabPre, LeaderPre, BufferPre},
-};
+use std::{env, io::{self, Write}, process};
+use lx::{Lx, LxResult, batch, cli::{self, Action}};
 
 #[tokio::main]
 async fn main() -> LxResult<()> {
 
+    let mut argv = env::args();
+    let program = cli::program(argv.next());
+    let args = match cli::parse(argv) {
+        Ok(Action::Edit(args)) => args,
+        Ok(Action::Help) => {
+            print!("{}", cli::help(&program));
+            return Ok(());
+        },
+        Ok(Action::Version) => {
+            println!("{}", cli::version());
+            return Ok(());
+        },
+        Err(e) => {
+            eprintln!("{}: {}\n{}", program, e, cli::usage(&program));
+            process::exit(2);
+        },
+    };
+    if args.batch {
+        process::exit(if batch::run(&args) { 0 } else { 1 });
+    }
+    let (filter, clean) = (args.filter, args.clean);
     let mut t = Lx::default();
-    t.run()?;
-    Ok(())
-
-}
-
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn test() {
+    t.start(args)?;
+    t.run().await?;
+    // Marks, jumps and folds are left alone by a session which didn't read them
+    if !clean {
+        if let Err(e) = t.save_marks() {
+            eprintln!("{}: Saving marks: {}", program, e);
+        }
+        if let Err(e) = t.save_folds() {
+            eprintln!("{}: Saving folds: {}", program, e);
+        }
     }
-
-    pub struct TestStruct {
-        field1: String,
-        num: i32,
+    match t.output {
+        Some(bytes) => io::stdout().write_all(&bytes)?,
+        // Nothing written means nothing for the rest of the pipeline
+        None if filter => process::exit(1),
+        None => {},
     }
-}
+    Ok(())
 
+}
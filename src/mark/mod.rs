@@ -0,0 +1,451 @@
+//! Marks, the jumplist and the changelist. Named marks and places of changes are kept in
+//! each buffer, where they follow lines added and deleted above them. Marks `A` to `Z`
+//! name a file as well, and are kept here while their file isn't open, as is the
+//! jumplist. Both are written to the state directory on exit and read back at startup.
+
+use std::{collections::BTreeMap, env, fs, io, path::{Path, PathBuf}};
+use tui::backend::Backend;
+
+use crate::{Lx, buffer::{Buffer, col_at}, config, types::{Cursor, Direction}};
+
+/// Jumps kept in the jumplist, and changes in a buffer's changelist
+const LIST_LENGTH: usize = 100;
+
+/// Mark set to where the cursor was before the last jump, for `''`
+pub const BEFORE_JUMP: char = '\'';
+
+/// Whether `name` is a mark `m` can set: a letter, or `'` for the place before a jump
+pub fn is_settable(name: char) -> bool {
+    name.is_ascii_alphabetic() || name == BEFORE_JUMP
+}
+
+/// `path` from the root, as marks and jumps of files are kept, so they still lead there
+/// from another directory
+pub fn absolute(path: &Path) -> PathBuf {
+    env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Marks of a buffer and places it was changed, which move with its lines
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BufferMarks {
+    named: BTreeMap<char, Cursor>,
+    /// Where changes were made, oldest first
+    changes: Vec<Cursor>,
+    /// Place in `changes` of the last `g;` or `g,`, or its end after a new change
+    change_idx: usize,
+}
+
+impl BufferMarks {
+    pub fn get(&self, name: char) -> Option<Cursor> {
+        self.named.get(&name).copied()
+    }
+    pub fn set(&mut self, name: char, at: Cursor) {
+        self.named.insert(name, at);
+    }
+    pub fn remove(&mut self, name: char) -> Option<Cursor> {
+        self.named.remove(&name)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (char, Cursor)> + '_ {
+        self.named.iter().map(|(name, at)| (*name, *at))
+    }
+    /// Places changes were made, oldest first
+    pub fn changes(&self) -> &[Cursor] {
+        &self.changes
+    }
+    /// Notes a change at `at`. One on the line of the last change takes its place.
+    pub fn changed(&mut self, at: Cursor) {
+        if self.changes.last().is_some_and(|last| last.line == at.line) {
+            self.changes.pop();
+        } else if self.changes.len() == LIST_LENGTH {
+            self.changes.remove(0);
+        }
+        self.changes.push(at);
+        self.change_idx = self.changes.len();
+    }
+    /// `g;` and `g,`: the place of the change `n` older or newer than the last one stepped
+    /// to, if there is one
+    pub fn step_change(&mut self, dir: Direction, n: usize) -> Option<Cursor> {
+        let idx = match dir {
+            Direction::Prev => self.change_idx.checked_sub(n)?,
+            Direction::Next => self.change_idx + n,
+        };
+        let at = *self.changes.get(idx)?;
+        self.change_idx = idx;
+        Some(at)
+    }
+    /// Keeps marks on the lines they were set on when `removed` lines after `line` are
+    /// deleted and `added` lines are inserted there. Marks on deleted lines are deleted,
+    /// while changes made there move to `line`.
+    pub fn shift(&mut self, line: usize, removed: usize, added: usize) {
+        if removed == added {
+            return;
+        }
+        let moved = |l: usize| match l {
+            l if l <= line => Some(l),
+            l if l > line + removed => Some(l - removed + added),
+            _ => None,
+        };
+        self.named = std::mem::take(&mut self.named).into_iter()
+            .filter_map(|(name, at)| Some((name, Cursor::new(moved(at.line)?, at.col))))
+            .collect();
+        for at in self.changes.iter_mut() {
+            *at = moved(at.line).map_or(Cursor::new(line, 0), |l| Cursor::new(l, at.col));
+        }
+    }
+}
+
+/// Where a jump or mark leads: an open buffer, or a file no buffer holds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Place {
+    Buffer(usize),
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jump {
+    pub place: Place,
+    pub at: Cursor,
+}
+
+/// Marks `A` to `Z` of files which aren't open
+#[derive(Debug, Default)]
+pub struct Marks(BTreeMap<char, (PathBuf, Cursor)>);
+
+impl Marks {
+    pub fn get(&self, name: char) -> Option<&(PathBuf, Cursor)> {
+        self.0.get(&name)
+    }
+    pub fn set(&mut self, name: char, path: PathBuf, at: Cursor) {
+        self.0.insert(name, (path, at));
+    }
+    pub fn remove(&mut self, name: char) -> Option<(PathBuf, Cursor)> {
+        self.0.remove(&name)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&char, &(PathBuf, Cursor))> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Takes out the marks of the file at `path`
+    fn take_file(&mut self, path: &Path) -> Vec<(char, Cursor)> {
+        let names: Vec<char> = self.0.iter().filter(|(_, (p, _))| p == path).map(|(name, _)| *name).collect();
+        names.into_iter().filter_map(|name| self.0.remove(&name).map(|(_, at)| (name, at))).collect()
+    }
+}
+
+/// Places jumped from, oldest first, stepped through with `<C-o>` and `<C-i>`
+#[derive(Debug, Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    /// Place in `jumps` of the last step, or its end after a new jump
+    idx: usize,
+}
+
+impl JumpList {
+    /// Adds a jump from `jump`, in place of any earlier one from the same line
+    pub fn push(&mut self, jump: Jump) {
+        self.jumps.retain(|j| !(j.place == jump.place && j.at.line == jump.at.line));
+        if self.jumps.len() == LIST_LENGTH {
+            self.jumps.remove(0);
+        }
+        self.jumps.push(jump);
+        self.idx = self.jumps.len();
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Jump> {
+        self.jumps.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.jumps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.jumps.is_empty()
+    }
+    /// Index of the jump the last step went to, or the length after a new jump
+    pub fn position(&self) -> usize {
+        self.idx
+    }
+    /// The jump `n` older or newer than the last one stepped to, if there is one. Stepping
+    /// back from the newest adds `from`, so stepping forward comes back to it.
+    pub fn step(&mut self, dir: Direction, n: usize, from: Jump) -> Option<Jump> {
+        if dir == Direction::Prev && self.idx == self.jumps.len() {
+            self.push(from);
+            self.idx = self.jumps.len() - 1;
+        }
+        let idx = match dir {
+            Direction::Prev => self.idx.checked_sub(n)?,
+            Direction::Next => self.idx + n,
+        };
+        let jump = self.jumps.get(idx)?.clone();
+        self.idx = idx;
+        Some(jump)
+    }
+    /// Points jumps into buffer `idx` at its file, if it has one, as it is closed, and
+    /// those into later buffers at their new index
+    fn closed(&mut self, idx: usize, path: Option<&Path>) {
+        self.jumps.retain_mut(|jump| match jump.place {
+            Place::Buffer(i) if i == idx => match path {
+                Some(path) => {
+                    jump.place = Place::File(path.to_path_buf());
+                    true
+                },
+                None => false,
+            },
+            Place::Buffer(i) if i > idx => {
+                jump.place = Place::Buffer(i - 1);
+                true
+            },
+            _ => true,
+        });
+        self.idx = self.idx.min(self.jumps.len());
+    }
+}
+
+/// File marks and jumps as written to the state file, all with their files
+#[derive(Debug, Default, PartialEq)]
+pub struct Saved {
+    pub marks: Vec<(char, PathBuf, Cursor)>,
+    pub jumps: Vec<(PathBuf, Cursor)>,
+}
+
+impl Saved {
+    /// Reads lines of `mark A LINE COL PATH` and `jump LINE COL PATH`, skipping any that
+    /// don't read
+    pub fn parse(text: &str) -> Self {
+        let mut saved = Self::default();
+        for line in text.lines() {
+            let mut fields = line.splitn(5, ' ');
+            let kind = fields.next();
+            let name = if kind == Some("mark") { fields.next().and_then(|n| n.chars().next()) } else { None };
+            let at = (fields.next().and_then(|l| l.parse().ok()), fields.next().and_then(|c| c.parse().ok()));
+            let path = fields.next().map(PathBuf::from);
+            match (kind, name, at, path) {
+                (Some("mark"), Some(name), (Some(line), Some(col)), Some(path)) => saved.marks.push((name, path, Cursor::new(line, col))),
+                (Some("jump"), None, (Some(line), Some(col)), Some(path)) => saved.jumps.push((path, Cursor::new(line, col))),
+                _ => {},
+            }
+        }
+        saved
+    }
+    pub fn write(&self) -> String {
+        let marks = self.marks.iter().map(|(name, path, at)| format!("mark {} {} {} {}\n", name, at.line, at.col, path.display()));
+        let jumps = self.jumps.iter().map(|(path, at)| format!("jump {} {} {}\n", at.line, at.col, path.display()));
+        marks.chain(jumps).collect()
+    }
+}
+
+/// File the marks and jumps are kept in between sessions
+fn state_file() -> PathBuf {
+    config::state_dir().join("marks")
+}
+
+impl<W: Backend> Lx<W> {
+    /// Where the cursor is now, as a jump back to it
+    fn here(&self) -> Jump {
+        Jump { place: Place::Buffer(self.buf_idx()), at: self.cursor() }
+    }
+    /// Adds the cursor's place to the jumplist before a jump away from it, and sets the
+    /// `'` mark there
+    pub fn push_jump(&mut self) {
+        let (idx, here) = (self.buf_idx(), self.here());
+        self.buf[idx].marks.set(BEFORE_JUMP, here.at);
+        self.jumps.push(here);
+    }
+    /// `m{name}`: sets a mark at the cursor. `A` to `Z` are taken from any other file.
+    pub fn set_mark(&mut self, name: char) {
+        if name.is_ascii_uppercase() {
+            self.marks.remove(name);
+            for buf in self.buf.iter_mut() {
+                buf.marks.remove(name);
+            }
+        }
+        let (idx, at) = (self.buf_idx(), self.cursor());
+        self.buf[idx].marks.set(name, at);
+    }
+    /// Where mark `name` is: in the focused buffer for `a` to `z` and `'`, or in any buffer
+    /// or file for `A` to `Z`
+    pub fn mark(&self, name: char) -> Option<Jump> {
+        let idx = self.buf_idx();
+        if !name.is_ascii_uppercase() {
+            return self.buf[idx].marks.get(name).map(|at| Jump { place: Place::Buffer(idx), at });
+        }
+        let open = self.buf.iter().enumerate()
+            .find_map(|(i, b)| b.marks.get(name).map(|at| Jump { place: Place::Buffer(i), at }));
+        open.or_else(|| self.marks.get(name).map(|(path, at)| Jump { place: Place::File(path.clone()), at: *at }))
+    }
+    /// `'{name}` and `` `{name} ``: jumps to a mark, to its first non-blank unless `exact`
+    pub fn go_to_mark(&mut self, name: char, exact: bool) {
+        let (name, exact) = if name == '`' { (BEFORE_JUMP, true) } else { (name, exact) };
+        let mut jump = match self.mark(name) {
+            Some(jump) => jump,
+            None => return self.error(format!("Mark not set: {}", name)),
+        };
+        self.push_jump();
+        if !self.go_to(&jump.place) {
+            return;
+        }
+        if !exact {
+            let text = self.buf[self.buf_idx()].line(jump.at.line.min(self.buf[self.buf_idx()].line_count() - 1));
+            jump.at.col = col_at(text, text.len() - text.trim_start().len());
+        }
+        self.set_cursor(jump.at);
+    }
+    /// Jumps to `jump`, as picked from a list of marks or jumps
+    pub fn jump_to(&mut self, jump: &Jump) {
+        self.push_jump();
+        if self.go_to(&jump.place) {
+            self.set_cursor(jump.at);
+        }
+    }
+    /// `<C-o>` and `<C-i>`: goes `n` places back or forward through the jumplist
+    pub fn step_jump(&mut self, dir: Direction, n: usize) {
+        let here = self.here();
+        let jump = match self.jumps.step(dir, n, here) {
+            Some(jump) => jump,
+            None => return,
+        };
+        if self.go_to(&jump.place) {
+            self.set_cursor(jump.at);
+        }
+    }
+    /// `g;` and `g,`: goes `n` places back or forward through the focused buffer's changes
+    pub fn step_change(&mut self, dir: Direction, n: usize) {
+        let idx = self.buf_idx();
+        match self.buf[idx].marks.step_change(dir, n) {
+            Some(at) => self.set_cursor(at),
+            None if self.buf[idx].marks.changes().is_empty() => self.error("Change list is empty"),
+            None => {},
+        }
+    }
+    /// Shows the buffer of `place` in the focused window, opening its file if need be,
+    /// without adding a jump. Returns whether it is shown.
+    fn go_to(&mut self, place: &Place) -> bool {
+        match place {
+            Place::Buffer(idx) if *idx < self.buf.len() => {
+                self.tabs[self.tab_idx].window_mut().show(*idx);
+                true
+            },
+            Place::Buffer(_) => false,
+            Place::File(path) => {
+                match self.load(path) {
+                    Ok(idx) => {
+                        self.tabs[self.tab_idx].window_mut().show(idx);
+                        true
+                    },
+                    Err(e) => {
+                        self.error(format!("{}: {}", path.display(), e));
+                        false
+                    },
+                }
+            },
+        }
+    }
+    /// Moves marks `A` to `Z` kept for the file of buffer `idx` into the buffer, as it is
+    /// opened
+    pub fn adopt_marks(&mut self, idx: usize) {
+        if let Some(path) = self.buf[idx].path.as_deref().map(absolute) {
+            for (name, at) in self.marks.take_file(&path) {
+                self.buf[idx].marks.set(name, at);
+            }
+        }
+    }
+    /// Keeps the file marks and jumps of buffer `idx` as it is closed
+    pub fn keep_marks(&mut self, idx: usize) {
+        let path = self.buf[idx].path.as_deref().map(absolute);
+        if let Some(path) = &path {
+            for (name, at) in self.buf[idx].marks.iter().filter(|(name, _)| name.is_ascii_uppercase()) {
+                self.marks.set(name, path.clone(), at);
+            }
+        }
+        self.jumps.closed(idx, path.as_deref());
+    }
+    /// The file marks and jumps to keep for the next session
+    pub fn saved_marks(&self) -> Saved {
+        let path = |place: &Place| match place {
+            Place::Buffer(idx) => self.buf.get(*idx).and_then(|b| b.path.as_deref()).map(absolute),
+            Place::File(path) => Some(path.clone()),
+        };
+        let mut marks: Vec<(char, PathBuf, Cursor)> = self.marks.iter().map(|(name, (path, at))| (*name, path.clone(), *at)).collect();
+        for (i, buf) in self.buf.iter().enumerate() {
+            for (name, at) in buf.marks.iter().filter(|(name, _)| name.is_ascii_uppercase()) {
+                if let Some(path) = path(&Place::Buffer(i)) {
+                    marks.push((name, path, at));
+                }
+            }
+        }
+        marks.sort_by_key(|(name, ..)| *name);
+        let jumps = self.jumps.iter().filter_map(|j| Some((path(&j.place)?, j.at))).collect();
+        Saved { marks, jumps }
+    }
+    /// Writes the file marks and jumps to the state file
+    pub fn save_marks(&self) -> io::Result<()> {
+        fs::create_dir_all(config::state_dir())?;
+        fs::write(state_file(), self.saved_marks().write())
+    }
+    /// Reads back the file marks and jumps of the last session, in place of the jumps made
+    /// so far. A missing state file is no error.
+    pub fn load_marks(&mut self) -> io::Result<()> {
+        let text = match fs::read_to_string(state_file()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let saved = Saved::parse(&text);
+        for (name, path, at) in saved.marks {
+            self.marks.set(name, path, at);
+        }
+        self.jumps = JumpList::default();
+        for (path, at) in saved.jumps {
+            self.jumps.push(Jump { place: Place::File(path), at });
+        }
+        for idx in 0..self.buf.len() {
+            self.adopt_marks(idx);
+        }
+        Ok(())
+    }
+}
+
+/// Every mark to be seen from buffer `buf_idx`: its own marks, then `A` to `Z` wherever
+/// they are, in order of name
+pub fn list(buffers: &[Buffer], buf_idx: usize, marks: &Marks) -> Vec<(char, Jump)> {
+    let own = buffers[buf_idx].marks.iter().filter(|(name, _)| !name.is_ascii_uppercase())
+        .map(|(name, at)| (name, Jump { place: Place::Buffer(buf_idx), at }));
+    let open = buffers.iter().enumerate().flat_map(|(i, b)| {
+        b.marks.iter().filter(|(name, _)| name.is_ascii_uppercase()).map(move |(name, at)| (name, Jump { place: Place::Buffer(i), at }))
+    });
+    let files = marks.iter().map(|(name, (path, at))| (*name, Jump { place: Place::File(path.clone()), at: *at }));
+    let mut all: Vec<(char, Jump)> = own.chain(open).chain(files).collect();
+    all.sort_by_key(|(name, _)| *name);
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_follow_their_lines() {
+        let mut marks = BufferMarks::default();
+        marks.set('a', Cursor::new(2, 3));
+        marks.set('b', Cursor::new(5, 0));
+        marks.set('c', Cursor::new(8, 1));
+        marks.shift(3, 0, 2);
+        assert_eq!((marks.get('a'), marks.get('b'), marks.get('c')), (Some(Cursor::new(2, 3)), Some(Cursor::new(7, 0)), Some(Cursor::new(10, 1))));
+        // Deleting lines 6 to 7 into line 5 takes `b` with them
+        marks.shift(5, 2, 0);
+        assert_eq!((marks.get('b'), marks.get('c')), (None, Some(Cursor::new(8, 1))));
+    }
+
+    #[test]
+    fn saved_state_reads_back() {
+        let saved = Saved {
+            marks: vec![('A', PathBuf::from("/src/a b.rs"), Cursor::new(4, 2))],
+            jumps: vec![(PathBuf::from("/src/c.rs"), Cursor::new(10, 0))],
+        };
+        assert_eq!(Saved::parse(&saved.write()), saved);
+        assert_eq!(Saved::parse("mark A x 0 /a\njunk\n"), Saved::default());
+    }
+}
@@ -1,9 +1,14 @@
-use std::io::Write;
-use crossterm::{
-    event::{KeyModifiers, KeyCode, KeyEvent,},
+use std::fmt;
+use crate::{
+    fold::FoldOp,
+    key::{GlobalKey, KeyCode, KeyEvent, KeyModifiers},
+    op::{EditOp, GlobalOp, ModeOp, MotionOp, Op, Operator, ScrollOp, Target},
+    mark,
+    pair::{self, Awaiting, Delim},
+    register,
+    types::Direction,
+    window::ScrollTo,
 };
-use tui::backend::Backend;
-use crate::{Lx, LxResult, key::GlobalKey, op::ModeOp};
 
 #[derive(Debug, PartialEq)]
 pub enum Mode {
@@ -14,27 +19,28 @@ pub enum Mode {
 }
 #[derive(Debug, Default, PartialEq)]
 pub struct OverviewMode {
-    view: OverviewPane
 }
 #[derive(Debug, Default, PartialEq)]
 pub struct CommandMode {
     pub command_buf: String,
 }
+/// Edit mode context: the count and prefix key typed so far of a key sequence such as
+/// `12j` or `zz`, an operator such as `gq` waiting for what it acts on, and a surround
+/// command waiting for its delimiters
 #[derive(Debug, Default, PartialEq)]
 pub struct EditMode {
+    pub count: Option<usize>,
+    pub pending: Option<char>,
+    /// The operator, with the count typed before it
+    pub operator: Option<(Operator, usize)>,
+    /// A surround command, once its target is typed
+    pub surround: Option<Awaiting>,
+    /// The tag a surround puts around text, typed so far after its `<`
+    pub tag: Option<String>,
 }
 #[derive(Debug, Default, PartialEq)]
 pub struct InsertMode {
 }
-#[derive(Debug, PartialEq)]
-pub enum OverviewPane {
-    Buffers, Tabs, History
-}
-impl Default for OverviewPane {
-    fn default() -> Self {
-        Self::Buffers
-    }
-}
 impl Mode {
 
     // TODO this is super wasteful and redundant
@@ -97,15 +103,220 @@ impl GlobalKey for OverviewMode {
     fn key() -> KeyEvent {
         KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('z') }
     }
-    fn match_key(ke: KeyEvent) -> Option<Self> {
-        match ke {
-            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('v') } |
-            _ => None,
-        }
+    fn match_key(_ke: KeyEvent) -> Option<Self> {
+        None
     }
 }
 impl CommandMode {
 }
+impl EditMode {
+    /// Whether part of a key sequence has been typed
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.pending.is_some() || self.operator.is_some() || self.surround.is_some()
+    }
+    /// The keys of the unfinished sequence, for showing while it is typed
+    pub fn pending_keys(&self) -> String {
+        let count = |c: Option<usize>| c.map(|c| c.to_string()).unwrap_or_default();
+        let operator = match self.operator {
+            Some((op, n)) => format!("{}{}", if n > 1 { n.to_string() } else { String::new() }, op.keys()),
+            None => String::new(),
+        };
+        let surround = match (&self.surround, &self.tag) {
+            (Some(s), Some(tag)) => format!("{}<{}", s.keys(), tag),
+            (Some(s), None) => s.keys(),
+            _ => String::new(),
+        };
+        format!("{}{}{}{}", operator, count(self.count), self.pending.map(String::from).unwrap_or_default(), surround)
+    }
+    /// Adds a key to the sequence being typed. Returns the operation once the sequence is
+    /// complete, or `None` while more keys are needed. Unknown sequences give `Op::Nothing`.
+    /// After an operator, a motion becomes what the operator acts on.
+    pub fn feed(&mut self, ke: KeyEvent) -> Option<Op> {
+        let op = self.feed_key(ke)?;
+        self.count = None;
+        let op = match (self.operator.take(), op) {
+            (None, op) => op,
+            (Some(_), op @ Op::Edit(EditOp::Operate(..))) => op,
+            (Some((operator, _)), Op::Motion(motion)) => Op::Edit(EditOp::Operate(operator, Target::Motion(motion))),
+            (Some(_), _) => Op::Nothing,
+        };
+        // `sa` has its target, and waits for the delimiters
+        if let Op::Edit(EditOp::Operate(Operator::Surround, target)) = op {
+            return self.await_surround(Awaiting::Add(target));
+        }
+        Some(op)
+    }
+    fn feed_key(&mut self, ke: KeyEvent) -> Option<Op> {
+        if let Some(awaiting) = self.surround.take() {
+            return self.surround_key(awaiting, ke);
+        }
+        let n = self.count.unwrap_or(1) * self.operator.map_or(1, |(_, n)| n);
+        if let Some(prefix) = self.pending.take() {
+            let operator = self.operator;
+            let operate = |target| operator.map_or(Op::Nothing, |(op, _)| Op::Edit(EditOp::Operate(op, target)));
+            return Some(match (prefix, ke.code) {
+                ('z', KeyCode::Char('z')) | ('z', KeyCode::Char('.')) => Op::Scroll(ScrollOp::Cursor(ScrollTo::Center)),
+                ('z', KeyCode::Char('t')) => Op::Scroll(ScrollOp::Cursor(ScrollTo::Top)),
+                ('z', KeyCode::Char('b')) | ('z', KeyCode::Char('-')) => Op::Scroll(ScrollOp::Cursor(ScrollTo::Bottom)),
+                ('z', KeyCode::Char('f')) => return self.operator(Operator::Fold, n),
+                ('z', KeyCode::Char('F')) => Op::Edit(EditOp::Operate(Operator::Fold, Target::Lines(n))),
+                ('z', KeyCode::Char('o')) => Op::Global(GlobalOp::Fold(FoldOp::Open(n))),
+                ('z', KeyCode::Char('c')) => Op::Global(GlobalOp::Fold(FoldOp::Close(n))),
+                ('z', KeyCode::Char('a')) => Op::Global(GlobalOp::Fold(FoldOp::Toggle(n))),
+                ('z', KeyCode::Char('R')) => Op::Global(GlobalOp::Fold(FoldOp::OpenAll)),
+                ('z', KeyCode::Char('M')) => Op::Global(GlobalOp::Fold(FoldOp::CloseAll)),
+                ('z', KeyCode::Char('d')) => Op::Global(GlobalOp::Fold(FoldOp::Delete)),
+                ('z', KeyCode::Char('E')) => Op::Global(GlobalOp::Fold(FoldOp::DeleteAll)),
+                ('g', KeyCode::Char('g')) => Op::Motion(MotionOp::GoToLine(n)),
+                ('g', KeyCode::Char('j')) | ('g', KeyCode::Down) => Op::Motion(MotionOp::DisplayLine(Direction::Next, n)),
+                ('g', KeyCode::Char('k')) | ('g', KeyCode::Up) => Op::Motion(MotionOp::DisplayLine(Direction::Prev, n)),
+                ('g', KeyCode::Char('q')) => return self.operator(Operator::Format, n),
+                ('q', KeyCode::Char(c)) if register::is_writable(c) => Op::Global(GlobalOp::Record(c)),
+                ('@', KeyCode::Char(c)) => Op::Global(GlobalOp::PlayMacro(c, n)),
+                ('m', KeyCode::Char(c)) if mark::is_settable(c) => Op::Global(GlobalOp::SetMark(c)),
+                ('\'', KeyCode::Char(c)) => Op::Motion(MotionOp::Mark { name: c, exact: false }),
+                ('`', KeyCode::Char(c)) => Op::Motion(MotionOp::Mark { name: c, exact: true }),
+                ('g', KeyCode::Char(';')) => Op::Global(GlobalOp::Change(Direction::Prev, n)),
+                ('g', KeyCode::Char(',')) => Op::Global(GlobalOp::Change(Direction::Next, n)),
+                ('i', KeyCode::Char('p')) => operate(Target::Paragraph { around: false, n }),
+                ('a', KeyCode::Char('p')) => operate(Target::Paragraph { around: true, n }),
+                ('i' | 'a', KeyCode::Char('w')) => operate(Target::Word { around: prefix == 'a', n }),
+                ('i' | 'a', KeyCode::Char(c)) if pair::is_pair_key(c) => operate(Target::Pair { delim: c, around: prefix == 'a', n }),
+                ('s', KeyCode::Char('a')) => return self.operator(Operator::Surround, n),
+                ('s', KeyCode::Char('d')) => return self.await_surround(Awaiting::Delete(n)),
+                ('s', KeyCode::Char('r')) => return self.await_surround(Awaiting::Replace(n, None)),
+                _ => Op::Nothing,
+            });
+        }
+        let op = match ke {
+            KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char(c) } => match c {
+                'd' => Op::Scroll(ScrollOp::HalfPage(Direction::Next, n)),
+                'u' => Op::Scroll(ScrollOp::HalfPage(Direction::Prev, n)),
+                'f' => Op::Scroll(ScrollOp::Page(Direction::Next, n)),
+                'b' => Op::Scroll(ScrollOp::Page(Direction::Prev, n)),
+                'o' => Op::Global(GlobalOp::Jump(Direction::Prev, n)),
+                // Terminals send <C-i> as <Tab>
+                'i' => Op::Global(GlobalOp::Jump(Direction::Next, n)),
+                _ => Op::Nothing,
+            },
+            KeyEvent { modifiers, code: KeyCode::Char(c) } if (modifiers - KeyModifiers::SHIFT).is_empty() => match c {
+                '1'..='9' | '0' if c != '0' || self.count.is_some() => {
+                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                    self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                    return None;
+                },
+                'z' | 'g' => {
+                    self.pending = Some(c);
+                    return None;
+                },
+                '\'' | '`' => {
+                    self.pending = Some(c);
+                    return None;
+                },
+                'q' | '@' | 'm' | 's' if self.operator.is_none() => {
+                    self.pending = Some(c);
+                    return None;
+                },
+                'i' | 'a' if self.operator.is_some() => {
+                    self.pending = Some(c);
+                    return None;
+                },
+                // A doubled operator acts on lines, as `gqq` does
+                c if self.operator.is_some_and(|(op, _)| op.line_key() == c) => {
+                    return self.operator.map(|(op, _)| Op::Edit(EditOp::Operate(op, Target::Lines(n))));
+                },
+                '>' => return self.operator(Operator::Shift(Direction::Next), n),
+                '<' => return self.operator(Operator::Shift(Direction::Prev), n),
+                '.' => Op::Edit(EditOp::Repeat(self.count)),
+                '%' => Op::Motion(MotionOp::MatchPair),
+                'o' => Op::Edit(EditOp::InsertLine(Direction::Next, n)),
+                'O' => Op::Edit(EditOp::InsertLine(Direction::Prev, n)),
+                'Q' => Op::Global(GlobalOp::CloseBuffer),
+                'c' => Op::Global(GlobalOp::NewBuffer),
+                'h' => Op::Motion(MotionOp::Left(n)),
+                'j' => Op::Motion(MotionOp::Down(n)),
+                'k' => Op::Motion(MotionOp::Up(n)),
+                'l' => Op::Motion(MotionOp::Right(n)),
+                'n' => Op::Motion(MotionOp::LineStart(Direction::Next, n)),
+                'p' => Op::Motion(MotionOp::LineStart(Direction::Prev, n)),
+                '0' => Op::Motion(MotionOp::LineEdge(Direction::Prev)),
+                '$' => Op::Motion(MotionOp::LineEdge(Direction::Next)),
+                '}' => Op::Motion(MotionOp::Paragraph(Direction::Next, n)),
+                '{' => Op::Motion(MotionOp::Paragraph(Direction::Prev, n)),
+                'G' => match self.count {
+                    Some(line) => Op::Motion(MotionOp::GoToLine(line)),
+                    None => Op::Motion(MotionOp::GoToBufferEnd(Direction::Next)),
+                },
+                _ => Op::Nothing,
+            },
+            KeyEvent { code, .. } => match code {
+                KeyCode::Left => Op::Motion(MotionOp::Left(n)),
+                KeyCode::Down => Op::Motion(MotionOp::Down(n)),
+                KeyCode::Up => Op::Motion(MotionOp::Up(n)),
+                KeyCode::Right => Op::Motion(MotionOp::Right(n)),
+                KeyCode::Home => Op::Motion(MotionOp::LineEdge(Direction::Prev)),
+                KeyCode::End => Op::Motion(MotionOp::LineEdge(Direction::Next)),
+                KeyCode::PageUp => Op::Scroll(ScrollOp::Page(Direction::Prev, n)),
+                KeyCode::PageDown => Op::Scroll(ScrollOp::Page(Direction::Next, n)),
+                KeyCode::Tab => Op::Global(GlobalOp::Jump(Direction::Next, n)),
+                _ => Op::Nothing,
+            },
+        };
+        Some(op)
+    }
+    /// Waits for the keys naming a surround command's delimiters
+    fn await_surround(&mut self, awaiting: Awaiting) -> Option<Op> {
+        self.surround = Some(awaiting);
+        self.count = None;
+        None
+    }
+    /// Takes a key naming the delimiters of a surround command: the pair `sd` and `sr` act
+    /// on, then what `sa` and `sr` put in place. A tag is typed out from `<` or `t` to its
+    /// closing `>` or `<CR>`.
+    fn surround_key(&mut self, awaiting: Awaiting, ke: KeyEvent) -> Option<Op> {
+        let surround = |op: Option<pair::SurroundOp>| Some(op.map_or(Op::Nothing, |op| Op::Edit(EditOp::Surround(op))));
+        let c = match (ke.code, &self.tag) {
+            (KeyCode::Char(c), _) => c,
+            (KeyCode::Enter, Some(_)) => '>',
+            _ => {
+                self.tag = None;
+                return Some(Op::Nothing);
+            },
+        };
+        if let Some(mut tag) = self.tag.take() {
+            return match c {
+                '>' if tag.trim().is_empty() => Some(Op::Nothing),
+                '>' => surround(awaiting.finish(Delim::Tag(tag.trim().to_string()))),
+                c => {
+                    tag.push(c);
+                    self.tag = Some(tag);
+                    self.await_surround(awaiting)
+                },
+            };
+        }
+        match awaiting {
+            Awaiting::Delete(n) if pair::is_pair_key(c) => surround(Some(pair::SurroundOp::Delete(c, n))),
+            Awaiting::Replace(n, None) if pair::is_pair_key(c) => self.await_surround(Awaiting::Replace(n, Some(c))),
+            Awaiting::Delete(_) | Awaiting::Replace(_, None) => Some(Op::Nothing),
+            awaiting if c == '<' || c == 't' => {
+                self.tag = Some(String::new());
+                self.await_surround(awaiting)
+            },
+            awaiting => surround(Delim::from_key(c).and_then(|delim| awaiting.finish(delim))),
+        }
+    }
+    /// Starts operator `op`, or when it is already pending, as in `gqgq`, applies it to lines
+    fn operator(&mut self, op: Operator, n: usize) -> Option<Op> {
+        match self.operator {
+            Some((pending, _)) if pending == op => Some(Op::Edit(EditOp::Operate(op, Target::Lines(n)))),
+            _ => {
+                self.operator = Some((op, n));
+                self.count = None;
+                None
+            },
+        }
+    }
+}
 impl GlobalKey for EditMode {
     type Op = ModeOp;
     fn key() -> KeyEvent {
@@ -127,13 +338,13 @@ impl GlobalKey for CommandMode {
     }
 } */
 
-impl ToString for Mode {
-    fn to_string(&self) -> String {
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Mode::Command(_) => "COMMAND".to_string(),
-            Mode::Edit(_) => "EDIT".to_string(),
-            Mode::Insert(_) => "INSERT".to_string(),
-            Mode::Overview(_) => "OVERVIEW".to_string(),
+            Mode::Command(_) => f.write_str("COMMAND"),
+            Mode::Edit(_) => f.write_str("EDIT"),
+            Mode::Insert(_) => f.write_str("INSERT"),
+            Mode::Overview(_) => f.write_str("OVERVIEW"),
         }
     }
 }
@@ -1,50 +1,57 @@
 use std::{io::Write, path::PathBuf};
-use crossterm::execute;
 use tui::backend::Backend;
-use crate::{Lx, error::{LxResult, LxError}, mode::Mode, prefix::FindTarget, types::Direction};
+use crate::{Lx, buffer::{grapheme, reflow}, error::LxResult, files::FileOp, fold::FoldOp, mark::Jump, mode::Mode, pair::{self, SurroundOp, Typed}, prefix::FindTarget, prompt::Prompt, types::{Cursor, Direction}, window::{OpenIn, ScrollTo}};
 
 pub trait Operation: Default {
     fn exec<W: Write>(&self, w: W) -> LxResult<()>;
 
-    fn exec_app<W: Write + Backend>(&self, app: &mut Lx<W>) -> LxResult<()> {
+    fn exec_app<W: Backend>(&self, app: &mut Lx<W>) -> LxResult<()> {
         app.mode = Mode::edit();
         Ok(())
     }
 }
 
-/* #[derive(Debug)]
+/// Any operation, for key handlers which can resolve to more than one kind
+#[derive(Debug, Default)]
 pub enum Op {
-    InsertChar(char),
-    Buffer(BufferOp),
+    Motion(MotionOp),
+    Scroll(ScrollOp),
+    Edit(EditOp),
     Insert(InsertOp),
-    Exit,
-} */
-#[derive(Debug)]
+    Mode(ModeOp),
+    Global(GlobalOp),
+    #[default]
+    Nothing,
+}
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum InsertOp {
     InsertChar(char),
     Backspace(usize),
     Delete(usize),
     Return(usize),
+    #[default]
     Nothing
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum ModeOp {
     ToggleInsert,
+    #[default]
     Insert,
     Edit,
     Overview,
     Command,
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum CommandOp {
     InsertChar(char),
     Backspace(usize),
     Delete(usize),
     Enter,
+    #[default]
     Nothing
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum EditOp {
     InsertChar(char),
     CutChar(Direction, usize),
@@ -55,115 +62,595 @@ pub enum EditOp {
     InsertLine(Direction, usize),
     Delete(usize),
     Return(usize),
+    Operate(Operator, Target),
+    Surround(SurroundOp),
+    /// `.`: repeat the last change, with the count given in place of the one it was made with
+    Repeat(Option<usize>),
+    #[default]
     Nothing,
 }
-#[derive(Debug)]
+/// Operators, which act on the text a motion or text object covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    /// `gq`: reflow lines to `textwidth`
+    Format,
+    /// `>` and `<`: indent or dedent lines by `shiftwidth`
+    Shift(Direction),
+    /// `zf`: fold lines by hand
+    Fold,
+    /// `sa`: put delimiters around text, which Edit mode waits for after the target
+    Surround,
+}
+impl Operator {
+    /// The keys which start the operator
+    pub fn keys(&self) -> &'static str {
+        match self {
+            Self::Format => "gq",
+            Self::Shift(Direction::Next) => ">",
+            Self::Shift(Direction::Prev) => "<",
+            Self::Fold => "zf",
+            Self::Surround => "sa",
+        }
+    }
+    /// The key which, typed after the operator, makes it act on lines, as the second `q`
+    /// of `gqq`. The last key of `sa` starts text objects, so `sas` does that.
+    pub fn line_key(&self) -> char {
+        match self {
+            Self::Surround => 's',
+            op => op.keys().chars().last().unwrap_or_default(),
+        }
+    }
+}
+/// What an operator acts on
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// `n` lines from the cursor's, for a doubled operator such as `gqq`
+    Lines(usize),
+    /// From the cursor to where a motion would move it
+    Motion(MotionOp),
+    /// `n` paragraphs, with the blank lines after them for `ap`
+    Paragraph { around: bool, n: usize },
+    /// `n` words, with the blanks after them for `aw`
+    Word { around: bool, n: usize },
+    /// Inside the `n`th pair named by `delim` around the cursor, or with it for `a(`
+    Pair { delim: char, around: bool, n: usize },
+}
+#[derive(Debug, Default)]
 pub enum GlobalOp {
     Find(FindTarget),
     OpenFile(PathBuf),
     NewBuffer,
+    CloseBuffer,
+    /// `q{register}`: record keys into the register
+    Record(char),
+    /// `@{register}`: play the keys in the register a number of times
+    PlayMacro(char, usize),
+    /// `m{mark}`: set a mark at the cursor
+    SetMark(char),
+    /// `<C-o>` and `<C-i>`: go back or forward through the jumplist
+    Jump(Direction, usize),
+    /// `g;` and `g,`: go back or forward through the buffer's changes
+    Change(Direction, usize),
+    Fold(FoldOp),
     InsertChar(char),
     Backspace(usize),
     Delete,
     Return,
+    #[default]
     Nothing
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum SearchOp {
+    #[default]
     Nothing,
     SearchInBuffer(Direction, String),
     ReplaceCharInBuffer(Direction, String),
     SearchInDir(String),
     ReplaceInDir(String),
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum MotionOp {
+    #[default]
     Nothing,
     SkipWord(Direction, usize),
     SkipWordEnd(Direction, usize),
     GoToChar(Direction, char, usize),
     GoFindChar(Direction, char, usize),
     GoToBufferEnd(Direction),
+    Left(usize),
+    Right(usize),
+    Up(usize),
+    Down(usize),
+    /// To the start of a following or preceding line
+    LineStart(Direction, usize),
+    /// To the first or last column of the cursor line
+    LineEdge(Direction),
+    /// To a line by number, starting from 1
+    GoToLine(usize),
+    /// By rows of wrapped lines rather than by lines (`gj`, `gk`)
+    DisplayLine(Direction, usize),
+    /// Past paragraphs to the blank line after or before them (`}`, `{`)
+    Paragraph(Direction, usize),
+    /// To a mark: its line's first non-blank with `'`, or just where it is with `` ` ``
+    Mark { name: char, exact: bool },
+    /// To the bracket matching the first one at or after the cursor on its line (`%`)
+    MatchPair,
 }
-
-impl Default for EditOp { fn default() -> Self { EditOp::Nothing } }
-impl Default for CommandOp { fn default() -> Self { CommandOp::Nothing } }
-impl Default for GlobalOp { fn default() -> Self { GlobalOp::Nothing } }
-impl Default for InsertOp { fn default() -> Self { InsertOp::Nothing }}
-impl Default for SearchOp { fn default() -> Self { SearchOp::Nothing }}
-impl Default for MotionOp { fn default() -> Self { MotionOp::Nothing }}
-impl Default for ModeOp {
-    fn default() -> Self {
-        ModeOp::Insert
+impl MotionOp {
+    /// The motion made `n` times, for those moving by a count
+    pub fn with_count(self, n: usize) -> Self {
+        match self {
+            Self::SkipWord(dir, _) => Self::SkipWord(dir, n),
+            Self::SkipWordEnd(dir, _) => Self::SkipWordEnd(dir, n),
+            Self::GoToChar(dir, c, _) => Self::GoToChar(dir, c, n),
+            Self::GoFindChar(dir, c, _) => Self::GoFindChar(dir, c, n),
+            Self::Left(_) => Self::Left(n),
+            Self::Right(_) => Self::Right(n),
+            Self::Up(_) => Self::Up(n),
+            Self::Down(_) => Self::Down(n),
+            Self::LineStart(dir, _) => Self::LineStart(dir, n),
+            Self::DisplayLine(dir, _) => Self::DisplayLine(dir, n),
+            Self::Paragraph(dir, _) => Self::Paragraph(dir, n),
+            motion => motion,
+        }
+    }
+    /// Whether the motion is a jump, which the jumplist remembers the place before.
+    /// Marks add their own jump, as they may lead to another buffer.
+    fn jumps(&self) -> bool {
+        matches!(self, Self::GoToLine(_) | Self::GoToBufferEnd(_) | Self::Paragraph(..) | Self::MatchPair)
+    }
+    /// Whether the motion moves by a count from the cursor, so that not moving at all, as
+    /// `j` on the last line, is a failure
+    fn steps(&self) -> bool {
+        matches!(self, Self::Left(_) | Self::Right(_) | Self::Up(_) | Self::Down(_) | Self::LineStart(..)
+            | Self::DisplayLine(..) | Self::Paragraph(..) | Self::MatchPair)
+    }
+    /// Whether the motion moves between lines, so operators on text take the whole lines
+    fn linewise(&self) -> bool {
+        matches!(self, Self::Up(_) | Self::Down(_) | Self::LineStart(..) | Self::GoToLine(_) | Self::GoToBufferEnd(_)
+            | Self::Mark { exact: false, .. })
+    }
+    /// Whether operators on text take the character the motion lands on too
+    fn inclusive(&self) -> bool {
+        matches!(self, Self::LineEdge(Direction::Next) | Self::MatchPair)
     }
 }
+#[derive(Debug, Default)]
+pub enum ScrollOp {
+    #[default]
+    Nothing,
+    /// Scroll so the cursor line is at the top, center or bottom of the window
+    Cursor(ScrollTo),
+    HalfPage(Direction, usize),
+    Page(Direction, usize),
+}
+
 
 impl Operation for EditOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
+        let idx = lx.buf_idx();
         match self {
-            Self::Nothing => {},
-            _ => {  }
+            // The count repeats what is typed once Insert mode is left
+            Self::InsertLine(dir, _) => {
+                let line = lx.cursor().line;
+                let indent = lx.buf[idx].indent(&lx.options).clone();
+                let to = lx.buf[idx].open_line(line, *dir, &indent);
+                lx.mode = Mode::insert();
+                lx.set_cursor(to);
+            },
+            Self::Operate(operator, target) => {
+                let (first, last) = match target.lines(lx)? {
+                    Some(lines) => lines,
+                    None => {
+                        lx.abort_macro();
+                        return Ok(());
+                    },
+                };
+                match operator {
+                    Operator::Format => {
+                        // Windows not drawn yet, as in batch mode, have no width
+                        let width = match (lx.buf[idx].textwidth(&lx.options), lx.tabs[lx.tab_idx].window().width) {
+                            (0, 0) => 79,
+                            (0, width) => width.min(79),
+                            (tw, _) => tw,
+                        };
+                        let buf = &mut lx.buf[idx];
+                        let comments = buf.comments();
+                        let lines: Vec<&str> = buf.lines().skip(first).take(last + 1 - first).collect();
+                        let formatted = reflow::reflow(&lines, width, &comments);
+                        buf.replace_lines(first, last, &formatted);
+                        lx.set_cursor(Cursor::new(first + formatted.len() - 1, 0));
+                    },
+                    Operator::Shift(dir) => {
+                        let buf = &mut lx.buf[idx];
+                        let indent = buf.indent(&lx.options).clone();
+                        buf.shift_lines(first, last, *dir, &indent);
+                        let col = buf.line(first).len() - buf.line(first).trim_start().len();
+                        lx.set_cursor(Cursor::new(first, col));
+                    },
+                    Operator::Fold => lx.fold_lines(first, last),
+                    // Edit mode makes it a surround once its delimiters are typed
+                    Operator::Surround => {},
+                }
+            },
+            Self::Surround(op) => { lx.surround(op)?; },
+            Self::Repeat(n) => { lx.repeat_change(*n)?; },
+            _ => {},
         }
         Ok(())
     }
 }
+impl EditOp {
+    /// Whether the operation changes the buffer, so `.` can repeat it
+    pub fn changes(&self) -> bool {
+        !matches!(self, Self::Repeat(_) | Self::Operate(Operator::Fold, _) | Self::Nothing)
+    }
+    /// The operation made with count `n`, for those taking one
+    pub fn with_count(self, n: usize) -> Self {
+        match self {
+            Self::CutChar(dir, _) => Self::CutChar(dir, n),
+            Self::Backspace(_) => Self::Backspace(n),
+            Self::MoveLine(dir, _) => Self::MoveLine(dir, n),
+            Self::InsertLine(dir, _) => Self::InsertLine(dir, n),
+            Self::Delete(_) => Self::Delete(n),
+            Self::Return(_) => Self::Return(n),
+            Self::Operate(op, target) => Self::Operate(op, target.with_count(n)),
+            Self::Surround(op) => Self::Surround(op.with_count(n)),
+            op => op,
+        }
+    }
+}
+impl Target {
+    /// The target counted `n` times: `n` lines, paragraphs or steps of the motion
+    pub fn with_count(self, n: usize) -> Self {
+        match self {
+            Self::Lines(_) => Self::Lines(n),
+            Self::Motion(motion) => Self::Motion(motion.with_count(n)),
+            Self::Paragraph { around, .. } => Self::Paragraph { around, n },
+            Self::Word { around, .. } => Self::Word { around, n },
+            Self::Pair { delim, around, .. } => Self::Pair { delim, around, n },
+        }
+    }
+    /// The first and last line covered, in order, with the whole of closed folds they
+    /// start or end in. `None` when there is no such text object around the cursor.
+    fn lines<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<Option<(usize, usize)>> {
+        let line = lx.cursor().line;
+        let buf = &lx.buf[lx.buf_idx()];
+        let (first, last) = match self {
+            Self::Lines(n) => (line, buf.folds.down(line, n - 1, buf.line_count() - 1)),
+            Self::Paragraph { around, n } => buf.paragraph(line, *n, *around),
+            Self::Motion(motion) => match motion_end(lx, motion)? {
+                Some(to) => (line.min(to.line), line.max(to.line)),
+                None => return Ok(None),
+            },
+            Self::Word { .. } | Self::Pair { .. } => match self.range(lx)? {
+                // Text ending at the start of a line doesn't take it
+                Some((from, to)) if to.col == 0 && to.line > from.line => (from.line, to.line - 1),
+                Some((from, to)) => (from.line, to.line),
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(lx.buf[lx.buf_idx()].folds.widen(first, last)))
+    }
+    /// The text covered, from its start to just past its end. Targets of whole lines take
+    /// them from their first non-blank. `None` when there is no such text object around
+    /// the cursor.
+    pub fn range<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<Option<(Cursor, Cursor)>> {
+        let at = lx.cursor();
+        let to = match self {
+            Self::Motion(motion) if !motion.linewise() => match motion_end(lx, motion)? {
+                Some(to) => to,
+                None => return Ok(None),
+            },
+            Self::Word { around, n } => return Ok(Some(lx.buf[lx.buf_idx()].word(at, *n, *around))),
+            Self::Pair { delim, around, n } => return Ok(pair::object(&lx.buf[lx.buf_idx()], at, *delim, *n, *around)),
+            _ => {
+                let buf_idx = lx.buf_idx();
+                return Ok(self.lines(lx)?.map(|(first, last)| {
+                    let buf = &lx.buf[buf_idx];
+                    let blank = buf.line(first).len() - buf.line(first).trim_start().len();
+                    (Cursor::new(first, blank), Cursor::new(last, buf.line_len(last)))
+                }));
+            },
+        };
+        let (from, to) = if to < at { (to, at) } else { (at, to) };
+        let inclusive = matches!(self, Self::Motion(motion) if motion.inclusive());
+        let buf = &lx.buf[lx.buf_idx()];
+        Ok(Some((from, if inclusive { buf.next_char(to, 1) } else { to })))
+    }
+}
+/// Where `motion` would take the cursor: it is made, then the window put back. `None`
+/// when it fails to move, as `%` without a bracket does. Motions between lines take the
+/// cursor line still, as `>3j` on the last line shifts it.
+fn motion_end<W: Backend>(lx: &mut Lx<W>, motion: &MotionOp) -> LxResult<Option<Cursor>> {
+    let win = lx.tabs[lx.tab_idx].window().clone();
+    let at = lx.cursor();
+    motion.exec_app(lx)?;
+    let to = lx.cursor();
+    *lx.tabs[lx.tab_idx].window_mut() = win;
+    Ok(Some(to).filter(|to| *to != at || !motion.steps() || motion.linewise()))
+}
 impl Operation for GlobalOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
         match self {
-            Self::Nothing => {},
-            Self::Return => {},
-            _ => {  }
+            Self::Find(target) => { lx.open_finder(target.clone()); },
+            Self::OpenFile(path) => {
+                if let Err(e) = lx.open(path, OpenIn::Current) {
+                    lx.error(e);
+                }
+            },
+            Self::NewBuffer => { lx.create_buf(); },
+            Self::CloseBuffer => { lx.close_buf(lx.buf_idx()); },
+            Self::Record(reg) => { lx.start_recording(*reg); },
+            Self::PlayMacro(reg, n) => { lx.play_macro(*reg, *n)?; },
+            Self::SetMark(name) => { lx.set_mark(*name); },
+            Self::Jump(dir, n) => { lx.step_jump(*dir, *n); },
+            Self::Change(dir, n) => { lx.step_change(*dir, *n); },
+            Self::Fold(op) => { lx.fold_op(*op); },
+            _ => {}
         }
         Ok(())
     }
 }
-impl Operation for InsertOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
+impl Operation for Op {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
         match self {
-            Self::Nothing => {},
-            _ => {  }
+            Self::Motion(op) => op.exec_app(lx),
+            Self::Scroll(op) => op.exec_app(lx),
+            Self::Edit(op) => op.exec_app(lx),
+            Self::Insert(op) => op.exec_app(lx),
+            Self::Mode(op) => op.exec_app(lx),
+            Self::Global(op) => op.exec_app(lx),
+            Self::Nothing => Ok(()),
         }
+    }
+}
+impl Operation for InsertOp {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
+        let idx = lx.buf_idx();
+        let at = lx.cursor();
+        let autopairs = lx.options.autopairs;
+        let buf = &mut lx.buf[idx];
+        let indent = buf.indent(&lx.options).clone();
+        let to = match self {
+            // With `expandtab`, Tab fills with spaces up to the next multiple of `shiftwidth`
+            Self::InsertChar('\t') if indent.expandtab => {
+                let vcol = grapheme::display_col(buf.line(at.line), at.col, indent.tabstop);
+                buf.insert(at, &" ".repeat(indent.width() - vcol % indent.width()))
+            },
+            Self::InsertChar(c) => match pair::typed(buf, at, *c).filter(|_| autopairs) {
+                Some(Typed::Skip) => buf.next_char(at, 1),
+                Some(Typed::Pair(close)) => {
+                    let to = buf.insert(at, &format!("{}{}", c, close));
+                    buf.prev_char(to, 1)
+                },
+                None => {
+                    let to = buf.insert(at, c.encode_utf8(&mut [0; 4]));
+                    buf.dedent_closer(to, &indent)
+                },
+            },
+            Self::Return(n) => (0..*n).fold(at, |at, _| buf.newline(at, &indent)),
+            // Backspace in a pair just typed takes its closer too
+            Self::Backspace(1) if autopairs && pair::in_empty_pair(buf, at) => {
+                let from = buf.prev_char(at, 1);
+                buf.delete(from, buf.next_char(at, 1));
+                from
+            },
+            Self::Backspace(n) => {
+                let from = buf.prev_char(at, *n);
+                buf.delete(from, at);
+                from
+            },
+            Self::Delete(n) => {
+                let to = buf.next_char(at, *n);
+                buf.delete(at, to);
+                at
+            },
+            Self::Nothing => at,
+        };
+        lx.set_cursor(to);
         Ok(())
     }
 }
 impl Operation for SearchOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
-        match self {
-            Self::Nothing => {},
-            _ => {  }
-        }
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
         Ok(())
     }
 }
 impl Operation for MotionOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
+        let at = lx.cursor();
+        let last = lx.buf[lx.buf_idx()].line_count() - 1;
+        if self.jumps() {
+            lx.push_jump();
+        }
+        // Closed folds count as one line
+        lx.update_folds();
+        let folds = &lx.buf[lx.buf_idx()].folds;
+        let (up, down) = match self {
+            Self::Up(n) | Self::Down(n) | Self::LineStart(_, n) | Self::DisplayLine(_, n) => {
+                (folds.up(at.line, *n), folds.down(at.line, *n, last))
+            },
+            _ => (at.line, at.line),
+        };
         match self {
-            Self::Nothing => {},
-            _ => {  }
+            Self::Left(n) => lx.set_cursor(Cursor::new(at.line, at.col.saturating_sub(*n))),
+            Self::Right(n) => lx.set_cursor(Cursor::new(at.line, at.col + n)),
+            Self::Up(_) => lx.move_vertical(up),
+            Self::Down(_) => lx.move_vertical(down),
+            Self::LineStart(Direction::Next, _) => lx.set_cursor(Cursor::new(down, 0)),
+            Self::LineStart(Direction::Prev, _) => lx.set_cursor(Cursor::new(up, 0)),
+            Self::LineEdge(Direction::Prev) => lx.set_cursor(Cursor::new(at.line, 0)),
+            Self::LineEdge(Direction::Next) => {
+                lx.set_cursor(Cursor::new(at.line, usize::MAX));
+                lx.tabs[lx.tab_idx].window_mut().want_col = usize::MAX;
+            },
+            Self::GoToLine(n) => lx.move_vertical(n.saturating_sub(1).min(last)),
+            Self::GoToBufferEnd(Direction::Next) => lx.move_vertical(last),
+            Self::GoToBufferEnd(Direction::Prev) => lx.move_vertical(0),
+            Self::DisplayLine(dir, n) => {
+                let win = lx.tabs[lx.tab_idx].window();
+                match win.display_move(&lx.buf[lx.buf_idx()], &lx.options, *dir, *n) {
+                    Some(to) => lx.set_cursor(to),
+                    None => match dir {
+                        Direction::Next => lx.move_vertical(down),
+                        Direction::Prev => lx.move_vertical(up),
+                    },
+                }
+            },
+            Self::Paragraph(dir, n) => {
+                let line = lx.buf[lx.buf_idx()].paragraph_edge(at.line, *dir, *n);
+                lx.set_cursor(Cursor::new(line, 0));
+            },
+            Self::Mark { name, exact } => lx.go_to_mark(*name, *exact),
+            Self::MatchPair => {
+                if let Some(to) = pair::matching(&lx.buf[lx.buf_idx()], at) {
+                    lx.set_cursor(to);
+                }
+            },
+            _ => {},
+        }
+        // Jumps open the folds they land in
+        if self.jumps() || matches!(self, Self::Mark { .. }) {
+            let (idx, line) = (lx.buf_idx(), lx.cursor().line);
+            lx.buf[idx].folds.reveal(line);
+            lx.scroll_to_cursor();
         }
+        if lx.cursor() == at && self.steps() {
+            lx.abort_macro();
+        }
+        Ok(())
+    }
+}
+impl Operation for ScrollOp {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
+        let so = lx.options.scrolloff;
+        let idx = lx.buf_idx();
+        let last = lx.buf[idx].line_count() - 1;
+        let win = lx.tabs[lx.tab_idx].window_mut();
+        let rows = win.rows();
+        let line = win.cursor.line;
+        let (scroll, line) = match self {
+            Self::Cursor(to) => {
+                win.scroll_cursor_to(*to, &lx.buf[idx], &lx.options);
+                return Ok(());
+            },
+            Self::HalfPage(dir, n) => {
+                let by = (rows / 2).max(1) * n;
+                match dir {
+                    Direction::Next => ((win.scroll + by).min(last), (line + by).min(last)),
+                    Direction::Prev => (win.scroll.saturating_sub(by), line.saturating_sub(by)),
+                }
+            },
+            Self::Page(dir, n) => {
+                // Keep two lines of the old page in view
+                let by = rows.saturating_sub(2).max(1) * n;
+                let so = so.min((rows - 1) / 2);
+                match dir {
+                    Direction::Next => {
+                        let scroll = (win.scroll + by).min(last);
+                        (scroll, line.max(scroll + so).min(last))
+                    },
+                    Direction::Prev => {
+                        let scroll = win.scroll.saturating_sub(by);
+                        (scroll, line.min((scroll + rows).saturating_sub(so + 1)))
+                    },
+                }
+            },
+            Self::Nothing => return Ok(()),
+        };
+        (win.scroll, win.skip) = (scroll, 0);
+        lx.move_vertical(line);
         Ok(())
     }
 }
 impl Operation for CommandOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
-        match self {
-            Self::Nothing => {},
-            _ => {  }
-        }
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
         Ok(())
     }
 }
 impl Operation for ModeOp {
-    fn exec<W: Write>(&self, w: W) -> LxResult<()> {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
         Ok(())
     }
-    fn exec_app<W: Write + Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
         match self {
             Self::Insert => { lx.mode = Mode::insert(); },
             Self::Edit => { lx.mode = Mode::edit(); },
             Self::Overview => { lx.mode = Mode::overview(); },
             Self::Command => { lx.mode = Mode::command(); },
-            _ => {  }
+            Self::ToggleInsert => { lx.mode = lx.mode.toggle_insert(); },
+        }
+        Ok(())
+    }
+}
+#[derive(Debug, Default)]
+pub enum OverviewOp {
+    SwitchBuffer(usize),
+    CloseBuffer(usize),
+    SwitchTab(usize),
+    Command(String),
+    Open(PathBuf, OpenIn),
+    File(FileOp),
+    UndoFile,
+    /// Go to a mark or jump picked from a list
+    GoTo(Jump),
+    /// Ask something on the message line
+    Ask(Prompt),
+    #[default]
+    Nothing,
+}
+impl Operation for OverviewOp {
+    fn exec<W: Write>(&self, _w: W) -> LxResult<()> {
+        Ok(())
+    }
+    fn exec_app<W: Backend>(&self, lx: &mut Lx<W>) -> LxResult<()> {
+        match self {
+            Self::SwitchBuffer(idx) => {
+                lx.switch_buf(*idx);
+                lx.mode = Mode::edit();
+            },
+            Self::CloseBuffer(idx) => { lx.close_buf(*idx); },
+            Self::GoTo(jump) => {
+                lx.jump_to(jump);
+                lx.mode = Mode::edit();
+            },
+            Self::SwitchTab(idx) => {
+                lx.switch_tab(*idx);
+                lx.mode = Mode::edit();
+            },
+            Self::Command(cmd) => {
+                lx.cmd_buf = cmd.clone();
+                lx.mode = Mode::command();
+            },
+            Self::Open(path, open_in) => {
+                match lx.open(path, *open_in) {
+                    Ok(()) => { lx.mode = Mode::edit(); },
+                    Err(e) => { lx.error(e); },
+                }
+            },
+            Self::File(op) => { lx.file_op(op.clone()); },
+            Self::UndoFile => { lx.undo_file_op(); },
+            Self::Ask(prompt) => { lx.prompts.push(prompt.clone()); },
+            Self::Nothing => {},
         }
         Ok(())
     }
@@ -0,0 +1,511 @@
+pub mod modeline;
+
+use std::{fmt, str::FromStr};
+
+use crate::{error::LxConfigError, ui::statusline::StatusLine};
+
+/// When the gutter shows its sign columns
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SignColumn {
+    /// Only for buffers which have signs
+    #[default]
+    Auto,
+    Yes,
+    No,
+}
+
+/// How long lines are soft-wrapped. Windows can override these with `:setlocal`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wrap {
+    /// Continue lines wider than the window on the rows below, instead of scrolling sideways
+    pub enabled: bool,
+    /// Break rows after a blank rather than in the middle of a word
+    pub linebreak: bool,
+    /// Indent continuation rows as far as the line itself is indented
+    pub breakindent: bool,
+    /// Shown at the start of continuation rows
+    pub showbreak: String,
+}
+
+impl Default for Wrap {
+    fn default() -> Self {
+        Self { enabled: true, linebreak: true, breakindent: true, showbreak: String::new() }
+    }
+}
+
+/// How lines are indented. Buffers can override these with `:setlocal`, and get their own
+/// when the indentation of their file is detected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Indent {
+    /// Indent with spaces rather than tabs
+    pub expandtab: bool,
+    /// Columns of one level of indentation. Zero uses `tabstop`.
+    pub shiftwidth: usize,
+    /// Columns between tab stops
+    pub tabstop: usize,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self { expandtab: false, shiftwidth: 0, tabstop: 8 }
+    }
+}
+
+/// The line ending a file is written with
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    /// `\n`
+    #[default]
+    Unix,
+    /// `\r\n`
+    Dos,
+    /// `\r`
+    Mac,
+}
+
+impl FileFormat {
+    pub fn eol(&self) -> &'static str {
+        match self {
+            Self::Unix => "\n",
+            Self::Dos => "\r\n",
+            Self::Mac => "\r",
+        }
+    }
+}
+
+/// The encoding a file is written in
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, a byte for each of the first 256 chars
+    Latin1,
+}
+
+/// How a buffer is written to its file. Every buffer has its own, starting from the global
+/// ones, which EditorConfig, modelines and `:setlocal` change.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileOptions {
+    pub fileformat: FileFormat,
+    pub fileencoding: Encoding,
+    /// End the file with a line ending even if it was read without one
+    pub fixendofline: bool,
+    /// Remove blanks at the end of lines when writing
+    pub trimtrailing: bool,
+}
+
+/// How a buffer's folds are made
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FoldMethod {
+    /// By hand, with `zf` and `:fold`
+    #[default]
+    Manual,
+    /// From the indentation of lines, a level per `shiftwidth`
+    Indent,
+    /// Between the lines holding `foldmarker`'s start and end
+    Marker,
+    /// Around brackets and comments spanning lines, from the syntax highlighting
+    Syntax,
+}
+
+/// How a buffer is folded. Buffers can override these with `:setlocal` and modelines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FoldOptions {
+    pub foldmethod: FoldMethod,
+    /// Text marking the start and end of a fold with `foldmethod=marker`
+    pub foldmarker: (String, String),
+}
+
+impl Default for FoldOptions {
+    fn default() -> Self {
+        Self { foldmethod: FoldMethod::default(), foldmarker: ("{{{".to_string(), "}}}".to_string()) }
+    }
+}
+
+/// Editor settings changed with `:set`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// Lines of context kept above and below the cursor
+    pub scrolloff: usize,
+    /// Show line numbers. Together with `relativenumber` the cursor line shows its absolute
+    /// number and the rest are relative to it.
+    pub number: bool,
+    /// Show line numbers relative to the cursor line
+    pub relativenumber: bool,
+    pub signcolumn: SignColumn,
+    /// Highlight the line the cursor is on
+    pub cursorline: bool,
+    pub wrap: Wrap,
+    /// Line width `gq` reflows text to. Zero uses the window width, up to 79.
+    pub textwidth: usize,
+    /// Type the closer of a bracket or quote with its opener in Insert mode, and step over
+    /// it when it is typed again
+    pub autopairs: bool,
+    pub indent: Indent,
+    /// Defaults for the file options of new buffers
+    pub file: FileOptions,
+    pub fold: FoldOptions,
+    pub statusline: StatusLine,
+    /// Milliseconds a prefix key waits for the rest of its key sequence before it is dropped
+    pub timeoutlen: u64,
+    /// Milliseconds input pauses before changed buffers are written to their files. Zero
+    /// leaves them for `:w`.
+    pub autosave: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            scrolloff: 3,
+            number: true,
+            relativenumber: false,
+            signcolumn: SignColumn::default(),
+            cursorline: false,
+            wrap: Wrap::default(),
+            textwidth: 0,
+            autopairs: false,
+            indent: Indent::default(),
+            file: FileOptions::default(),
+            fold: FoldOptions::default(),
+            statusline: StatusLine::default(),
+            timeoutlen: 1000,
+            autosave: 0,
+        }
+    }
+}
+
+fn invalid(arg: &str) -> LxConfigError {
+    LxConfigError::UnrecognizedKeyword(format!("Invalid argument: {}", arg))
+}
+
+fn unknown(arg: &str) -> LxConfigError {
+    LxConfigError::UnrecognizedKeyword(format!("Unknown option: {}", arg))
+}
+
+/// Splits a `:set` argument into the option name, its value and what to do if it is a flag
+fn parse(arg: &str, is_flag: impl Fn(&str) -> bool) -> (&str, Option<&str>, Flag) {
+    let (name, value) = match arg.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (arg, None),
+    };
+    match (name.strip_suffix('!'), name.strip_prefix("no")) {
+        (Some(name), _) => (name, value, Flag::Toggle),
+        (None, Some(name)) if is_flag(name) => (name, value, Flag::Off),
+        _ => (name, value, Flag::On),
+    }
+}
+
+impl Wrap {
+    /// Whether `name` is one of the window-local wrap options
+    pub fn is_option(name: &str) -> bool {
+        let name = name.split_once('=').map_or(name, |(n, _)| n).trim_end_matches(['!', '?']);
+        Self::is_flag(name) || Self::is_flag(name.strip_prefix("no").unwrap_or_default())
+            || matches!(name, "showbreak" | "sbr")
+    }
+    fn is_flag(name: &str) -> bool {
+        matches!(name, "wrap" | "linebreak" | "lbr" | "breakindent" | "bri")
+    }
+    /// Applies one `:set` argument naming a wrap option
+    pub fn set(&mut self, arg: &str) -> Result<(), LxConfigError> {
+        match parse(arg, Self::is_flag) {
+            ("wrap", None, flag) => flag.apply(&mut self.enabled),
+            ("linebreak" | "lbr", None, flag) => flag.apply(&mut self.linebreak),
+            ("breakindent" | "bri", None, flag) => flag.apply(&mut self.breakindent),
+            ("showbreak" | "sbr", Some(v), _) => { self.showbreak = v.to_string(); },
+            _ => return Err(unknown(arg)),
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "wrap" => flag("wrap", self.enabled),
+            "linebreak" | "lbr" => flag("linebreak", self.linebreak),
+            "breakindent" | "bri" => flag("breakindent", self.breakindent),
+            "showbreak" | "sbr" => format!("showbreak={}", self.showbreak),
+            _ => return None,
+        })
+    }
+}
+
+impl Indent {
+    /// Whether `name` is one of the buffer-local indent options
+    pub fn is_option(name: &str) -> bool {
+        let name = name.split_once('=').map_or(name, |(n, _)| n).trim_end_matches(['!', '?']);
+        Self::is_flag(name) || Self::is_flag(name.strip_prefix("no").unwrap_or_default())
+            || matches!(name, "shiftwidth" | "sw" | "tabstop" | "ts")
+    }
+    fn is_flag(name: &str) -> bool {
+        matches!(name, "expandtab" | "et")
+    }
+    /// Applies one `:set` argument naming an indent option
+    pub fn set(&mut self, arg: &str) -> Result<(), LxConfigError> {
+        match parse(arg, Self::is_flag) {
+            ("expandtab" | "et", None, flag) => flag.apply(&mut self.expandtab),
+            ("shiftwidth" | "sw", Some(v), _) => { self.shiftwidth = v.parse().map_err(|_| invalid(arg))?; },
+            ("tabstop" | "ts", Some(v), _) => {
+                self.tabstop = v.parse().ok().filter(|ts| *ts > 0).ok_or_else(|| invalid(arg))?;
+            },
+            _ => return Err(unknown(arg)),
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "expandtab" | "et" => flag("expandtab", self.expandtab),
+            "shiftwidth" | "sw" => format!("shiftwidth={}", self.shiftwidth),
+            "tabstop" | "ts" => format!("tabstop={}", self.tabstop),
+            _ => return None,
+        })
+    }
+    /// Columns of one level of indentation
+    pub fn width(&self) -> usize {
+        if self.shiftwidth == 0 { self.tabstop } else { self.shiftwidth }
+    }
+    /// Whitespace indenting a line by `width` columns: tabs and then spaces, or only spaces
+    /// with `expandtab`
+    pub fn indentation(&self, width: usize) -> String {
+        if self.expandtab {
+            " ".repeat(width)
+        } else {
+            format!("{}{}", "\t".repeat(width / self.tabstop), " ".repeat(width % self.tabstop))
+        }
+    }
+}
+
+impl FileOptions {
+    /// Whether `name` is one of the buffer-local file options
+    pub fn is_option(name: &str) -> bool {
+        let name = name.split_once('=').map_or(name, |(n, _)| n).trim_end_matches(['!', '?']);
+        Self::is_flag(name) || Self::is_flag(name.strip_prefix("no").unwrap_or_default())
+            || matches!(name, "fileformat" | "ff" | "fileencoding" | "fenc")
+    }
+    fn is_flag(name: &str) -> bool {
+        matches!(name, "fixendofline" | "fixeol" | "trimtrailing")
+    }
+    /// Applies one `:set` argument naming a file option
+    pub fn set(&mut self, arg: &str) -> Result<(), LxConfigError> {
+        match parse(arg, Self::is_flag) {
+            ("fileformat" | "ff", Some(v), _) => { self.fileformat = v.parse().map_err(|_| invalid(arg))?; },
+            ("fileencoding" | "fenc", Some(v), _) => { self.fileencoding = v.parse().map_err(|_| invalid(arg))?; },
+            ("fixendofline" | "fixeol", None, flag) => flag.apply(&mut self.fixendofline),
+            ("trimtrailing", None, flag) => flag.apply(&mut self.trimtrailing),
+            _ => return Err(unknown(arg)),
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "fileformat" | "ff" => format!("fileformat={}", self.fileformat),
+            "fileencoding" | "fenc" => format!("fileencoding={}", self.fileencoding),
+            "fixendofline" | "fixeol" => flag("fixendofline", self.fixendofline),
+            "trimtrailing" => flag("trimtrailing", self.trimtrailing),
+            _ => return None,
+        })
+    }
+}
+
+impl FoldOptions {
+    /// Whether `name` is one of the buffer-local fold options
+    pub fn is_option(name: &str) -> bool {
+        let name = name.split_once('=').map_or(name, |(n, _)| n).trim_end_matches(['!', '?']);
+        matches!(name, "foldmethod" | "fdm" | "foldmarker" | "fmr")
+    }
+    /// Applies one `:set` argument naming a fold option
+    pub fn set(&mut self, arg: &str) -> Result<(), LxConfigError> {
+        match parse(arg, |_| false) {
+            ("foldmethod" | "fdm", Some(v), _) => { self.foldmethod = v.parse().map_err(|_| invalid(arg))?; },
+            ("foldmarker" | "fmr", Some(v), _) => {
+                let (start, end) = v.split_once(',').filter(|(s, e)| !s.is_empty() && !e.is_empty()).ok_or_else(|| invalid(arg))?;
+                self.foldmarker = (start.to_string(), end.to_string());
+            },
+            _ => return Err(unknown(arg)),
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "foldmethod" | "fdm" => format!("foldmethod={}", self.foldmethod),
+            "foldmarker" | "fmr" => format!("foldmarker={},{}", self.foldmarker.0, self.foldmarker.1),
+            _ => return None,
+        })
+    }
+}
+
+fn flag(name: &str, on: bool) -> String {
+    if on { name.to_string() } else { format!("no{}", name) }
+}
+
+impl Options {
+    /// Applies one `:set` argument: `name` or `noname` for flags, `name!` to toggle a flag
+    /// and `name=value` for values
+    pub fn set(&mut self, arg: &str) -> Result<(), LxConfigError> {
+        if Wrap::is_option(arg) {
+            return self.wrap.set(arg);
+        }
+        if Indent::is_option(arg) {
+            return self.indent.set(arg);
+        }
+        if FileOptions::is_option(arg) {
+            return self.file.set(arg);
+        }
+        if FoldOptions::is_option(arg) {
+            return self.fold.set(arg);
+        }
+        let (name, value, flag) = parse(arg, |n| self.is_flag(n));
+        match (name, value) {
+            ("scrolloff" | "so", Some(v)) => { self.scrolloff = v.parse().map_err(|_| invalid(arg))?; },
+            ("textwidth" | "tw", Some(v)) => { self.textwidth = v.parse().map_err(|_| invalid(arg))?; },
+            ("timeoutlen" | "tm", Some(v)) => { self.timeoutlen = v.parse().map_err(|_| invalid(arg))?; },
+            ("autosave", Some(v)) => { self.autosave = v.parse().map_err(|_| invalid(arg))?; },
+            ("statusline" | "stl", Some(v)) => {
+                self.statusline = v.parse().map_err(|seg| invalid(&format!("{} (no segment {})", arg, seg)))?;
+            },
+            ("number" | "nu", None) => flag.apply(&mut self.number),
+            ("relativenumber" | "rnu", None) => flag.apply(&mut self.relativenumber),
+            ("cursorline" | "cul", None) => flag.apply(&mut self.cursorline),
+            ("autopairs" | "ap", None) => flag.apply(&mut self.autopairs),
+            ("signcolumn" | "scl", Some(v)) => {
+                self.signcolumn = match v {
+                    "auto" => SignColumn::Auto,
+                    "yes" => SignColumn::Yes,
+                    "no" => SignColumn::No,
+                    _ => return Err(invalid(arg)),
+                };
+            },
+            _ => return Err(unknown(arg)),
+        }
+        Ok(())
+    }
+    fn is_flag(&self, name: &str) -> bool {
+        matches!(name, "number" | "nu" | "relativenumber" | "rnu" | "cursorline" | "cul" | "autopairs" | "ap")
+    }
+    /// Current value of option `name` as `:set` would take it
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name {
+            "scrolloff" | "so" => format!("scrolloff={}", self.scrolloff),
+            "textwidth" | "tw" => format!("textwidth={}", self.textwidth),
+            "timeoutlen" | "tm" => format!("timeoutlen={}", self.timeoutlen),
+            "autosave" => format!("autosave={}", self.autosave),
+            "number" | "nu" => flag("number", self.number),
+            "relativenumber" | "rnu" => flag("relativenumber", self.relativenumber),
+            "cursorline" | "cul" => flag("cursorline", self.cursorline),
+            "autopairs" | "ap" => flag("autopairs", self.autopairs),
+            "signcolumn" | "scl" => format!("signcolumn={}", self.signcolumn),
+            "statusline" | "stl" => format!("statusline={}", self.statusline),
+            _ => return self.wrap.get(name).or_else(|| self.indent.get(name)).or_else(|| self.file.get(name))
+                .or_else(|| self.fold.get(name)),
+        })
+    }
+}
+
+enum Flag { On, Off, Toggle }
+
+impl Flag {
+    fn apply(&self, value: &mut bool) {
+        *value = match self {
+            Self::On => true,
+            Self::Off => false,
+            Self::Toggle => !*value,
+        };
+    }
+}
+
+impl fmt::Display for SignColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => f.write_str("auto"),
+            Self::Yes => f.write_str("yes"),
+            Self::No => f.write_str("no"),
+        }
+    }
+}
+
+impl fmt::Display for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = ["scrolloff", "number", "relativenumber", "signcolumn", "cursorline", "wrap",
+            "linebreak", "breakindent", "showbreak", "textwidth", "autopairs", "expandtab",
+            "shiftwidth", "tabstop", "fileformat", "fileencoding", "fixendofline", "trimtrailing", "foldmethod",
+            "foldmarker", "statusline", "timeoutlen", "autosave"];
+        let values: Vec<String> = names.iter().filter_map(|n| self.get(n)).collect();
+        f.write_str(&values.join(" "))
+    }
+}
+
+impl fmt::Display for FoldMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Manual => f.write_str("manual"),
+            Self::Indent => f.write_str("indent"),
+            Self::Marker => f.write_str("marker"),
+            Self::Syntax => f.write_str("syntax"),
+        }
+    }
+}
+
+impl FromStr for FoldMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "manual" => Ok(Self::Manual),
+            "indent" => Ok(Self::Indent),
+            "marker" => Ok(Self::Marker),
+            "syntax" => Ok(Self::Syntax),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unix => f.write_str("unix"),
+            Self::Dos => f.write_str("dos"),
+            Self::Mac => f.write_str("mac"),
+        }
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unix" => Ok(Self::Unix),
+            "dos" => Ok(Self::Dos),
+            "mac" => Ok(Self::Mac),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utf8 => f.write_str("utf-8"),
+            Self::Utf8Bom => f.write_str("utf-8-bom"),
+            Self::Utf16Le => f.write_str("utf-16le"),
+            Self::Utf16Be => f.write_str("utf-16be"),
+            Self::Latin1 => f.write_str("latin1"),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "utf-8-bom" | "utf8-bom" => Ok(Self::Utf8Bom),
+            "utf-16le" | "utf16le" => Ok(Self::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Self::Utf16Be),
+            "latin1" | "iso-8859-1" => Ok(Self::Latin1),
+            _ => Err(()),
+        }
+    }
+}
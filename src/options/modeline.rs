@@ -0,0 +1,82 @@
+/// Lines at the start and at the end of a file searched for modelines
+const MODELINES: usize = 5;
+
+/// Where the modeline in `line` starts: just after `vi:`, `vim:` or `ex:` at the start of the
+/// line or after a blank
+fn start(line: &str) -> Option<usize> {
+    ["vim:", "Vim:", "vi:", "ex:"].iter()
+        .flat_map(|marker| line.match_indices(marker))
+        .filter(|(i, _)| line[..*i].chars().next_back().map_or(true, char::is_whitespace))
+        .map(|(i, marker)| i + marker.len())
+        .min()
+}
+
+/// The `:set` arguments of the vim-style modeline in `line`, if it has one. It is either
+/// `vim: ts=4 sw=4 et`, with options separated by blanks or colons up to the end of the
+/// line, or `vim: set ts=4 sw=4 et:`, ending at the first colon not escaped as `\:` so
+/// text such as a closing `*/` can follow.
+pub fn parse(line: &str) -> Option<Vec<String>> {
+    let rest = line[start(line)?..].trim_start();
+    let args: Vec<String> = match rest.strip_prefix("set ").or_else(|| rest.strip_prefix("se ")) {
+        Some(set) => {
+            let mut args = String::new();
+            let mut chars = set.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' if chars.as_str().starts_with(':') => {
+                        args.push(':');
+                        chars.next();
+                    },
+                    ':' => break,
+                    c => args.push(c),
+                }
+            }
+            args.split_whitespace().map(String::from).collect()
+        },
+        None => rest.split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|a| !a.is_empty())
+            .map(String::from)
+            .collect(),
+    };
+    Some(args).filter(|a| !a.is_empty())
+}
+
+/// The `:set` arguments of the modelines in the first and last lines of `text`, in order
+pub fn find(text: &str) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let tail = lines.len().saturating_sub(MODELINES).max(MODELINES.min(lines.len()));
+    lines[..MODELINES.min(lines.len())].iter()
+        .chain(lines[tail..].iter())
+        .filter_map(|l| parse(l))
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modelines() {
+        assert_eq!(parse("# vim: ts=4 sw=4 et"), Some(vec!["ts=4".into(), "sw=4".into(), "et".into()]));
+        assert_eq!(parse("// vi:noet:tw=72"), Some(vec!["noet".into(), "tw=72".into()]));
+        assert_eq!(parse("/* vim: set ts=8 fdm=marker: */"), Some(vec!["ts=8".into(), "fdm=marker".into()]));
+        assert_eq!(parse("/* ex: se fmr=a\\:b: */"), Some(vec!["fmr=a:b".into()]));
+        assert_eq!(parse("Vim:set et:"), Some(vec!["et".into()]));
+        // The marker must start the line or follow a blank
+        assert_eq!(parse("nvim: ts=2"), None);
+        assert_eq!(parse("vim:"), None);
+        assert_eq!(parse("no modeline"), None);
+    }
+
+    #[test]
+    fn only_the_first_and_last_lines_are_searched() {
+        let mut lines: Vec<String> = (0..20).map(|n| format!("line {}", n)).collect();
+        lines[0] = "# vim: ts=2".to_string();
+        lines[10] = "# vim: ts=3".to_string();
+        lines[19] = "# vim: set et:".to_string();
+        assert_eq!(find(&lines.join("\n")), ["ts=2", "et"]);
+        assert_eq!(find("# vi: sw=2"), ["sw=2"]);
+        assert!(find("").is_empty());
+    }
+}
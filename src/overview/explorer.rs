@@ -0,0 +1,278 @@
+use std::{collections::HashSet, env, fs, path::{Path, PathBuf}};
+use crate::key::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{ListItem, Paragraph, Widget},
+};
+use super::{ListSelect, OverviewView, ViewContext};
+use crate::{
+    files::GitIgnore,
+    op::OverviewOp,
+    prompt::{Prompt, Subject},
+    window::{OpenIn, SplitDir},
+};
+
+/// Ticks between rescans of the tree, to pick up changes made outside the editor
+const RESCAN_TICKS: usize = 8;
+
+#[derive(Debug)]
+struct Entry {
+    path: PathBuf,
+    depth: usize,
+    is_dir: bool,
+}
+
+/// A tree of the files below a root directory, with file management.
+///
+/// `<CR>`/`l` expands a directory or opens a file, `h` collapses, `s`/`v`/`t` open in a
+/// split, vertical split or tab. `a` creates, `r` renames, `m` moves, `c` copies and `d`
+/// deletes to the trash directory, asking on the message line first; `u` undoes the last of
+/// these. `.` toggles hidden files,
+/// `i` toggles `.gitignore` filtering, `-` moves the root up and `C` roots at the selection.
+#[derive(Debug)]
+pub struct ExplorerView {
+    root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    show_hidden: bool,
+    use_gitignore: bool,
+    ignore: GitIgnore,
+    entries: Vec<Entry>,
+    list: ListSelect,
+    stale: bool,
+    /// The editor's `files_version` when the tree was last scanned
+    files_version: u64,
+    ticks: usize,
+}
+
+impl Default for ExplorerView {
+    fn default() -> Self {
+        Self::new(env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+    }
+}
+
+impl ExplorerView {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            expanded: HashSet::new(),
+            show_hidden: false,
+            use_gitignore: true,
+            ignore: GitIgnore::new(),
+            entries: Vec::new(),
+            list: ListSelect::default(),
+            stale: true,
+            files_version: 0,
+            ticks: 0,
+        }
+    }
+
+    fn rescan(&mut self) {
+        let selected = self.selected().map(|e| e.path.clone());
+        self.ignore.clear();
+        self.entries.clear();
+        let root = self.root.clone();
+        self.scan_dir(&root, 0);
+        if let Some(path) = selected {
+            if let Some(idx) = self.entries.iter().position(|e| e.path == path) {
+                self.list.select(Some(idx));
+            }
+        }
+        self.stale = false;
+    }
+
+    fn scan_dir(&mut self, dir: &Path, depth: usize) {
+        let mut children: Vec<(PathBuf, bool)> = match fs::read_dir(dir) {
+            Ok(rd) => rd.filter_map(Result::ok)
+                .map(|e| {
+                    let path = e.path();
+                    let is_dir = path.is_dir();
+                    (path, is_dir)
+                })
+                .collect(),
+            Err(_) => return,
+        };
+        children.sort_by(|(a, a_dir), (b, b_dir)| b_dir.cmp(a_dir).then_with(|| a.cmp(b)));
+        for (path, is_dir) in children {
+            let hidden = path.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            if hidden && !self.show_hidden {
+                continue;
+            }
+            if self.use_gitignore && self.ignore.is_ignored(&path, is_dir) {
+                continue;
+            }
+            let expanded = is_dir && self.expanded.contains(&path);
+            self.entries.push(Entry { path: path.clone(), depth, is_dir });
+            if expanded {
+                self.scan_dir(&path, depth + 1);
+            }
+        }
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.list.selected().and_then(|i| self.entries.get(i))
+    }
+
+    /// Directory new files go in: the selection if it's a directory, else its parent
+    fn target_dir(&self) -> PathBuf {
+        match self.selected() {
+            Some(e) if e.is_dir => e.path.clone(),
+            Some(e) => e.path.parent().map_or_else(|| self.root.clone(), Path::to_path_buf),
+            None => self.root.clone(),
+        }
+    }
+
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root).unwrap_or(path).display().to_string()
+    }
+
+    fn set_root(&mut self, root: PathBuf) {
+        self.root = root;
+        self.list.select(None);
+        self.stale = true;
+    }
+}
+
+impl OverviewView for ExplorerView {
+    fn title(&self) -> String { "Files".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        if self.stale || self.files_version != ctx.files_version {
+            self.files_version = ctx.files_version;
+            self.rescan();
+        }
+        let ch = Layout::default()
+            .direction(tui::layout::Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)].as_ref())
+            .split(area);
+        let header = format!("{}  [hidden: {}] [gitignore: {}]",
+            self.root.display(),
+            if self.show_hidden { "shown" } else { "off" },
+            if self.use_gitignore { "on" } else { "off" });
+        Paragraph::new(header)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .render(ch[0], buf);
+        let items = self.entries.iter()
+            .map(|e| {
+                let name = e.path.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                let marker = match (e.is_dir, self.expanded.contains(&e.path)) {
+                    (true, true) => "v ",
+                    (true, false) => "> ",
+                    _ => "  ",
+                };
+                let slash = if e.is_dir { "/" } else { "" };
+                ListItem::new(format!("{}{}{}{}", "  ".repeat(e.depth), marker, name, slash))
+            })
+            .collect::<Vec<ListItem>>();
+        if items.is_empty() {
+            Paragraph::new("Empty directory").render(ch[1], buf);
+        } else {
+            self.list.render(items, ctx.theme.get("ui.selection"), ch[1], buf);
+        }
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, self.entries.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        let expand = ctx.keymaps.cursor.right.matches(&ke)
+            || ke == KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Right };
+        let collapse = ctx.keymaps.cursor.left.matches(&ke)
+            || ke == KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Left };
+        let enter = ke == KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter };
+        if expand || collapse || enter {
+            let (path, is_dir) = match self.selected() {
+                Some(e) => (e.path.clone(), e.is_dir),
+                None => return if enter { None } else { Some(OverviewOp::Nothing) },
+            };
+            if !is_dir {
+                if collapse {
+                    // Collapse the directory containing the file
+                    if let Some(parent) = path.parent() {
+                        self.expanded.remove(parent);
+                        if let Some(idx) = self.entries.iter().position(|e| e.path == parent) {
+                            self.list.select(Some(idx));
+                        }
+                    }
+                    self.stale = true;
+                    return Some(OverviewOp::Nothing);
+                }
+                return Some(OverviewOp::Open(path, OpenIn::Current));
+            }
+            let open = if enter { !self.expanded.contains(&path) } else { expand };
+            if open {
+                self.expanded.insert(path);
+            } else {
+                self.expanded.remove(&path);
+            }
+            self.stale = true;
+            return Some(OverviewOp::Nothing);
+        }
+        if ke.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return None;
+        }
+        let selected = self.selected().map(|e| (e.path.clone(), e.is_dir));
+        let op = match (ke.code, selected) {
+            (KeyCode::Char('s'), Some((path, false))) => OverviewOp::Open(path, OpenIn::Split(SplitDir::Horizontal)),
+            (KeyCode::Char('v'), Some((path, false))) => OverviewOp::Open(path, OpenIn::Split(SplitDir::Vertical)),
+            (KeyCode::Char('t'), Some((path, false))) => OverviewOp::Open(path, OpenIn::Tab),
+            (KeyCode::Char('a'), _) => {
+                let dir = self.target_dir();
+                let question = format!("Create in {}/:", dir.display());
+                OverviewOp::Ask(Prompt::text_input(Subject::Create { dir }, question, String::new()))
+            },
+            (KeyCode::Char('r'), Some((path, _))) => {
+                let input = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                OverviewOp::Ask(Prompt::text_input(Subject::Rename { path }, "Rename to:".into(), input))
+            },
+            (KeyCode::Char('m'), Some((path, _))) => {
+                let input = self.display_path(&path);
+                let subject = Subject::Move { path, root: self.root.clone() };
+                OverviewOp::Ask(Prompt::text_input(subject, "Move to:".into(), input))
+            },
+            (KeyCode::Char('c'), Some((path, _))) => {
+                let input = self.display_path(&path);
+                let subject = Subject::Copy { path, root: self.root.clone() };
+                OverviewOp::Ask(Prompt::text_input(subject, "Copy to:".into(), input))
+            },
+            (KeyCode::Char('d'), Some((path, _))) => {
+                let question = format!("Delete {}?", self.display_path(&path));
+                OverviewOp::Ask(Prompt::new(Subject::Delete { path }, question, &["yes", "no"]))
+            },
+            (KeyCode::Char('u'), _) => OverviewOp::UndoFile,
+            (KeyCode::Char('.'), _) => {
+                self.show_hidden = !self.show_hidden;
+                self.stale = true;
+                OverviewOp::Nothing
+            },
+            (KeyCode::Char('i'), _) => {
+                self.use_gitignore = !self.use_gitignore;
+                self.stale = true;
+                OverviewOp::Nothing
+            },
+            (KeyCode::Char('-'), _) => {
+                if let Some(parent) = self.root.parent().map(Path::to_path_buf) {
+                    self.set_root(parent);
+                }
+                OverviewOp::Nothing
+            },
+            (KeyCode::Char('C'), Some((path, true))) => {
+                self.set_root(path);
+                OverviewOp::Nothing
+            },
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn tick(&mut self, _ctx: &ViewContext<'_>) {
+        self.ticks += 1;
+        if self.ticks % RESCAN_TICKS == 0 {
+            self.stale = true;
+        }
+    }
+}
@@ -0,0 +1,219 @@
+pub mod views;
+pub mod explorer;
+
+use crate::key::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    widgets::{List, ListItem, ListState, StatefulWidget},
+};
+use crate::{
+    buffer,
+    key::maps::KeyMaps,
+    mark::{JumpList, Marks},
+    op::OverviewOp,
+    register::Registers,
+    theme::Theme,
+    types::Message,
+    window::Tab,
+};
+
+pub use self::{
+    views::{BuffersView, TabsView, HistoryView, RegistersView, MarksView, JumpsView, MessagesView},
+    explorer::ExplorerView,
+};
+
+/// Editor state visible to overview views while rendering or handling keys.
+pub struct ViewContext<'a> {
+    pub buffers: &'a [buffer::Buffer],
+    pub buf_idx: usize,
+    pub tabs: &'a [Tab],
+    pub tab_idx: usize,
+    pub history: &'a [String],
+    pub registers: &'a Registers,
+    /// The editor's file marks, for the marks view
+    pub marks: &'a Marks,
+    pub jumps: &'a JumpList,
+    pub keymaps: &'a KeyMaps,
+    pub theme: &'a Theme,
+    /// Messages shown this session, oldest first
+    pub messages: &'a [Message],
+    /// Bumped on every file operation done or undone
+    pub files_version: u64,
+}
+
+/// A pane in Overview mode. Views are kept in a [`ViewRegistry`], which cycles focus between
+/// them with the navigation keys and forwards all other keys to the focused view.
+pub trait OverviewView {
+    /// Title shown in the overview tab bar
+    fn title(&self) -> String;
+
+    /// Draws the view into `area` of the frame buffer
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer);
+
+    /// Handles a key pressed while this view is focused. Returns `None` when the key was not
+    /// used by the view, letting it fall through to the mode-switching keys.
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp>;
+
+    /// Called periodically while the view is focused, for views that need to refresh
+    /// state from outside the editor.
+    fn tick(&mut self, _ctx: &ViewContext<'_>) {}
+}
+
+/// The set of views available in Overview mode, along with the currently focused one.
+#[derive(Default)]
+pub struct ViewRegistry {
+    views: Vec<Box<dyn OverviewView>>,
+    focus: usize,
+}
+
+impl ViewRegistry {
+    /// A registry holding the built-in views
+    pub fn builtin() -> Self {
+        let mut reg = Self::default();
+        reg.register(BuffersView::default());
+        reg.register(ExplorerView::default());
+        reg.register(TabsView::default());
+        reg.register(HistoryView::default());
+        reg.register(RegistersView::default());
+        reg.register(MarksView::default());
+        reg.register(JumpsView::default());
+        reg.register(MessagesView::default());
+        reg
+    }
+    pub fn register<V: OverviewView + 'static>(&mut self, view: V) {
+        self.views.push(Box::new(view));
+    }
+    pub fn titles(&self) -> Vec<String> {
+        self.views.iter().map(|v| v.title()).collect()
+    }
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+    /// Focuses the first view with the given title, returning whether one was found
+    pub fn focus_title(&mut self, title: &str) -> bool {
+        match self.views.iter().position(|v| v.title() == title) {
+            Some(idx) => { self.focus = idx; true },
+            None => false,
+        }
+    }
+    pub fn focused_mut(&mut self) -> Option<&mut (dyn OverviewView + 'static)> {
+        self.views.get_mut(self.focus).map(|v| v.as_mut())
+    }
+    pub fn next(&mut self) {
+        if !self.views.is_empty() {
+            self.focus = (self.focus + 1) % self.views.len();
+        }
+    }
+    pub fn prev(&mut self) {
+        if !self.views.is_empty() {
+            self.focus = (self.focus + self.views.len() - 1) % self.views.len();
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+}
+
+/// Selection state shared by the list-based views.
+#[derive(Debug, Default)]
+pub struct ListSelect {
+    state: ListState,
+}
+
+impl ListSelect {
+    pub fn selected(&self) -> Option<usize> {
+        self.state.selected()
+    }
+    pub fn select(&mut self, idx: Option<usize>) {
+        self.state.select(idx);
+    }
+    /// Moves the selection with the cursor up/down keys (or arrows). Returns whether the key
+    /// was a selection key.
+    pub fn handle_key(&mut self, keymaps: &KeyMaps, ke: KeyEvent, len: usize) -> bool {
+        let down = keymaps.cursor.down.matches(&ke)
+            || ke == KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Down };
+        let up = keymaps.cursor.up.matches(&ke)
+            || ke == KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Up };
+        if !(up || down) {
+            return false;
+        }
+        if len == 0 {
+            self.state.select(None);
+        } else {
+            let sel = match self.state.selected() {
+                Some(i) if down => (i + 1).min(len - 1),
+                Some(i) => i.saturating_sub(1),
+                None => 0,
+            };
+            self.state.select(Some(sel));
+        }
+        true
+    }
+    /// Renders `items` as a list with the current selection in `selected` style. Keeps the
+    /// selection in bounds when the underlying list has shrunk.
+    pub fn render(&mut self, items: Vec<ListItem<'_>>, selected: Style, area: Rect, buf: &mut Buffer) {
+        match self.state.selected() {
+            _ if items.is_empty() => self.state.select(None),
+            Some(i) if i >= items.len() => self.state.select(Some(items.len() - 1)),
+            None => self.state.select(Some(0)),
+            _ => {}
+        }
+        let list = List::new(items)
+            .highlight_style(selected)
+            .highlight_symbol("> ");
+        StatefulWidget::render(list, area, buf, &mut self.state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A view doing nothing but having a title
+    struct Named(&'static str);
+
+    impl OverviewView for Named {
+        fn title(&self) -> String { self.0.into() }
+        fn render(&mut self, _ctx: &ViewContext<'_>, _area: Rect, _buf: &mut Buffer) {}
+        fn handle_key(&mut self, _ctx: &ViewContext<'_>, _ke: KeyEvent) -> Option<OverviewOp> { None }
+    }
+
+    #[test]
+    fn views_are_cycled_in_the_order_they_were_registered() {
+        let mut reg = ViewRegistry::default();
+        reg.register(Named("a"));
+        reg.register(Named("b"));
+        reg.register(Named("c"));
+        assert_eq!(reg.titles(), ["a", "b", "c"]);
+        assert_eq!(reg.focus(), 0);
+        reg.next();
+        reg.next();
+        assert_eq!(reg.focused_mut().map(|v| v.title()), Some("c".to_string()));
+        reg.next();
+        assert_eq!(reg.focus(), 0);
+        reg.prev();
+        assert_eq!(reg.focus(), 2);
+        assert!(reg.focus_title("b"));
+        assert_eq!(reg.focus(), 1);
+        assert!(!reg.focus_title("d"));
+        assert_eq!(reg.focus(), 1);
+    }
+
+    #[test]
+    fn an_empty_registry_has_nothing_to_focus() {
+        let mut reg = ViewRegistry::default();
+        reg.next();
+        reg.prev();
+        assert!(reg.is_empty());
+        assert!(reg.focused_mut().is_none());
+        // The built-in views come registered, Buffers first
+        let reg = ViewRegistry::builtin();
+        assert_eq!(reg.len(), 8);
+        assert_eq!(reg.titles()[..3], ["Buffers", "Files", "Tabs"]);
+    }
+}
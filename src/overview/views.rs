@@ -0,0 +1,257 @@
+use crate::key::{KeyCode, KeyEvent, KeyModifiers};
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{ListItem, Paragraph, Widget},
+};
+use super::{ListSelect, OverviewView, ViewContext};
+use crate::{mark::{self, Place}, op::OverviewOp, types::Level};
+
+fn render_empty(msg: &str, area: Rect, buf: &mut Buffer) {
+    Paragraph::new(msg.to_string()).render(area, buf);
+}
+
+/// Short single-line summary of some buffer or register text
+fn preview(text: &str, width: usize) -> String {
+    let line = text.lines().next().unwrap_or("");
+    let mut out: String = line.chars().take(width).collect();
+    if line.chars().count() > width || text.lines().nth(1).is_some() {
+        out.push_str("...");
+    }
+    out
+}
+
+/// Lists open buffers. `<CR>` switches to the selected buffer, `d` closes it.
+#[derive(Debug, Default)]
+pub struct BuffersView {
+    list: ListSelect,
+}
+
+impl OverviewView for BuffersView {
+    fn title(&self) -> String { "Buffers".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        let items = ctx.buffers.iter().enumerate()
+            .map(|(i, b)| {
+                let current = if i == ctx.buf_idx { '%' } else { ' ' };
+                let modified = if b.modified { " [+]" } else { "" };
+                ListItem::new(format!("{:>3} {} {}{}", i + 1, current, b.name(), modified))
+            })
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, ctx.buffers.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        let sel = self.list.selected()?;
+        match ke {
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter } => Some(OverviewOp::SwitchBuffer(sel)),
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Char('d') } => Some(OverviewOp::CloseBuffer(sel)),
+            _ => None,
+        }
+    }
+}
+
+/// Lists tab pages and the buffers shown in their windows. `<CR>` switches to the selected tab.
+#[derive(Debug, Default)]
+pub struct TabsView {
+    list: ListSelect,
+}
+
+impl OverviewView for TabsView {
+    fn title(&self) -> String { "Tabs".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        let items = ctx.tabs.iter().enumerate()
+            .map(|(i, tab)| {
+                let current = if i == ctx.tab_idx { '%' } else { ' ' };
+                let wins = tab.windows.iter()
+                    .map(|w| format!("[{}]", w.buf_idx + 1))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                ListItem::new(format!("{:>3} {} {}", i + 1, current, wins))
+            })
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, ctx.tabs.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        match ke {
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter } => self.list.selected().map(OverviewOp::SwitchTab),
+            _ => None,
+        }
+    }
+}
+
+/// Lists executed commands, most recent first. `<CR>` loads the command into the command line.
+#[derive(Debug, Default)]
+pub struct HistoryView {
+    list: ListSelect,
+}
+
+impl OverviewView for HistoryView {
+    fn title(&self) -> String { "History".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        if ctx.history.is_empty() {
+            return render_empty("No command history", area, buf);
+        }
+        let items = ctx.history.iter().rev()
+            .map(|cmd| ListItem::new(format!(":{}", cmd)))
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, ctx.history.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        match ke {
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter } => self.list.selected()
+                .and_then(|i| ctx.history.iter().rev().nth(i))
+                .map(|cmd| OverviewOp::Command(cmd.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Lists the contents of all non-empty registers.
+#[derive(Debug, Default)]
+pub struct RegistersView {
+    list: ListSelect,
+}
+
+impl OverviewView for RegistersView {
+    fn title(&self) -> String { "Registers".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        if ctx.registers.is_empty() {
+            return render_empty("No registers set", area, buf);
+        }
+        let items = ctx.registers.iter()
+            .map(|(reg, text)| ListItem::new(format!("\"{}  {}", reg, preview(text, 60))))
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, ctx.registers.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        None
+    }
+}
+
+/// Name of the buffer or file a mark or jump leads to
+fn place_name(ctx: &ViewContext<'_>, place: &Place) -> String {
+    match place {
+        Place::Buffer(idx) => ctx.buffers.get(*idx).map(|b| b.name()).unwrap_or_default(),
+        Place::File(path) => path.display().to_string(),
+    }
+}
+
+/// Lists the marks of the focused buffer and the file marks, with where they are. `<CR>`
+/// jumps to the selected mark.
+#[derive(Debug, Default)]
+pub struct MarksView {
+    list: ListSelect,
+}
+
+impl OverviewView for MarksView {
+    fn title(&self) -> String { "Marks".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        let marks = mark::list(ctx.buffers, ctx.buf_idx, ctx.marks);
+        if marks.is_empty() {
+            return render_empty("No marks set", area, buf);
+        }
+        let items = marks.iter()
+            .map(|(name, m)| ListItem::new(format!("{}  {:>5}:{:<4} {}", name, m.at.line + 1, m.at.col + 1, place_name(ctx, &m.place))))
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        let marks = mark::list(ctx.buffers, ctx.buf_idx, ctx.marks);
+        if self.list.handle_key(ctx.keymaps, ke, marks.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        match ke {
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter } => self.list.selected()
+                .and_then(|i| marks.into_iter().nth(i))
+                .map(|(_, jump)| OverviewOp::GoTo(jump)),
+            _ => None,
+        }
+    }
+}
+
+/// Lists the jumplist, oldest first, with `>` at the place `<C-o>` and `<C-i>` last went
+/// to. `<CR>` jumps to the selected place.
+#[derive(Debug, Default)]
+pub struct JumpsView {
+    list: ListSelect,
+}
+
+impl OverviewView for JumpsView {
+    fn title(&self) -> String { "Jumps".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        if ctx.jumps.is_empty() {
+            return render_empty("No jumps", area, buf);
+        }
+        let items = ctx.jumps.iter().enumerate()
+            .map(|(i, j)| {
+                let current = if i == ctx.jumps.position() { '>' } else { ' ' };
+                ListItem::new(format!("{} {:>5}:{:<4} {}", current, j.at.line + 1, j.at.col + 1, place_name(ctx, &j.place)))
+            })
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, ctx.jumps.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        match ke {
+            KeyEvent { modifiers: KeyModifiers::NONE, code: KeyCode::Enter } => self.list.selected()
+                .and_then(|i| ctx.jumps.iter().nth(i))
+                .map(|jump| OverviewOp::GoTo(jump.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Lists the messages shown this session, oldest first, with errors highlighted.
+#[derive(Debug, Default)]
+pub struct MessagesView {
+    list: ListSelect,
+}
+
+impl OverviewView for MessagesView {
+    fn title(&self) -> String { "Messages".into() }
+
+    fn render(&mut self, ctx: &ViewContext<'_>, area: Rect, buf: &mut Buffer) {
+        if ctx.messages.is_empty() {
+            return render_empty("No messages", area, buf);
+        }
+        let items = ctx.messages.iter()
+            .map(|m| match m.level {
+                Level::Error => ListItem::new(m.text.clone()).style(ctx.theme.get("ui.message.error")),
+                Level::Info => ListItem::new(m.text.clone()),
+            })
+            .collect();
+        self.list.render(items, ctx.theme.get("ui.selection"), area, buf);
+    }
+
+    fn handle_key(&mut self, ctx: &ViewContext<'_>, ke: KeyEvent) -> Option<OverviewOp> {
+        if self.list.handle_key(ctx.keymaps, ke, ctx.messages.len()) {
+            return Some(OverviewOp::Nothing);
+        }
+        None
+    }
+}
@@ -0,0 +1,401 @@
+//! Pairs of delimiters: brackets `%` moves between and which show matched under the
+//! cursor, closers typed along with their openers when `autopairs` is set, text objects
+//! such as `i(` and `at`, and the surround commands adding, replacing and deleting the
+//! brackets, quotes or tags around text.
+
+use std::ops::{Range, RangeInclusive};
+use regex::Regex;
+use tui::backend::Backend;
+
+use crate::{Lx, buffer::{Buffer, col_at, col_offset}, error::LxResult, op::Target, types::Cursor};
+
+/// Brackets `%` matches, each opener with its closer
+const BRACKETS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// Quotes `autopairs` types in pairs
+const QUOTES: [char; 3] = ['"', '\'', '`'];
+
+/// Delimiters the surround commands put around text
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delim {
+    Chars(char, char),
+    /// A start tag, with any attributes, and its end tag
+    Tag(String),
+}
+
+/// The surround commands
+#[derive(Debug, Clone, PartialEq)]
+pub enum SurroundOp {
+    /// `sa{target}{delim}`: put delimiters around the text the target covers
+    Add(Target, Delim),
+    /// `sd{pair}`: delete the `n`th pair around the cursor
+    Delete(char, usize),
+    /// `sr{pair}{delim}`: replace the `n`th pair around the cursor
+    Replace(char, Delim, usize),
+}
+
+/// A surround command waiting for the keys naming its delimiters
+#[derive(Debug, Clone, PartialEq)]
+pub enum Awaiting {
+    /// `sa{target}`: the delimiters to add
+    Add(Target),
+    /// `sd`: the pair to delete, the `n`th around the cursor
+    Delete(usize),
+    /// `sr`: the pair to replace, then what replaces it
+    Replace(usize, Option<char>),
+}
+
+/// What typing a character does in Insert mode with `autopairs` set
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Typed {
+    /// Step over the same closer just after the cursor
+    Skip,
+    /// Insert the character with this closer after it
+    Pair(char),
+}
+
+impl Delim {
+    /// The delimiters named by key `c` for adding, if it names any: a bracket, `b`, `r`,
+    /// `B` or `a` for `()`, `[]`, `{}` or `<>`, or a quote or other mark put on both sides.
+    /// Tags are typed out from `<` or `t`.
+    pub fn from_key(c: char) -> Option<Self> {
+        match brackets(c) {
+            Some((open, close)) => Some(Self::Chars(open, close)),
+            None if is_mark(c) => Some(Self::Chars(c, c)),
+            None => None,
+        }
+    }
+    /// The text of the opening and closing delimiters
+    fn text(&self) -> (String, String) {
+        match self {
+            Self::Chars(open, close) => (open.to_string(), close.to_string()),
+            Self::Tag(tag) => {
+                let name = tag.split_whitespace().next().unwrap_or_default();
+                (format!("<{}>", tag), format!("</{}>", name))
+            },
+        }
+    }
+}
+
+impl SurroundOp {
+    /// The command made with count `n`: `n` of the target, or the `n`th pair out
+    pub fn with_count(self, n: usize) -> Self {
+        match self {
+            Self::Add(target, delim) => Self::Add(target.with_count(n), delim),
+            Self::Delete(c, _) => Self::Delete(c, n),
+            Self::Replace(c, delim, _) => Self::Replace(c, delim, n),
+        }
+    }
+}
+
+impl Awaiting {
+    /// The keys of the command typed so far, for showing while it is typed
+    pub fn keys(&self) -> String {
+        match self {
+            Self::Add(_) => "sa".to_string(),
+            Self::Delete(_) => "sd".to_string(),
+            Self::Replace(_, None) => "sr".to_string(),
+            Self::Replace(_, Some(c)) => format!("sr{}", c),
+        }
+    }
+    /// The command once `delim` names its delimiters
+    pub fn finish(self, delim: Delim) -> Option<SurroundOp> {
+        match self {
+            Self::Add(target) => Some(SurroundOp::Add(target, delim)),
+            Self::Replace(n, Some(c)) => Some(SurroundOp::Replace(c, delim, n)),
+            _ => None,
+        }
+    }
+}
+
+/// The opener and closer of the brackets named by `c`: either of them, or `b`, `r`, `B`
+/// or `a` for `()`, `[]`, `{}` and `<>`
+fn brackets(c: char) -> Option<(char, char)> {
+    match c {
+        '(' | ')' | 'b' => Some(('(', ')')),
+        '[' | ']' | 'r' => Some(('[', ']')),
+        '{' | '}' | 'B' => Some(('{', '}')),
+        '<' | '>' | 'a' => Some(('<', '>')),
+        _ => None,
+    }
+}
+
+/// Whether `c` is a quote or other mark which pairs with itself, such as `*` or `|`
+fn is_mark(c: char) -> bool {
+    c.is_ascii_punctuation() && brackets(c).is_none()
+}
+
+/// Whether `c` names a pair around the cursor for `sd`, `sr` or a text object
+pub fn is_pair_key(c: char) -> bool {
+    c == 't' || brackets(c).is_some() || is_mark(c)
+}
+
+/// Whether byte `byte` of line `line` is code rather than in a string or comment. Without
+/// syntax everything is code.
+fn is_code(buf: &Buffer, line: usize, byte: usize) -> bool {
+    buf.syntax.as_ref().map_or(true, |h| h.is_code(line, byte))
+}
+
+/// Line and byte of the first `target` past byte `byte` of line `line`, searching forward
+/// or back within lines `lines`, which isn't paired with a `nest` in between. Only
+/// brackets which are code, or which aren't, count, as `code` says.
+fn scan(buf: &Buffer, (line, byte): (usize, usize), nest: char, target: char, forward: bool, code: bool, lines: RangeInclusive<usize>) -> Option<(usize, usize)> {
+    let first = *lines.start();
+    let texts: Vec<&str> = buf.lines().skip(first).take(lines.end() + 1 - first).collect();
+    let mut depth = 0;
+    let mut l = line;
+    loop {
+        let text = texts.get(l.checked_sub(first)?)?;
+        let mut chars: Vec<(usize, char)> = text.char_indices()
+            .filter(|(i, _)| l != line || if forward { *i > byte } else { *i < byte })
+            .collect();
+        if !forward {
+            chars.reverse();
+        }
+        for (i, c) in chars {
+            if (c != nest && c != target) || is_code(buf, l, i) != code {
+                continue;
+            }
+            match depth {
+                _ if c == nest => depth += 1,
+                0 => return Some((l, i)),
+                _ => depth -= 1,
+            }
+        }
+        l = if forward { l + 1 } else { l.checked_sub(1)? };
+    }
+}
+
+/// Position of the bracket matching the one at byte `byte` of line `line`, among lines
+/// `lines`
+fn bracket_match(buf: &Buffer, line: usize, byte: usize, lines: RangeInclusive<usize>) -> Option<Cursor> {
+    let c = buf.line(line)[byte..].chars().next()?;
+    let (nest, target, forward) = BRACKETS.iter().find_map(|&(open, close)| match c {
+        _ if c == open => Some((open, close, true)),
+        _ if c == close => Some((close, open, false)),
+        _ => None,
+    })?;
+    let (l, i) = scan(buf, (line, byte), nest, target, forward, is_code(buf, line, byte), lines)?;
+    Some(Cursor::new(l, col_at(buf.line(l), i)))
+}
+
+fn is_bracket(c: char) -> bool {
+    BRACKETS.iter().any(|(open, close)| c == *open || c == *close)
+}
+
+/// Where `%` goes from `at`: the bracket matching the first one at or after it on its line.
+/// Brackets in strings and comments are skipped unless that is where the first one is.
+pub fn matching(buf: &Buffer, at: Cursor) -> Option<Cursor> {
+    let text = buf.line(at.line);
+    let from = col_offset(text, at.col);
+    let byte = from + text[from..].find(is_bracket)?;
+    bracket_match(buf, at.line, byte, 0..=buf.line_count() - 1)
+}
+
+/// The bracket matching the one under the cursor at `at`, if it is among lines `lines`,
+/// for showing the two together
+pub fn shown_match(buf: &Buffer, at: Cursor, lines: RangeInclusive<usize>) -> Option<Cursor> {
+    let text = buf.line(at.line);
+    let byte = col_offset(text, at.col);
+    if !text[byte..].starts_with(is_bracket) {
+        return None;
+    }
+    bracket_match(buf, at.line, byte, lines)
+}
+
+/// The `n`th pair of delimiters named by `c` around `at`, innermost first: the byte ranges
+/// of the opener and the closer in the text. Brackets may span lines and `t` finds tags,
+/// while quotes and other marks pair up along the cursor line, the pair holding the
+/// cursor or else the next one.
+pub fn around(buf: &Buffer, at: Cursor, c: char, n: usize) -> Option<(Range<usize>, Range<usize>)> {
+    if c == 't' {
+        return tag_around(buf, buf.offset(at), n);
+    }
+    let text = buf.line(at.line);
+    let byte = col_offset(text, at.col);
+    let start = buf.line_offset(at.line);
+    if let Some((open, close)) = brackets(c) {
+        let code = is_code(buf, at.line, byte);
+        let whole = 0..=buf.line_count() - 1;
+        let mut opener = match text[byte..].starts_with(open) {
+            true => (at.line, byte),
+            false => scan(buf, (at.line, byte), close, open, false, code, whole.clone())?,
+        };
+        for _ in 1..n {
+            opener = scan(buf, opener, close, open, false, code, whole.clone())?;
+        }
+        let closer = scan(buf, opener, open, close, true, code, whole)?;
+        let offset = |(l, i): (usize, usize)| buf.line_offset(l) + i;
+        return Some((offset(opener)..offset(opener) + open.len_utf8(), offset(closer)..offset(closer) + close.len_utf8()));
+    }
+    if !is_mark(c) {
+        return None;
+    }
+    let marks: Vec<usize> = text.match_indices(c).map(|(i, _)| i).filter(|i| !text[..*i].ends_with('\\')).collect();
+    let pair = marks.chunks_exact(2).find(|p| byte <= p[1])?;
+    let len = c.len_utf8();
+    Some((start + pair[0]..start + pair[0] + len, start + pair[1]..start + pair[1] + len))
+}
+
+/// The `n`th element around byte `offset`, innermost first: the byte ranges of its start
+/// and end tags
+fn tag_around(buf: &Buffer, offset: usize, n: usize) -> Option<(Range<usize>, Range<usize>)> {
+    let re = Regex::new(r"<(/?)([A-Za-z][\w:.-]*)[^<>]*?(/?)>").ok()?;
+    let mut open: Vec<(&str, Range<usize>)> = Vec::new();
+    let mut pairs = Vec::new();
+    for cap in re.captures_iter(&buf.text) {
+        let (tag, name) = (cap.get(0)?, cap.get(2)?.as_str());
+        if &cap[3] == "/" {
+            continue;
+        }
+        if &cap[1] != "/" {
+            open.push((name, tag.range()));
+            continue;
+        }
+        // An end tag closes the latest element of its name and any left open inside it
+        if let Some(i) = open.iter().rposition(|(n, _)| *n == name) {
+            let start = open[i].1.clone();
+            open.truncate(i);
+            if start.start <= offset && offset < tag.end() {
+                pairs.push((start, tag.range()));
+            }
+        }
+    }
+    pairs.into_iter().nth(n.saturating_sub(1))
+}
+
+/// The text inside the `n`th pair named by `c` around `at`, from its start to just past its
+/// end, or with the pair too if `around`. Inside brackets or tags on lines of their own,
+/// the text is the lines between them.
+pub fn object(buf: &Buffer, at: Cursor, c: char, n: usize, around: bool) -> Option<(Cursor, Cursor)> {
+    let (open, close) = self::around(buf, at, c, n)?;
+    if around {
+        return Some((buf.cursor_at(open.start), buf.cursor_at(close.end)));
+    }
+    let (mut from, mut to) = (buf.cursor_at(open.end), buf.cursor_at(close.start));
+    if from.line < to.line && buf.line(from.line)[col_offset(buf.line(from.line), from.col)..].trim().is_empty() {
+        from = Cursor::new(from.line + 1, 0);
+    }
+    if from.line < to.line && buf.line(to.line)[..col_offset(buf.line(to.line), to.col)].trim().is_empty() {
+        to = Cursor::new(to.line - 1, buf.line_len(to.line - 1));
+    }
+    Some((from, to))
+}
+
+/// What typing `c` at `at` does with `autopairs` set, or `None` when it is typed as it is.
+/// Closers step over the same closer after the cursor. Openers and quotes come with their
+/// closers before blanks, closers and the end of the line, quotes only after a non-word.
+pub fn typed(buf: &Buffer, at: Cursor, c: char) -> Option<Typed> {
+    let line = buf.line(at.line);
+    let (before, after) = line.split_at(col_offset(line, at.col));
+    let closes = BRACKETS.iter().any(|(_, close)| *close == c) || QUOTES.contains(&c);
+    if closes && after.starts_with(c) {
+        return Some(Typed::Skip);
+    }
+    let free = after.chars().next().map_or(true, |n| n.is_whitespace() || BRACKETS.iter().any(|(_, close)| *close == n));
+    match BRACKETS.iter().find(|(open, _)| *open == c) {
+        Some((_, close)) if free => Some(Typed::Pair(*close)),
+        None if free && QUOTES.contains(&c) && !before.ends_with(|b: char| b.is_alphanumeric() || b == '_') => Some(Typed::Pair(c)),
+        _ => None,
+    }
+}
+
+/// Whether `at` is between an opener or quote and its closer, as after typing the pair,
+/// so that backspace deletes both
+pub fn in_empty_pair(buf: &Buffer, at: Cursor) -> bool {
+    let line = buf.line(at.line);
+    let (before, after) = line.split_at(col_offset(line, at.col));
+    let pairs = BRACKETS.iter().copied().chain(QUOTES.iter().map(|q| (*q, *q)));
+    before.chars().next_back().is_some_and(|b| pairs.clone().any(|(open, close)| open == b && after.starts_with(close)))
+}
+
+impl<W: Backend> Lx<W> {
+    /// Makes a surround command: puts delimiters around the text a target covers, or deletes
+    /// or replaces the `n`th pair around the cursor. The cursor goes to the opener. Without
+    /// such text or pair nothing changes, and a macro making the command stops.
+    pub fn surround(&mut self, op: &SurroundOp) -> LxResult<()> {
+        let idx = self.buf_idx();
+        let at = self.cursor();
+        let cursor = match op {
+            SurroundOp::Add(target, delim) => match target.range(self)? {
+                Some((from, to)) => {
+                    let (open, close) = delim.text();
+                    let buf = &mut self.buf[idx];
+                    buf.insert(to, &close);
+                    buf.insert(from, &open);
+                    Some(from)
+                },
+                None => None,
+            },
+            SurroundOp::Delete(c, n) | SurroundOp::Replace(c, _, n) => match around(&self.buf[idx], at, *c, *n) {
+                Some((open, close)) => {
+                    let (new_open, new_close) = match op {
+                        SurroundOp::Replace(_, delim, _) => delim.text(),
+                        _ => (String::new(), String::new()),
+                    };
+                    let (buf, start) = (&mut self.buf[idx], open.start);
+                    // The closer first, so the opener's offsets still hold
+                    for (range, text) in [(close, new_close), (open, new_open)] {
+                        let from = buf.cursor_at(range.start);
+                        buf.delete(from, buf.cursor_at(range.end));
+                        buf.insert(from, &text);
+                    }
+                    Some(buf.cursor_at(start))
+                },
+                None => None,
+            },
+        };
+        match cursor {
+            Some(to) => self.set_cursor(to),
+            None => self.abort_macro(),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer { text: text.to_string(), ..Buffer::default() }
+    }
+
+    #[test]
+    fn brackets_match_across_lines() {
+        let buf = buffer("f(a, [b]) {\n  g(c)\n}");
+        assert_eq!(matching(&buf, Cursor::new(0, 0)), Some(Cursor::new(0, 8)));
+        assert_eq!(matching(&buf, Cursor::new(0, 8)), Some(Cursor::new(0, 1)));
+        assert_eq!(matching(&buf, Cursor::new(0, 9)), Some(Cursor::new(2, 0)));
+        assert_eq!(matching(&buf, Cursor::new(2, 0)), Some(Cursor::new(0, 10)));
+        assert_eq!(shown_match(&buf, Cursor::new(0, 0), 0..=2), None);
+    }
+
+    #[test]
+    fn pairs_around_the_cursor() {
+        let buf = buffer("a(b(c) \"d\" <p><i>e</i></p>)");
+        let text = |r: Range<usize>| &buf.text[r];
+        let (open, close) = around(&buf, Cursor::new(0, 4), 'b', 1).unwrap();
+        assert_eq!((open.start, close.start), (3, 5));
+        let (open, close) = around(&buf, Cursor::new(0, 4), ')', 2).unwrap();
+        assert_eq!((open.start, close.start), (1, 26));
+        let (open, close) = around(&buf, Cursor::new(0, 1), '"', 1).unwrap();
+        assert_eq!((text(open), close.start), ("\"", 9));
+        let (open, close) = around(&buf, Cursor::new(0, 18), 't', 2).unwrap();
+        assert_eq!((text(open), text(close)), ("<p>", "</p>"));
+        assert_eq!(around(&buf, Cursor::new(0, 0), 'B', 1), None);
+    }
+
+    #[test]
+    fn autopairs_open_and_skip() {
+        let buf = buffer("x) it");
+        assert_eq!(typed(&buf, Cursor::new(0, 0), '('), None);
+        assert_eq!(typed(&buf, Cursor::new(0, 1), '('), Some(Typed::Pair(')')));
+        assert_eq!(typed(&buf, Cursor::new(0, 1), ')'), Some(Typed::Skip));
+        assert_eq!(typed(&buf, Cursor::new(0, 4), '\''), None);
+        assert_eq!(typed(&buf, Cursor::new(0, 5), '"'), None);
+        assert_eq!(typed(&buf, Cursor::new(0, 2), '"'), Some(Typed::Pair('"')));
+        assert!(!in_empty_pair(&buf, Cursor::new(0, 1)));
+        assert!(in_empty_pair(&buffer("f()"), Cursor::new(0, 2)));
+    }
+}
@@ -1,6 +1,6 @@
-use crossterm::{event::{KeyModifiers, KeyCode, KeyEvent}};
+use std::{env, fmt, path::PathBuf};
 use crate::{
-    key::{EditPrefixKey, GlobalPrefixKey},
+    key::{GlobalPrefixKey, KeyCode, KeyEvent, KeyModifiers},
     op::GlobalOp,
     types::Direction
 };
@@ -30,14 +30,12 @@ impl Prefix {
             Some(Self::Window(window))
         } else if let Some(motion) = <MotionPre as GlobalPrefixKey>::match_key(kv) {
             Some(Self::Motion(motion))
-        } else if let Some(search) = <SearchPre as GlobalPrefixKey>::match_key(kv) {
-            Some(Self::Search(search))
         } else {
-            None
+            <SearchPre as GlobalPrefixKey>::match_key(kv).map(Self::Search)
         }
 
     }
-    pub fn match_edit_key(ke: KeyEvent) -> Option<Self> {
+    pub fn match_edit_key(_ke: KeyEvent) -> Option<Self> {
         None
     }
     pub fn leader() -> Self { Self::Leader(LeaderPre::default()) }
@@ -74,56 +72,56 @@ impl Prefix {
 /// ctrl + ? binding in any mode, and take precedence.
 ///
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum LeaderPre {
+    #[default]
     Cancel
 
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum BufferPre {
+    #[default]
     Cancel
 
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum TabPre {
+    #[default]
     Cancel
 
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum FindPre {
     Files { dir: String },
     Buffers,
     History,
+    #[default]
     Cancel
 
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum MotionPre {
     Word(Direction, usize),
     Line(Direction, usize),
+    #[default]
     Cancel
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum SearchPre {
     Search(Option<Direction>, String, bool), //case insensitive?
     Replace(Option<Direction>, String, bool),
+    #[default]
     Cancel
 }
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub enum WindowPre {
     Split{},
     Move{},
+    #[default]
     Cancel
 
 }
 
-impl Default for LeaderPre { fn default() -> Self { LeaderPre::Cancel } }
-impl Default for MotionPre { fn default() -> Self { MotionPre::Cancel } }
-impl Default for BufferPre { fn default() -> Self { BufferPre::Cancel } }
-impl Default for TabPre { fn default() -> Self { TabPre::Cancel } }
-impl Default for FindPre { fn default() -> Self { FindPre::Cancel }}
-impl Default for SearchPre { fn default() -> Self { SearchPre::Cancel }}
-impl Default for WindowPre { fn default() -> Self { WindowPre::Cancel }}
 
 impl GlobalPrefixKey for LeaderPre {
     type Op = GlobalOp;
@@ -142,6 +140,32 @@ impl GlobalPrefixKey for FindPre {
     fn key() -> KeyEvent {
         KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('f') }
     }
+    /// `f` finds files below the working directory, `b` buffers and `h` command history
+    fn match_key_op(ke: KeyEvent) -> Option<Self::Op> {
+        let target = match FindPre::from_key(ke)? {
+            FindPre::Files { dir } => FindTarget::Files(PathBuf::from(dir)),
+            FindPre::Buffers => FindTarget::Buffers,
+            FindPre::History => FindTarget::History,
+            FindPre::Cancel => return None,
+        };
+        Some(GlobalOp::Find(target))
+    }
+}
+impl FindPre {
+    pub fn from_key(ke: KeyEvent) -> Option<Self> {
+        match ke.code {
+            KeyCode::Char('f') => {
+                let dir = env::current_dir()
+                    .map(|d| d.display().to_string())
+                    .unwrap_or_else(|_| ".".into());
+                Some(FindPre::Files { dir })
+            },
+            KeyCode::Char('b') => Some(FindPre::Buffers),
+            KeyCode::Char('h') => Some(FindPre::History),
+            KeyCode::Esc => Some(FindPre::Cancel),
+            _ => None,
+        }
+    }
 }
 impl GlobalPrefixKey for SearchPre {
     type Op = GlobalOp;
@@ -167,31 +191,32 @@ impl GlobalPrefixKey for TabPre {
         KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('\\') }
     }
 }
-#[derive(Debug, PartialEq)]
-pub enum FindTarget { Files, Buffers, }
-impl ToString for FindTarget {
-    fn to_string(&self) -> String {
-        match self {
-            Self::Files => "Files".into(),
-            Self::Buffers => "Buffers".into()
-        }
-    }
-}
+#[derive(Debug, Clone, PartialEq)]
+pub enum FindTarget { Files(PathBuf), Buffers, History }
 impl Default for FindTarget {
     fn default() -> Self {
-        Self::Files
+        Self::Files(PathBuf::from("."))
+    }
+}
+impl fmt::Display for FindTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Files(_) => f.write_str("Files"),
+            Self::Buffers => f.write_str("Buffers"),
+            Self::History => f.write_str("History"),
+        }
     }
 }
-impl ToString for Prefix {
-    fn to_string(&self) -> String {
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Prefix::Leader(_lp) => "Leader".to_string(),
-            Prefix::Buffer(_bp) => "Buffer".to_string(),
-            Prefix::Tab(_tp) => "Tab".to_string(),
-            Prefix::Find(_fp) => "Find ".to_string(),
-            Prefix::Window(_wp) => "Win".to_string(),
-            Prefix::Motion(_wp) => "Move ".to_string(),
-            Prefix::Search(_wp) => "Search".to_string(),
+            Prefix::Leader(_lp) => f.write_str("Leader"),
+            Prefix::Buffer(_bp) => f.write_str("Buffer"),
+            Prefix::Tab(_tp) => f.write_str("Tab"),
+            Prefix::Find(_fp) => f.write_str("Find "),
+            Prefix::Window(_wp) => f.write_str("Win"),
+            Prefix::Motion(_wp) => f.write_str("Move "),
+            Prefix::Search(_wp) => f.write_str("Search"),
         }
     }
 }
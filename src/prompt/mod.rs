@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use crate::swap::Swap;
+
+/// What a prompt asks about, which decides what its answers do
+#[derive(Debug, Clone, PartialEq)]
+pub enum Subject {
+    /// A swap file found when opening buffer `buf`
+    Swap { buf: usize, swap: Swap },
+    /// The file of buffer `buf` changed on disk while the buffer had unsaved changes
+    Changed { buf: usize },
+    /// The name of a file to create in `dir`, or of a directory if it ends in `/`
+    Create { dir: PathBuf },
+    /// A new name for the file at `path`, in the same directory
+    Rename { path: PathBuf },
+    /// Where to move the file at `path`, relative to `root` unless absolute
+    Move { path: PathBuf, root: PathBuf },
+    /// Where to copy the file at `path`, relative to `root` unless absolute
+    Copy { path: PathBuf, root: PathBuf },
+    /// Whether to move the file at `path` to the trash
+    Delete { path: PathBuf },
+}
+
+/// A question on the message line, answered with the first letter of one of its choices,
+/// or with text typed after it and `<CR>`. It takes every key until answered, and `<Esc>`
+/// gives no answer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub subject: Subject,
+    pub question: String,
+    pub choices: &'static [&'static str],
+    /// The text typed so far, for a prompt answered with text rather than a choice
+    pub input: Option<String>,
+}
+
+impl Prompt {
+    pub fn new(subject: Subject, question: String, choices: &'static [&'static str]) -> Self {
+        Self { subject, question, choices, input: None }
+    }
+    /// A prompt answered with text, which starts out as `input`
+    pub fn text_input(subject: Subject, question: String, input: String) -> Self {
+        Self { subject, question, choices: &[], input: Some(input) }
+    }
+    /// The question followed by the text typed so far, or by the choices, each with its key
+    /// in parentheses
+    pub fn text(&self) -> String {
+        if let Some(input) = &self.input {
+            return format!("{} {}", self.question, input);
+        }
+        let choices: Vec<String> = self.choices.iter()
+            .map(|c| format!("({}){}", &c[..1], &c[1..]))
+            .collect();
+        format!("{} {}", self.question, choices.join(", "))
+    }
+    /// The choice `key` answers with
+    pub fn choice(&self, key: char) -> Option<&'static str> {
+        self.choices.iter().copied().find(|c| c.starts_with(key))
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+/// The register written to when no register is given
+pub const UNNAMED: char = '"';
+/// Read-only register holding the last executed command
+pub const COMMAND: char = ':';
+
+/// Whether `reg` names a register text can be put in: a letter, in upper case to append
+/// to it, a digit or the unnamed register
+pub fn is_writable(reg: char) -> bool {
+    reg.is_ascii_alphanumeric() || reg == UNNAMED
+}
+
+/// Text registers, keyed by register name.
+#[derive(Debug, Default)]
+pub struct Registers(BTreeMap<char, String>);
+
+impl Registers {
+    pub fn get(&self, reg: char) -> Option<&str> {
+        self.0.get(&reg).map(String::as_str)
+    }
+    pub fn set(&mut self, reg: char, text: String) {
+        self.0.insert(reg, text);
+    }
+    /// Sets `reg`, or for a capital letter appends to the register of the lower case letter
+    pub fn store(&mut self, reg: char, text: String) {
+        if reg.is_ascii_uppercase() {
+            self.0.entry(reg.to_ascii_lowercase()).or_default().push_str(&text);
+        } else {
+            self.set(reg, text);
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&char, &String)> {
+        self.0.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
@@ -0,0 +1,126 @@
+use std::{env, fs, io, path::{Path, PathBuf}, process::{self, Command, Stdio}};
+
+use crate::{buffer::grapheme, config};
+
+/// Start of the line every swap file begins with, followed by the pid of the editor writing it
+const MAGIC: &str = "lx swap";
+
+/// Directory holding the swap files of buffers with unsaved changes, unless the editor is
+/// given another
+pub fn default_dir() -> PathBuf {
+    config::state_dir().join("swap")
+}
+
+/// Swap file in `dir` of the file at `file`: its absolute path with each `/` turned into
+/// `%`, as vim names them, so every file has its own
+pub fn path(dir: &Path, file: &Path) -> PathBuf {
+    let abs = env::current_dir().map(|cwd| cwd.join(file)).unwrap_or_else(|_| file.to_path_buf());
+    dir.join(format!("{}.swp", abs.to_string_lossy().replace(['/', '\\'], "%")))
+}
+
+/// A swap file on disk: the editor which wrote it and the unsaved text it holds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Swap {
+    pub path: PathBuf,
+    pub pid: u32,
+    pub text: String,
+}
+
+impl Swap {
+    /// Whether another editor which is still running wrote the swap file, and so is editing
+    /// the file right now rather than having crashed
+    pub fn is_running(&self) -> bool {
+        self.pid != process::id() && is_running(self.pid)
+    }
+}
+
+/// Whether a process with the given pid exists
+fn is_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        return proc.join(pid.to_string()).exists();
+    }
+    // Not knowing counts as running, so a live session's swap file is never taken over
+    Command::new("kill").args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .map_or(true, |s| s.success())
+}
+
+/// Journals `text` to `dir` as the unsaved contents of the file at `file`. The swap file is
+/// written next to the old one and renamed over it, so a crash mid-write leaves the last
+/// full copy.
+pub fn write(dir: &Path, file: &Path, text: &str) -> io::Result<()> {
+    let swap = path(dir, file);
+    fs::create_dir_all(dir)?;
+    let tmp = swap.with_extension("swp.tmp");
+    let mut bytes = format!("{} {}\n", MAGIC, process::id()).into_bytes();
+    bytes.extend(grapheme::encode(text));
+    fs::write(&tmp, bytes)?;
+    fs::rename(tmp, swap)
+}
+
+/// The swap file left in `dir` for the file at `file`, if there is one
+pub fn read(dir: &Path, file: &Path) -> io::Result<Option<Swap>> {
+    let path = path(dir, file);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let end = bytes.iter().position(|b| *b == b'\n').unwrap_or(bytes.len());
+    let pid = std::str::from_utf8(&bytes[..end]).ok()
+        .and_then(|header| header.strip_prefix(MAGIC))
+        .and_then(|pid| pid.trim().parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{}: Not a swap file", path.display())))?;
+    let text = grapheme::decode(bytes.get(end + 1..).unwrap_or_default());
+    Ok(Some(Swap { path, pid, text }))
+}
+
+/// Removes the swap file in `dir` of the file at `file`, if it has one
+pub fn remove(dir: &Path, file: &Path) -> io::Result<()> {
+    match fs::remove_file(path(dir, file)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    #[test]
+    fn swap_files_round_trip() {
+        let dir = TempDir::new("swap");
+        let file = dir.join("a file.txt");
+        assert_eq!(read(&dir, &file).unwrap(), None);
+        let text = grapheme::decode(b"unsaved \xff\nlines");
+        write(&dir, &file, &text).unwrap();
+        let swap = read(&dir, &file).unwrap().unwrap();
+        assert_eq!((swap.pid, swap.text.as_str()), (process::id(), text.as_str()));
+        assert!(swap.path.starts_with(&*dir) && swap.path.to_string_lossy().ends_with("%a file.txt.swp"));
+        // Editing the file itself doesn't count as another editor
+        assert!(!swap.is_running());
+        write(&dir, &file, "again").unwrap();
+        assert_eq!(read(&dir, &file).unwrap().map(|s| s.text), Some("again".to_string()));
+        remove(&dir, &file).unwrap();
+        assert_eq!(read(&dir, &file).unwrap(), None);
+        remove(&dir, &file).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_not_swap_files() {
+        let dir = TempDir::new("not-swap");
+        let file = dir.join("other");
+        fs::write(path(&dir, &file), "not a swap file\n").unwrap();
+        assert_eq!(read(&dir, &file).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn dead_editors_are_not_running() {
+        assert!(is_running(process::id()));
+        let swap = Swap { path: PathBuf::new(), pid: u32::MAX, text: String::new() };
+        assert!(!swap.is_running());
+    }
+}
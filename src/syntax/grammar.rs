@@ -0,0 +1,224 @@
+use std::{fs, path::Path, sync::Arc};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{config, error::LxConfigError};
+
+/// Grammars shipped with lx, used unless the config dir has one of the same name
+const BUILTIN: [(&str, &str); 4] = [
+    ("rust.toml", include_str!("../../runtime/grammars/rust.toml")),
+    ("toml.toml", include_str!("../../runtime/grammars/toml.toml")),
+    ("markdown.toml", include_str!("../../runtime/grammars/markdown.toml")),
+    ("shell.toml", include_str!("../../runtime/grammars/shell.toml")),
+];
+
+/// Index of a scope name in its grammar's scope table
+pub type Scope = usize;
+
+/// A grammar file as written on disk
+#[derive(Debug, Deserialize)]
+struct GrammarDef {
+    name: String,
+    #[serde(default)]
+    extensions: Vec<String>,
+    #[serde(default)]
+    filenames: Vec<String>,
+    #[serde(default)]
+    shebangs: Vec<String>,
+    #[serde(default)]
+    comments: Vec<String>,
+    #[serde(default)]
+    indent: IndentDef,
+    #[serde(default)]
+    rules: Vec<RuleDef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct IndentDef {
+    increase: Option<String>,
+    decrease: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleDef {
+    scope: String,
+    #[serde(rename = "match")]
+    pattern: Option<String>,
+    begin: Option<String>,
+    end: Option<String>,
+    skip: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum RuleKind {
+    /// A single-line token. If the regex has a capture group only the first group is scoped.
+    Match(Regex),
+    /// A region which may span lines, such as a block comment. `skip` matches escapes
+    /// which can't end the region.
+    Region { begin: Regex, end: Regex, skip: Option<Regex> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub scope: Scope,
+    pub kind: RuleKind,
+}
+
+/// When lines are indented further or less than the line before them
+#[derive(Debug, Clone, Default)]
+pub struct IndentRules {
+    /// Matches lines which open a block, so the line after them is indented one level more
+    pub increase: Option<Regex>,
+    /// Matches the start of lines which close a block, so they are indented one level less
+    pub decrease: Option<Regex>,
+}
+
+/// A compiled set of highlighting rules for one filetype
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    pub name: String,
+    pub extensions: Vec<String>,
+    pub filenames: Vec<String>,
+    pub shebangs: Vec<String>,
+    /// Line comment leaders, longest first, kept at the start of lines reflowed by `gq`
+    pub comments: Vec<String>,
+    pub indent: IndentRules,
+    pub rules: Vec<Rule>,
+    scopes: Vec<String>,
+}
+
+fn compile(name: &str, re: &str) -> Result<Regex, LxConfigError> {
+    Regex::new(re).map_err(|e| LxConfigError::InvalidGrammar(format!("{}: {}", name, e)))
+}
+
+impl Grammar {
+    /// Parses a grammar from its textual (TOML) definition
+    pub fn parse(src: &str) -> Result<Self, LxConfigError> {
+        let GrammarDef { name, extensions, filenames, shebangs, mut comments, indent, rules: defs } = toml::from_str(src)
+            .map_err(|e| LxConfigError::InvalidGrammar(e.to_string()))?;
+        let indent = IndentRules {
+            increase: indent.increase.map(|re| compile(&name, &re)).transpose()?,
+            decrease: indent.decrease.map(|re| compile(&name, &re)).transpose()?,
+        };
+        let mut scopes: Vec<String> = Vec::new();
+        let mut rules = Vec::with_capacity(defs.len());
+        for r in defs {
+            let scope = match scopes.iter().position(|s| *s == r.scope) {
+                Some(i) => i,
+                None => {
+                    scopes.push(r.scope.clone());
+                    scopes.len() - 1
+                },
+            };
+            let kind = match (r.pattern, r.begin, r.end) {
+                (Some(m), None, None) => RuleKind::Match(compile(&name, &m)?),
+                (None, Some(begin), Some(end)) => RuleKind::Region {
+                    begin: compile(&name, &begin)?,
+                    end: compile(&name, &end)?,
+                    skip: r.skip.map(|s| compile(&name, &s)).transpose()?,
+                },
+                _ => return Err(LxConfigError::InvalidGrammar(format!(
+                    "{}: rule for {} needs either `match` or `begin` and `end`", name, r.scope))),
+            };
+            rules.push(Rule { scope, kind });
+        }
+        comments.sort_by_key(|c| std::cmp::Reverse(c.len()));
+        Ok(Self { name, extensions, filenames, shebangs, comments, indent, rules, scopes })
+    }
+    pub fn scope_name(&self, scope: Scope) -> &str {
+        &self.scopes[scope]
+    }
+    /// Whether this grammar applies to the file at `path` starting with `first_line`
+    pub fn detects(&self, path: &Path, first_line: &str) -> bool {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if self.filenames.iter().any(|f| f == name) || self.extensions.iter().any(|e| e == ext) {
+            return true;
+        }
+        // `#!/bin/sh` or `#!/usr/bin/env bash`
+        match first_line.strip_prefix("#!") {
+            Some(cmd) => {
+                let mut words = cmd.split_whitespace();
+                let prog = words.next().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+                let prog = if prog == "env" { words.next().unwrap_or_default() } else { prog };
+                self.shebangs.iter().any(|s| s == prog)
+            },
+            None => false,
+        }
+    }
+}
+
+/// The grammars available to buffers
+#[derive(Debug, Clone, Default)]
+pub struct Grammars(Vec<Arc<Grammar>>);
+
+impl Grammars {
+    /// Loads the built in grammars, then any `*.toml` grammars in `<config dir>/grammars`,
+    /// which replace built in grammars of the same name. Grammars which fail to load are
+    /// skipped and their errors returned.
+    pub fn load() -> (Self, Vec<LxConfigError>) {
+        let mut grammars = Self::default();
+        let mut errors = Vec::new();
+        for (file, src) in BUILTIN.iter() {
+            match Grammar::parse(src) {
+                Ok(g) => grammars.insert(g),
+                Err(e) => errors.push(LxConfigError::InvalidGrammar(format!("{}: {}", file, e))),
+            }
+        }
+        let dir = config::config_dir().join("grammars");
+        let mut paths: Vec<_> = match fs::read_dir(&dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+        for path in paths {
+            match fs::read_to_string(&path).map_err(LxConfigError::from).and_then(|s| Grammar::parse(&s)) {
+                Ok(g) => grammars.insert(g),
+                Err(e) => errors.push(LxConfigError::InvalidGrammar(
+                    format!("{}: {}", path.display(), e))),
+            }
+        }
+        (grammars, errors)
+    }
+    /// Adds a grammar, replacing one with the same name
+    pub fn insert(&mut self, grammar: Grammar) {
+        match self.0.iter().position(|g| g.name == grammar.name) {
+            Some(i) => self.0[i] = Arc::new(grammar),
+            None => self.0.push(Arc::new(grammar)),
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<Arc<Grammar>> {
+        self.0.iter().find(|g| g.name == name).cloned()
+    }
+    /// The grammar for a file at `path` whose text starts with `text`
+    pub fn detect(&self, path: &Path, text: &str) -> Option<Arc<Grammar>> {
+        let first_line = text.lines().next().unwrap_or_default();
+        self.0.iter().find(|g| g.detects(path, first_line)).cloned()
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|g| g.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_grammars_detect_their_files() {
+        let mut grammars = Grammars::default();
+        for (file, src) in BUILTIN.iter() {
+            grammars.insert(Grammar::parse(src).unwrap_or_else(|e| panic!("{}: {}", file, e)));
+        }
+        let detect = |path: &str, text: &str| grammars.detect(Path::new(path), text).map(|g| g.name.clone());
+        assert_eq!(detect("src/main.rs", ""), Some("rust".into()));
+        assert_eq!(detect("Cargo.toml", ""), Some("toml".into()));
+        assert_eq!(detect("Cargo.lock", ""), Some("toml".into()));
+        assert_eq!(detect("README.md", ""), Some("markdown".into()));
+        assert_eq!(detect("build", "#!/usr/bin/env bash\nset -e"), Some("shell".into()));
+        assert_eq!(detect(".bashrc", ""), Some("shell".into()));
+        assert_eq!(detect("notes.txt", "#!/usr/bin/python3"), None);
+    }
+}
@@ -0,0 +1,373 @@
+pub mod grammar;
+
+use std::{
+    collections::{HashMap, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    ops::Range,
+    sync::Arc,
+    time::Instant,
+};
+use regex::Regex;
+use tui::text::{Span, Spans};
+
+pub use grammar::{Grammar, Grammars, Scope};
+use grammar::RuleKind;
+use crate::theme::Theme;
+
+/// Highlighter state carried from one line to the next: the region rule the line ends inside
+type State = Option<usize>;
+
+/// A rule's next match as (match start, scoped range), `None` once it has no more matches
+type NextMatch = Option<(usize, Range<usize>)>;
+
+/// Memoized results are dropped once there are this many, to bound memory
+const MEMO_LIMIT: usize = 1 << 16;
+
+/// Scoped byte ranges of one line and the state the next line starts in
+#[derive(Debug, Default)]
+struct LineHighlights {
+    spans: Vec<(Range<usize>, Scope)>,
+    end: State,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    hash: u64,
+    start: State,
+    highlights: Arc<LineHighlights>,
+}
+
+/// Incrementally highlights a buffer's text.
+///
+/// Lines are keyed on their content and start state, so after an edit only lines whose text
+/// changed, or whose start state changed because of an edit above them, are run through the
+/// grammar again. Work is bounded by a deadline; lines not reached yet keep their last spans.
+#[derive(Debug, Clone)]
+pub struct Highlighter {
+    pub grammar: Arc<Grammar>,
+    lines: Vec<Line>,
+    /// Lines before this are up to date with `version`
+    valid: usize,
+    version: u64,
+    /// First line edited since `version`, if edits were reported
+    dirty: Option<usize>,
+    memo: HashMap<(u64, State), Arc<LineHighlights>>,
+}
+
+fn hash_line(line: &str) -> u64 {
+    let mut h = DefaultHasher::new();
+    line.hash(&mut h);
+    h.finish()
+}
+
+/// End of the first match of `end` at or after `pos` that isn't inside a `skip` match
+fn region_end(line: &str, mut pos: usize, end: &Regex, skip: Option<&Regex>) -> Option<usize> {
+    loop {
+        let e = end.find_at(line, pos)?;
+        match skip.and_then(|s| s.find_at(line, pos)) {
+            Some(s) if s.start() < e.start() || (s.start() == e.start() && s.end() > e.start()) => {
+                pos = s.end().max(s.start() + char_len(line, s.start()));
+                if pos > line.len() {
+                    return None;
+                }
+            },
+            _ => return Some(e.end()),
+        }
+    }
+}
+
+/// Adds a scoped range, extending the previous one when it continues it
+fn push_span(spans: &mut Vec<(Range<usize>, Scope)>, range: Range<usize>, scope: Scope) {
+    match spans.last_mut() {
+        Some((last, s)) if *s == scope && last.end == range.start => last.end = range.end,
+        _ if range.start < range.end => spans.push((range, scope)),
+        _ => {},
+    }
+}
+
+/// Length of the char starting at `pos`, so zero length matches still make progress
+fn char_len(line: &str, pos: usize) -> usize {
+    line[pos..].chars().next().map_or(1, char::len_utf8)
+}
+
+impl Highlighter {
+    pub fn new(grammar: Arc<Grammar>) -> Self {
+        Self { grammar, lines: Vec::new(), valid: 0, version: 0, dirty: None, memo: HashMap::new() }
+    }
+
+    /// Highlights lines of `text` until the first `upto` are current or `deadline` passes.
+    /// `version` should change whenever the text does. Returns whether all `upto` lines
+    /// are current.
+    pub fn update(&mut self, text: &str, version: u64, upto: usize, deadline: Instant) -> bool {
+        if version != self.version {
+            self.version = version;
+            // A change not reported with `edited` could be anywhere
+            self.valid = self.valid.min(self.dirty.take().unwrap_or(0));
+        }
+        if self.valid >= upto {
+            return true;
+        }
+        let mut state = match self.valid {
+            0 => None,
+            n => self.lines[n - 1].highlights.end,
+        };
+        let mut lines = text.split('\n').enumerate().skip(self.valid);
+        for (i, line) in &mut lines {
+            let hash = hash_line(line);
+            let reuse = matches!(self.lines.get(i), Some(l) if l.hash == hash && l.start == state);
+            if !reuse {
+                let highlights = match self.memo.get(&(hash, state)) {
+                    Some(h) => h.clone(),
+                    None => {
+                        if self.memo.len() >= MEMO_LIMIT {
+                            self.memo.clear();
+                        }
+                        let h = Arc::new(self.highlight_line(line, state));
+                        self.memo.insert((hash, state), h.clone());
+                        h
+                    },
+                };
+                let l = Line { hash, start: state, highlights };
+                match self.lines.get_mut(i) {
+                    Some(old) => *old = l,
+                    None => self.lines.push(l),
+                }
+            }
+            state = self.lines[i].highlights.end;
+            self.valid = i + 1;
+            if self.valid >= upto {
+                return true;
+            }
+            if i % 32 == 31 && Instant::now() >= deadline {
+                return false;
+            }
+        }
+        // Reached the end of the text
+        self.lines.truncate(self.valid);
+        self.valid = usize::MAX;
+        true
+    }
+
+    /// Notes that the text changed from line `line` on, so the next update starts there
+    /// rather than at the top
+    pub fn edited(&mut self, line: usize) {
+        self.dirty = Some(self.dirty.map_or(line, |d| d.min(line)));
+    }
+
+    /// Runs the grammar over one line starting in `state`
+    fn highlight_line(&self, line: &str, mut state: State) -> LineHighlights {
+        let rules = &self.grammar.rules;
+        let mut spans = Vec::new();
+        // Earliest match of each rule at or after the position it was last searched from
+        let mut next: Vec<Option<NextMatch>> = vec![None; rules.len()];
+        let mut pos = 0;
+        while pos <= line.len() {
+            if let Some(r) = state {
+                let (scope, end, skip) = match &rules[r].kind {
+                    RuleKind::Region { end, skip, .. } => (rules[r].scope, end, skip.as_ref()),
+                    RuleKind::Match(_) => unreachable!("state is always a region"),
+                };
+                match region_end(line, pos, end, skip) {
+                    Some(e) => {
+                        push_span(&mut spans, pos..e, scope);
+                        pos = e;
+                        state = None;
+                    },
+                    None => {
+                        push_span(&mut spans, pos..line.len(), scope);
+                        return LineHighlights { spans, end: state };
+                    },
+                }
+                continue;
+            }
+            let mut best: Option<(usize, usize)> = None;
+            for (ri, rule) in rules.iter().enumerate() {
+                let stale = match &next[ri] {
+                    Some(Some((start, _))) => *start < pos,
+                    Some(None) => false,
+                    None => true,
+                };
+                if stale {
+                    next[ri] = Some(match &rule.kind {
+                        RuleKind::Match(re) if re.captures_len() > 1 => {
+                            let mut locs = re.capture_locations();
+                            re.captures_read_at(&mut locs, line, pos).map(|m| {
+                                let group = locs.get(1).unwrap_or((m.start(), m.end()));
+                                (m.start(), group.0..group.1)
+                            })
+                        },
+                        RuleKind::Match(re) => re.find_at(line, pos).map(|m| (m.start(), m.range())),
+                        RuleKind::Region { begin, .. } => {
+                            begin.find_at(line, pos).map(|m| (m.start(), m.range()))
+                        },
+                    });
+                }
+                if let Some(Some((start, _))) = &next[ri] {
+                    if best.map_or(true, |(s, _)| *start < s) {
+                        best = Some((*start, ri));
+                    }
+                }
+            }
+            let (start, ri) = match best {
+                Some(b) => b,
+                None => break,
+            };
+            let range = match &next[ri] {
+                Some(Some((_, range))) => range.clone(),
+                _ => unreachable!(),
+            };
+            push_span(&mut spans, range.clone(), rules[ri].scope);
+            if let RuleKind::Region { .. } = rules[ri].kind {
+                state = Some(ri);
+            }
+            // Always move forward, even past empty matches
+            pos = if range.end > pos { range.end } else { start + char_len(line, start) };
+        }
+        LineHighlights { spans, end: state }
+    }
+
+    /// Whether byte `byte` of line `idx` is code rather than part of a string or comment,
+    /// as of the last highlights computed for the line. Lines not highlighted yet are code.
+    pub fn is_code(&self, idx: usize, byte: usize) -> bool {
+        let scope = self.lines.get(idx)
+            .and_then(|l| l.highlights.spans.iter().find(|(range, _)| range.contains(&byte)))
+            .map(|(_, scope)| self.grammar.scope_name(*scope));
+        !scope.is_some_and(|s| s.starts_with("string") || s.starts_with("comment"))
+    }
+    /// First and last line of each region, such as a block comment, which spans lines
+    pub fn regions(&self) -> Vec<(usize, usize)> {
+        let mut regions = Vec::new();
+        let mut start = None;
+        for (i, line) in self.lines.iter().enumerate() {
+            match (start, line.highlights.end) {
+                (None, Some(_)) => start = Some(i),
+                (Some(s), None) => {
+                    regions.push((s, i));
+                    start = None;
+                },
+                _ => {},
+            }
+        }
+        regions
+    }
+
+    /// Spans for line `idx` with text `line` styled by `theme`, using the last highlights
+    /// computed for it
+    pub fn line_spans<'a>(&self, idx: usize, line: &'a str, theme: &Theme) -> Spans<'a> {
+        let highlights = match self.lines.get(idx) {
+            Some(l) => &l.highlights,
+            None => return Spans::from(line),
+        };
+        let mut spans = Vec::new();
+        let mut pos = 0;
+        for (range, scope) in highlights.spans.iter() {
+            let (start, end) = (range.start.max(pos), range.end.min(line.len()));
+            // Stale spans may not fit the line's current text
+            if start >= end || !line.is_char_boundary(start) || !line.is_char_boundary(end) {
+                continue;
+            }
+            if start > pos {
+                spans.push(Span::raw(&line[pos..start]));
+            }
+            spans.push(Span::styled(&line[start..end], theme.syntax(self.grammar.scope_name(*scope))));
+            pos = end;
+        }
+        if pos < line.len() {
+            spans.push(Span::raw(&line[pos..]));
+        }
+        Spans::from(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn highlighter(src: &str) -> Highlighter {
+        Highlighter::new(Arc::new(Grammar::parse(src).unwrap()))
+    }
+
+    fn rust() -> Highlighter {
+        highlighter(include_str!("../../runtime/grammars/rust.toml"))
+    }
+
+    fn later() -> Instant {
+        Instant::now() + Duration::from_secs(60)
+    }
+
+    /// The text of each span of line `idx` with its scope's name
+    fn scoped<'a, 'h>(h: &'h Highlighter, text: &'a str, idx: usize) -> Vec<(&'a str, &'h str)> {
+        let line = text.split('\n').nth(idx).unwrap();
+        h.lines[idx].highlights.spans.iter()
+            .map(|(range, scope)| (&line[range.clone()], h.grammar.scope_name(*scope)))
+            .collect()
+    }
+
+    #[test]
+    fn regions_carry_over_lines() {
+        let mut h = rust();
+        let text = "let a = 1; /* one\ntwo \"\nthree */ fn f() {}";
+        assert!(h.update(text, 1, usize::MAX, later()));
+        assert_eq!(h.regions(), [(0, 2)]);
+        assert_eq!(scoped(&h, text, 1), [("two \"", "comment")]);
+        assert_eq!(scoped(&h, text, 2)[..2], [("three */", "comment"), ("fn", "keyword")]);
+        assert!(!h.is_code(2, 0));
+        assert!(h.is_code(2, 9));
+    }
+
+    #[test]
+    fn edits_restart_at_the_first_edited_line() {
+        let mut h = rust();
+        let text = "let x = 1;\n".repeat(200);
+        assert!(h.update(&text, 1, usize::MAX, later()));
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        lines[100] = "let y = 2;";
+        let text = lines.join("\n");
+        h.edited(100);
+        // With the deadline gone, one batch of lines is highlighted from where the edit was
+        assert!(!h.update(&text, 2, usize::MAX, Instant::now()));
+        assert_eq!(h.valid, 128);
+        // A change it wasn't told about starts over at the top
+        assert!(!h.update(&text, 3, usize::MAX, Instant::now()));
+        assert_eq!(h.valid, 32);
+    }
+
+    #[test]
+    fn lines_are_memoized_by_text_and_start_state() {
+        let mut h = rust();
+        assert!(h.update("a\nb\nc", 1, usize::MAX, later()));
+        h.edited(0);
+        assert!(h.update("/* a\nb\nc", 2, usize::MAX, later()));
+        assert_eq!(scoped(&h, "/* a\nb\nc", 2), [("c", "comment")]);
+        let memoized = h.memo.len();
+        h.edited(0);
+        assert!(h.update("a\nb\nc", 3, usize::MAX, later()));
+        assert_eq!(h.memo.len(), memoized);
+        assert_eq!(scoped(&h, "a\nb\nc", 2), []);
+    }
+
+    #[test]
+    fn shipped_grammars() {
+        let cases = [
+            (include_str!("../../runtime/grammars/rust.toml"), "let s = \"a\\\"b\"; // c", vec![
+                ("let", "keyword"), ("=", "operator"), ("\"a\\\"b\"", "string"), ("// c", "comment"),
+            ]),
+            (include_str!("../../runtime/grammars/toml.toml"), "name = \"lx\" # c", vec![
+                ("name", "property"), ("\"lx\"", "string"), ("# c", "comment"),
+            ]),
+            (include_str!("../../runtime/grammars/shell.toml"), "echo $HOME 'x' # c", vec![
+                ("echo", "builtin"), ("$HOME", "variable"), ("'x'", "string"), ("# c", "comment"),
+            ]),
+            (include_str!("../../runtime/grammars/markdown.toml"), "Some `code` and **bold**", vec![
+                ("`code`", "code"), ("**bold**", "strong"),
+            ]),
+        ];
+        for (src, line, spans) in cases.iter() {
+            let mut h = highlighter(src);
+            assert!(h.update(line, 1, usize::MAX, later()));
+            assert_eq!(&scoped(&h, line, 0), spans, "{}", h.grammar.name);
+        }
+    }
+}
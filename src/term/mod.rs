@@ -0,0 +1,200 @@
+//! The terminal frontend: runs the editor on a crossterm terminal, turning terminal events
+//! into the editor's own key events.
+
+use std::{fs::{File, OpenOptions}, io::{self, Stdout, Write, stdout}, panic::{self, AssertUnwindSafe}};
+use crossterm::{cursor::{self, CursorShape}, event::{self, Event, EventStream}, execute, queue, style, terminal::{self, ClearType}, tty::IsTty};
+use futures::{FutureExt, StreamExt};
+use tui::backend::CrosstermBackend;
+
+use crate::{
+    Lx, LxResult, Mode,
+    key::{KeyCode, KeyEvent, KeyModifiers},
+    ui,
+};
+
+impl From<event::KeyModifiers> for KeyModifiers {
+    fn from(m: event::KeyModifiers) -> Self {
+        let mut modifiers = KeyModifiers::NONE;
+        for (from, to) in [
+            (event::KeyModifiers::SHIFT, KeyModifiers::SHIFT),
+            (event::KeyModifiers::CONTROL, KeyModifiers::CONTROL),
+            (event::KeyModifiers::ALT, KeyModifiers::ALT),
+        ] {
+            if m.contains(from) {
+                modifiers |= to;
+            }
+        }
+        modifiers
+    }
+}
+
+impl From<event::KeyCode> for KeyCode {
+    fn from(code: event::KeyCode) -> Self {
+        match code {
+            event::KeyCode::Char(c) => KeyCode::Char(c),
+            event::KeyCode::Enter => KeyCode::Enter,
+            event::KeyCode::Esc => KeyCode::Esc,
+            event::KeyCode::Tab => KeyCode::Tab,
+            event::KeyCode::BackTab => KeyCode::BackTab,
+            event::KeyCode::Backspace => KeyCode::Backspace,
+            event::KeyCode::Delete => KeyCode::Delete,
+            event::KeyCode::Insert => KeyCode::Insert,
+            event::KeyCode::Up => KeyCode::Up,
+            event::KeyCode::Down => KeyCode::Down,
+            event::KeyCode::Left => KeyCode::Left,
+            event::KeyCode::Right => KeyCode::Right,
+            event::KeyCode::Home => KeyCode::Home,
+            event::KeyCode::End => KeyCode::End,
+            event::KeyCode::PageUp => KeyCode::PageUp,
+            event::KeyCode::PageDown => KeyCode::PageDown,
+            event::KeyCode::F(n) => KeyCode::F(n),
+            event::KeyCode::Null => KeyCode::Null,
+        }
+    }
+}
+
+impl From<event::KeyEvent> for KeyEvent {
+    fn from(ke: event::KeyEvent) -> Self {
+        KeyEvent::new(ke.code.into(), ke.modifiers.into())
+    }
+}
+
+/// Shape of the terminal cursor in `mode`: a bar where typing inserts text
+fn cursor_shape(mode: &Mode) -> CursorShape {
+    match mode {
+        Mode::Insert(_) | Mode::Command(_) => CursorShape::Line,
+        Mode::Edit(_) | Mode::Overview(_) => CursorShape::Block,
+    }
+}
+
+/// Where the editor draws: standard output, or the terminal itself when standard output
+/// goes somewhere else, as it does in a pipeline. Keys are read from the terminal either way.
+pub enum Screen {
+    Stdout(Stdout),
+    Tty(File),
+}
+
+impl Screen {
+    pub fn open() -> Self {
+        let out = stdout();
+        if out.is_tty() {
+            return Screen::Stdout(out);
+        }
+        match OpenOptions::new().write(true).open("/dev/tty") {
+            Ok(tty) => Screen::Tty(tty),
+            Err(_) => Screen::Stdout(out),
+        }
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Screen::Stdout(out) => out.write(buf),
+            Screen::Tty(tty) => tty.write(buf),
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Screen::Stdout(out) => out.flush(),
+            Screen::Tty(tty) => tty.flush(),
+        }
+    }
+}
+
+impl Default for Lx<CrosstermBackend<Screen>> {
+    fn default() -> Self {
+        Self::new_with_backend(CrosstermBackend::new(Screen::open())).expect("Could not initialize TUI")
+    }
+}
+
+impl Lx<CrosstermBackend<Screen>> {
+
+    /// Runs the editor until it quits. Should it panic, the terminal is restored before the
+    /// panic is reported, and unsaved changes are journaled to swap files on the way out.
+    pub async fn run(&mut self) -> LxResult<()> {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            // The report would be lost on the alternate screen
+            let _ = restore_terminal();
+            hook(info);
+        }));
+        match AssertUnwindSafe(self.run_terminal()).catch_unwind().await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => {
+                self.journal();
+                let _ = restore_terminal();
+                Err(e)
+            },
+            Err(panic) => {
+                self.journal();
+                panic::resume_unwind(panic)
+            },
+        }
+    }
+    /// Sets up the terminal and runs the event loop on its events, setting the cursor shape
+    /// for the mode whenever the screen is drawn
+    async fn run_terminal(&mut self) -> LxResult<()> {
+        terminal::enable_raw_mode()?;
+        self.term.clear()?;
+        execute!(self.term.backend_mut(),
+            event::EnableMouseCapture,
+            terminal::EnterAlternateScreen,
+            terminal::EnableLineWrap,
+            cursor::EnableBlinking,
+            terminal::SetTitle("lx editor"),
+        )?;
+        let input = EventStream::new().map(|event| event.map(|event| match event {
+            Event::Key(ke) => Some(ke.into()),
+            _ => None,
+        }));
+        let mut shape = None;
+        self.event_loop(input, |lx| {
+            queue!(lx.term.backend_mut(), style::ResetColor, cursor::Show)?;
+            let want = cursor_shape(&lx.mode);
+            if shape != Some(want) {
+                shape = Some(want);
+                queue!(lx.term.backend_mut(), cursor::SetCursorShape(want))?;
+            }
+            // Highlighting that runs out of budget comes back for more after any input
+            let done = lx.highlight();
+            ui::draw_ui(lx)?;
+            Ok(!done)
+        }).await?;
+        if self.quit {
+            self.exit()?;
+        }
+        Ok(())
+    }
+
+    pub fn exit(&mut self, ) -> LxResult<()> {
+        execute!(self.term.backend_mut(),
+            event::DisableMouseCapture,
+            terminal::LeaveAlternateScreen,
+            terminal::Clear(ClearType::All),
+            )?;
+        terminal::disable_raw_mode()?;
+        Ok(())
+    }
+}
+
+/// Puts the terminal back the way it was before the editor started
+fn restore_terminal() -> crossterm::Result<()> {
+    execute!(Screen::open(), event::DisableMouseCapture, terminal::LeaveAlternateScreen, cursor::Show)?;
+    terminal::disable_raw_mode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossterm_keys_become_editor_keys() {
+        let ke = event::KeyEvent::new(event::KeyCode::Char('w'), event::KeyModifiers::CONTROL | event::KeyModifiers::ALT);
+        assert_eq!(KeyEvent::from(ke), KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL | KeyModifiers::ALT));
+        let ke = event::KeyEvent::new(event::KeyCode::F(5), event::KeyModifiers::SHIFT);
+        assert_eq!(KeyEvent::from(ke), KeyEvent::new(KeyCode::F(5), KeyModifiers::SHIFT));
+        assert_eq!(KeyCode::from(event::KeyCode::BackTab), KeyCode::BackTab);
+        assert_eq!(KeyModifiers::from(event::KeyModifiers::NONE), KeyModifiers::NONE);
+    }
+}
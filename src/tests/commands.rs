@@ -0,0 +1,24 @@
+use super::*;
+
+#[test]
+fn global_delete_and_substitute() {
+    let mut lx = editor();
+    lx.feed("keep a<cr>drop b<cr>keep c<cr>drop d<esc>").unwrap();
+    lx.feed(":g/drop/d<cr>").unwrap();
+    assert_text(&lx, "keep a\nkeep c");
+    lx.feed(":%s/keep (\\w)/\\1 &/<cr>").unwrap();
+    assert_text(&lx, "a keep a\nc keep c");
+    assert_cursor(&lx, 1, 0);
+    lx.feed(":1<cr>").unwrap();
+    assert_cursor(&lx, 0, 0);
+}
+
+#[test]
+fn quitting_with_unwritten_changes() {
+    let mut lx = sized(70, 10);
+    lx.feed("text<esc>:q<cr>").unwrap();
+    assert!(!lx.quit);
+    assert_rows(&mut lx, 9, &["No write since last change for [No Name] (add ! to override)"]);
+    lx.feed(":q!<cr>").unwrap();
+    assert!(lx.quit);
+}
@@ -0,0 +1,108 @@
+use super::*;
+
+#[test]
+fn typing_and_backspace() {
+    let mut lx = editor();
+    lx.feed("hello<cr>wrold<bs><bs><bs><bs>orld").unwrap();
+    assert_text(&lx, "hello\nworld");
+    assert_cursor(&lx, 1, 5);
+}
+
+#[test]
+fn leaving_insert_steps_back_onto_the_line() {
+    let mut lx = editor();
+    lx.feed("hello<esc>").unwrap();
+    assert_cursor(&lx, 0, 4);
+    lx.feed("0").unwrap();
+    assert_cursor(&lx, 0, 0);
+}
+
+#[test]
+fn motions_with_counts() {
+    let mut lx = editor();
+    lx.feed("one<cr>two<cr>three<cr>four<esc>").unwrap();
+    // The column is kept where the line allows
+    lx.feed("gg").unwrap();
+    assert_cursor(&lx, 0, 2);
+    lx.feed("02jll").unwrap();
+    assert_cursor(&lx, 2, 2);
+    lx.feed("G$").unwrap();
+    assert_cursor(&lx, 3, 3);
+    lx.feed("3k").unwrap();
+    assert_cursor(&lx, 0, 2);
+}
+
+#[test]
+fn open_lines_and_shift() {
+    let mut lx = editor();
+    lx.feed("middle<esc>").unwrap();
+    lx.feed("Oabove<esc>jolast<esc>").unwrap();
+    assert_text(&lx, "above\nmiddle\nlast");
+    lx.feed("gg>j").unwrap();
+    assert_text(&lx, "\tabove\n\tmiddle\nlast");
+}
+
+#[test]
+fn set_command() {
+    let mut lx = editor();
+    lx.feed("text<esc>:set ff=dos<cr>").unwrap();
+    assert!(matches!(lx.mode, crate::mode::Mode::Edit(_)));
+    assert_rows(&mut lx, 8, &[" EDIT  [No Name]  [+]  utf-8  dos  1:4"]);
+}
+
+#[test]
+fn screen_snapshot() {
+    let mut lx = editor();
+    lx.feed("hello<cr>world<esc>").unwrap();
+    assert_rows(&mut lx, 0, &[
+        "┌[No Name] [+]─────────────────────────┐",
+        "│  1 hello                             │",
+        "│  2 world                             │",
+        "│                                      │",
+    ]);
+    assert_rows(&mut lx, 7, &[
+        "└──────────────────────────────────────┘",
+        " EDIT  [No Name]  [+]  utf-8  unix  2:5",
+        "",
+    ]);
+}
+
+#[test]
+fn dot_repeats_the_last_change() {
+    let mut lx = editor();
+    lx.feed("a<cr>b<cr>c<cr>d<cr>e<esc>gg>j").unwrap();
+    lx.feed("jj.").unwrap();
+    assert_text(&lx, "\ta\n\tb\n\tc\n\td\ne");
+    // A new count replaces the old one, and is kept for the next repeat
+    lx.feed("gg3.").unwrap();
+    assert_text(&lx, "\t\ta\n\t\tb\n\t\tc\n\t\td\ne");
+    lx.feed("G.").unwrap();
+    assert_text(&lx, "\t\ta\n\t\tb\n\t\tc\n\t\td\n\te");
+}
+
+#[test]
+fn dot_repeats_what_was_typed() {
+    let mut lx = editor();
+    lx.feed("x<esc>ofoo<esc>.").unwrap();
+    assert_text(&lx, "x\nfoo\nfoo");
+    assert_cursor(&lx, 2, 2);
+    lx.feed("0<cr>ab<esc>2.").unwrap();
+    assert_text(&lx, "x\nfoo\naababbfoo");
+    assert_eq!(lx.changes.iter().count(), 5);
+}
+
+#[test]
+fn counts_repeat_opened_lines() {
+    let mut lx = editor();
+    lx.feed("x<esc>3ofoo<esc>").unwrap();
+    assert_text(&lx, "x\nfoo\nfoo\nfoo");
+    assert_cursor(&lx, 3, 2);
+    lx.feed("gg2Obar<esc>").unwrap();
+    assert_text(&lx, "bar\nbar\nx\nfoo\nfoo\nfoo");
+    assert_cursor(&lx, 1, 2);
+    lx.feed("G.").unwrap();
+    assert_text(&lx, "bar\nbar\nx\nfoo\nfoo\nbar\nbar\nfoo");
+    lx.feed("G3.").unwrap();
+    assert_text(&lx, "bar\nbar\nx\nfoo\nfoo\nbar\nbar\nbar\nbar\nbar\nfoo");
+    assert_cursor(&lx, 9, 2);
+}
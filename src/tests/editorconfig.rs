@@ -0,0 +1,19 @@
+use std::fs;
+
+use super::*;
+use crate::window::OpenIn;
+
+#[test]
+fn editorconfig_is_applied_on_save() {
+    let dir = TempDir::new("editorconfig-save");
+    dir.file(".editorconfig", "root = true\n[*.rs]\nmax_line_length = 40\ntrim_trailing_whitespace = true\ninsert_final_newline = true\n");
+    let long = "    let s = \"a string literal much longer than forty columns\";";
+    let path = dir.file("main.rs", &format!("fn main() {{   \n{}\n}}", long));
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    assert_eq!(lx.buf[lx.buf_idx()].textwidth, Some(40));
+    lx.feed("<esc>:w<cr>").unwrap();
+    // The line length only reaches gq: long lines are written as they are
+    assert_eq!(fs::read_to_string(&path).unwrap(), format!("fn main() {{\n{}\n}}\n", long));
+    assert!(!lx.buf[lx.buf_idx()].modified);
+}
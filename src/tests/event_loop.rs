@@ -0,0 +1,94 @@
+use std::{fs, time::{Duration, Instant}};
+use futures::{StreamExt, stream};
+use tui::backend::TestBackend;
+
+use super::*;
+use crate::{LxResult, files::FileOp, key::{KeyCode, KeyEvent, KeyModifiers, notation}, window::OpenIn};
+
+/// Runs the event loop on `keys` followed by input which never comes, failing should it
+/// not quit within a few seconds
+async fn run(lx: &mut Lx<TestBackend>, keys: Vec<KeyEvent>, draw: impl FnMut(&mut Lx<TestBackend>) -> LxResult<bool>) {
+    let input = stream::iter(keys.into_iter().map(|k| Ok(Some(k)))).chain(stream::pending());
+    tokio::time::timeout(Duration::from_secs(5), lx.event_loop(input, draw)).await
+        .expect("the event loop should have quit")
+        .expect("the event loop failed");
+}
+
+#[tokio::test]
+async fn waiting_input_is_handled_before_drawing() {
+    let mut lx = editor();
+    let mut drawn = Vec::new();
+    run(&mut lx, notation::parse("one<cr>two<esc>"), |lx| {
+        drawn.push(lx.buf[lx.buf_idx()].text.clone());
+        lx.quit = true;
+        Ok(false)
+    }).await;
+    assert_eq!(drawn, ["one\ntwo"]);
+}
+
+#[tokio::test]
+async fn prefix_keys_time_out() {
+    let mut lx = editor();
+    lx.run_cmd("set timeoutlen=30").unwrap();
+    let leader = KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char(' ') };
+    let start = Instant::now();
+    let mut draws = 0;
+    run(&mut lx, vec![leader], |lx| {
+        draws += 1;
+        lx.quit = lx.prefix.is_none();
+        Ok(false)
+    }).await;
+    assert_eq!(draws, 2);
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
+
+#[tokio::test]
+async fn changes_are_written_once_input_pauses() {
+    let dir = TempDir::new("event-loop-autosave");
+    let path = dir.file("saved.txt", "before\n");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.run_cmd("set autosave=30").unwrap();
+    let start = Instant::now();
+    run(&mut lx, notation::parse("<esc>oafter<esc>"), |lx| {
+        lx.quit = !lx.buf[lx.buf_idx()].modified;
+        Ok(false)
+    }).await;
+    assert_eq!(fs::read_to_string(&path).unwrap(), "before\nafter\n");
+    assert!(start.elapsed() >= Duration::from_millis(30));
+}
+
+#[tokio::test]
+async fn files_changed_on_disk_are_reloaded() {
+    let dir = TempDir::new("event-loop");
+    let path = dir.file("watched.txt", "before\n");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    let changed = path.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        fs::write(changed, "after the change\n").unwrap();
+    });
+    run(&mut lx, Vec::new(), |lx| {
+        lx.quit = lx.buf[lx.buf_idx()].text == "after the change";
+        Ok(false)
+    }).await;
+}
+
+#[tokio::test]
+async fn moved_files_are_watched_where_they_went() {
+    let dir = TempDir::new("event-loop-moved");
+    let path = dir.file("watched.txt", "before\n");
+    let moved = dir.join("moved/watched.txt");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.file_op(FileOp::rename(path, moved.clone()));
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        fs::write(moved, "after the move\n").unwrap();
+    });
+    run(&mut lx, Vec::new(), |lx| {
+        lx.quit = lx.buf[lx.buf_idx()].text == "after the move";
+        Ok(false)
+    }).await;
+}
@@ -0,0 +1,108 @@
+use std::fs;
+
+use super::*;
+use crate::{files::FileOp, options::{Encoding, FileFormat}, overview::{ExplorerView, ViewRegistry}, window::OpenIn};
+
+#[test]
+fn buffers_follow_file_operations() {
+    let dir = TempDir::new("file-ops");
+    let path = dir.file("open.rs", "text\n");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.feed("<esc>").unwrap();
+
+    let moved = dir.join("sub/moved.rs");
+    lx.file_op(FileOp::rename(path.clone(), moved.clone()));
+    assert_eq!(lx.buf[lx.buf_idx()].path.as_deref(), Some(moved.as_path()));
+    assert!(lx.watcher.watches(&moved) && !lx.watcher.watches(&path));
+    lx.undo_file_op();
+    assert_eq!(lx.buf[lx.buf_idx()].path.as_deref(), Some(path.as_path()));
+    assert!(lx.watcher.watches(&path) && !lx.watcher.watches(&moved));
+
+    // Trashed, the buffer isn't pointed into the trash, so writing it puts the file back
+    lx.file_op(FileOp::Trash { path: path.clone(), trashed: dir.join("trash/open.rs") });
+    let b = &lx.buf[lx.buf_idx()];
+    assert_eq!(b.path.as_deref(), Some(path.as_path()));
+    assert!(b.modified);
+    lx.check_file(&path);
+    assert!(lx.prompts.is_empty());
+    lx.feed(":w<cr>").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "text\n");
+    assert!(dir.join("trash/open.rs").exists());
+}
+
+#[test]
+fn closed_buffers_are_no_longer_watched() {
+    let dir = TempDir::new("file-close");
+    let (a, b) = (dir.file("a.txt", "a\n"), dir.file("b.txt", "b\n"));
+    let mut lx = editor();
+    lx.open(&a, OpenIn::Current).unwrap();
+    lx.open(&b, OpenIn::Current).unwrap();
+    assert!(lx.watcher.watches(&a) && lx.watcher.watches(&b));
+    let idx = lx.buf.iter().position(|buf| buf.path.as_deref() == Some(a.as_path())).unwrap();
+    lx.close_buf(idx);
+    assert!(!lx.watcher.watches(&a));
+    assert!(lx.watcher.watches(&b));
+}
+
+#[test]
+fn converted_files_are_written_in_their_new_format() {
+    let dir = TempDir::new("file-convert");
+    let path = dir.file("text.txt", "héllo\nwörld\n");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.feed("<esc>:set fileformat=dos<cr>:w<cr>").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), "héllo\r\nwörld\r\n".as_bytes());
+    lx.feed(":set fileencoding=latin1<cr>:w<cr>").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"h\xe9llo\r\nw\xf6rld\r\n");
+    // Read again, the file is found to be what it was written as
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    let file = &lx.buf[lx.buf_idx()].file;
+    assert_eq!((file.fileencoding, file.fileformat), (Encoding::Latin1, FileFormat::Dos));
+    assert_text(&lx, "héllo\nwörld");
+}
+
+#[test]
+fn utf16_files_keep_going_without_a_byte_order_mark() {
+    let dir = TempDir::new("file-utf16");
+    let path = dir.join("text.txt");
+    fs::write(&path, "ab\n".encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>()).unwrap();
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.feed("<esc>oc<esc>:w<cr>").unwrap();
+    assert_eq!(fs::read(&path).unwrap(), b"a\0b\0\n\0c\0\n\0");
+}
+
+#[test]
+fn explorer_asks_on_the_message_line() {
+    let dir = TempDir::new("file-explorer");
+    dir.file("a.txt", "a\n");
+    let mut lx = editor();
+    lx.views = ViewRegistry::default();
+    lx.views.register(ExplorerView::new(dir.to_path_buf()));
+    lx.feed("<esc><esc>").unwrap();
+    screen(&mut lx);
+    lx.feed("anwe<bs><bs>ew.txt").unwrap();
+    assert_eq!(lx.prompts[0].text(), format!("Create in {}/: new.txt", dir.display()));
+    lx.feed("<cr>").unwrap();
+    assert!(lx.prompts.is_empty());
+    assert!(dir.join("new.txt").is_file());
+    assert_rows(&mut lx, 5, &["│>   a.txt                             │", "│    new.txt                           │"]);
+
+    // Renaming starts from the old name
+    lx.feed("jr<bs><bs><bs>md").unwrap();
+    assert_eq!(lx.prompts[0].text(), "Rename to: new.md");
+    lx.feed("<cr>").unwrap();
+    assert!(dir.join("new.md").is_file() && !dir.join("new.txt").exists());
+
+    // Keys which are no choice leave the question up
+    screen(&mut lx);
+    lx.feed("d").unwrap();
+    assert_eq!(lx.prompts[0].text(), "Delete new.md? (y)es, (n)o");
+    lx.feed("xn").unwrap();
+    assert!(lx.prompts.is_empty());
+    lx.feed("m<esc>").unwrap();
+    assert!(lx.prompts.is_empty());
+    assert!(dir.join("new.md").is_file());
+}
@@ -0,0 +1,46 @@
+use super::*;
+
+/// An editor on `text` as read from standard input in filter mode
+fn filter(text: &str) -> Lx<TestBackend> {
+    let mut lx = editor();
+    lx.open_bytes(text.as_bytes());
+    let idx = lx.buf_idx();
+    lx.buf[idx].stdout = true;
+    lx.feed("<esc>").unwrap();
+    lx
+}
+
+#[test]
+fn written_buffers_go_to_standard_output() {
+    let mut lx = filter("one\ntwo\n");
+    assert_rows(&mut lx, 0, &["┌[stdout]──────────────────────────────┐"]);
+    lx.feed("<cr>a <esc>:w<cr>").unwrap();
+    assert_eq!(lx.output.as_deref(), Some(&b"a one\ntwo\n"[..]));
+    assert!(!lx.buf[lx.buf_idx()].modified);
+    // Writing again replaces what was written
+    lx.feed("<cr>b <esc>:wq<cr>").unwrap();
+    assert_eq!(lx.output.as_deref(), Some(&b"ab  one\ntwo\n"[..]));
+    assert!(lx.quit);
+}
+
+#[test]
+fn x_writes_standard_output_once_even_unchanged() {
+    let mut lx = filter("text");
+    lx.feed(":x<cr>").unwrap();
+    assert_eq!(lx.output.as_deref(), Some(&b"text"[..]));
+    assert!(lx.quit);
+}
+
+#[test]
+fn quitting_without_writing_outputs_nothing() {
+    let mut lx = filter("text\n");
+    lx.feed(":q<cr>").unwrap();
+    assert!(lx.quit);
+    assert_eq!(lx.output, None);
+    let mut lx = filter("text\n");
+    lx.feed("<cr>changed <esc>:q<cr>").unwrap();
+    assert!(!lx.quit);
+    lx.feed(":q!<cr>").unwrap();
+    assert!(lx.quit);
+    assert_eq!(lx.output, None);
+}
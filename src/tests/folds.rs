@@ -0,0 +1,55 @@
+use super::*;
+use crate::syntax::Highlighter;
+
+#[test]
+fn manual_folds_move_and_shift_as_one_line() {
+    let mut lx = editor();
+    lx.feed("one<cr>two<cr>three<cr>four<cr>five<esc>ggjzfj").unwrap();
+    assert_rows(&mut lx, 1, &["│  1 one                               │", "│  2 +--   2 lines: two----------------│", "│  4 four                              │"]);
+    lx.feed("j").unwrap();
+    assert_cursor(&lx, 3, 2);
+    lx.feed("k").unwrap();
+    assert_cursor(&lx, 1, 2);
+    lx.feed(">>").unwrap();
+    assert_text(&lx, "one\n\ttwo\n\tthree\nfour\nfive");
+    lx.feed("zo").unwrap();
+    assert_rows(&mut lx, 2, &["│  2         two                       │"]);
+    lx.feed("jza").unwrap();
+    assert_rows(&mut lx, 2, &["│  2 +--   2 lines: two----------------│"]);
+    lx.feed("zd").unwrap();
+    assert_rows(&mut lx, 2, &["│  2         two                       │"]);
+    lx.feed("zd").unwrap();
+    assert_rows(&mut lx, 9, &["No fold found"]);
+}
+
+#[test]
+fn indent_folds_keep_closed_across_edits() {
+    let mut lx = editor();
+    lx.feed("a<cr>b<cr>c<cr>d<esc>:set sw=2 et fdm=indent<cr>ggj>jzM").unwrap();
+    assert_rows(&mut lx, 2, &["│  2 +--   2 lines: b------------------│", "│  4 d                                 │"]);
+    lx.feed("ggOnew<esc>").unwrap();
+    assert_rows(&mut lx, 3, &["│  3 +--   2 lines: b------------------│"]);
+    lx.feed("zR").unwrap();
+    assert_rows(&mut lx, 3, &["│  3   b                               │"]);
+    lx.feed("zfj").unwrap();
+    assert_rows(&mut lx, 9, &["Cannot create fold with current foldmeth"]);
+}
+
+#[test]
+fn syntax_folds_skip_strings_and_comments() {
+    let mut lx = editor();
+    lx.buf[0].syntax = lx.grammars.get("rust").map(Highlighter::new);
+    lx.feed("fn main() {<cr>// }<cr>let s = \"{\";<cr>}<cr>/*<cr>a<cr>*/<esc>:set fdm=syntax<cr>zM").unwrap();
+    assert_rows(&mut lx, 1, &["│  1 +--   4 lines: fn main() {--------│", "│  5 +--   3 lines: /*-----------------│"]);
+}
+
+#[test]
+fn lines_below_closed_folds_are_highlighted() {
+    let mut lx = editor();
+    lx.buf[0].syntax = lx.grammars.get("rust").map(Highlighter::new);
+    lx.feed(&format!("x{}<cr>// after<esc>gg", "<cr>a".repeat(20))).unwrap();
+    lx.feed("jzf19j").unwrap();
+    assert_rows(&mut lx, 3, &["│ 22 // after                          │"]);
+    assert!(lx.highlight());
+    assert!(!lx.buf[0].syntax.as_ref().unwrap().is_code(21, 0));
+}
@@ -0,0 +1,34 @@
+use super::*;
+
+#[test]
+fn motions_and_deletes_take_whole_graphemes() {
+    let mut lx = editor();
+    lx.feed("a漢e\u{301}👩\u{200d}💻z<esc>0").unwrap();
+    for col in 1..5 {
+        lx.feed("l").unwrap();
+        assert_cursor(&lx, 0, col);
+    }
+    lx.feed("$").unwrap();
+    assert_cursor(&lx, 0, 4);
+    // Backspace deletes the whole grapheme before the cursor
+    lx.feed("<cr><bs>").unwrap();
+    assert_text(&lx, "a漢e\u{301}z");
+    lx.feed("<bs>").unwrap();
+    assert_text(&lx, "a漢z");
+    lx.feed("\u{e9}<esc>").unwrap();
+    assert_text(&lx, "a漢\u{e9}z");
+    assert_cursor(&lx, 0, 2);
+}
+
+#[test]
+fn wide_chars_tabs_and_control_chars_are_drawn_by_width() {
+    let mut lx = editor();
+    lx.feed("漢字\tz<cr>x\u{1}y\u{7f}<esc>").unwrap();
+    assert_rows(&mut lx, 1, &["│  1 漢 字     z                         │", "│  2 x^Ay^?                            │"]);
+    assert_rows(&mut lx, 8, &[" EDIT  [No Name]  [+]  utf-8  unix  2:4"]);
+    // The cursor keeps its screen column across lines of different widths
+    lx.feed("k").unwrap();
+    assert_cursor(&lx, 0, 2);
+    lx.feed("$j").unwrap();
+    assert_cursor(&lx, 1, 3);
+}
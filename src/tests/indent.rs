@@ -0,0 +1,23 @@
+use super::*;
+use crate::window::OpenIn;
+
+#[test]
+fn new_lines_follow_the_detected_indent() {
+    let dir = TempDir::new("indent");
+    let path = dir.file("main.rs", "fn main() {\n  let a = 1;\n}\n");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    // Two spaces are detected, so blocks opened and closed step by two
+    lx.feed("<esc>joif a {<cr>b;<cr>}<esc>").unwrap();
+    assert_text(&lx, "fn main() {\n  let a = 1;\n  if a {\n    b;\n  }\n}");
+    assert_cursor(&lx, 4, 2);
+    lx.feed("kOc<esc>").unwrap();
+    assert_text(&lx, "fn main() {\n  let a = 1;\n  if a {\n    c\n    b;\n  }\n}");
+    lx.feed("gg>j").unwrap();
+    assert_text(&lx, "  fn main() {\n    let a = 1;\n  if a {\n    c\n    b;\n  }\n}");
+    assert_cursor(&lx, 0, 2);
+    lx.feed("<lt>G").unwrap();
+    assert_text(&lx, "fn main() {\n  let a = 1;\nif a {\n  c\n  b;\n}\n}");
+    lx.feed(":setlocal noet sw=0 ts=4<cr>>>").unwrap();
+    assert_text(&lx, "\tfn main() {\n  let a = 1;\nif a {\n  c\n  b;\n}\n}");
+}
@@ -0,0 +1,45 @@
+use super::*;
+use crate::register;
+
+#[test]
+fn record_and_play_with_counts() {
+    let mut lx = editor();
+    lx.feed("a<cr>b<cr>c<cr>d<cr>e<esc>gg").unwrap();
+    lx.feed("qa>>jq").unwrap();
+    assert_eq!(lx.registers.get('a'), Some(">>j"));
+    assert_rows(&mut lx, 8, &[" EDIT  [No Name]  [+]  utf-8  unix  2:1"]);
+    lx.feed("2@a").unwrap();
+    assert_text(&lx, "\ta\n\tb\n\tc\nd\ne");
+    lx.feed("@@").unwrap();
+    assert_text(&lx, "\ta\n\tb\n\tc\n\td\ne");
+    assert_cursor(&lx, 4, 0);
+}
+
+#[test]
+fn recursive_macro_stops_at_the_last_line() {
+    let mut lx = editor();
+    lx.feed("x = 1<cr>x = 2<cr>x = 3<esc>gg").unwrap();
+    lx.feed("qaqqa:s/x/y/<cr>j@aq").unwrap();
+    assert_eq!(lx.registers.get('a'), Some(":s/x/y/<cr>j@a"));
+    assert_text(&lx, "y = 1\nx = 2\nx = 3");
+    lx.feed("@a").unwrap();
+    assert_text(&lx, "y = 1\ny = 2\ny = 3");
+    assert!(lx.replay.is_empty() && lx.message.is_none());
+    // A failing command stops it too
+    lx.feed("gg@a").unwrap();
+    assert_cursor(&lx, 0, 0);
+}
+
+#[test]
+fn macros_edited_as_text() {
+    let mut lx = editor();
+    lx.feed("one<cr>two<esc>gg").unwrap();
+    lx.feed("qb>>q").unwrap();
+    lx.feed(":put b<cr>").unwrap();
+    assert_text(&lx, "\tone\n>>\ntwo");
+    lx.feed("<cr><end>j>><esc>:d b<cr>").unwrap();
+    assert_eq!(lx.registers.get('b'), Some(">>j>>\n"));
+    lx.feed("gg@b").unwrap();
+    assert_text(&lx, "\t\tone\n\ttwo");
+    assert_eq!(lx.registers.get(register::UNNAMED), None);
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+#[test]
+fn marks_follow_their_lines() {
+    let mut lx = editor();
+    lx.feed("a<cr>  bcd<cr>c<cr>d<esc>2Gllma").unwrap();
+    lx.feed("ggOabove<esc>G'a").unwrap();
+    assert_cursor(&lx, 2, 2);
+    lx.feed("gg`a").unwrap();
+    assert_cursor(&lx, 2, 4);
+    // `''` goes back to before the jump
+    lx.feed("''").unwrap();
+    assert_cursor(&lx, 0, 0);
+    lx.feed("'b").unwrap();
+    assert_rows(&mut lx, 9, &["Mark not set: b"]);
+}
+
+#[test]
+fn file_marks_lead_to_their_buffer() {
+    let mut lx = editor();
+    lx.feed("first<esc>mAc").unwrap();
+    assert_eq!(lx.buf_idx(), 1);
+    lx.feed("'A").unwrap();
+    assert_eq!(lx.buf_idx(), 0);
+    assert_cursor(&lx, 0, 0);
+    lx.feed("<c-o>").unwrap();
+    assert_eq!(lx.buf_idx(), 1);
+    lx.feed("<tab>").unwrap();
+    assert_eq!(lx.buf_idx(), 0);
+}
+
+#[test]
+fn jumps_and_changes() {
+    let mut lx = editor();
+    lx.feed("one<cr>two<cr>three<cr>four<cr>five<esc>gg3G").unwrap();
+    lx.feed("<c-o>").unwrap();
+    assert_cursor(&lx, 0, 2);
+    lx.feed("<c-o>").unwrap();
+    assert_cursor(&lx, 4, 3);
+    lx.feed("<c-i><c-i>").unwrap();
+    assert_cursor(&lx, 2, 3);
+    lx.feed("g;").unwrap();
+    assert_cursor(&lx, 4, 3);
+    lx.feed("3g;").unwrap();
+    assert_cursor(&lx, 1, 2);
+    lx.feed("g,").unwrap();
+    assert_cursor(&lx, 2, 4);
+}
@@ -0,0 +1,99 @@
+//! Editor tests without a terminal: keys are fed to an editor drawing to a `TestBackend`,
+//! then its buffer, cursor and screen are checked against what they should be.
+
+mod commands;
+mod editing;
+mod macros;
+mod marks;
+mod folds;
+mod pairs;
+mod reload;
+mod files;
+mod scroll;
+mod editorconfig;
+mod wrap;
+mod graphemes;
+mod indent;
+mod swap;
+mod event_loop;
+mod filter;
+mod overview;
+
+use std::{fs, ops::Deref, path::{Path, PathBuf}};
+use tui::backend::TestBackend;
+
+use crate::{Lx, types::Cursor, ui};
+
+/// A directory of its own for a test, removed with everything in it once dropped, even
+/// when an assertion fails
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("lx-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Could not create the test directory");
+        Self(dir)
+    }
+    /// Writes `text` to the file `name` in the directory, giving its path
+    pub fn file(&self, name: &str, text: &str) -> PathBuf {
+        let path = self.0.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Could not create the test directory");
+        }
+        fs::write(&path, text).expect("Could not write the test file");
+        path
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A 40 by 10 editor with one empty buffer in Insert mode
+pub fn editor() -> Lx<TestBackend> {
+    sized(40, 10)
+}
+
+/// A `width` by `height` editor which journals nothing, so it leaves no swap files behind
+pub fn sized(width: u16, height: u16) -> Lx<TestBackend> {
+    let mut lx = Lx::new_with_backend(TestBackend::new(width, height)).expect("Could not create the test editor");
+    lx.swap_dir = None;
+    lx
+}
+
+/// Draws the editor, giving the text of each row of the screen without trailing blanks
+pub fn screen(lx: &mut Lx<TestBackend>) -> Vec<String> {
+    ui::draw_ui(lx).expect("Could not draw the test editor");
+    let buf = lx.term.backend().buffer();
+    (0..buf.area.height)
+        .map(|y| (0..buf.area.width).map(|x| buf.get(x, y).symbol.as_str()).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+#[track_caller]
+pub fn assert_text(lx: &Lx<TestBackend>, text: &str) {
+    assert_eq!(lx.buf[lx.buf_idx()].text, text, "buffer text");
+}
+
+/// Checks the cursor is on the 0-based `line` and `col`
+#[track_caller]
+pub fn assert_cursor(lx: &Lx<TestBackend>, line: usize, col: usize) {
+    assert_eq!(lx.cursor(), Cursor::new(line, col), "cursor");
+}
+
+/// Checks the rows of the screen starting at row `first`
+#[track_caller]
+pub fn assert_rows(lx: &mut Lx<TestBackend>, first: usize, rows: &[&str]) {
+    let screen = screen(lx);
+    assert_eq!(&screen[first..first + rows.len()], rows, "screen rows from {}:\n{}", first, screen.join("\n"));
+}
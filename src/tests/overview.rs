@@ -0,0 +1,44 @@
+use super::*;
+use crate::mode::Mode;
+
+/// The row of the focused list view holding the selection marker, 0-based
+fn selected_row(lx: &mut Lx<TestBackend>) -> Option<usize> {
+    screen(lx)[4..].iter().position(|row| row.starts_with("│>"))
+}
+
+#[test]
+fn list_views_move_the_selection_with_keys() {
+    let mut lx = editor();
+    lx.open_bytes(b"second\n");
+    lx.open_bytes(b"third\n");
+    lx.feed("<esc><esc>").unwrap();
+    assert_eq!(lx.mode, Mode::overview());
+    assert_rows(&mut lx, 3, &["┌Buffers───────────────────────────────┐", "│>   1   [No Name]                     │"]);
+    lx.feed("jj").unwrap();
+    assert_eq!(selected_row(&mut lx), Some(2));
+    // The selection stops at either end, and the arrow keys move it too
+    lx.feed("j").unwrap();
+    assert_eq!(selected_row(&mut lx), Some(2));
+    lx.feed("k").unwrap();
+    assert_eq!(selected_row(&mut lx), Some(1));
+    lx.feed("<up><up><down>").unwrap();
+    assert_eq!(selected_row(&mut lx), Some(1));
+    lx.feed("<cr>").unwrap();
+    assert_eq!(lx.mode, Mode::edit());
+    assert_eq!(lx.buf_idx(), 1);
+    assert_text(&lx, "second");
+}
+
+#[test]
+fn tab_focuses_the_next_view() {
+    let mut lx = editor();
+    lx.feed("<esc><esc>").unwrap();
+    assert_eq!(lx.views.focus(), 0);
+    lx.feed("<tab><tab>").unwrap();
+    assert_eq!(lx.views.focus(), 2);
+    assert_rows(&mut lx, 3, &["┌Tabs──────────────────────────────────┐"]);
+    for _ in 2..lx.views.len() {
+        lx.feed("<tab>").unwrap();
+    }
+    assert_eq!(lx.views.focus(), 0);
+}
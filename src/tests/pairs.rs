@@ -0,0 +1,60 @@
+use tui::style::Modifier;
+
+use super::*;
+use crate::syntax::Highlighter;
+
+#[test]
+fn percent_skips_strings_and_shows_the_match() {
+    let mut lx = editor();
+    lx.buf[0].syntax = lx.grammars.get("rust").map(Highlighter::new);
+    lx.feed("f(\")\", x)<esc>0").unwrap();
+    lx.highlight();
+    lx.feed("%").unwrap();
+    assert_cursor(&lx, 0, 8);
+    lx.feed("%").unwrap();
+    assert_cursor(&lx, 0, 1);
+    screen(&mut lx);
+    let matched = |x| lx.term.backend().buffer().get(x, 1).modifier.contains(Modifier::UNDERLINED);
+    assert_eq!((5..14).filter(|x| matched(*x)).collect::<Vec<_>>(), [6, 13]);
+}
+
+#[test]
+fn autopairs_type_and_step_over_closers() {
+    let mut lx = editor();
+    lx.feed("<esc>:set autopairs<cr><cr>f(x<bs><bs>a[\"b\"]").unwrap();
+    assert_text(&lx, "fa[\"b\"]");
+    assert_cursor(&lx, 0, 7);
+}
+
+#[test]
+fn surround_adds_replaces_and_deletes() {
+    let mut lx = editor();
+    lx.feed("foo bar<esc>0saiw(").unwrap();
+    assert_text(&lx, "(foo) bar");
+    lx.feed("lsr(<lt>em class=\"x\">").unwrap();
+    assert_text(&lx, "<em class=\"x\">foo</em> bar");
+    lx.feed("sdt").unwrap();
+    assert_text(&lx, "foo bar");
+    lx.feed("sas\"").unwrap();
+    assert_text(&lx, "\"foo bar\"");
+    lx.feed("$sa%]").unwrap();
+    assert_text(&lx, "\"foo bar\"");
+    lx.feed("0lsa$]").unwrap();
+    assert_text(&lx, "\"[foo bar\"]");
+    lx.feed("sd]sd\"").unwrap();
+    assert_text(&lx, "foo bar");
+}
+
+
+#[test]
+fn text_objects_take_what_pairs_hold() {
+    let mut lx = editor();
+    lx.feed("f {<cr>a(b)<cr>}<esc>k>>").unwrap();
+    assert_text(&lx, "f {\n\ta(b)\n}");
+    lx.feed("<i{").unwrap();
+    assert_text(&lx, "f {\na(b)\n}");
+    lx.feed("2>a(").unwrap();
+    assert_text(&lx, "f {\na(b)\n}");
+    lx.feed("$sdbsaiwB").unwrap();
+    assert_text(&lx, "f {\n{ab}\n}");
+}
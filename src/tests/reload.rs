@@ -0,0 +1,50 @@
+use std::fs;
+
+use super::*;
+use crate::window::OpenIn;
+
+#[test]
+fn reload_keeps_cursors_on_a_shorter_file() {
+    let dir = TempDir::new("reload-shrink");
+    let path = dir.file("shrink", &"line\n".repeat(30));
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.feed("<esc>Gk").unwrap();
+    screen(&mut lx);
+    fs::write(&path, "one\ntwo\n").unwrap();
+    lx.check_file(&path);
+    assert_text(&lx, "one\ntwo");
+    assert_cursor(&lx, 1, 0);
+    assert!(!lx.buf[lx.buf_idx()].modified);
+    assert_rows(&mut lx, 1, &["│  1 one                               │", "│  2 two                               │"]);
+}
+
+#[test]
+fn changed_file_with_unsaved_changes_asks() {
+    let dir = TempDir::new("reload-conflict");
+    let path = dir.file("conflict", "disk\n");
+    let mut lx = editor();
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.feed("<esc><cr>my <esc>").unwrap();
+    fs::write(&path, "changed\nagain\n").unwrap();
+    lx.check_file(&path);
+    assert_eq!(lx.prompts.len(), 1);
+    // Checked again without a further change, it doesn't ask twice
+    lx.check_file(&path);
+    assert_eq!(lx.prompts.len(), 1);
+    let bufs = lx.buf.len();
+    lx.feed("d").unwrap();
+    assert_eq!(lx.buf.len(), bufs + 1);
+    assert!(lx.buf[bufs].text.contains("-changed\n-again\n+my disk"));
+    assert_eq!(lx.prompts[0].choice('d'), None);
+    lx.feed("k").unwrap();
+    assert!(lx.prompts.is_empty());
+    assert_eq!(lx.buf[bufs - 1].text, "my disk");
+
+    fs::write(&path, "third\n").unwrap();
+    lx.check_file(&path);
+    lx.feed("r").unwrap();
+    assert!(lx.prompts.is_empty());
+    assert_eq!(lx.buf[bufs - 1].text, "third");
+    assert!(!lx.buf[bufs - 1].modified);
+}
@@ -0,0 +1,87 @@
+use super::*;
+use crate::buffer::sign::{Severity, VcsStatus};
+
+/// An editor with 8 rows of text, showing lines numbered 1 to 50 from the top
+fn numbered() -> Lx<TestBackend> {
+    let mut lx = sized(30, 12);
+    let text = (1..=50).map(|n| n.to_string()).collect::<Vec<_>>().join("<cr>");
+    lx.feed(&format!("{}<esc>gg", text)).unwrap();
+    screen(&mut lx);
+    lx
+}
+
+/// After `keys`, the first line shown and the cursor line, both 1-based
+#[track_caller]
+fn assert_view(lx: &mut Lx<TestBackend>, keys: &str, first: usize, cursor: usize) {
+    lx.feed(keys).unwrap();
+    screen(lx);
+    let w = lx.tabs[lx.tab_idx].window();
+    assert_eq!((w.scroll + 1, w.cursor.line + 1), (first, cursor), "after {}", keys);
+}
+
+#[test]
+fn scrolloff_keeps_lines_around_the_cursor() {
+    let mut lx = numbered();
+    assert_view(&mut lx, "4j", 1, 5);
+    assert_view(&mut lx, "j", 2, 6);
+    assert_rows(&mut lx, 1, &["│  2 2                       │"]);
+    assert_view(&mut lx, "G", 43, 50);
+    assert_view(&mut lx, "k", 43, 49);
+    assert_view(&mut lx, "3k", 43, 46);
+    assert_view(&mut lx, "k", 42, 45);
+    // Without it the cursor goes right to the edges
+    lx.feed(":set so=0<cr>").unwrap();
+    assert_view(&mut lx, "gg7j", 1, 8);
+    assert_view(&mut lx, "j", 2, 9);
+}
+
+#[test]
+fn z_scrolls_the_cursor_line_into_place() {
+    let mut lx = numbered();
+    assert_view(&mut lx, "20G", 16, 20);
+    assert_view(&mut lx, "zt", 17, 20);
+    assert_view(&mut lx, "zb", 16, 20);
+    assert_view(&mut lx, "zt", 17, 20);
+    assert_view(&mut lx, "zz", 16, 20);
+    // Near the top there is nothing to scroll
+    assert_view(&mut lx, "2Gzz", 1, 2);
+    lx.feed(":set so=0<cr>").unwrap();
+    assert_view(&mut lx, "20Gzt", 20, 20);
+    assert_view(&mut lx, "zb", 13, 20);
+}
+
+#[test]
+fn pages_and_half_pages() {
+    let mut lx = numbered();
+    assert_view(&mut lx, "20Gzz", 16, 20);
+    assert_view(&mut lx, "<C-d>", 20, 24);
+    assert_view(&mut lx, "<C-d>", 24, 28);
+    assert_view(&mut lx, "<C-u>", 20, 24);
+    assert_view(&mut lx, "<C-f>", 26, 29);
+    assert_view(&mut lx, "<C-b>", 20, 24);
+    // They stop at either end of the buffer
+    assert_view(&mut lx, "gg<C-b>", 1, 1);
+    assert_view(&mut lx, "gg<C-u>", 1, 1);
+    assert_view(&mut lx, "G<C-f>", 47, 50);
+}
+
+#[test]
+fn diagnostic_signs_come_before_vcs_signs() {
+    let mut lx = numbered();
+    let idx = lx.buf_idx();
+    let signs = &mut lx.buf[idx].signs;
+    signs.add_diagnostic(0, Severity::Warning);
+    signs.add_diagnostic(0, Severity::Error);
+    signs.add_diagnostic(2, Severity::Hint);
+    signs.vcs.insert(0, VcsStatus::Modified);
+    signs.vcs.insert(1, VcsStatus::Added);
+    assert_rows(&mut lx, 1, &["│E~  1 1                     │", "│ +  2 2                     │", "│H   3 3                     │"]);
+    // A gutter with room for one sign shows the diagnostic
+    let mut lx = sized(3, 6);
+    lx.feed("a<esc>:set nonu<cr>").unwrap();
+    let idx = lx.buf_idx();
+    let signs = &mut lx.buf[idx].signs;
+    signs.add_diagnostic(0, Severity::Error);
+    signs.vcs.insert(0, VcsStatus::Modified);
+    assert_rows(&mut lx, 1, &["│E│"]);
+}
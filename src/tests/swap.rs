@@ -0,0 +1,49 @@
+use std::fs;
+
+use super::*;
+use crate::{swap, window::OpenIn};
+
+/// An editor journaling to the `swap` directory in `dir`
+fn journaling(dir: &TempDir) -> Lx<TestBackend> {
+    let mut lx = editor();
+    lx.swap_dir = Some(dir.join("swap"));
+    lx
+}
+
+#[test]
+fn unsaved_changes_are_journaled_and_recovered() {
+    let dir = TempDir::new("swap-recover");
+    let swaps = dir.join("swap");
+    let path = dir.file("notes.txt", "saved\n");
+    let mut lx = journaling(&dir);
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.journal();
+    assert_eq!(swap::read(&swaps, &path).unwrap(), None);
+    lx.feed("<esc><cr>unsaved <esc>").unwrap();
+    lx.journal();
+    assert_eq!(swap::read(&swaps, &path).unwrap().map(|s| s.text), Some("unsaved saved".to_string()));
+    lx.feed(":w<cr>").unwrap();
+    assert_eq!(swap::read(&swaps, &path).unwrap(), None);
+
+    // A swap file left by an editor that crashed
+    fs::write(swap::path(&swaps, &path), format!("lx swap {}\nrecovered\ntext", u32::MAX)).unwrap();
+    let mut lx = journaling(&dir);
+    lx.open(&path, OpenIn::Current).unwrap();
+    assert_eq!(lx.prompts.len(), 1);
+    lx.feed("r").unwrap();
+    assert!(lx.prompts.is_empty());
+    assert_text(&lx, "recovered\ntext");
+    assert!(lx.buf[lx.buf_idx()].modified);
+    lx.feed("<esc>:w<cr>").unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "recovered\ntext\n");
+    assert_eq!(swap::read(&swaps, &path).unwrap(), None);
+
+    // Read-only keeps the swap file
+    fs::write(swap::path(&swaps, &path), format!("lx swap {}\nother", u32::MAX)).unwrap();
+    let mut lx = journaling(&dir);
+    lx.open(&path, OpenIn::Current).unwrap();
+    lx.feed("o").unwrap();
+    assert!(lx.buf[lx.buf_idx()].readonly);
+    assert_text(&lx, "recovered\ntext");
+    assert!(swap::read(&swaps, &path).unwrap().is_some());
+}
@@ -0,0 +1,40 @@
+use super::*;
+
+#[test]
+fn long_lines_wrap_and_gj_gk_move_by_rows() {
+    let mut lx = editor();
+    lx.feed("first line of words that is too long for one row<cr>second<esc>").unwrap();
+    lx.feed(":set showbreak=>><cr>").unwrap();
+    assert_rows(&mut lx, 1, &[
+        "│  1 first line of words that is too   │",
+        "│    >>long for one row                │",
+        "│  2 second                            │",
+    ]);
+    // The screen column is kept, past the showbreak
+    lx.feed("gk").unwrap();
+    assert_cursor(&lx, 0, 35);
+    lx.feed("gk").unwrap();
+    assert_cursor(&lx, 0, 5);
+    lx.feed("gj").unwrap();
+    assert_cursor(&lx, 0, 35);
+    lx.feed("gj").unwrap();
+    assert_cursor(&lx, 1, 5);
+    lx.feed(":set nowrap<cr>").unwrap();
+    assert_rows(&mut lx, 1, &["│  1 first line of words that is too lo│", "│  2 second                            │"]);
+}
+
+#[test]
+fn gq_reflows_to_textwidth() {
+    let mut lx = editor();
+    lx.feed("  first line of words that is<cr>too long<cr><cr>next paragraph<esc>").unwrap();
+    lx.feed(":set tw=16<cr>gggqG").unwrap();
+    assert_text(&lx, "  first line of\n  words that is\n  too long\n\n  next paragraph");
+    assert_cursor(&lx, 4, 0);
+    // Before the window is drawn there is no width to use
+    lx.feed(":set tw=0<cr>gggqG").unwrap();
+    assert_text(&lx, "  first line of words that is too long\n\n  next paragraph");
+    // Without a textwidth the width of the window's text is used
+    screen(&mut lx);
+    lx.feed(":set tw=0<cr>gggqG").unwrap();
+    assert_text(&lx, "  first line of words that is too\n  long\n\n  next paragraph");
+}
@@ -0,0 +1,215 @@
+use std::{collections::HashMap, env};
+use tui::style::{Color, Modifier};
+
+use crate::error::LxConfigError;
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ColorDepth {
+    #[default]
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guesses the terminal's color support from `$COLORTERM` and `$TERM`
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+}
+
+/// The 16 ANSI colors with the RGB values xterm gives them
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Channel values of the 6x6x6 color cube in the 256 color palette
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// RGB value of an entry in the 256 color palette
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (CUBE[(i / 36) as usize], CUBE[(i / 6 % 6) as usize], CUBE[(i % 6) as usize])
+        },
+        _ => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        },
+    }
+}
+
+/// Closest entry of the 256 color palette, from the color cube or the gray ramp
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |c: u8| (0..6).min_by_key(|&i| (CUBE[i] as i32 - c as i32).abs()).unwrap_or(0) as u8;
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let avg = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + ((avg.saturating_sub(3) / 10).min(23)) as u8;
+    if distance(indexed_rgb(gray), rgb) < distance(indexed_rgb(cube), rgb) { gray } else { cube }
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    ANSI.iter().min_by_key(|(_, c)| distance(*c, rgb)).map_or(Color::Reset, |(c, _)| *c)
+}
+
+/// Converts `color` to one the terminal can show
+pub fn downgrade(color: Color, depth: ColorDepth) -> Color {
+    match (color, depth) {
+        (_, ColorDepth::TrueColor) => color,
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => Color::Indexed(nearest_256((r, g, b))),
+        (Color::Rgb(r, g, b), ColorDepth::Ansi16) => nearest_16((r, g, b)),
+        (Color::Indexed(i), ColorDepth::Ansi16) if i < 16 => ANSI[i as usize].0,
+        (Color::Indexed(i), ColorDepth::Ansi16) => nearest_16(indexed_rgb(i)),
+        _ => color,
+    }
+}
+
+fn named(name: &str) -> Option<Color> {
+    let name = name.to_ascii_lowercase().replace(['_', '-'], "");
+    Some(match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    })
+}
+
+/// Parses a color written as a palette name, `#rrggbb` or `#rgb` hex, a 256 color palette
+/// index or one of the named ANSI colors
+pub fn parse_color(s: &str, palette: &HashMap<String, Color>) -> Result<Color, LxConfigError> {
+    let s = s.trim();
+    if let Some(c) = palette.get(s) {
+        return Ok(*c);
+    }
+    let invalid = || LxConfigError::InvalidColor(format!("Invalid color: {}", s));
+    if let Some(hex) = s.strip_prefix('#') {
+        let channel = |i: usize, n: usize| u8::from_str_radix(hex.get(i * n..(i + 1) * n)?, 16).ok();
+        return match hex.len() {
+            6 => Some((channel(0, 2), channel(1, 2), channel(2, 2))),
+            3 => Some((channel(0, 1), channel(1, 1), channel(2, 1)))
+                .map(|(r, g, b)| (r.map(|v| v * 17), g.map(|v| v * 17), b.map(|v| v * 17))),
+            _ => None,
+        }
+        .and_then(|rgb| match rgb {
+            (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+            _ => None,
+        })
+        .ok_or_else(invalid);
+    }
+    if let Ok(i) = s.parse::<u8>() {
+        return Ok(Color::Indexed(i));
+    }
+    named(s).ok_or_else(invalid)
+}
+
+pub fn parse_modifier(s: &str) -> Result<Modifier, LxConfigError> {
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "bold" => Modifier::BOLD,
+        "dim" => Modifier::DIM,
+        "italic" => Modifier::ITALIC,
+        "underlined" | "underline" => Modifier::UNDERLINED,
+        "slow_blink" | "blink" => Modifier::SLOW_BLINK,
+        "rapid_blink" => Modifier::RAPID_BLINK,
+        "reversed" | "reverse" => Modifier::REVERSED,
+        "hidden" => Modifier::HIDDEN,
+        "crossed_out" | "strikethrough" => Modifier::CROSSED_OUT,
+        _ => return Err(LxConfigError::UnrecognizedKeyword(format!("Unknown modifier: {}", s))),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Option<Color> {
+        parse_color(s, &HashMap::new()).ok()
+    }
+
+    #[test]
+    fn colors_parse() {
+        assert_eq!(parse("#ff8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(parse(" #f80 "), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(parse("208"), Some(Color::Indexed(208)));
+        assert_eq!(parse("Light-Blue"), Some(Color::LightBlue));
+        assert_eq!(parse("grey"), Some(Color::Gray));
+        assert_eq!(parse("default"), Some(Color::Reset));
+        for bad in ["#12345", "#ggg", "#ff80", "256", "mauve", ""] {
+            assert_eq!(parse(bad), None, "{}", bad);
+        }
+        // Palette names come first, even over the named colors
+        let mut palette = HashMap::new();
+        palette.insert("red".to_string(), Color::Blue);
+        assert_eq!(parse_color("red", &palette).ok(), Some(Color::Blue));
+    }
+
+    #[test]
+    fn nearest_palette_entries() {
+        assert_eq!(nearest_256((255, 0, 0)), 196);
+        assert_eq!(nearest_256((0, 0, 0)), 16);
+        assert_eq!(nearest_256((95, 135, 175)), 67);
+        // Grays go to the gray ramp rather than the cube
+        assert_eq!(nearest_256((128, 128, 128)), 244);
+        assert_eq!(nearest_16((250, 0, 0)), Color::LightRed);
+        assert_eq!(nearest_16((190, 10, 0)), Color::Red);
+        assert_eq!(nearest_16((128, 128, 128)), Color::DarkGray);
+        assert_eq!(nearest_16((0, 0, 255)), Color::Blue);
+    }
+
+    #[test]
+    fn colors_downgrade_to_the_terminal() {
+        let rgb = Color::Rgb(255, 0, 0);
+        assert_eq!(downgrade(rgb, ColorDepth::TrueColor), rgb);
+        assert_eq!(downgrade(rgb, ColorDepth::Ansi256), Color::Indexed(196));
+        assert_eq!(downgrade(rgb, ColorDepth::Ansi16), Color::LightRed);
+        assert_eq!(downgrade(Color::Indexed(9), ColorDepth::Ansi16), Color::LightRed);
+        assert_eq!(downgrade(Color::Indexed(196), ColorDepth::Ansi16), Color::LightRed);
+        assert_eq!(downgrade(Color::Indexed(244), ColorDepth::Ansi16), Color::DarkGray);
+        assert_eq!(downgrade(Color::Indexed(244), ColorDepth::Ansi256), Color::Indexed(244));
+        assert_eq!(downgrade(Color::Cyan, ColorDepth::Ansi16), Color::Cyan);
+    }
+}
@@ -0,0 +1,248 @@
+pub mod color;
+
+use std::{collections::HashMap, fs, path::PathBuf};
+use serde::Deserialize;
+use tui::style::{Color, Modifier, Style};
+
+use crate::{config, error::LxConfigError};
+pub use color::ColorDepth;
+
+/// Themes shipped with lx, used unless the config dir has one of the same name
+const BUILTIN: [(&str, &str); 2] = [
+    ("default", include_str!("../../runtime/themes/default.toml")),
+    ("ember", include_str!("../../runtime/themes/ember.toml")),
+];
+
+/// Themes can't inherit deeper than this, which also stops inheritance cycles
+const MAX_INHERIT: usize = 8;
+
+/// A theme file as written on disk
+#[derive(Debug, Deserialize)]
+struct ThemeDef {
+    inherits: Option<String>,
+    #[serde(default)]
+    palette: HashMap<String, String>,
+    #[serde(default)]
+    styles: HashMap<String, StyleDef>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StyleDef {
+    /// Just a foreground color
+    Fg(String),
+    Full {
+        fg: Option<String>,
+        bg: Option<String>,
+        #[serde(default)]
+        modifiers: Vec<String>,
+    },
+}
+
+/// Styles for UI elements and syntax scopes, by dotted name like `ui.statusline` or
+/// `syntax.comment`
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    pub name: String,
+    palette: HashMap<String, Color>,
+    styles: HashMap<String, Style>,
+}
+
+fn theme_path(name: &str) -> PathBuf {
+    config::config_dir().join("themes").join(format!("{}.toml", name))
+}
+
+/// Text of the theme called `name`, from the config dir or else built in
+fn theme_source(name: &str) -> Option<String> {
+    fs::read_to_string(theme_path(name)).ok()
+        .or_else(|| BUILTIN.iter().find(|(n, _)| *n == name).map(|(_, src)| src.to_string()))
+}
+
+impl Theme {
+    /// Loads the theme called `name` from `<config dir>/themes/<name>.toml`, or the built
+    /// in theme of that name, with its colors converted to ones the terminal can show
+    pub fn load(name: &str, depth: ColorDepth) -> Result<Self, LxConfigError> {
+        Self::load_from(name, depth, &theme_source)
+    }
+
+    /// Loads the theme called `name`, and those it inherits from, with the text `source`
+    /// gives for them
+    fn load_from(name: &str, depth: ColorDepth, source: &impl Fn(&str) -> Option<String>) -> Result<Self, LxConfigError> {
+        let mut theme = Self::load_inherited(name, 0, source)?;
+        for style in theme.styles.values_mut() {
+            style.fg = style.fg.map(|c| color::downgrade(c, depth));
+            style.bg = style.bg.map(|c| color::downgrade(c, depth));
+        }
+        Ok(theme)
+    }
+
+    fn load_inherited(name: &str, level: usize, source: &impl Fn(&str) -> Option<String>) -> Result<Self, LxConfigError> {
+        if level > MAX_INHERIT {
+            return Err(LxConfigError::InvalidTheme(
+                format!("Theme {} inherits too deeply, is there a cycle?", name)));
+        }
+        let src = source(name).ok_or_else(|| LxConfigError::InvalidTheme(format!("No theme named {}", name)))?;
+        let def: ThemeDef = toml::from_str(&src)
+            .map_err(|e| LxConfigError::InvalidTheme(format!("{}: {}", name, e)))?;
+        let mut theme = match &def.inherits {
+            Some(parent) => Self::load_inherited(parent, level + 1, source)?,
+            None => Self::default(),
+        };
+        theme.name = name.to_string();
+        // Entries may name others of the same file, which are resolved first, or those of the
+        // theme inherited from
+        let mut pending: Vec<(&String, &String)> = def.palette.iter().collect();
+        while !pending.is_empty() {
+            let (ready, rest): (Vec<_>, Vec<_>) = pending.iter().partition(|(key, value)| {
+                let value = value.trim();
+                value == key.as_str() || !pending.iter().any(|(k, _)| k.as_str() == value)
+            });
+            if ready.is_empty() {
+                return Err(LxConfigError::InvalidTheme(format!("{}: palette entries name each other", name)));
+            }
+            for (key, value) in ready {
+                let color = color::parse_color(value, &theme.palette)?;
+                theme.palette.insert(key.clone(), color);
+            }
+            pending = rest;
+        }
+        for (key, def) in def.styles.iter() {
+            let style = theme.parse_style(def)?;
+            theme.styles.insert(key.clone(), style);
+        }
+        Ok(theme)
+    }
+
+    fn parse_style(&self, def: &StyleDef) -> Result<Style, LxConfigError> {
+        let parse = |c: &str| color::parse_color(c, &self.palette);
+        Ok(match def {
+            StyleDef::Fg(fg) => Style::default().fg(parse(fg)?),
+            StyleDef::Full { fg, bg, modifiers } => {
+                let mut style = Style::default();
+                if let Some(fg) = fg {
+                    style = style.fg(parse(fg)?);
+                }
+                if let Some(bg) = bg {
+                    style = style.bg(parse(bg)?);
+                }
+                let mods = modifiers.iter()
+                    .try_fold(Modifier::empty(), |m, s| color::parse_modifier(s).map(|n| m | n))?;
+                style.add_modifier(mods)
+            },
+        })
+    }
+
+    /// Style for `key`, falling back to its parent keys (`ui.border.focus`, then
+    /// `ui.border`, then `ui`) and then to the terminal's default style
+    pub fn get(&self, key: &str) -> Style {
+        let mut key = key;
+        loop {
+            if let Some(style) = self.styles.get(key) {
+                return *style;
+            }
+            match key.rsplit_once('.') {
+                Some((parent, _)) => key = parent,
+                None => return Style::default(),
+            }
+        }
+    }
+
+    /// Style for a syntax scope, drawn over `ui.text`
+    pub fn syntax(&self, scope: &str) -> Style {
+        self.get(&format!("syntax.{}", scope))
+    }
+
+    /// Names of the built in themes and those in the config dir
+    pub fn available() -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN.iter().map(|(n, _)| n.to_string()).collect();
+        if let Ok(entries) = fs::read_dir(config::config_dir().join("themes")) {
+            names.extend(entries.filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+                .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned())));
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r##"
+        [palette]
+        accent = "#ff0000"
+        [styles]
+        "ui" = "white"
+        "ui.statusline" = { fg = "accent", bg = "#00f", modifiers = ["bold", "reversed"] }
+        "syntax.comment" = "244"
+    "##;
+    const CHILD: &str = r##"
+        inherits = "base"
+        [palette]
+        accent = "#0f0"
+        muted = "accent"
+        [styles]
+        "syntax.comment" = { fg = "muted", modifiers = ["italic"] }
+    "##;
+
+    fn source(name: &str) -> Option<String> {
+        match name {
+            "base" => Some(BASE.to_string()),
+            "child" => Some(CHILD.to_string()),
+            "loop" => Some("inherits = \"pool\"".to_string()),
+            "pool" => Some("inherits = \"loop\"".to_string()),
+            "bad" => Some("[styles]\n\"ui\" = \"#12\"".to_string()),
+            "knot" => Some("[palette]\na = \"b\"\nb = \"a\"".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn styles_and_fallbacks() {
+        let theme = Theme::load_from("base", ColorDepth::TrueColor, &source).unwrap();
+        let bar = Style::default().fg(Color::Rgb(255, 0, 0)).bg(Color::Rgb(0, 0, 255))
+            .add_modifier(Modifier::BOLD | Modifier::REVERSED);
+        assert_eq!(theme.get("ui.statusline"), bar);
+        assert_eq!(theme.get("ui.statusline.mode.insert"), bar);
+        assert_eq!(theme.get("ui.border"), Style::default().fg(Color::White));
+        assert_eq!(theme.get("other"), Style::default());
+        assert_eq!(theme.syntax("comment"), Style::default().fg(Color::Indexed(244)));
+    }
+
+    #[test]
+    fn inherited_themes_override_their_parents() {
+        let theme = Theme::load_from("child", ColorDepth::TrueColor, &source).unwrap();
+        assert_eq!(theme.name, "child");
+        // The parent's styles keep the parent's palette
+        assert_eq!(theme.get("ui.statusline").fg, Some(Color::Rgb(255, 0, 0)));
+        // Palette entries can name others of the same file
+        assert_eq!(theme.syntax("comment"), Style::default().fg(Color::Rgb(0, 255, 0)).add_modifier(Modifier::ITALIC));
+        assert!(matches!(Theme::load_from("loop", ColorDepth::TrueColor, &source), Err(LxConfigError::InvalidTheme(_))));
+        assert!(matches!(Theme::load_from("knot", ColorDepth::TrueColor, &source), Err(LxConfigError::InvalidTheme(_))));
+        assert!(matches!(Theme::load_from("none", ColorDepth::TrueColor, &source), Err(LxConfigError::InvalidTheme(_))));
+        assert!(matches!(Theme::load_from("bad", ColorDepth::TrueColor, &source), Err(LxConfigError::InvalidColor(_))));
+    }
+
+    #[test]
+    fn colors_are_downgraded_for_the_terminal() {
+        let theme = Theme::load_from("base", ColorDepth::Ansi256, &source).unwrap();
+        assert_eq!(theme.get("ui.statusline").fg, Some(Color::Indexed(196)));
+        assert_eq!(theme.get("ui.statusline").bg, Some(Color::Indexed(21)));
+        let theme = Theme::load_from("base", ColorDepth::Ansi16, &source).unwrap();
+        assert_eq!(theme.get("ui.statusline").fg, Some(Color::LightRed));
+        assert_eq!(theme.get("ui.statusline").bg, Some(Color::Blue));
+        assert_eq!(theme.syntax("comment").fg, Some(Color::DarkGray));
+        assert_eq!(theme.get("ui").fg, Some(Color::White));
+    }
+
+    #[test]
+    fn builtin_themes_load() {
+        let builtin = |name: &str| BUILTIN.iter().find(|(n, _)| *n == name).map(|(_, src)| src.to_string());
+        for (name, _) in BUILTIN.iter() {
+            Theme::load_from(name, ColorDepth::Ansi16, &builtin).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+    }
+}
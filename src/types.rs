@@ -1,32 +1,64 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq)]
 pub enum CursorDirection { Up, Down, Left, Right }
 
-impl ToString for CursorDirection {
-    fn to_string(&self) -> String {
+impl fmt::Display for CursorDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Up => "U".into(),
-            Self::Down => "D".into(),
-            Self::Left => "L".into(),
-            Self::Right => "R".into(),
+            Self::Up => f.write_str("U"),
+            Self::Down => f.write_str("D"),
+            Self::Left => f.write_str("L"),
+            Self::Right => f.write_str("R"),
         }
     }
 
 }
 
-#[derive(Debug, PartialEq)]
-pub enum Direction { Next, Prev }
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Direction { #[default] Next, Prev }
 
-impl Default for Direction {
-    fn default() -> Self {
-        Self::Next
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Next => f.write_str("->"),
+            Self::Prev => f.write_str("<-"),
+        }
     }
 }
 
-impl ToString for Direction {
-    fn to_string(&self) -> String {
-        match self {
-            Self::Next => "->".into(),
-            Self::Prev => "<-".into(),
-        }
+/// A position in a buffer: a line index and a column within that line
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cursor {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Cursor {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.col + 1)
+    }
+}
+
+/// Whether a message reports a failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level { Info, Error }
+
+/// A message shown on the message line and kept for `:messages`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub level: Level,
+    pub text: String,
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
     }
 }
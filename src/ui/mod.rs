@@ -1,13 +1,17 @@
-use std::io::Write;
-use crossterm::{event::{KeyCode, KeyEvent, KeyModifiers}, style::{Attribute, Color, SetForegroundColor, Stylize}, terminal::{self, SetTitle, EnableLineWrap, ScrollUp, ScrollDown}};
+pub mod statusline;
+pub mod window;
+
+use unicode_width::UnicodeWidthStr;
 use tui::{
-    Terminal, backend::{CrosstermBackend, Backend},
-    layout::{Layout, Constraint},
-    widgets::{Block, Borders, Paragraph},
-    style::{Style, self},
+    backend::Backend,
+    buffer::Buffer,
+    layout::{Layout, Constraint, Rect},
+    text::Spans,
+    widgets::{Block, Borders, Paragraph, Tabs, Widget},
 };
 
-use crate::{Lx, LxResult, Mode, mode::CommandMode};
+use self::{statusline::{Status, StatusLineView}, window::WindowView};
+use crate::{Lx, LxResult, Mode, buffer, finder::Finder, overview::{OverviewView, ViewContext}, theme::Theme, types::Level, window::SplitDir};
 
 /* pub fn status_bar() -> Paragraph {
     Paragraph::default()
@@ -18,60 +22,150 @@ pub fn header() -> Paragraph {
 
 } */
 
-pub fn draw_ui<W: Write + Backend>(app: &mut Lx<W>) -> LxResult<()> {
-    let mode = &app.mode;
-    let st = format!("MODE: {}, PRE: {:?}, B: {}/{} Q: {}",
-        &mode.to_string(),
-        &app.prefix,
-        &app.buf_idx,
-        &app.buf.len(),
-        &app.quit
-        // &terminal
-        // &self.prev_key ==  &Some(KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char(' ') })
-    );
-    let cursor = crossterm::cursor::position().expect("Could not get cursor");
-    let bu = &app.buf[app.buf_idx].clone();
-    let debug_str: String = match &app.mode {
-        Mode::Command(_) =>  format!("CMD: {}", &app.cmd_buf ),
-        _ =>  format!("POS: [{}, {}], P: {:?}", cursor.0, cursor.1,
-            app.prev_keys.last().unwrap_or(&KeyEvent { modifiers: KeyModifiers::CONTROL, code: KeyCode::Char('q') })),
+/// Adapts the focused overview view to tui's `Widget` interface
+struct ViewWidget<'a, 'c> {
+    view: &'a mut dyn OverviewView,
+    ctx: &'a ViewContext<'c>,
+}
+
+impl Widget for ViewWidget<'_, '_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.view.render(self.ctx, area, buf);
+    }
+}
+
+/// Adapts the finder popup to tui's `Widget` interface
+struct FinderWidget<'a> {
+    finder: &'a mut Finder,
+    buffers: &'a [buffer::Buffer],
+    theme: &'a Theme,
+}
+
+impl Widget for FinderWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.finder.render(self.buffers, self.theme, area, buf);
+    }
+}
+
+/// A rect of the given percentage size centered in `area`
+fn centered(area: Rect, width_pct: u16, height_pct: u16) -> Rect {
+    let width = area.width * width_pct / 100;
+    let height = area.height * height_pct / 100;
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+pub fn draw_ui<W: Backend>(app: &mut Lx<W>) -> LxResult<()> {
+    app.update_folds();
+    let s = app.term.size()?;
+    let ch = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Min(2),
+            Constraint::Length(1),
+            Constraint::Length(1)
+        ].as_ref())
+        .split(s);
+    let tab = &mut app.tabs[app.tab_idx];
+    let split = match tab.split {
+        SplitDir::Horizontal => tui::layout::Direction::Vertical,
+        SplitDir::Vertical => tui::layout::Direction::Horizontal,
+    };
+    let n = tab.windows.len() as u32;
+    let areas = Layout::default()
+        .direction(split)
+        .constraints(vec![Constraint::Ratio(1, n); tab.windows.len()])
+        .split(ch[0]);
+    // Windows learn their size here, then keep the cursor in view at that size
+    for (w, area) in tab.windows.iter_mut().zip(areas.iter()) {
+        let b = &app.buf[w.buf_idx];
+        let (_, text) = window::layout(*area, b, &app.options);
+        w.height = text.height as usize;
+        w.width = text.width as usize;
+        w.cursor = b.clamp(w.cursor, matches!(app.mode, Mode::Insert(_)));
+        w.scroll_to_cursor(b, &app.options);
+    }
+    let win = app.tabs[app.tab_idx].window();
+    let (message, message_style) = match (&app.mode, &app.message) {
+        _ if !app.prompts.is_empty() => (app.prompts[0].text(), app.theme.get("ui.message.prompt")),
+        (Mode::Command(_), _) => (format!(":{}", app.cmd_buf), app.theme.get("ui.message")),
+        (_, Some(msg)) if msg.level == Level::Error => (msg.to_string(), app.theme.get("ui.message.error")),
+        (_, Some(msg)) => (msg.to_string(), app.theme.get("ui.message")),
+        _ => (String::new(), app.theme.get("ui.message")),
+    };
+    let statusline = StatusLineView {
+        line: &app.options.statusline,
+        status: Status { mode: &app.mode, prefix: app.prefix.as_ref(), recording: app.recording.map(|r| r.reg), buf: &app.buf[win.buf_idx], window: win },
+        theme: &app.theme,
+    };
+    let overview = matches!(app.mode, Mode::Overview(_));
+    let command = matches!(app.mode, Mode::Command(_));
+    let titles = app.views.titles();
+    let focus = app.views.focus();
+    let ctx = ViewContext {
+        buffers: &app.buf,
+        buf_idx: app.tabs[app.tab_idx].window().buf_idx,
+        tabs: &app.tabs,
+        tab_idx: app.tab_idx,
+        history: &app.history,
+        registers: &app.registers,
+        marks: &app.marks,
+        jumps: &app.jumps,
+        keymaps: &app.keymaps,
+        theme: &app.theme,
+        messages: &app.messages,
+        files_version: app.files_version,
     };
+    let tab = &app.tabs[app.tab_idx];
+    let theme = &app.theme;
+    let options = &app.options;
+    let views = &mut app.views;
+    let finder = app.finder.as_mut();
+    let buffers = &app.buf;
     app.term.draw(|r| {
-        let s = r.size();
-        let ch = Layout::default()
-            .direction(tui::layout::Direction::Vertical)
-            .margin(2)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Min(2),
-                Constraint::Length(3)
-            ].as_ref())
-            .split(s);
-        let pb = Paragraph::new(bu.to_string()).style(Style::default())
-            .alignment(tui::layout::Alignment::Left)
-            .block(
-                Block::default()
+        r.render_widget(Paragraph::new(message.as_str()).style(message_style), ch[2]);
+        if command {
+            let len = (message.width() as u16).min(ch[2].width.saturating_sub(1));
+            r.set_cursor(ch[2].x + len, ch[2].y);
+        }
+        match views.focused_mut() {
+            Some(view) if overview => {
+                let oc = Layout::default()
+                    .direction(tui::layout::Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+                    .split(ch[0]);
+                let tabs = Tabs::new(titles.into_iter().map(Spans::from).collect())
+                    .select(focus)
+                    .style(theme.get("ui.tabline"))
+                    .highlight_style(theme.get("ui.tabline.active"))
+                    .block(Block::default().borders(Borders::ALL).border_style(theme.get("ui.border")));
+                let block = Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default())
-                    .border_type(tui::widgets::BorderType::Plain)
-            );
-        let p = Paragraph::new(st.to_string())
-            .style(Style::default())
-            .alignment(tui::layout::Alignment::Center)
-            .block(Block::default().borders(Borders::ALL).style(Style::default())
-                .border_type(tui::widgets::BorderType::Plain));
-        let debug = Paragraph::new(debug_str.to_string())
-            .style(Style::default())
-            .alignment(tui::layout::Alignment::Center)
-            .block(
-                Block::default()
-                .borders(Borders::ALL)
-                .style(Style::default())
-                .border_type(tui::widgets::BorderType::Plain)
-            );
-        r.render_widget(debug, ch[0]);
-        r.render_widget(pb, ch[1]);
-        r.render_widget(p, ch[2]);
+                    .border_style(theme.get("ui.border.focus"))
+                    .style(theme.get("ui.text"))
+                    .title(view.title());
+                let inner = block.inner(oc[1]);
+                r.render_widget(tabs, oc[0]);
+                r.render_widget(block, oc[1]);
+                r.render_widget(ViewWidget { view, ctx: &ctx }, inner);
+            },
+            _ => {
+                for (i, (w, area)) in tab.windows.iter().zip(areas).enumerate() {
+                    let buf = &buffers[w.buf_idx];
+                    let focused = i == tab.focus;
+                    r.render_widget(WindowView { buf, window: w, theme, options, focused }, area);
+                    if focused && !command {
+                        if let Some((x, y)) = window::cursor_position(area, buf, w, options) {
+                            r.set_cursor(x, y);
+                        }
+                    }
+                }
+            }
+        }
+        r.render_widget(statusline, ch[1]);
+        if let Some(finder) = finder {
+            let area = centered(s, 80, 70);
+            r.render_widget(FinderWidget { finder, buffers, theme }, area);
+        }
     })?;
     Ok(())
 }
@@ -0,0 +1,289 @@
+use std::{fmt, str::FromStr};
+use tui::{buffer::Buffer, layout::Rect, style::Style, widgets::Widget};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{buffer::{self, sign::Severity}, mode::Mode, prefix::Prefix, theme::Theme, window::Window};
+
+/// A piece of information the status line can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Mode,
+    Path,
+    /// `[+]` while the buffer has unsaved changes, and `[RO]` while it is read-only
+    Modified,
+    Filetype,
+    Encoding,
+    /// The line ending the file is written with
+    FileFormat,
+    /// Cursor line and column
+    Position,
+    /// How far through the buffer the cursor line is
+    Percent,
+    /// Lines and characters selected, while there is a selection
+    Selection,
+    /// Keys of an unfinished key sequence
+    Pending,
+    /// `recording @a` while keys are recorded into a register
+    Recording,
+    /// Git branch of the buffer's file
+    Branch,
+    /// Errors and warnings in the buffer
+    Diagnostics,
+}
+
+impl Segment {
+    const ALL: [Segment; 13] = [Self::Mode, Self::Path, Self::Modified, Self::Filetype, Self::Encoding,
+        Self::FileFormat, Self::Position, Self::Percent, Self::Selection, Self::Pending, Self::Recording,
+        Self::Branch, Self::Diagnostics];
+
+    /// Name used in the `statusline` option and in the segment's theme key
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mode => "mode",
+            Self::Path => "path",
+            Self::Modified => "modified",
+            Self::Filetype => "filetype",
+            Self::Encoding => "encoding",
+            Self::FileFormat => "fileformat",
+            Self::Position => "position",
+            Self::Percent => "percent",
+            Self::Selection => "selection",
+            Self::Pending => "pending",
+            Self::Recording => "recording",
+            Self::Branch => "branch",
+            Self::Diagnostics => "diagnostics",
+        }
+    }
+    /// Text of the segment for the focused window, empty if it has nothing to show
+    fn text(&self, status: &Status<'_>) -> String {
+        let (buf, win) = (status.buf, status.window);
+        match self {
+            Self::Mode => status.mode.to_string(),
+            Self::Path => buf.name(),
+            Self::Modified => {
+                let flags = [(buf.modified, "[+]"), (buf.readonly, "[RO]")];
+                flags.iter().filter(|(on, _)| *on).map(|(_, flag)| *flag).collect::<Vec<_>>().join(" ")
+            },
+            Self::Filetype => buf.filetype().unwrap_or_default().to_string(),
+            Self::Encoding => buf.file.fileencoding.to_string(),
+            Self::FileFormat => buf.file.fileformat.to_string(),
+            Self::Position => win.cursor.to_string(),
+            Self::Percent => {
+                let (line, count) = (win.cursor.line + 1, buf.line_count());
+                match line {
+                    1 => "Top".into(),
+                    _ if line >= count => "Bot".into(),
+                    _ => format!("{}%", line * 100 / count),
+                }
+            },
+            // There is nothing to select in edit mode yet
+            Self::Selection => String::new(),
+            Self::Pending => {
+                let prefix = status.prefix.map(|p| p.to_string()).unwrap_or_default();
+                match status.mode {
+                    Mode::Edit(em) => format!("{}{}", prefix.trim_end(), em.pending_keys()),
+                    _ => prefix.trim_end().to_string(),
+                }
+            },
+            Self::Recording => status.recording.map(|reg| format!("recording @{}", reg)).unwrap_or_default(),
+            Self::Branch => buf.branch.clone().unwrap_or_default(),
+            Self::Diagnostics => {
+                let count = |s| buf.signs.diagnostics.values().filter(|d| **d == s).count();
+                let counts = [(Severity::Error, count(Severity::Error)), (Severity::Warning, count(Severity::Warning))];
+                counts.iter().filter(|(_, n)| *n > 0)
+                    .map(|(s, n)| format!("{}:{}", s.symbol(), n))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            },
+        }
+    }
+    /// Theme key the segment is styled with. The mode segment has one per mode.
+    fn style_key(&self, mode: &Mode) -> String {
+        match self {
+            Self::Mode => format!("ui.statusline.mode.{}", mode.to_string().to_lowercase()),
+            _ => format!("ui.statusline.{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for Segment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied().find(|seg| seg.name() == s).ok_or_else(|| s.to_string())
+    }
+}
+
+/// Which segments the status line shows on its left, in its center and on its right. Set
+/// with `:set statusline=` as the three sections separated by `|`, each a comma separated
+/// list of segment names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusLine {
+    pub left: Vec<Segment>,
+    pub center: Vec<Segment>,
+    pub right: Vec<Segment>,
+}
+
+impl Default for StatusLine {
+    fn default() -> Self {
+        use Segment::*;
+        Self {
+            left: vec![Mode, Recording, Pending, Path, Modified],
+            center: vec![],
+            right: vec![Selection, Diagnostics, Branch, Filetype, Encoding, FileFormat, Position, Percent],
+        }
+    }
+}
+
+impl FromStr for StatusLine {
+    /// The name that isn't a segment
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let section = |s: &str| -> Result<Vec<Segment>, String> {
+            s.split(',').map(str::trim).filter(|n| !n.is_empty()).map(str::parse).collect()
+        };
+        let mut sections = s.splitn(3, '|');
+        Ok(Self {
+            left: section(sections.next().unwrap_or_default())?,
+            center: section(sections.next().unwrap_or_default())?,
+            right: section(sections.next().unwrap_or_default())?,
+        })
+    }
+}
+
+impl fmt::Display for StatusLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |segs: &[Segment]| segs.iter().map(|s| s.name()).collect::<Vec<&str>>().join(",");
+        write!(f, "{}|{}|{}", names(&self.left), names(&self.center), names(&self.right))
+    }
+}
+
+/// What the status line describes: the focused window and the editor state around it
+pub struct Status<'a> {
+    pub mode: &'a Mode,
+    pub prefix: Option<&'a Prefix>,
+    /// Register keys are being recorded into
+    pub recording: Option<char>,
+    pub buf: &'a buffer::Buffer,
+    pub window: &'a Window,
+}
+
+/// Draws the status line, with each segment styled by its `ui.statusline.<segment>` theme
+/// key. The center section is left out when it would overlap the others.
+pub struct StatusLineView<'a> {
+    pub line: &'a StatusLine,
+    pub status: Status<'a>,
+    pub theme: &'a Theme,
+}
+
+impl StatusLineView<'_> {
+    /// Texts and styles of the shown segments of a section, each padded with a space
+    fn section(&self, segments: &[Segment]) -> Vec<(String, Style)> {
+        segments.iter()
+            .map(|seg| (seg.text(&self.status), seg))
+            .filter(|(text, _)| !text.is_empty())
+            .map(|(text, seg)| (format!(" {} ", text), self.theme.get(&seg.style_key(self.status.mode))))
+            .collect()
+    }
+}
+
+fn section_width(section: &[(String, Style)]) -> u16 {
+    section.iter().map(|(text, _)| text.width()).sum::<usize>() as u16
+}
+
+fn draw_section(section: &[(String, Style)], mut x: u16, area: Rect, buf: &mut Buffer) {
+    for (text, style) in section {
+        // Sections which don't fit are cut off at the edge
+        if x >= area.right() {
+            break;
+        }
+        let (end, _) = buf.set_stringn(x, area.y, text, (area.right().saturating_sub(x)) as usize, *style);
+        x = end;
+    }
+}
+
+impl Widget for StatusLineView<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, self.theme.get("ui.statusline"));
+        let left = self.section(&self.line.left);
+        let center = self.section(&self.line.center);
+        let right = self.section(&self.line.right);
+        let (lw, cw, rw) = (section_width(&left), section_width(&center), section_width(&right));
+        draw_section(&left, area.x, area, buf);
+        // The left section wins when both don't fit
+        let right_x = area.right().saturating_sub(rw).max(area.x + lw);
+        draw_section(&right, right_x, area, buf);
+        let center_x = area.x + area.width.saturating_sub(cw) / 2;
+        if center_x >= area.x + lw && center_x + cw <= right_x {
+            draw_section(&center, center_x, area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn sections_parse_and_print() {
+        let line: StatusLine = "mode, path|pending|position,percent".parse().unwrap();
+        assert_eq!(line.left, [Segment::Mode, Segment::Path]);
+        assert_eq!(line.center, [Segment::Pending]);
+        assert_eq!(line.right, [Segment::Position, Segment::Percent]);
+        assert_eq!(line.to_string(), "mode,path|pending|position,percent");
+        let line: StatusLine = "mode".parse().unwrap();
+        assert_eq!((line.center.len(), line.right.len()), (0, 0));
+        assert_eq!("mode|selected".parse::<StatusLine>(), Err("selected".to_string()));
+        assert_eq!(StatusLine::default().to_string().parse(), Ok(StatusLine::default()));
+    }
+
+    /// The status line drawn `width` columns wide
+    fn drawn(line: &str, width: u16) -> String {
+        let buf = buffer::Buffer { text: "a\nb\nc".into(), path: Some(PathBuf::from("main.rs")), ..Default::default() };
+        drawn_for(&buf, line, width)
+    }
+
+    fn drawn_for(buf: &buffer::Buffer, line: &str, width: u16) -> String {
+        let window = Window::default();
+        let view = StatusLineView {
+            line: &line.parse().unwrap(),
+            status: Status { mode: &Mode::edit(), prefix: None, recording: Some('q'), buf, window: &window },
+            theme: &Theme::default(),
+        };
+        let area = Rect::new(0, 0, width, 1);
+        let mut screen = Buffer::empty(area);
+        view.render(area, &mut screen);
+        screen.content.iter().map(|c| c.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn sections_are_laid_out_left_center_and_right() {
+        assert_eq!(drawn("mode,path|recording|position,percent", 50), " EDIT  main.rs     recording @q          1:1  Top ");
+        // Segments with nothing to show take no room
+        assert_eq!(drawn("modified,path||", 12), " main.rs    ");
+    }
+
+    #[test]
+    fn narrow_lines_drop_the_center_then_cut_the_right() {
+        assert_eq!(drawn("mode,path|recording|position,percent", 30), " EDIT  main.rs       1:1  Top ");
+        assert_eq!(drawn("mode,path|recording|position,percent", 20), " EDIT  main.rs  1:1 ");
+        assert_eq!(drawn("mode,path|recording|position,percent", 10), " EDIT  mai");
+    }
+
+    #[test]
+    fn diagnostics_are_counted_and_empty_segments_take_no_room() {
+        let mut buf = buffer::Buffer { text: "a\nb\nc".into(), ..Default::default() };
+        assert_eq!(drawn_for(&buf, "|selection,diagnostics|", 10), " ".repeat(10));
+        buf.signs.add_diagnostic(0, Severity::Error);
+        buf.signs.add_diagnostic(1, Severity::Warning);
+        buf.signs.add_diagnostic(2, Severity::Error);
+        // Hints and infos aren't counted
+        buf.signs.diagnostics.insert(3, Severity::Hint);
+        assert_eq!(drawn_for(&buf, "|selection,diagnostics|", 10), " E:2 W:1  ");
+        buf.signs.diagnostics.remove(&1);
+        assert_eq!(drawn_for(&buf, "||diagnostics", 10), "      E:2 ");
+    }
+}
@@ -0,0 +1,281 @@
+use tui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Widget},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    buffer::{self, grapheme::{self, display_col}},
+    fold,
+    options::{Options, SignColumn},
+    pair,
+    theme::Theme,
+    types::Cursor,
+    window::{Window, wrap::{Row, line_rows, row_of}},
+};
+
+/// Columns taken by the gutter of `window` showing `buf`
+pub fn gutter_width(buf: &buffer::Buffer, options: &Options) -> u16 {
+    let signs = match options.signcolumn {
+        SignColumn::Yes => 2,
+        SignColumn::Auto if !buf.signs.is_empty() => 2,
+        _ => 0,
+    };
+    let numbers = if options.number || options.relativenumber {
+        // Room for the largest line number and a space after it
+        buf.line_count().to_string().len().max(3) + 1
+    } else {
+        0
+    };
+    (signs + numbers) as u16
+}
+
+/// The area inside a window's border, split into the gutter and the text
+pub fn layout(area: Rect, buf: &buffer::Buffer, options: &Options) -> (Rect, Rect) {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let gutter = gutter_width(buf, options).min(inner.width);
+    (
+        Rect { width: gutter, ..inner },
+        Rect { x: inner.x + gutter, width: inner.width - gutter, ..inner },
+    )
+}
+
+/// The rows shown in a text area `width` by `height` of `window`, from its scroll position:
+/// each a buffer line index, the row's index within the line and the row. A closed fold
+/// takes one empty row on its first line.
+fn visible_rows(buf: &buffer::Buffer, window: &Window, options: &Options, width: usize, height: usize) -> Vec<(usize, usize, Row)> {
+    let wrap = window.wrap(options);
+    let mut visible = Vec::with_capacity(height);
+    let mut folded = None;
+    for (n, line) in buf.lines().enumerate().skip(window.scroll) {
+        if folded.is_some_and(|end| n <= end) {
+            continue;
+        }
+        if let Some((_, end)) = buf.folds.closed_at(n) {
+            if visible.len() == height {
+                return visible;
+            }
+            folded = Some(end);
+            visible.push((n, 0, Row { start: 0, end: 0, prefix: 0 }));
+            continue;
+        }
+        let skip = if n == window.scroll { window.skip } else { 0 };
+        for (i, row) in line_rows(line, width, wrap, buf.indent(options).tabstop).into_iter().enumerate().skip(skip) {
+            if visible.len() == height {
+                return visible;
+            }
+            visible.push((n, i, row));
+        }
+    }
+    visible
+}
+
+/// How `row` of `line` is drawn, styled by `spans`, the line's highlighted text. Graphemes
+/// which can't be drawn as they are get their replacements, styled with `nontext` unless
+/// they are tabs. The first `left` columns are scrolled off to the left.
+fn row_spans(line: &str, spans: &Spans, row: Row, left: usize, tabstop: usize, nontext: Style) -> Vec<Span<'static>> {
+    let mut styles = Vec::with_capacity(spans.0.len());
+    let mut offset = 0;
+    for span in spans.0.iter() {
+        styles.push((offset, span.style));
+        offset += span.content.len();
+    }
+    let mut drawn: Vec<Span<'static>> = Vec::new();
+    let mut push = |text: &str, style: Style| match drawn.last_mut() {
+        Some(last) if last.style == style => last.content.to_mut().push_str(text),
+        _ => drawn.push(Span::styled(text.to_string(), style)),
+    };
+    let (mut vcol, mut s) = (0, 0);
+    for (col, (i, g)) in line.grapheme_indices(true).enumerate().take(row.end) {
+        let (at, width) = (vcol, grapheme::width(g, vcol, tabstop));
+        vcol += width;
+        if col < row.start || vcol <= left {
+            continue;
+        }
+        while s + 1 < styles.len() && styles[s + 1].0 <= i {
+            s += 1;
+        }
+        let style = styles.get(s).map_or_else(Style::default, |(_, style)| *style);
+        let (text, style) = match grapheme::replacement(g, at, tabstop) {
+            Some(r) if g == "\t" => (r, style),
+            Some(r) => (r, style.patch(nontext)),
+            None => (g.to_string(), style),
+        };
+        if at >= left {
+            push(&text, style);
+        } else if text.is_ascii() {
+            push(&text[left - at..], style);
+        } else {
+            // What's left of a wide character
+            push(&" ".repeat(vcol - left), style);
+        }
+    }
+    drawn
+}
+
+/// Draws a window: its border and title, the gutter and the visible part of its buffer
+pub struct WindowView<'a> {
+    pub buf: &'a buffer::Buffer,
+    pub window: &'a Window,
+    pub theme: &'a Theme,
+    pub options: &'a Options,
+    pub focused: bool,
+}
+
+impl WindowView<'_> {
+    /// Gutter text for buffer line `line`: the diagnostic and VCS sign columns then the line
+    /// number. A gutter cut down to one column shows the diagnostic sign over the VCS one.
+    fn gutter(&self, line: usize, width: usize) -> Spans<'static> {
+        let mut spans = Vec::new();
+        let mut width = width;
+        if width >= 1 && (self.options.signcolumn == SignColumn::Yes
+            || (self.options.signcolumn == SignColumn::Auto && !self.buf.signs.is_empty())) {
+            let signs = &self.buf.signs;
+            let diagnostic = signs.diagnostics.get(&line).map(|s| (s.symbol(), s.style_key()));
+            let vcs = signs.vcs.get(&line).map(|s| (s.symbol(), s.style_key()));
+            let columns = if width >= 2 { vec![diagnostic, vcs] } else { vec![diagnostic.or(vcs)] };
+            width -= columns.len();
+            for sign in columns {
+                spans.push(match sign {
+                    Some((symbol, key)) => Span::styled(symbol.to_string(), self.theme.get(key)),
+                    None => Span::styled(" ", self.theme.get("ui.gutter")),
+                });
+            }
+        }
+        if width > 0 {
+            let cursor = self.window.cursor.line;
+            let (number, key) = match (self.options.number, self.options.relativenumber) {
+                (_, true) if line != cursor => (line.abs_diff(cursor), "ui.linenr"),
+                (false, true) => (0, "ui.linenr.current"),
+                _ if line == cursor => (line + 1, "ui.linenr.current"),
+                _ => (line + 1, "ui.linenr"),
+            };
+            // Hybrid numbering left-aligns the cursor line's absolute number, like vim
+            let text = if line == cursor && self.options.number && self.options.relativenumber {
+                format!("{:<w$} ", number, w = width - 1)
+            } else {
+                format!("{:>w$} ", number, w = width - 1)
+            };
+            spans.push(Span::styled(text, self.theme.get(key)));
+        }
+        Spans::from(spans)
+    }
+    /// Screen cell of buffer position `at` among `rows` drawn into `text`, if it is shown
+    fn cell(&self, rows: &[(usize, usize, Row)], text: Rect, at: Cursor) -> Option<(u16, u16)> {
+        let y = rows.iter().position(|(n, _, row)| *n == at.line && row.start <= at.col && at.col < row.end)?;
+        let row = rows[y].2;
+        let line = self.buf.line(at.line);
+        let ts = self.buf.indent(self.options).tabstop;
+        let col = (row.prefix + display_col(line, at.col, ts) - display_col(line, row.start, ts)).checked_sub(self.window.left)?;
+        (col < text.width as usize).then(|| (text.x + col as u16, text.y + y as u16))
+    }
+}
+
+impl Widget for WindowView<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let title = if self.buf.modified { format!("{} [+]", self.buf.name()) } else { self.buf.name() };
+        let border = self.theme.get(if self.focused { "ui.border.focus" } else { "ui.border" });
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .border_style(border)
+            .style(self.theme.get("ui.text"))
+            .render(area, buf);
+        let (gutter, text) = layout(area, self.buf, self.options);
+        let rows = visible_rows(self.buf, self.window, self.options, text.width as usize, text.height as usize);
+        let showbreak = &self.window.wrap(self.options).showbreak;
+        let nontext = self.theme.get("ui.nontext");
+        let mut gutter_lines = Vec::with_capacity(rows.len());
+        let mut text_lines = Vec::with_capacity(rows.len());
+        let mut line_spans = None;
+        let mut folds = Vec::new();
+        for (y, (n, i, row)) in rows.iter().enumerate() {
+            let line = self.buf.line(*n);
+            if let Some((start, end)) = self.buf.folds.closed_at(*n) {
+                gutter_lines.push(self.gutter(*n, gutter.width as usize));
+                let summary = fold::summary(line, end + 1 - start, self.buf.folds.level(start));
+                let fill = (text.width as usize).saturating_sub(summary.width());
+                text_lines.push(Spans::from(format!("{}{}", summary, "-".repeat(fill))));
+                folds.push(y);
+                line_spans = None;
+                continue;
+            }
+            if *i == 0 || line_spans.is_none() {
+                line_spans = Some(match &self.buf.syntax {
+                    Some(h) => h.line_spans(*n, line, self.theme),
+                    None => Spans::from(line),
+                });
+            }
+            // Continuation rows of wrapped lines leave the gutter blank
+            gutter_lines.push(match i {
+                0 => self.gutter(*n, gutter.width as usize),
+                _ => Spans::from(" ".repeat(gutter.width as usize)),
+            });
+            let mut spans = Vec::new();
+            let sbr = showbreak.width();
+            if *i > 0 && sbr > 0 && row.prefix >= sbr {
+                spans.push(Span::raw(" ".repeat(row.prefix - sbr)));
+                spans.push(Span::styled(showbreak.clone(), nontext));
+            } else if row.prefix > 0 {
+                spans.push(Span::raw(" ".repeat(row.prefix)));
+            }
+            if let Some(styled) = &line_spans {
+                spans.extend(row_spans(line, styled, *row, self.window.left, self.buf.indent(self.options).tabstop, nontext));
+            }
+            text_lines.push(Spans::from(spans));
+        }
+        Paragraph::new(gutter_lines).render(gutter, buf);
+        Paragraph::new(text_lines).render(text, buf);
+        for y in folds {
+            buf.set_style(Rect { y: text.y + y as u16, height: 1, ..text }, self.theme.get("ui.fold"));
+        }
+        if self.options.cursorline && self.focused {
+            let cursor = self.buf.folds.shown_line(self.window.cursor.line);
+            for (y, _) in rows.iter().enumerate().filter(|(_, (n, _, _))| *n == cursor) {
+                let row = Rect { y: text.y + y as u16, height: 1, ..text };
+                buf.set_style(row, self.theme.get("ui.cursorline"));
+            }
+        }
+        // The bracket under the cursor and the one matching it, if that is in view
+        let cursor = self.window.cursor;
+        if let (true, Some(first), Some(last)) = (self.focused, rows.first(), rows.last()) {
+            if let Some(other) = pair::shown_match(self.buf, cursor, first.0..=last.0) {
+                for (x, y) in [cursor, other].iter().filter_map(|at| self.cell(&rows, text, *at)) {
+                    buf.set_style(Rect { x, y, width: 1, height: 1 }, self.theme.get("ui.match"));
+                }
+            }
+        }
+    }
+}
+
+/// Screen position of the cursor of `window` drawn into `area`, if it is visible. In a
+/// closed fold it is at the start of the fold's row.
+pub fn cursor_position(area: Rect, buf: &buffer::Buffer, window: &Window, options: &Options) -> Option<(u16, u16)> {
+    let (_, text) = layout(area, buf, options);
+    if let Some((start, _)) = buf.folds.closed_at(window.cursor.line) {
+        let rows = visible_rows(buf, window, options, text.width as usize, text.height as usize);
+        let y = rows.iter().position(|(n, _, _)| *n == start)?;
+        return Some((text.x, text.y + y as u16));
+    }
+    let cursor = window.cursor;
+    let line_rows = window.line_rows(buf, cursor.line, options);
+    let at = row_of(&line_rows, cursor.col);
+    let rows = visible_rows(buf, window, options, text.width as usize, text.height as usize);
+    let y = rows.iter().position(|(n, i, _)| *n == cursor.line && *i == at)?;
+    let row = rows[y].2;
+    let line = buf.line(cursor.line);
+    let ts = buf.indent(options).tabstop;
+    let vcol = display_col(line, cursor.col, ts) - display_col(line, row.start, ts);
+    let col = (row.prefix + vcol).checked_sub(window.left)?;
+    // Just past the end of a full row, where Insert mode can put the cursor
+    let col = if line_rows.len() > 1 { col.min(text.width as usize - 1) } else { col };
+    if col < text.width as usize {
+        Some((text.x + col as u16, text.y + y as u16))
+    } else {
+        None
+    }
+}
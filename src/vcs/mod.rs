@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, env, fs, io::{self, Write}, path::{Path, PathBuf}, process::{self, Command, Stdio}, sync::atomic::{AtomicUsize, Ordering}, thread};
+use futures::{StreamExt, channel::mpsc::{self, UnboundedReceiver, UnboundedSender}, future};
+
+use crate::buffer::sign::VcsStatus;
+
+/// Line statuses of one file, keyed by line index
+pub type VcsSigns = BTreeMap<usize, VcsStatus>;
+
+/// Git state of one file: how its lines differ from `HEAD` and the branch checked out
+#[derive(Debug, Clone, Default)]
+pub struct VcsInfo {
+    pub signs: VcsSigns,
+    pub branch: Option<String>,
+}
+
+/// Parses the `a,b` or `a` half of a hunk header into a start line and length
+fn hunk_range(s: &str) -> Option<(usize, usize)> {
+    match s.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((s.parse().ok()?, 1)),
+    }
+}
+
+/// Signs for the hunks of a `git diff -U0`
+fn parse_diff(diff: &str) -> VcsSigns {
+    let mut signs = VcsSigns::new();
+    for header in diff.lines().filter_map(|l| l.strip_prefix("@@ ")) {
+        let mut ranges = header.split_whitespace();
+        let old = ranges.next().and_then(|r| r.strip_prefix('-')).and_then(hunk_range);
+        let new = ranges.next().and_then(|r| r.strip_prefix('+')).and_then(hunk_range);
+        let ((_, removed), (start, added)) = match (old, new) {
+            (Some(old), Some(new)) => (old, new),
+            _ => continue,
+        };
+        if added == 0 {
+            // Lines were removed after line `start`, which is 1-based
+            signs.insert(start.saturating_sub(1), VcsStatus::Removed);
+            continue;
+        }
+        for i in 0..added {
+            let status = if i < removed { VcsStatus::Modified } else { VcsStatus::Added };
+            signs.insert(start - 1 + i, status);
+        }
+    }
+    signs
+}
+
+/// How `text`, the contents of the file at `path` as the editor has them, differs from
+/// the file in `HEAD`. `None` when `HEAD` has no such file, as outside a git work tree.
+pub fn diff(path: &Path, text: &str) -> Option<VcsSigns> {
+    static BASES: AtomicUsize = AtomicUsize::new(0);
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    let name = path.file_name()?.to_str()?;
+    let output = Command::new("git")
+        .args(["show", "--no-textconv"])
+        .arg(format!("HEAD:./{}", name))
+        .current_dir(dir)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // `diff` reads the buffer's text from stdin, so the committed file goes to a file
+    let base = env::temp_dir().join(format!("lx-base-{}-{}", process::id(), BASES.fetch_add(1, Ordering::Relaxed)));
+    fs::write(&base, &output.stdout).ok()?;
+    let diff = run_diff(&["-U0", "--strip-trailing-cr"], &base, text);
+    let _ = fs::remove_file(&base);
+    Some(parse_diff(&diff.ok()?))
+}
+
+/// Unified diff from the file at `path` to `text`, the file's contents as the editor has them
+pub fn diff_text(path: &Path, text: &str) -> io::Result<String> {
+    run_diff(&["-u", "--label", "on disk", "--label", "buffer"], path, text)
+}
+
+/// Runs `diff` with `args` from the file at `path` to `text`, giving its output
+fn run_diff(args: &[&str], path: &Path, text: &str) -> io::Result<String> {
+    let mut child = Command::new("diff")
+        .args(args)
+        .arg(path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let text = text.to_string();
+    // Written from another thread, so a large diff filling the pipe can't deadlock
+    let writer = thread::spawn(move || stdin.write_all(text.as_bytes()));
+    let output = child.wait_with_output()?;
+    writer.join().expect("diff input thread panicked")?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The branch checked out in the git work tree holding `path`, or the abbreviated commit
+/// when `HEAD` is detached
+pub fn branch(path: &Path) -> Option<String> {
+    let path = env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf());
+    for dir in path.ancestors().skip(1) {
+        let dot_git = dir.join(".git");
+        // Linked work trees and submodules have a `.git` file pointing at the git dir
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if let Ok(link) = fs::read_to_string(&dot_git) {
+            dir.join(link.trim().strip_prefix("gitdir:")?.trim())
+        } else {
+            continue;
+        };
+        let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        return Some(match head.strip_prefix("ref: ") {
+            Some(r) => r.strip_prefix("refs/heads/").unwrap_or(r).to_string(),
+            None => head.chars().take(7).collect(),
+        });
+    }
+    None
+}
+
+/// Runs diffs on background threads, handing back results as they finish
+pub struct Differ {
+    tx: UnboundedSender<(PathBuf, VcsInfo)>,
+    rx: UnboundedReceiver<(PathBuf, VcsInfo)>,
+    /// Whether diffs run at all, which they don't in batch mode
+    enabled: bool,
+}
+
+impl Default for Differ {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::unbounded();
+        Self { tx, rx, enabled: true }
+    }
+}
+
+impl Differ {
+    /// A differ which runs no diffs
+    pub fn off() -> Self {
+        Self { enabled: false, ..Self::default() }
+    }
+    /// Starts diffing `text`, the contents of the file at `path`, and looking up its branch
+    pub fn request(&self, path: PathBuf, text: String) {
+        if !self.enabled {
+            return;
+        }
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            let branch = branch(&path);
+            // Files git doesn't have yet get no signs
+            let signs = diff(&path, &text).or_else(|| branch.as_ref().map(|_| VcsSigns::new()));
+            if let Some(signs) = signs {
+                let _ = tx.unbounded_send((path, VcsInfo { signs, branch }));
+            }
+        });
+    }
+    /// Waits for the next diff to finish
+    pub async fn next(&mut self) -> (PathBuf, VcsInfo) {
+        match self.rx.next().await {
+            Some(result) => result,
+            // The sender half is kept in `self`, so the channel never closes
+            None => future::pending().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TempDir;
+
+    #[test]
+    fn hunks_become_signs() {
+        let diff = "@@ -2 +2 @@\n-b\n+B\n@@ -4,0 +5,2 @@\n+e\n+f\n@@ -7,2 +8,0 @@\n-x\n-y\n";
+        let signs = parse_diff(diff);
+        assert_eq!(signs.into_iter().collect::<Vec<_>>(), [
+            (1, VcsStatus::Modified), (4, VcsStatus::Added), (5, VcsStatus::Added), (7, VcsStatus::Removed),
+        ]);
+    }
+
+    #[test]
+    fn buffer_text_is_diffed_against_head() {
+        let dir = TempDir::new("vcs");
+        let git = |args: &[&str]| {
+            let status = Command::new("git").args(["-c", "user.name=lx", "-c", "user.email=lx@example.com"])
+                .args(args).current_dir(&*dir).stdout(Stdio::null()).status().unwrap();
+            assert!(status.success(), "git {:?}", args);
+        };
+        dir.file("f.txt", "a\nb\nc\n");
+        git(&["init", "-q"]);
+        git(&["add", "f.txt"]);
+        git(&["commit", "-q", "-m", "f"]);
+        // Unsaved edits count, not the file on disk
+        let signs = diff(&dir.join("f.txt"), "a\nB\nc\nd\n").unwrap();
+        assert_eq!(signs.into_iter().collect::<Vec<_>>(), [(1, VcsStatus::Modified), (3, VcsStatus::Added)]);
+        assert_eq!(diff(&dir.join("f.txt"), "a\nb\nc\n").unwrap().len(), 0);
+        assert_eq!(diff(&dir.join("new.txt"), "a\n"), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashSet, env, fs, path::{Path, PathBuf}, time::{Duration, Instant, SystemTime}};
+use futures::future;
+use tokio::time;
+
+/// How often open files are checked for changes when there are no filesystem events
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What a file looked like on disk when it was last read or written, so a change made to
+/// it by something else can be told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Stamp {
+    /// The stamp of the file at `path`, `None` when there is no such file
+    pub fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        Some(Self { modified: meta.modified().ok(), len: meta.len() })
+    }
+}
+
+/// Changes in watched directories, from inotify. Directories are watched rather than the
+/// files themselves, so files replaced by renaming another over them stay watched.
+#[cfg(target_os = "linux")]
+struct Events {
+    fd: tokio::io::unix::AsyncFd<inotify::Inotify>,
+    dirs: std::collections::HashMap<inotify::WatchDescriptor, PathBuf>,
+    buffer: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+impl Events {
+    fn new() -> Option<Self> {
+        // Waiting for events takes the async runtime, without which files are polled
+        tokio::runtime::Handle::try_current().ok()?;
+        let fd = tokio::io::unix::AsyncFd::new(inotify::Inotify::init().ok()?).ok()?;
+        Some(Self { fd, dirs: Default::default(), buffer: vec![0; 4096] })
+    }
+    fn watch(&mut self, dir: &Path) -> std::io::Result<()> {
+        use inotify::WatchMask;
+        let mask = WatchMask::MODIFY | WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE
+            | WatchMask::DELETE | WatchMask::ATTRIB;
+        let wd = self.fd.get_mut().add_watch(dir, mask)?;
+        self.dirs.insert(wd, dir.to_path_buf());
+        Ok(())
+    }
+    fn unwatch(&mut self, dir: &Path) {
+        if let Some(wd) = self.dirs.iter().find(|(_, d)| *d == dir).map(|(wd, _)| wd.clone()) {
+            self.dirs.remove(&wd);
+            let _ = self.fd.get_mut().rm_watch(wd);
+        }
+    }
+    /// Waits for files to change, giving their paths
+    async fn read(&mut self) -> Vec<PathBuf> {
+        let (fd, buffer, dirs) = (&mut self.fd, &mut self.buffer, &self.dirs);
+        loop {
+            let mut guard = match fd.readable_mut().await {
+                Ok(guard) => guard,
+                Err(_) => return future::pending().await,
+            };
+            let read = guard.try_io(|fd| {
+                let events: Vec<_> = fd.get_mut().read_events(buffer)?
+                    .map(|e| (e.wd, e.name.map(|name| name.to_os_string())))
+                    .collect();
+                // Nothing to read comes back as no events rather than as an error, and the
+                // fd only stops counting as readable on an error
+                if events.is_empty() { Err(std::io::ErrorKind::WouldBlock.into()) } else { Ok(events) }
+            });
+            match read {
+                Ok(Ok(events)) => return events.into_iter().filter_map(|(wd, name)| Some(dirs.get(&wd)?.join(name?))).collect(),
+                Ok(Err(_)) => return Vec::new(),
+                // Woken without anything to read
+                Err(_) => continue,
+            }
+        }
+    }
+}
+
+/// There are no filesystem events to go by, so files are polled
+#[cfg(not(target_os = "linux"))]
+struct Events;
+
+#[cfg(not(target_os = "linux"))]
+impl Events {
+    fn new() -> Option<Self> {
+        None
+    }
+    fn watch(&mut self, _dir: &Path) -> std::io::Result<()> {
+        Ok(())
+    }
+    fn unwatch(&mut self, _dir: &Path) {}
+    async fn read(&mut self) -> Vec<PathBuf> {
+        future::pending().await
+    }
+}
+
+fn absolute(file: &Path) -> PathBuf {
+    env::current_dir().map(|cwd| cwd.join(file)).unwrap_or_else(|_| file.to_path_buf())
+}
+
+/// Watches open files for changes made outside the editor, such as by `git checkout` or a
+/// formatter. Uses inotify where it can, and otherwise has every watched file checked
+/// every few seconds. It only says which files may have changed: their `Stamp`s tell.
+pub struct Watcher {
+    events: Option<Events>,
+    files: HashSet<PathBuf>,
+    polled: Instant,
+    /// Whether files are watched at all, which they aren't in batch mode
+    enabled: bool,
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Self { events: Events::new(), files: HashSet::new(), polled: Instant::now(), enabled: true }
+    }
+}
+
+impl Watcher {
+    /// A watcher which watches nothing
+    pub fn off() -> Self {
+        Self { events: None, files: HashSet::new(), polled: Instant::now(), enabled: false }
+    }
+    pub fn watch(&mut self, file: &Path) {
+        if !self.enabled {
+            return;
+        }
+        let file = absolute(file);
+        if let (Some(events), Some(dir)) = (self.events.as_mut(), file.parent()) {
+            // Out of inotify watches, say, so every file is polled from now on
+            if events.watch(dir).is_err() {
+                self.events = None;
+            }
+        }
+        self.files.insert(file);
+    }
+    /// Stops watching `file`, and its directory once no other watched file is in it
+    pub fn unwatch(&mut self, file: &Path) {
+        let file = absolute(file);
+        self.files.remove(&file);
+        if let (Some(events), Some(dir)) = (self.events.as_mut(), file.parent()) {
+            if !self.files.iter().any(|f| f.parent() == Some(dir)) {
+                events.unwatch(dir);
+            }
+        }
+    }
+    /// Whether changes to `file` are watched for
+    pub fn watches(&self, file: &Path) -> bool {
+        self.files.contains(&absolute(file))
+    }
+    /// Waits for watched files to change, giving the ones which may have
+    pub async fn changed(&mut self) -> Vec<PathBuf> {
+        let files = &self.files;
+        let mut changed: Vec<PathBuf> = match self.events.as_mut() {
+            Some(events) => events.read().await.into_iter().filter(|p| files.contains(p)).collect(),
+            None => {
+                time::sleep_until((self.polled + POLL_INTERVAL).into()).await;
+                self.polled = Instant::now();
+                self.files.iter().cloned().collect()
+            },
+        };
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
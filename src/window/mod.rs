@@ -0,0 +1,252 @@
+pub mod wrap;
+
+use crate::{
+    buffer::{Buffer, grapheme::{col_at_display, display_col}},
+    options::{Options, Wrap},
+    types::{Cursor, Direction},
+};
+use self::wrap::{Row, line_rows, row_of};
+
+/// A single view onto one of the editor's buffers, with its own cursor and scroll position.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Window {
+    pub buf_idx: usize,
+    pub cursor: Cursor,
+    /// Display column vertical motions try to return to, kept across shorter lines
+    pub want_col: usize,
+    /// First buffer line shown
+    pub scroll: usize,
+    /// Rows of the `scroll` line hidden above the window, when it is wrapped
+    pub skip: usize,
+    /// First display column shown of lines too long for the window
+    pub left: usize,
+    /// Rows and columns of text in the window, as of the last draw
+    pub height: usize,
+    pub width: usize,
+    /// Wrap options set with `:setlocal`, used instead of the global ones
+    pub wrap: Option<Wrap>,
+}
+
+impl Window {
+    pub fn new(buf_idx: usize) -> Self {
+        Self { buf_idx, ..Self::default() }
+    }
+    /// Shows buffer `buf_idx` from its start, unless it is already shown
+    pub fn show(&mut self, buf_idx: usize) {
+        if buf_idx != self.buf_idx {
+            self.reset(buf_idx);
+        }
+    }
+    pub fn reset(&mut self, buf_idx: usize) {
+        *self = Self { buf_idx, height: self.height, width: self.width, wrap: self.wrap.take(), ..Self::default() };
+    }
+    /// Moves the cursor, remembering its display column for later vertical motions
+    pub fn set_cursor(&mut self, cursor: Cursor, buf: &Buffer, tabstop: usize) {
+        self.cursor = cursor;
+        self.want_col = display_col(buf.line(cursor.line), cursor.col, tabstop);
+    }
+    /// Rows in the window, or one if it hasn't been drawn yet
+    pub fn rows(&self) -> usize {
+        self.height.max(1)
+    }
+    /// The wrap options in effect in this window
+    pub fn wrap<'a>(&'a self, options: &'a Options) -> &'a Wrap {
+        self.wrap.as_ref().unwrap_or(&options.wrap)
+    }
+    /// The rows line `n` of `buf` takes in this window: one for a closed fold
+    pub fn line_rows(&self, buf: &Buffer, n: usize, options: &Options) -> Vec<Row> {
+        match buf.folds.closed_at(n) {
+            Some(_) => vec![Row { start: 0, end: 0, prefix: 0 }],
+            None => line_rows(buf.line(n), self.width, self.wrap(options), buf.indent(options).tabstop),
+        }
+    }
+    /// The `(line, row)` position `n` rows above `from`, stopping at the first line
+    fn rows_up(&self, buf: &Buffer, options: &Options, from: (usize, usize), n: usize) -> (usize, usize) {
+        let ((mut line, mut row), mut n) = (from, n);
+        while n > row {
+            if line == 0 {
+                return (0, 0);
+            }
+            n -= row + 1;
+            line = buf.folds.shown_line(line - 1);
+            row = self.line_rows(buf, line, options).len() - 1;
+        }
+        (line, row - n)
+    }
+    /// The `(line, row)` position `n` rows below `from`, stopping at the last line
+    fn rows_down(&self, buf: &Buffer, options: &Options, from: (usize, usize), n: usize) -> (usize, usize) {
+        let ((mut line, mut row), mut n) = (from, n);
+        let last = buf.line_count() - 1;
+        loop {
+            let count = self.line_rows(buf, line, options).len();
+            if row + n < count {
+                return (line, row + n);
+            }
+            let next = buf.folds.next_line(line);
+            if next > last {
+                return (line, count - 1);
+            }
+            n -= count - row;
+            line = next;
+            row = 0;
+        }
+    }
+    /// The last line at least partly shown in the first `rows` rows of the window, past
+    /// closed folds and counting the rows wrapped lines take
+    pub fn last_line(&self, buf: &Buffer, options: &Options, rows: usize) -> usize {
+        self.rows_down(buf, options, (self.scroll, self.skip), rows.saturating_sub(1)).0
+    }
+    /// The `(line, row)` position of the cursor, on the first line of a closed fold
+    fn cursor_row(&self, buf: &Buffer, options: &Options) -> (usize, usize) {
+        let line = buf.folds.shown_line(self.cursor.line);
+        (line, row_of(&self.line_rows(buf, line, options), self.cursor.col))
+    }
+    /// Scrolls as little as needed to show the cursor with `scrolloff` rows of context above
+    /// and below it, and `scroll` no further than the last line of `buf`
+    pub fn scroll_to_cursor(&mut self, buf: &Buffer, options: &Options) {
+        let rows = self.rows();
+        let so = options.scrolloff.min((rows - 1) / 2);
+        let last = buf.line_count() - 1;
+        if self.scroll > last {
+            (self.scroll, self.skip) = (last, 0);
+        }
+        self.scroll = buf.folds.shown_line(self.scroll);
+        self.skip = self.skip.min(self.line_rows(buf, self.scroll, options).len() - 1);
+        let at = self.cursor_row(buf, options);
+        let above = self.rows_up(buf, options, at, so);
+        let below = self.rows_down(buf, options, at, so);
+        if above < (self.scroll, self.skip) {
+            (self.scroll, self.skip) = above;
+        } else if below > self.rows_down(buf, options, (self.scroll, self.skip), rows - 1) {
+            (self.scroll, self.skip) = self.rows_up(buf, options, below, rows - 1);
+        }
+        if self.wrap(options).enabled {
+            self.left = 0;
+        } else if self.width > 0 {
+            let line = buf.line(self.cursor.line);
+            let ts = buf.indent(options).tabstop;
+            let start = display_col(line, self.cursor.col, ts);
+            let end = display_col(line, self.cursor.col + 1, ts).max(start + 1);
+            if start < self.left {
+                self.left = start;
+            } else if end > self.left + self.width {
+                self.left = end - self.width;
+            }
+        }
+    }
+    /// Scrolls so the cursor line is at `row` of the window, keeping `scrolloff` rows of
+    /// context where the buffer allows
+    pub fn scroll_cursor_to(&mut self, row: ScrollTo, buf: &Buffer, options: &Options) {
+        let rows = self.rows();
+        let so = options.scrolloff.min((rows - 1) / 2);
+        let line = buf.folds.shown_line(self.cursor.line);
+        (self.scroll, self.skip) = match row {
+            ScrollTo::Top => self.rows_up(buf, options, (line, 0), so),
+            ScrollTo::Center => self.rows_up(buf, options, self.cursor_row(buf, options), rows / 2),
+            ScrollTo::Bottom => {
+                let end = self.line_rows(buf, line, options).len() - 1;
+                self.rows_up(buf, options, (line, end), rows - 1 - so)
+            },
+        };
+    }
+    /// Where `gj` and `gk` move the cursor: `n` rows down or up, keeping its column on
+    /// screen. `None` when lines aren't wrapped, as they then move like `j` and `k`.
+    pub fn display_move(&self, buf: &Buffer, options: &Options, dir: Direction, n: usize) -> Option<Cursor> {
+        if !self.wrap(options).enabled {
+            return None;
+        }
+        let ts = buf.indent(options).tabstop;
+        let from = self.cursor_row(buf, options);
+        let rows = self.line_rows(buf, from.0, options);
+        let line = buf.line(from.0);
+        let row = rows[from.1];
+        let x = row.prefix + display_col(line, self.cursor.col, ts) - display_col(line, row.start, ts);
+        let (to, r) = match dir {
+            Direction::Next => self.rows_down(buf, options, from, n),
+            Direction::Prev => self.rows_up(buf, options, from, n),
+        };
+        let rows = self.line_rows(buf, to, options);
+        let (line, row) = (buf.line(to), rows[r]);
+        let col = col_at_display(line, display_col(line, row.start, ts) + x.saturating_sub(row.prefix), ts);
+        // Stay on the target row rather than run onto the next one
+        let col = if r + 1 < rows.len() { col.min(row.end - 1) } else { col };
+        Some(Cursor::new(to, col))
+    }
+}
+
+/// Where `zt`, `zz` and `zb` put the cursor line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollTo {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// How the windows of a tab share its area
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SplitDir {
+    /// Windows stacked on top of each other
+    #[default]
+    Horizontal,
+    /// Windows side by side
+    Vertical,
+}
+
+/// Where a newly opened buffer is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenIn {
+    Current,
+    Split(SplitDir),
+    Tab,
+}
+
+/// A tab page, holding one or more windows and the index of the focused one.
+#[derive(Debug, PartialEq)]
+pub struct Tab {
+    pub windows: Vec<Window>,
+    pub focus: usize,
+    pub split: SplitDir,
+}
+
+impl Default for Tab {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Tab {
+    pub fn new(buf_idx: usize) -> Self {
+        Self { windows: vec![Window::new(buf_idx)], focus: 0, split: SplitDir::default() }
+    }
+    pub fn window(&self) -> &Window {
+        &self.windows[self.focus]
+    }
+    pub fn window_mut(&mut self) -> &mut Window {
+        &mut self.windows[self.focus]
+    }
+    /// Adds a window after the focused one and focuses it. All windows of a tab share one
+    /// split direction, so this also sets the direction for the whole tab.
+    pub fn split(&mut self, dir: SplitDir, buf_idx: usize) {
+        self.split = dir;
+        self.focus += 1;
+        let current = self.windows[self.focus - 1].clone();
+        let window = if current.buf_idx == buf_idx { current } else { Window::new(buf_idx) };
+        self.windows.insert(self.focus, window);
+    }
+    /// Closes the focused window, unless it is the last one. Returns whether it was closed.
+    pub fn close_window(&mut self) -> bool {
+        if self.windows.len() > 1 {
+            self.windows.remove(self.focus);
+            self.focus = self.focus.min(self.windows.len() - 1);
+            true
+        } else {
+            false
+        }
+    }
+    pub fn focus_next(&mut self) {
+        self.focus = (self.focus + 1) % self.windows.len();
+    }
+    pub fn focus_prev(&mut self) {
+        self.focus = (self.focus + self.windows.len() - 1) % self.windows.len();
+    }
+}
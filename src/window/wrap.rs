@@ -0,0 +1,107 @@
+use unicode_width::UnicodeWidthStr;
+
+use crate::{buffer::grapheme::{self, columns}, options::Wrap};
+
+/// One screen row of a line: graphemes `start..end` of it, drawn after `prefix` columns of
+/// break indent and showbreak
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Row {
+    pub start: usize,
+    pub end: usize,
+    pub prefix: usize,
+}
+
+/// The rows `line` takes in a window `width` columns wide, with rows starting and ending
+/// at graphemes. Unwrapped lines, and any line when the width isn't known yet, take a
+/// single row.
+pub fn line_rows(line: &str, width: usize, wrap: &Wrap, tabstop: usize) -> Vec<Row> {
+    // The width of each grapheme and whether it is blank
+    let cells: Vec<(usize, bool)> = columns(line, tabstop)
+        .map(|(vcol, g)| (grapheme::width(g, vcol, tabstop), g.chars().all(char::is_whitespace)))
+        .collect();
+    let len = cells.len();
+    if !wrap.enabled || width == 0 || cells.iter().map(|(w, _)| w).sum::<usize>() <= width {
+        return vec![Row { start: 0, end: len, prefix: 0 }];
+    }
+    let indent = if wrap.breakindent { cells.iter().take_while(|(_, blank)| *blank).map(|(w, _)| w).sum() } else { 0 };
+    let showbreak = wrap.showbreak.width();
+    // Drop the indent, then the showbreak, rather than leave continuation rows too narrow
+    let prefix = [indent + showbreak, showbreak, 0].iter().copied()
+        .find(|p| *p <= width / 2)
+        .unwrap_or(0);
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut row_prefix = 0;
+    loop {
+        let avail = width - row_prefix;
+        // Graphemes up to the first which doesn't fit, but always at least one
+        let mut used = 0;
+        let mut end = start;
+        while end < len && (end == start || used + cells[end].0 <= avail) {
+            used += cells[end].0;
+            end += 1;
+        }
+        if end == len {
+            rows.push(Row { start, end, prefix: row_prefix });
+            return rows;
+        }
+        if wrap.linebreak {
+            if let Some(b) = (start + 1..=end).rev().find(|b| cells[b - 1].1) {
+                end = b;
+            }
+        }
+        rows.push(Row { start, end, prefix: row_prefix });
+        start = end;
+        row_prefix = prefix;
+    }
+}
+
+/// Index of the row of `rows` column `col` is drawn in. Columns past the end of the line,
+/// where the Insert mode cursor can be, belong to the last row.
+pub fn row_of(rows: &[Row], col: usize) -> usize {
+    rows.iter().position(|r| col < r.end).unwrap_or(rows.len() - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(line: &str, width: usize, wrap: &Wrap) -> Vec<(usize, usize, usize)> {
+        line_rows(line, width, wrap, 4).iter().map(|r| (r.start, r.end, r.prefix)).collect()
+    }
+
+    #[test]
+    fn lines_wrap_at_blanks() {
+        let wrap = Wrap::default();
+        assert_eq!(rows("short", 10, &wrap), [(0, 5, 0)]);
+        assert_eq!(rows("one two three", 10, &wrap), [(0, 8, 0), (8, 13, 0)]);
+        // Words wider than a row are broken anyway
+        assert_eq!(rows("abcdefghijkl", 5, &wrap), [(0, 5, 0), (5, 10, 0), (10, 12, 0)]);
+        let nolbr = Wrap { linebreak: false, ..Wrap::default() };
+        assert_eq!(rows("one two three", 10, &nolbr), [(0, 10, 0), (10, 13, 0)]);
+        let nowrap = Wrap { enabled: false, ..Wrap::default() };
+        assert_eq!(rows("one two three", 10, &nowrap), [(0, 13, 0)]);
+        assert_eq!(rows("one two three", 0, &wrap), [(0, 13, 0)]);
+    }
+
+    #[test]
+    fn continuation_rows_get_indent_and_showbreak() {
+        let wrap = Wrap { showbreak: "> ".to_string(), ..Wrap::default() };
+        assert_eq!(rows("  aa bb cc dd", 10, &wrap), [(0, 8, 0), (8, 13, 4)]);
+        // Past half the width the indent is dropped, then the showbreak
+        assert_eq!(rows("    aa bb", 8, &wrap), [(0, 7, 0), (7, 9, 2)]);
+        assert_eq!(rows("aaaaaa", 3, &wrap), [(0, 3, 0), (3, 6, 0)]);
+        // Wide graphemes and tabs take their display width
+        assert_eq!(rows("日本語", 4, &Wrap::default()), [(0, 2, 0), (2, 3, 0)]);
+        assert_eq!(rows("\tabcdefghij", 10, &Wrap { linebreak: false, ..Wrap::default() }), [(0, 7, 0), (7, 11, 4)]);
+    }
+
+    #[test]
+    fn columns_belong_to_rows() {
+        let rows = line_rows("one two three", 10, &Wrap::default(), 4);
+        assert_eq!(row_of(&rows, 0), 0);
+        assert_eq!(row_of(&rows, 7), 0);
+        assert_eq!(row_of(&rows, 8), 1);
+        assert_eq!(row_of(&rows, 13), 1);
+    }
+}
@@ -0,0 +1,37 @@
+//! The library driven the way an embedder would, through its public API alone and without
+//! the terminal frontend, so it also runs under `--no-default-features`.
+
+use futures::stream;
+use tui::backend::TestBackend;
+
+use lx::{Lx, Mode, key::{KeyCode, KeyEvent, KeyModifiers, notation}, types::Cursor, ui};
+
+#[test]
+fn keys_edit_buffers() {
+    let mut lx = Lx::new_with_backend(TestBackend::new(30, 6)).unwrap();
+    lx.open_bytes(b"hello\n");
+    lx.match_key_event(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE)).unwrap();
+    assert!(matches!(lx.mode, Mode::Edit(_)));
+    lx.feed("<cr>well, <esc>").unwrap();
+    assert_eq!(lx.buf[lx.buf_idx()].text, "well, hello");
+    assert_eq!(lx.cursor(), Cursor::new(0, 5));
+    ui::draw_ui(&mut lx).unwrap();
+    let row: String = (0..30).map(|x| lx.term.backend().buffer().get(x, 1).symbol.clone()).collect();
+    assert!(row.contains("well, hello"), "{}", row);
+}
+
+#[test]
+fn key_notation_round_trips() {
+    let keys = notation::parse("a<c-w><lt><cr><esc>");
+    assert_eq!(keys[1], KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+    assert_eq!(keys[3].code, KeyCode::Enter);
+    assert_eq!(notation::format(&keys), "a<c-w><lt><cr><esc>");
+}
+
+#[tokio::test]
+async fn the_event_loop_runs_on_any_input() {
+    let mut lx = Lx::new_with_backend(TestBackend::new(30, 6)).unwrap();
+    let keys = notation::parse("typed<esc>:q!<cr>").into_iter().map(|k| Ok(Some(k)));
+    lx.event_loop(stream::iter(keys), |lx| ui::draw_ui(lx).map(|_| false)).await.unwrap();
+    assert!(lx.quit);
+}
@@ -0,0 +1,32 @@
+//! The `vi` binary in a pipeline: `--filter` reads standard input and writes the buffer to
+//! standard output, if it was written.
+#![cfg(feature = "term")]
+
+use std::{io::Write, process::{Command, Output, Stdio}};
+
+fn vi(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vi"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn written_buffers_go_to_standard_output() {
+    let out = vi(&["--batch", "--filter", "-c", "g/drop/d", "-c", "%s/keep/kept/", "-c", "wq"], "keep a\ndrop b\nkeep c\n");
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "kept a\nkept c\n");
+}
+
+#[test]
+fn unwritten_buffers_fail_with_no_output() {
+    let out = vi(&["--batch", "--filter", "-c", "%s/a/b/", "-c", "q!"], "a\n");
+    assert_eq!(out.status.code(), Some(1));
+    assert!(out.stdout.is_empty());
+    assert_eq!(String::from_utf8_lossy(&out.stderr), "lx: Nothing written to standard output\n");
+}